@@ -0,0 +1,614 @@
+use crate::forward_manager::SimulationDebugManager;
+use crate::{
+    AccountableSignal, AllocationCheck, BucketResources, ChannelSnapshot, ForwardResolution,
+    ForwardingOutcome, HtlcRef, ProposedForward, ReputationCheck, ReputationError,
+    ReputationManager, ResourceBucketType, ResourceCheck,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The protocol limit on the number of htlcs that a channel can have in flight.
+const MAX_HTLC_SLOTS: u16 = 483;
+
+/// Defines how a [`BaselineManager`] limits the resources that htlcs may use on their incoming channel. None of these
+/// policies track reputation, so that the reputation-based [`crate::forward_manager::ForwardManager`] can be compared
+/// against simpler defences.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum BaselinePolicy {
+    /// Forwards every htlc that the incoming channel has space for, relaying the accountable signal it received.
+    NoMitigation,
+    /// Limits the number of htlcs and liquidity that each incoming channel may have in flight at once, regardless of
+    /// their accountable signal.
+    CircuitBreaker {
+        max_pending_htlcs: u16,
+        max_pending_liquidity_portion: u8,
+    },
+    /// Splits each channel's resources into a static general bucket that any htlc may use, and a protected bucket
+    /// that is reserved for htlcs that are already accountable. Unaccountable htlcs are never upgraded.
+    StaticBuckets {
+        general_slot_portion: u8,
+        general_liquidity_portion: u8,
+    },
+}
+
+impl BaselinePolicy {
+    /// Returns the general and protected bucket sizes for a channel with the capacity provided.
+    fn bucket_limits(&self, capacity_msat: u64) -> (BucketLimit, BucketLimit) {
+        match self {
+            BaselinePolicy::NoMitigation => (
+                BucketLimit {
+                    slots: MAX_HTLC_SLOTS,
+                    liquidity_msat: capacity_msat,
+                },
+                BucketLimit::default(),
+            ),
+            BaselinePolicy::CircuitBreaker {
+                max_pending_htlcs,
+                max_pending_liquidity_portion,
+            } => (
+                BucketLimit {
+                    slots: *max_pending_htlcs,
+                    liquidity_msat: capacity_msat * *max_pending_liquidity_portion as u64 / 100,
+                },
+                BucketLimit::default(),
+            ),
+            BaselinePolicy::StaticBuckets {
+                general_slot_portion,
+                general_liquidity_portion,
+            } => {
+                let general = BucketLimit {
+                    slots: MAX_HTLC_SLOTS * *general_slot_portion as u16 / 100,
+                    liquidity_msat: capacity_msat * *general_liquidity_portion as u64 / 100,
+                };
+
+                (
+                    general,
+                    BucketLimit {
+                        slots: MAX_HTLC_SLOTS - general.slots,
+                        liquidity_msat: capacity_msat - general.liquidity_msat,
+                    },
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct BucketLimit {
+    slots: u16,
+    liquidity_msat: u64,
+}
+
+#[derive(Debug)]
+struct BaselineChannel {
+    capacity_msat: u64,
+    general: BucketLimit,
+    protected: BucketLimit,
+}
+
+#[derive(Debug)]
+struct BaselineHtlc {
+    outgoing_channel_id: u64,
+    amount_in_msat: u64,
+    bucket: ResourceBucketType,
+}
+
+/// Implements simple resource limits for an individual node, without tracking reputation. Used as a baseline to
+/// quantify what the reputation scheme provides over doing nothing or over simpler defences.
+///
+/// Decisions are expressed as an [`AllocationCheck`] so that forwards can be reported in the same format as the
+/// reputation scheme. As reputation is not tracked, the reputation check is set so that only htlcs that are already
+/// accountable may use protected resources, and congestion resources are never available.
+#[derive(Debug)]
+pub struct BaselineManager {
+    policy: BaselinePolicy,
+    inner: Mutex<BaselineManagerImpl>,
+}
+
+#[derive(Debug, Default)]
+struct BaselineManagerImpl {
+    channels: HashMap<u64, BaselineChannel>,
+    htlcs: HashMap<HtlcRef, BaselineHtlc>,
+}
+
+impl BaselineManagerImpl {
+    fn bucket_resources(
+        &self,
+        channel_id: u64,
+        bucket: ResourceBucketType,
+        limit: BucketLimit,
+    ) -> BucketResources {
+        let in_bucket = self
+            .htlcs
+            .iter()
+            .filter(|(htlc_ref, htlc)| htlc_ref.channel_id == channel_id && htlc.bucket == bucket);
+
+        BucketResources {
+            slots_used: in_bucket.clone().count() as u16,
+            slots_available: limit.slots,
+            liquidity_used_msat: in_bucket.map(|(_, htlc)| htlc.amount_in_msat).sum(),
+            liquidity_available_msat: limit.liquidity_msat,
+        }
+    }
+
+    fn get_allocation_snapshot(
+        &self,
+        forward: &ProposedForward,
+    ) -> Result<AllocationCheck, ReputationError> {
+        forward.validate()?;
+
+        if !self.channels.contains_key(&forward.outgoing_channel_id) {
+            return Err(ReputationError::ErrOutgoingNotFound(
+                forward.outgoing_channel_id,
+            ));
+        }
+
+        let incoming_channel = self.channels.get(&forward.incoming_ref.channel_id).ok_or(
+            ReputationError::ErrIncomingNotFound(forward.incoming_ref.channel_id),
+        )?;
+
+        // Protected resources are only available to htlcs that are already accountable, which is expressed by
+        // setting a threshold that any reputation meets.
+        let revenue_threshold = match forward.incoming_accountable {
+            AccountableSignal::Accountable => i64::MIN,
+            AccountableSignal::Unaccountable => 0,
+        };
+
+        Ok(AllocationCheck {
+            reputation_check: ReputationCheck {
+                reputation: 0,
+                revenue_threshold,
+                in_flight_total_risk: 0,
+                htlc_risk: 0,
+            },
+            general_eligible: true,
+            congestion_eligible: false,
+            resource_check: ResourceCheck {
+                general_bucket: self.bucket_resources(
+                    forward.incoming_ref.channel_id,
+                    ResourceBucketType::General,
+                    incoming_channel.general,
+                ),
+                congestion_bucket: self.bucket_resources(
+                    forward.incoming_ref.channel_id,
+                    ResourceBucketType::Congestion,
+                    BucketLimit::default(),
+                ),
+                protected_bucket: self.bucket_resources(
+                    forward.incoming_ref.channel_id,
+                    ResourceBucketType::Protected,
+                    incoming_channel.protected,
+                ),
+            },
+        })
+    }
+}
+
+impl BaselineManager {
+    pub fn new(policy: BaselinePolicy) -> Self {
+        match policy {
+            BaselinePolicy::NoMitigation => {}
+            BaselinePolicy::CircuitBreaker {
+                max_pending_htlcs,
+                max_pending_liquidity_portion,
+            } => {
+                assert!(max_pending_htlcs <= MAX_HTLC_SLOTS);
+                assert!(max_pending_liquidity_portion <= 100);
+            }
+            BaselinePolicy::StaticBuckets {
+                general_slot_portion,
+                general_liquidity_portion,
+            } => {
+                assert!(general_slot_portion <= 100);
+                assert!(general_liquidity_portion <= 100);
+            }
+        }
+
+        Self {
+            policy,
+            inner: Mutex::new(BaselineManagerImpl::default()),
+        }
+    }
+}
+
+impl SimulationDebugManager for BaselineManager {
+    fn general_jam_channel(&self, channel: u64) -> Result<(), ReputationError> {
+        self.inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?
+            .channels
+            .get_mut(&channel)
+            .ok_or(ReputationError::ErrChannelNotFound(channel))?
+            .general = BucketLimit::default();
+        Ok(())
+    }
+
+    /// Baselines have no congestion resources, so there is nothing to jam.
+    fn congestion_jam_channel(&self, channel: u64) -> Result<(), ReputationError> {
+        self.inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?
+            .channels
+            .get(&channel)
+            .ok_or(ReputationError::ErrChannelNotFound(channel))
+            .map(|_| ())
+    }
+}
+
+impl ReputationManager for BaselineManager {
+    fn add_channel(
+        &self,
+        channel_id: u64,
+        capacity_msat: u64,
+        _add_ins: Instant,
+        channel_reputation: Option<ChannelSnapshot>,
+    ) -> Result<(), ReputationError> {
+        if let Some(ref channel) = channel_reputation {
+            if channel.capacity_msat != capacity_msat {
+                return Err(ReputationError::ErrChannelCapacityMismatch(
+                    capacity_msat,
+                    channel.capacity_msat,
+                ));
+            }
+        }
+
+        match self
+            .inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?
+            .channels
+            .entry(channel_id)
+        {
+            Entry::Occupied(_) => Err(ReputationError::ErrChannelExists(channel_id)),
+            Entry::Vacant(v) => {
+                let (general, protected) = self.policy.bucket_limits(capacity_msat);
+                v.insert(BaselineChannel {
+                    capacity_msat,
+                    general,
+                    protected,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn remove_channel(&self, channel_id: u64) -> Result<(), ReputationError> {
+        let mut inner_lock = self
+            .inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
+
+        // Any htlcs still in flight on the channel will be resolved on-chain, so we no longer track them.
+        inner_lock.htlcs.retain(|htlc_ref, htlc| {
+            htlc_ref.channel_id != channel_id && htlc.outgoing_channel_id != channel_id
+        });
+
+        inner_lock
+            .channels
+            .remove(&channel_id)
+            .ok_or(ReputationError::ErrChannelNotFound(channel_id))
+            .map(|_| ())
+    }
+
+    fn get_allocation_snapshot(
+        &self,
+        forward: &ProposedForward,
+    ) -> Result<AllocationCheck, ReputationError> {
+        self.inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?
+            .get_allocation_snapshot(forward)
+    }
+
+    fn add_htlc(&self, forward: &ProposedForward) -> Result<ForwardingOutcome, ReputationError> {
+        let mut inner_lock = self
+            .inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
+
+        if inner_lock.htlcs.contains_key(&forward.incoming_ref) {
+            return Err(ReputationError::ErrDuplicateHtlc(forward.incoming_ref));
+        }
+
+        let fwd_outcome = inner_lock
+            .get_allocation_snapshot(forward)?
+            .inner_forwarding_outcome(
+                forward.amount_in_msat,
+                forward.incoming_accountable,
+                forward.upgradable_accountability,
+            );
+
+        Ok(match fwd_outcome {
+            Ok(fwd_success) => {
+                inner_lock.htlcs.insert(
+                    forward.incoming_ref,
+                    BaselineHtlc {
+                        outgoing_channel_id: forward.outgoing_channel_id,
+                        amount_in_msat: forward.amount_in_msat,
+                        bucket: fwd_success.bucket,
+                    },
+                );
+                ForwardingOutcome::Forward(fwd_success.accountable_signal)
+            }
+            Err(e) => ForwardingOutcome::Fail(e),
+        })
+    }
+
+    fn resolve_htlc(
+        &self,
+        outgoing_channel: u64,
+        incoming_ref: HtlcRef,
+        _resolution: ForwardResolution,
+        _resolved_instant: Instant,
+    ) -> Result<(), ReputationError> {
+        let mut inner_lock = self
+            .inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
+
+        match inner_lock.htlcs.entry(incoming_ref) {
+            Entry::Occupied(e) if e.get().outgoing_channel_id == outgoing_channel => {
+                e.remove();
+                Ok(())
+            }
+            _ => Err(ReputationError::ErrForwardNotFound(
+                outgoing_channel,
+                incoming_ref,
+            )),
+        }
+    }
+
+    /// Lists the channels tracked by the manager. Reputation and revenue are not tracked, so are always zero.
+    fn list_channels(
+        &self,
+        _access_ins: Instant,
+    ) -> Result<HashMap<u64, ChannelSnapshot>, ReputationError> {
+        Ok(self
+            .inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?
+            .channels
+            .iter()
+            .map(|(scid, channel)| {
+                (
+                    *scid,
+                    ChannelSnapshot {
+                        capacity_msat: channel.capacity_msat,
+                        outgoing_reputation: 0,
+                        incoming_revenue: 0,
+                    },
+                )
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{BaselineManager, BaselinePolicy};
+    use crate::forward_manager::SimulationDebugManager;
+    use crate::{
+        AccountableSignal, FailureReason, ForwardResolution, ForwardingOutcome, HtlcRef,
+        ProposedForward, ReputationError, ReputationManager,
+    };
+
+    fn test_proposed_forward(
+        htlc_index: u64,
+        amount_in_msat: u64,
+        accountable: AccountableSignal,
+    ) -> ProposedForward {
+        ProposedForward {
+            incoming_ref: HtlcRef {
+                channel_id: 0,
+                htlc_index,
+            },
+            outgoing_channel_id: 1,
+            amount_in_msat,
+            amount_out_msat: amount_in_msat - 100,
+            expiry_in_height: 80,
+            expiry_out_height: 40,
+            added_height: 0,
+            added_at: Instant::now(),
+            incoming_accountable: accountable,
+            upgradable_accountability: true,
+        }
+    }
+
+    fn setup_test_manager(policy: BaselinePolicy) -> BaselineManager {
+        let manager = BaselineManager::new(policy);
+        let now = Instant::now();
+        for scid in 0..2 {
+            manager.add_channel(scid, 100_000, now, None).unwrap();
+        }
+        manager
+    }
+
+    /// Tests that no mitigation forwards htlcs up to the channel's capacity and relays their accountable signal, and
+    /// that jamming the channel fills all of its resources.
+    #[test]
+    fn test_no_mitigation() {
+        let manager = setup_test_manager(BaselinePolicy::NoMitigation);
+
+        for (i, signal) in [
+            AccountableSignal::Unaccountable,
+            AccountableSignal::Accountable,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert_eq!(
+                manager
+                    .add_htlc(&test_proposed_forward(i as u64, 50_000, signal))
+                    .unwrap(),
+                ForwardingOutcome::Forward(signal)
+            );
+        }
+
+        assert_eq!(
+            manager
+                .add_htlc(&test_proposed_forward(
+                    2,
+                    1_000,
+                    AccountableSignal::Unaccountable
+                ))
+                .unwrap(),
+            ForwardingOutcome::Fail(FailureReason::NoGeneralResources)
+        );
+
+        let htlc = test_proposed_forward(0, 50_000, AccountableSignal::Unaccountable);
+        manager
+            .resolve_htlc(
+                1,
+                htlc.incoming_ref,
+                ForwardResolution::Settled,
+                Instant::now(),
+            )
+            .unwrap();
+        assert!(matches!(
+            manager
+                .resolve_htlc(
+                    1,
+                    htlc.incoming_ref,
+                    ForwardResolution::Settled,
+                    Instant::now()
+                )
+                .err()
+                .unwrap(),
+            ReputationError::ErrForwardNotFound(1, _)
+        ));
+
+        manager.general_jam_channel(0).unwrap();
+        assert_eq!(
+            manager
+                .add_htlc(&test_proposed_forward(
+                    3,
+                    1_000,
+                    AccountableSignal::Unaccountable
+                ))
+                .unwrap(),
+            ForwardingOutcome::Fail(FailureReason::NoGeneralResources)
+        );
+    }
+
+    /// Tests that a circuit breaker limits the number of htlcs in flight from an incoming channel, regardless of
+    /// their accountable signal.
+    #[test]
+    fn test_circuit_breaker() {
+        let manager = setup_test_manager(BaselinePolicy::CircuitBreaker {
+            max_pending_htlcs: 2,
+            max_pending_liquidity_portion: 50,
+        });
+
+        // Liquidity is limited to half of the channel.
+        assert_eq!(
+            manager
+                .add_htlc(&test_proposed_forward(
+                    0,
+                    60_000,
+                    AccountableSignal::Unaccountable
+                ))
+                .unwrap(),
+            ForwardingOutcome::Fail(FailureReason::NoGeneralResources)
+        );
+
+        for i in 0..2 {
+            assert_eq!(
+                manager
+                    .add_htlc(&test_proposed_forward(
+                        i,
+                        1_000,
+                        AccountableSignal::Unaccountable
+                    ))
+                    .unwrap(),
+                ForwardingOutcome::Forward(AccountableSignal::Unaccountable)
+            );
+        }
+
+        assert_eq!(
+            manager
+                .add_htlc(&test_proposed_forward(
+                    2,
+                    1_000,
+                    AccountableSignal::Accountable
+                ))
+                .unwrap(),
+            ForwardingOutcome::Fail(FailureReason::NoResources)
+        );
+
+        // Once the incoming channel is removed, its htlcs no longer occupy slots.
+        manager.remove_channel(0).unwrap();
+        manager
+            .add_channel(0, 100_000, Instant::now(), None)
+            .unwrap();
+        let check = manager
+            .get_allocation_snapshot(&test_proposed_forward(
+                2,
+                1_000,
+                AccountableSignal::Unaccountable,
+            ))
+            .unwrap();
+        assert_eq!(check.resource_check.general_bucket.slots_used, 0);
+        assert_eq!(check.resource_check.general_bucket.slots_available, 2);
+    }
+
+    /// Tests that static buckets reserve protected resources for accountable htlcs, and never upgrade unaccountable
+    /// htlcs to use them.
+    #[test]
+    fn test_static_buckets() {
+        let manager = setup_test_manager(BaselinePolicy::StaticBuckets {
+            general_slot_portion: 50,
+            general_liquidity_portion: 50,
+        });
+
+        assert_eq!(
+            manager
+                .add_htlc(&test_proposed_forward(
+                    0,
+                    50_000,
+                    AccountableSignal::Unaccountable
+                ))
+                .unwrap(),
+            ForwardingOutcome::Forward(AccountableSignal::Unaccountable)
+        );
+        assert_eq!(
+            manager
+                .add_htlc(&test_proposed_forward(
+                    1,
+                    1_000,
+                    AccountableSignal::Unaccountable
+                ))
+                .unwrap(),
+            ForwardingOutcome::Fail(FailureReason::NoGeneralResources)
+        );
+
+        let accountable = test_proposed_forward(2, 1_000, AccountableSignal::Accountable);
+        assert_eq!(
+            manager.add_htlc(&accountable).unwrap(),
+            ForwardingOutcome::Forward(AccountableSignal::Accountable)
+        );
+        assert!(matches!(
+            manager.add_htlc(&accountable).err().unwrap(),
+            ReputationError::ErrDuplicateHtlc(_)
+        ));
+
+        let check = manager
+            .get_allocation_snapshot(&test_proposed_forward(
+                3,
+                1_000,
+                AccountableSignal::Accountable,
+            ))
+            .unwrap();
+        assert_eq!(check.resource_check.protected_bucket.slots_used, 1);
+        assert_eq!(check.resource_check.protected_bucket.slots_available, 242);
+        assert_eq!(
+            check.resource_check.general_bucket.liquidity_used_msat,
+            50_000
+        );
+    }
+}
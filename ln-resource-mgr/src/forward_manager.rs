@@ -1,11 +1,14 @@
 use crate::htlc_manager::{ChannelFilter, InFlightHtlc, InFlightManager};
-use crate::incoming_channel::{BucketParameters, IncomingChannel};
+use crate::incoming_channel::{BucketParameters, ChannelBuckets, IncomingChannel};
 use crate::outgoing_channel::OutgoingChannel;
 use crate::{
     AllocationCheck, BucketResources, ChannelSnapshot, ForwardResolution, ForwardingOutcome,
     HtlcRef, ProposedForward, ReputationCheck, ReputationError, ReputationManager,
-    ReputationParams, ResourceBucketType, ResourceCheck,
+    ReputationParams, ResourceBucketType, ResourceCheck, MINIMUM_CONGESTION_SLOT_LIQUDITY,
 };
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::sync::Mutex;
@@ -19,7 +22,7 @@ struct TrackedChannel {
     incoming_direction: IncomingChannel,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ForwardManagerParams {
     pub reputation_params: ReputationParams,
     pub general_slot_portion: u8,
@@ -51,6 +54,24 @@ impl ForwardManagerParams {
         self.reputation_params
             .opportunity_cost(fee_msat, Duration::from_secs(expiry as u64 * 10 * 60))
     }
+
+    /// Returns the largest htlc that can occupy a single congestion slot on a channel with the capacity provided, or
+    /// zero if the channel has no congestion resources.
+    pub fn congestion_htlc_limit(&self, capacity_msat: u64) -> u64 {
+        let slot_count = 483 * self.congestion_slot_portion as u64 / 100;
+        let liquidity_msat = capacity_msat * self.congestion_liquidity_portion as u64 / 100;
+        if slot_count == 0 || liquidity_msat == 0 {
+            return 0;
+        }
+
+        u64::min(
+            liquidity_msat,
+            u64::max(
+                liquidity_msat / slot_count,
+                MINIMUM_CONGESTION_SLOT_LIQUDITY,
+            ),
+        )
+    }
 }
 
 /// Defines special actions that can be taken during a simulation that wouldn't otherwise be used in regular operation.
@@ -59,10 +80,22 @@ pub trait SimulationDebugManager {
     fn congestion_jam_channel(&self, channel: u64) -> Result<(), ReputationError>;
 }
 
+/// The outcome of adding a htlc to a [`ForwardManager`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObservedOutcome {
+    /// The outcome that should be applied to the htlc.
+    pub enforced: ForwardingOutcome,
+    /// The outcome that the manager would have applied if it was enforcing its decisions. This will only differ from
+    /// the enforced outcome for observe-only managers.
+    pub would_be: ForwardingOutcome,
+}
+
 /// Implements outgoing reputation algorithm and resource bucketing for an individual node.
 #[derive(Debug)]
 pub struct ForwardManager {
     params: ForwardManagerParams,
+    /// Observe-only managers forward every htlc, tracking htlcs that they would have failed in an overflow bucket.
+    observe_only: bool,
     inner: Mutex<ForwardManagerImpl>,
 }
 
@@ -70,6 +103,8 @@ pub struct ForwardManager {
 struct ForwardManagerImpl {
     channels: HashMap<u64, TrackedChannel>,
     htlcs: InFlightManager,
+    /// Seeds the salt used to assign general bucket slots for each channel that is added.
+    rng: StdRng,
 }
 
 impl ForwardManagerImpl {
@@ -109,10 +144,11 @@ impl ForwardManagerImpl {
                 revenue_threshold: incoming_revenue_threshold,
                 in_flight_total_risk: self.htlcs.channel_in_flight_risk(
                     ChannelFilter::OutgoingChannel(forward.outgoing_channel_id),
+                    forward.added_height,
                 ),
                 htlc_risk: self
                     .htlcs
-                    .htlc_risk(forward.fee_msat(), forward.expiry_in_height),
+                    .htlc_risk(forward.fee_msat(), forward.expiry_delta()),
             },
             general_eligible: incoming_channel
                 .incoming_direction
@@ -185,17 +221,110 @@ impl ForwardManagerImpl {
 }
 
 impl ForwardManager {
-    pub fn new(params: ForwardManagerParams) -> Self {
+    /// Creates a forward manager with the parameters provided. All randomness used by the manager is derived from the
+    /// seed, so managers with the same seed that are provided with the same sequence of channels and forwards will
+    /// make the same decisions.
+    pub fn new(params: ForwardManagerParams, seed: u64) -> Self {
         assert!(params.general_slot_portion + params.congestion_slot_portion < 100);
         assert!(params.general_liquidity_portion + params.congestion_liquidity_portion < 100);
         Self {
             params,
+            observe_only: false,
             inner: Mutex::new(ForwardManagerImpl {
                 channels: HashMap::new(),
                 htlcs: InFlightManager::new(params.reputation_params),
+                rng: StdRng::seed_from_u64(seed),
             }),
         }
     }
+
+    /// Creates a forward manager that never fails htlcs, so that it can be deployed without affecting traffic. Htlcs
+    /// are tracked in the bucket they would have been assigned to, or in an overflow bucket if they would have been
+    /// failed, so that reputation and revenue are updated as usual. Use [`Self::add_observed_htlc`] to get the outcome
+    /// that would have been applied to each htlc.
+    pub fn new_observe_only(params: ForwardManagerParams, seed: u64) -> Self {
+        Self {
+            observe_only: true,
+            ..Self::new(params, seed)
+        }
+    }
+
+    /// Adds a htlc to the manager, returning the outcome that should be applied to the htlc and the outcome that would
+    /// have been applied if the manager was enforcing its decisions. See [`ReputationManager::add_htlc`].
+    pub fn add_observed_htlc(
+        &self,
+        forward: &ProposedForward,
+    ) -> Result<ObservedOutcome, ReputationError> {
+        let mut inner_lock = self
+            .inner
+            .lock()
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
+
+        let allocation_check = inner_lock.get_allocation_snapshot(forward)?;
+
+        let fwd_outcome = allocation_check.inner_forwarding_outcome(
+            forward.amount_in_msat,
+            forward.incoming_accountable,
+            forward.upgradable_accountability,
+        );
+
+        let (bucket, outgoing_accountable, would_be) = match fwd_outcome {
+            Ok(fwd_sucess) => {
+                // Add to our inner channel's bucket, failing if we can't add the HTLC. We've just
+                // checked our forwarding outcome and our state is locked so this should always
+                // succeed.
+                if fwd_sucess.bucket == ResourceBucketType::General
+                    && !inner_lock
+                        .channels
+                        .get_mut(&forward.incoming_ref.channel_id)
+                        .ok_or(ReputationError::ErrIncomingNotFound(
+                            forward.incoming_ref.channel_id,
+                        ))?
+                        .incoming_direction
+                        .general_bucket
+                        .add_htlc(forward.outgoing_channel_id, forward.amount_in_msat)?
+                {
+                    return Err(ReputationError::ErrUnrecoverable("Could not assign HTLC previously considered eligible with internal lock held - we have a bug!".to_string()));
+                }
+
+                (
+                    fwd_sucess.bucket,
+                    fwd_sucess.accountable_signal,
+                    ForwardingOutcome::Forward(fwd_sucess.accountable_signal),
+                )
+            }
+            // Observe-only managers forward htlcs that would have failed with the signal they were received with.
+            Err(e) if self.observe_only => (
+                ResourceBucketType::Overflow,
+                forward.incoming_accountable,
+                ForwardingOutcome::Fail(e),
+            ),
+            Err(e) => {
+                return Ok(ObservedOutcome {
+                    enforced: ForwardingOutcome::Fail(e.clone()),
+                    would_be: ForwardingOutcome::Fail(e),
+                })
+            }
+        };
+
+        inner_lock.htlcs.add_htlc(
+            forward.incoming_ref,
+            InFlightHtlc {
+                outgoing_channel_id: forward.outgoing_channel_id,
+                expiry_in_height: forward.expiry_in_height,
+                incoming_amt_msat: forward.amount_in_msat,
+                fee_msat: forward.fee_msat(),
+                added_instant: forward.added_at,
+                outgoing_accountable,
+                bucket,
+            },
+        )?;
+
+        Ok(ObservedOutcome {
+            enforced: ForwardingOutcome::Forward(outgoing_accountable),
+            would_be,
+        })
+    }
 }
 
 impl SimulationDebugManager for ForwardManager {
@@ -232,13 +361,13 @@ impl ReputationManager for ForwardManager {
         add_ins: Instant,
         channel_reputation: Option<ChannelSnapshot>,
     ) -> Result<(), ReputationError> {
-        match self
+        let mut inner_lock = self
             .inner
             .lock()
-            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?
-            .channels
-            .entry(channel_id)
-        {
+            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
+        let salt_seed = inner_lock.rng.random();
+
+        match inner_lock.channels.entry(channel_id) {
             Entry::Occupied(_) => Err(ReputationError::ErrChannelExists(channel_id)),
             Entry::Vacant(v) => {
                 let general_slot_count = 483 * self.params.general_slot_portion as u16 / 100;
@@ -278,20 +407,23 @@ impl ReputationManager for ForwardManager {
                     incoming_direction: IncomingChannel::new(
                         &self.params.reputation_params,
                         channel_id,
-                        BucketParameters {
-                            slot_count: general_slot_count,
-                            liquidity_msat: general_liquidity_amount,
-                        },
-                        BucketParameters {
-                            slot_count: congestion_slot_count,
-                            liquidity_msat: congestion_liquidity_amount,
-                        },
-                        BucketParameters {
-                            slot_count: protected_slot_count,
-                            liquidity_msat: protected_liquidity_amount,
+                        ChannelBuckets {
+                            general: BucketParameters {
+                                slot_count: general_slot_count,
+                                liquidity_msat: general_liquidity_amount,
+                            },
+                            congestion: BucketParameters {
+                                slot_count: congestion_slot_count,
+                                liquidity_msat: congestion_liquidity_amount,
+                            },
+                            protected: BucketParameters {
+                                slot_count: protected_slot_count,
+                                liquidity_msat: protected_liquidity_amount,
+                            },
                         },
                         add_ins,
-                        channel_reputation.map(|snapshot| (snapshot.incoming_revenue)),
+                        channel_reputation.map(|snapshot| snapshot.incoming_revenue),
+                        salt_seed,
                     )?,
                     outgoing_direction: OutgoingChannel::new(
                         self.params.reputation_params,
@@ -323,6 +455,9 @@ impl ReputationManager for ForwardManager {
                 .remove_channel(channel_id);
         }
 
+        // Any htlcs still in flight on the channel will be resolved on-chain, so we no longer track them.
+        inner_lock.htlcs.remove_channel_htlcs(channel_id);
+
         inner_lock
             .channels
             .remove(&channel_id)
@@ -341,56 +476,8 @@ impl ReputationManager for ForwardManager {
     }
 
     fn add_htlc(&self, forward: &ProposedForward) -> Result<ForwardingOutcome, ReputationError> {
-        let mut inner_lock = self
-            .inner
-            .lock()
-            .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
-
-        let allocation_check = inner_lock.get_allocation_snapshot(forward)?;
-
-        let fwd_outcome = allocation_check.inner_forwarding_outcome(
-            forward.amount_in_msat,
-            forward.incoming_accountable,
-            forward.upgradable_accountability,
-        );
-
-        let fwd_outcome = match fwd_outcome {
-            Ok(fwd_sucess) => {
-                // Add to our inner channel's bucket, failing if we can't add the HTLC. We've just
-                // checked our forwarding outcome and our state is locked so this should always
-                // succeed.
-                if fwd_sucess.bucket == ResourceBucketType::General
-                    && !inner_lock
-                        .channels
-                        .get_mut(&forward.incoming_ref.channel_id)
-                        .ok_or(ReputationError::ErrIncomingNotFound(
-                            forward.incoming_ref.channel_id,
-                        ))?
-                        .incoming_direction
-                        .general_bucket
-                        .add_htlc(forward.outgoing_channel_id, forward.amount_in_msat)?
-                {
-                    return Err(ReputationError::ErrUnrecoverable("Could not assign HTLC previously considered eligible with internal lock held - we have a bug!".to_string()));
-                }
-
-                inner_lock.htlcs.add_htlc(
-                    forward.incoming_ref,
-                    InFlightHtlc {
-                        outgoing_channel_id: forward.outgoing_channel_id,
-                        hold_blocks: forward.expiry_in_height,
-                        incoming_amt_msat: forward.amount_in_msat,
-                        fee_msat: forward.fee_msat(),
-                        added_instant: forward.added_at,
-                        outgoing_accountable: fwd_sucess.accountable_signal,
-                        bucket: fwd_sucess.bucket,
-                    },
-                )?;
-                ForwardingOutcome::Forward(fwd_sucess.accountable_signal)
-            }
-            Err(e) => ForwardingOutcome::Fail(e),
-        };
-
-        Ok(fwd_outcome)
+        self.add_observed_htlc(forward)
+            .map(|outcome| outcome.enforced)
     }
 
     fn resolve_htlc(
@@ -487,15 +574,15 @@ mod tests {
 
     use super::ForwardManagerParams;
     use crate::{
-        forward_manager::{ForwardManager, SimulationDebugManager},
-        AccountableSignal, ChannelSnapshot, FailureReason, ForwardingOutcome, HtlcRef,
-        ProposedForward, ReputationError, ReputationManager, ReputationParams,
+        forward_manager::{ForwardManager, ObservedOutcome, SimulationDebugManager},
+        AccountableSignal, ChannelSnapshot, FailureReason, ForwardResolution, ForwardingOutcome,
+        HtlcRef, ProposedForward, ReputationError, ReputationManager, ReputationParams,
     };
 
     #[test]
     fn test_jamming_helpers() {
         // Sanity check that jamming helpers set the appropriate bucket resources to 0.
-        let fwd_manager = ForwardManager::new(test_forward_manager_params());
+        let fwd_manager = ForwardManager::new(test_forward_manager_params(), 0);
 
         let now = Instant::now();
         let channel_capacity = 10_000_000;
@@ -527,6 +614,62 @@ mod tests {
         assert!(channel_0.congestion_bucket.slot_count == 0);
     }
 
+    /// Tests that forward managers created with the same seed assign the same general bucket slots, regardless of the
+    /// order that candidate channels are queried in.
+    #[test]
+    fn test_seeded_slot_assignment() {
+        let now = Instant::now();
+        let channel_capacity = 10_000_000;
+
+        let managers: Vec<ForwardManager> = [5, 5, 6]
+            .into_iter()
+            .map(|seed| {
+                let manager = ForwardManager::new(test_forward_manager_params(), seed);
+                for scid in 0..3 {
+                    manager
+                        .add_channel(scid, channel_capacity, now, None)
+                        .unwrap();
+                }
+                manager
+            })
+            .collect();
+
+        let slots = |manager: &ForwardManager, incoming: u64, outgoing: u64| {
+            manager
+                .inner
+                .lock()
+                .unwrap()
+                .channels
+                .get_mut(&incoming)
+                .unwrap()
+                .incoming_direction
+                .general_bucket
+                .get_candidate_slots(outgoing)
+                .unwrap()
+        };
+
+        let slots_0_1 = slots(&managers[0], 0, 1);
+        let slots_1_2 = slots(&managers[0], 1, 2);
+        assert_eq!(slots(&managers[1], 1, 2), slots_1_2);
+        assert_eq!(slots(&managers[1], 0, 1), slots_0_1);
+        assert_ne!(slots(&managers[2], 0, 1), slots_0_1);
+    }
+
+    #[test]
+    fn test_congestion_htlc_limit() {
+        let mut params = test_forward_manager_params();
+
+        // Large channels share congestion liquidity evenly between slots.
+        assert_eq!(params.congestion_htlc_limit(10_000_000_000), 20_833_333);
+
+        // Smaller channels are allowed the minimum slot liquidity, limited to the bucket's total liquidity.
+        assert_eq!(params.congestion_htlc_limit(100_000_000), 15_000_000);
+        assert_eq!(params.congestion_htlc_limit(10_000_000), 2_000_000);
+
+        params.congestion_slot_portion = 0;
+        assert_eq!(params.congestion_htlc_limit(10_000_000_000), 0);
+    }
+
     fn test_forward_manager_params() -> ForwardManagerParams {
         ForwardManagerParams {
             reputation_params: ReputationParams {
@@ -547,7 +690,7 @@ mod tests {
         let params = test_forward_manager_params();
         let now = Instant::now();
 
-        let fwd_manager = ForwardManager::new(params);
+        let fwd_manager = ForwardManager::new(params, 0);
 
         let channel_capacity = 10_000_000;
         assert!(fwd_manager
@@ -611,17 +754,60 @@ mod tests {
             amount_out_msat: 10_000 - 100,
             expiry_in_height: 80,
             expiry_out_height: 40,
+            added_height: 0,
             added_at: Instant::now(),
             incoming_accountable: accountable,
             upgradable_accountability: true,
         }
     }
 
+    /// Tests that htlcs in flight on a channel are no longer tracked once it is removed.
+    #[test]
+    fn test_remove_channel_in_flight() {
+        let params = test_forward_manager_params();
+        let now = Instant::now();
+        let fwd_manager = ForwardManager::new(params, 0);
+
+        for scid in 0..3 {
+            fwd_manager
+                .add_channel(scid, 10_000_000, now, None)
+                .unwrap();
+        }
+
+        let htlc_1 = test_proposed_forward(0, 1, 1, AccountableSignal::Unaccountable);
+        assert_eq!(
+            fwd_manager.add_htlc(&htlc_1).unwrap(),
+            ForwardingOutcome::Forward(AccountableSignal::Unaccountable)
+        );
+
+        let htlc_2 = test_proposed_forward(0, 2, 2, AccountableSignal::Unaccountable);
+        let check = fwd_manager.get_allocation_snapshot(&htlc_2).unwrap();
+        assert_eq!(check.resource_check.general_bucket.slots_used, 1);
+        assert_eq!(
+            check.resource_check.general_bucket.liquidity_used_msat,
+            10_000
+        );
+
+        // Once the outgoing channel is removed, its htlc no longer occupies resources on the incoming channel.
+        fwd_manager.remove_channel(1).unwrap();
+        let check = fwd_manager.get_allocation_snapshot(&htlc_2).unwrap();
+        assert_eq!(check.resource_check.general_bucket.slots_used, 0);
+        assert_eq!(check.resource_check.general_bucket.liquidity_used_msat, 0);
+
+        assert!(matches!(
+            fwd_manager
+                .resolve_htlc(1, htlc_1.incoming_ref, ForwardResolution::Settled, now)
+                .err()
+                .unwrap(),
+            ReputationError::ErrForwardNotFound(1, _)
+        ));
+    }
+
     #[test]
     fn test_add_htlc_incoming_unaccountable() {
         let params = test_forward_manager_params();
         let now = Instant::now();
-        let fwd_manager = ForwardManager::new(params);
+        let fwd_manager = ForwardManager::new(params, 0);
 
         let channel_capacity = 10_000_000;
         fwd_manager
@@ -684,7 +870,7 @@ mod tests {
     fn test_add_htlc_incoming_accountable() {
         let params = test_forward_manager_params();
         let now = Instant::now();
-        let fwd_manager = ForwardManager::new(params);
+        let fwd_manager = ForwardManager::new(params, 0);
 
         let channel_capacity = 10_000_000;
         fwd_manager
@@ -727,4 +913,123 @@ mod tests {
         let fwd_outcome = fwd_manager.add_htlc(&htlc_2).unwrap();
         assert!(fwd_outcome == ForwardingOutcome::Forward(AccountableSignal::Accountable));
     }
+
+    /// Tests that an observe-only manager forwards htlcs that it would have failed, tracking them without occupying
+    /// any of the channel's resources and updating revenue when they settle.
+    #[test]
+    fn test_add_htlc_observe_only() {
+        let params = test_forward_manager_params();
+        let now = Instant::now();
+        let fwd_manager = ForwardManager::new_observe_only(params, 0);
+
+        let channel_capacity = 10_000_000;
+        fwd_manager
+            .add_channel(0, channel_capacity, now, None)
+            .unwrap();
+        fwd_manager
+            .add_channel(1, channel_capacity, now, None)
+            .unwrap();
+
+        // Htlcs that would have been forwarded are assigned to the bucket they would have used.
+        let htlc_1 = test_proposed_forward(0, 1, 1, AccountableSignal::Unaccountable);
+        assert_eq!(
+            fwd_manager.add_observed_htlc(&htlc_1).unwrap(),
+            ObservedOutcome {
+                enforced: ForwardingOutcome::Forward(AccountableSignal::Unaccountable),
+                would_be: ForwardingOutcome::Forward(AccountableSignal::Unaccountable),
+            }
+        );
+
+        // An accountable htlc for a peer with no reputation would fail, but is forwarded with its incoming signal.
+        let htlc_2 = test_proposed_forward(0, 1, 2, AccountableSignal::Accountable);
+        assert_eq!(
+            fwd_manager.add_observed_htlc(&htlc_2).unwrap(),
+            ObservedOutcome {
+                enforced: ForwardingOutcome::Forward(AccountableSignal::Accountable),
+                would_be: ForwardingOutcome::Fail(FailureReason::NoReputation),
+            }
+        );
+        assert_eq!(
+            fwd_manager
+                .add_htlc(&test_proposed_forward(
+                    0,
+                    1,
+                    3,
+                    AccountableSignal::Accountable
+                ))
+                .unwrap(),
+            ForwardingOutcome::Forward(AccountableSignal::Accountable)
+        );
+
+        // Overflow htlcs don't occupy any bucket's resources.
+        let check = fwd_manager
+            .get_allocation_snapshot(&test_proposed_forward(
+                0,
+                1,
+                4,
+                AccountableSignal::Accountable,
+            ))
+            .unwrap();
+        assert_eq!(check.resource_check.general_bucket.slots_used, 1);
+        assert_eq!(check.resource_check.congestion_bucket.slots_used, 0);
+        assert_eq!(check.resource_check.protected_bucket.slots_used, 0);
+
+        // Overflow htlcs are resolved as usual, updating the incoming channel's revenue when they settle.
+        fwd_manager
+            .resolve_htlc(1, htlc_2.incoming_ref, ForwardResolution::Settled, now)
+            .unwrap();
+        let channels = fwd_manager.list_channels(now).unwrap();
+        assert!(channels.get(&0).unwrap().incoming_revenue > 0);
+    }
+
+    /// Tests that htlc risk is calculated using the number of blocks remaining until the htlc's expiry, and that
+    /// htlcs that have already expired are rejected.
+    #[test]
+    fn test_htlc_risk_remaining_expiry() {
+        let params = test_forward_manager_params();
+        let now = Instant::now();
+        let fwd_manager = ForwardManager::new(params, 0);
+
+        let snapshot = ChannelSnapshot {
+            capacity_msat: 10_000_000,
+            outgoing_reputation: 10_000_000,
+            incoming_revenue: 1_000_000,
+        };
+        fwd_manager
+            .add_channel(0, 10_000_000, now, Some(snapshot.clone()))
+            .unwrap();
+        fwd_manager
+            .add_channel(1, 10_000_000, now, Some(snapshot))
+            .unwrap();
+
+        let mut htlc_1 = test_proposed_forward(0, 1, 1, AccountableSignal::Accountable);
+        htlc_1.added_height = 30;
+        let check = fwd_manager.get_allocation_snapshot(&htlc_1).unwrap();
+        assert_eq!(
+            check.reputation_check.htlc_risk,
+            params.reputation_params.htlc_risk(100, 50)
+        );
+        assert_eq!(check.reputation_check.in_flight_total_risk, 0);
+
+        assert_eq!(
+            fwd_manager.add_htlc(&htlc_1).unwrap(),
+            ForwardingOutcome::Forward(AccountableSignal::Accountable)
+        );
+
+        // Once blocks have passed, the htlc in flight contributes risk for its remaining blocks.
+        let mut htlc_2 = test_proposed_forward(0, 1, 2, AccountableSignal::Accountable);
+        htlc_2.added_height = 60;
+        let check = fwd_manager.get_allocation_snapshot(&htlc_2).unwrap();
+        assert_eq!(
+            check.reputation_check.in_flight_total_risk,
+            params.reputation_params.htlc_risk(100, 20)
+        );
+
+        // A htlc that has already expired cannot be forwarded.
+        htlc_2.added_height = htlc_2.expiry_in_height;
+        assert_eq!(
+            fwd_manager.add_htlc(&htlc_2).err().unwrap(),
+            ReputationError::ErrExpiredHtlc(htlc_2.expiry_in_height, htlc_2.added_height)
+        );
+    }
 }
@@ -2,20 +2,22 @@ use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
+
 use crate::{validate_msat, AccountableSignal, HtlcRef, ReputationError, ResourceBucketType};
 
 #[derive(Clone, Debug)]
 pub(super) struct InFlightHtlc {
     pub(super) outgoing_channel_id: u64,
     pub(super) fee_msat: u64,
-    pub(super) hold_blocks: u32,
+    pub(super) expiry_in_height: u32,
     pub(super) incoming_amt_msat: u64,
     pub(super) added_instant: Instant,
     pub(super) outgoing_accountable: AccountableSignal,
     pub(super) bucket: ResourceBucketType,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ReputationParams {
     /// The period of time that revenue should be tracked to determine the threshold for reputation decisions.
     pub revenue_window: Duration,
@@ -128,8 +130,20 @@ impl InFlightManager {
             ))
     }
 
-    /// Returns the total htlc risk of all the accountable htlcs that a channel currently has in-flight on our channels.
-    pub(super) fn channel_in_flight_risk(&self, filter: ChannelFilter) -> u64 {
+    /// Removes all htlcs that use the channel provided as their incoming or outgoing channel, returning the number of
+    /// htlcs removed. Used when a channel is closed with htlcs still in flight, as they'll be resolved on-chain.
+    pub(super) fn remove_channel_htlcs(&mut self, channel_id: u64) -> usize {
+        let start_count = self.in_flight.len();
+        self.in_flight.retain(|htlc_ref, htlc| {
+            htlc_ref.channel_id != channel_id && htlc.outgoing_channel_id != channel_id
+        });
+
+        start_count - self.in_flight.len()
+    }
+
+    /// Returns the total htlc risk of all the accountable htlcs that a channel currently has in-flight on our channels,
+    /// assuming that each htlc will be held until it expires relative to the current block height provided.
+    pub(super) fn channel_in_flight_risk(&self, filter: ChannelFilter, height: u32) -> u64 {
         self.in_flight
             .iter()
             .filter(|(k, v)| {
@@ -143,7 +157,10 @@ impl InFlightManager {
                     ChannelFilter::OutgoingChannel(scid) => v.outgoing_channel_id == scid,
                 }
             })
-            .map(|(_, v)| self.params.htlc_risk(v.fee_msat, v.hold_blocks))
+            .map(|(_, v)| {
+                self.params
+                    .htlc_risk(v.fee_msat, v.expiry_in_height.saturating_sub(height))
+            })
             .sum()
     }
 
@@ -219,7 +236,7 @@ mod tests {
     ) -> InFlightHtlc {
         InFlightHtlc {
             outgoing_channel_id: outgoing_channel,
-            hold_blocks: 1000,
+            expiry_in_height: 1000,
             incoming_amt_msat: 2000,
             fee_msat,
             added_instant: Instant::now(),
@@ -332,11 +349,11 @@ mod tests {
         let channel_2 = 2;
 
         assert_eq!(
-            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_0)),
+            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_0), 0),
             0
         );
         assert_eq!(
-            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_1)),
+            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_1), 0),
             0
         );
 
@@ -348,7 +365,7 @@ mod tests {
         let htlc_1 = get_test_htlc(channel_1, true, ResourceBucketType::Protected, 1000);
         let htlc_1_risk = tracker
             .params
-            .htlc_risk(htlc_1.fee_msat, htlc_1.hold_blocks);
+            .htlc_risk(htlc_1.fee_msat, htlc_1.expiry_in_height);
 
         // Accountable htlc contribute to in flight risk and count despite general bucket, 0 -> 1.
         let htlc_2_ref = HtlcRef {
@@ -358,7 +375,7 @@ mod tests {
         let htlc_2 = get_test_htlc(channel_0, true, ResourceBucketType::General, 5000);
         let htlc_2_risk = tracker
             .params
-            .htlc_risk(htlc_2.fee_msat, htlc_2.hold_blocks);
+            .htlc_risk(htlc_2.fee_msat, htlc_2.expiry_in_height);
 
         // Unaccountable htlc no contribution to in flight risk, 1 -> 2.
         let htlc_3_ref = HtlcRef {
@@ -375,7 +392,7 @@ mod tests {
         let htlc_4 = get_test_htlc(channel_2, true, ResourceBucketType::Congestion, 1250);
         let htlc_4_risk = tracker
             .params
-            .htlc_risk(htlc_4.fee_msat, htlc_4.hold_blocks);
+            .htlc_risk(htlc_4.fee_msat, htlc_4.expiry_in_height);
 
         assert!(tracker.add_htlc(htlc_1_ref, htlc_1).is_ok());
         assert!(tracker.add_htlc(htlc_2_ref, htlc_2).is_ok());
@@ -383,28 +400,43 @@ mod tests {
         assert!(tracker.add_htlc(htlc_4_ref, htlc_4).is_ok());
 
         assert_eq!(
-            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_0)),
+            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_0), 0),
             htlc_2_risk,
         );
         assert_eq!(
-            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_1)),
+            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_1), 0),
             htlc_1_risk,
         );
         assert_eq!(
-            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_2)),
+            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_2), 0),
             htlc_4_risk, // Unaccountable does not contribute to risk, so no htlc_3.
         );
 
         assert_eq!(
-            tracker.channel_in_flight_risk(ChannelFilter::IncomingChannel(channel_0)),
+            tracker.channel_in_flight_risk(ChannelFilter::IncomingChannel(channel_0), 0),
             htlc_1_risk,
         );
         assert_eq!(
-            tracker.channel_in_flight_risk(ChannelFilter::IncomingChannel(channel_1)),
+            tracker.channel_in_flight_risk(ChannelFilter::IncomingChannel(channel_1), 0),
             htlc_2_risk + htlc_4_risk, // Unaccountable does not contribute to risk, so no htlc_3.
         );
         assert_eq!(
-            tracker.channel_in_flight_risk(ChannelFilter::IncomingChannel(channel_2)),
+            tracker.channel_in_flight_risk(ChannelFilter::IncomingChannel(channel_2), 0),
+            0,
+        );
+
+        // As the block height advances, htlcs have less time left until expiry so contribute less risk.
+        let half_height = 500;
+        assert_eq!(
+            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_1), half_height),
+            tracker.params.htlc_risk(1000, 1000 - half_height),
+        );
+        assert_eq!(
+            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_1), 1000),
+            0,
+        );
+        assert_eq!(
+            tracker.channel_in_flight_risk(ChannelFilter::OutgoingChannel(channel_1), 2000),
             0,
         );
     }
@@ -1,6 +1,7 @@
 use bitcoin::hashes::sha256d::Hash as Sha256dHash;
 use bitcoin::hashes::Hash;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
@@ -116,6 +117,14 @@ pub struct BucketParameters {
     pub liquidity_msat: u64,
 }
 
+/// The sizes of each of the resource buckets on an incoming channel.
+#[derive(Clone, Debug)]
+pub(super) struct ChannelBuckets {
+    pub(super) general: BucketParameters,
+    pub(super) congestion: BucketParameters,
+    pub(super) protected: BucketParameters,
+}
+
 /// Tracks resources available on the channel when it is utilized as the incoming direction in a htlc forward.
 #[derive(Debug)]
 pub(super) struct IncomingChannel {
@@ -138,17 +147,16 @@ impl IncomingChannel {
     pub(super) fn new(
         params: &ReputationParams,
         scid: u64,
-        general_bucket: BucketParameters,
-        congestion_bucket: BucketParameters,
-        protected_bucket: BucketParameters,
+        buckets: ChannelBuckets,
         start_ins: Instant,
         // Starting state for revenue.
         start_state: Option<i64>,
+        salt_seed: u64,
     ) -> Result<Self, ReputationError> {
         Ok(Self {
-            general_bucket: GeneralBucket::new(scid, general_bucket)?,
-            congestion_bucket,
-            protected_bucket,
+            general_bucket: GeneralBucket::new(scid, buckets.general, salt_seed)?,
+            congestion_bucket: buckets.congestion,
+            protected_bucket: buckets.protected,
             revenue: RevenueAverage::new(params, start_ins, start_state)?,
         })
     }
@@ -189,6 +197,9 @@ pub(super) struct GeneralBucket {
     // A u16 is used so that we can account for the possiblity that we assign our protocol max of
     // 483 slots, this can be changed to a u8 when only dealing with V3 channels.
     candidate_slots: HashMap<u64, [(u16, bool); ASSIGNED_SLOTS]>,
+    /// Generates the salt used to assign slots to each candidate channel. Seeded on creation so that slot assignment
+    /// is reproducible.
+    salt_rng: StdRng,
 }
 
 impl GeneralBucket {
@@ -197,7 +208,11 @@ impl GeneralBucket {
     /// Note that the current implementation is not restart safe:
     /// - It assigns new salt every time a channel is added (should be persisted across restarts).
     /// - It assumes that the bucket is empty on start (should account for in-flight HTLCs).
-    pub(super) fn new(scid: u64, params: BucketParameters) -> Result<Self, ReputationError> {
+    pub(super) fn new(
+        scid: u64,
+        params: BucketParameters,
+        salt_seed: u64,
+    ) -> Result<Self, ReputationError> {
         let slot_size_msat = params.liquidity_msat / params.slot_count as u64;
         if slot_size_msat == 0 {
             return Err(ReputationError::ErrUnrecoverable(format!(
@@ -213,20 +228,31 @@ impl GeneralBucket {
             htlc_slots: vec![false; params.slot_count as usize],
             slot_size_msat,
             candidate_slots: HashMap::new(),
+            salt_rng: StdRng::seed_from_u64(salt_seed),
         })
     }
 
-    /// Removes a channel from internal state, returning a boolean indicating whether anything
-    /// was remove from state.
+    /// Removes a channel from internal state, releasing any slots that its htlcs occupy and
+    /// returning a boolean indicating whether anything was remove from state.
     pub(super) fn remove_channel(&mut self, candidate_scid: u64) -> bool {
-        self.candidate_slots.remove(&candidate_scid).is_some()
+        match self.candidate_slots.remove(&candidate_scid) {
+            Some(channel_slots) => {
+                for (index, occupied) in channel_slots {
+                    if occupied {
+                        self.htlc_slots[index as usize] = false;
+                    }
+                }
+                true
+            }
+            None => false,
+        }
     }
 
     /// Produces the set of slots that a channel has permission to use.
     /// Assumes that [`self.htlc_slots`] has been initialized with values set for each slot.
     /// Retries up to ASSIGNED_SLOTS * 2 times to avoid duplicates, then fails (as it's highly
     /// improbably that we can't get non-duplicates after that many attempts).
-    fn get_candidate_slots(
+    pub(super) fn get_candidate_slots(
         &mut self,
         candidate_scid: u64,
     ) -> Result<[u16; ASSIGNED_SLOTS], ReputationError> {
@@ -240,9 +266,8 @@ impl GeneralBucket {
         match self.candidate_slots.entry(candidate_scid) {
             Entry::Occupied(entry) => Ok(entry.get().map(|slot| slot.0)),
             Entry::Vacant(entry) => {
-                let mut rng = rand::rng();
                 let mut salt = [0u8; 32];
-                rng.fill(&mut salt);
+                self.salt_rng.fill(&mut salt);
 
                 let mut result = [(0u16, false); ASSIGNED_SLOTS];
                 let mut assigned_count = 0;
@@ -457,18 +482,36 @@ mod tests {
 
     #[test]
     fn test_new_bucket() {
-        let bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         assert_eq!(bucket.slot_size_msat, 10_000);
         assert_eq!(bucket.htlc_slots.len(), 100);
         assert!(bucket.htlc_slots.iter().all(|b| !*b));
     }
 
+    /// Tests that buckets created with the same seed assign the same slots to candidate channels.
+    #[test]
+    fn test_candidate_slots_seeded() {
+        let mut bucket_1 = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 42).unwrap();
+        let mut bucket_2 = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 42).unwrap();
+        let mut bucket_3 = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 43).unwrap();
+
+        for scid in [345, 678] {
+            let slots = bucket_1.get_candidate_slots(scid).unwrap();
+            assert_eq!(slots, bucket_2.get_candidate_slots(scid).unwrap());
+            assert_ne!(slots, bucket_3.get_candidate_slots(scid).unwrap());
+        }
+    }
+
     #[test]
     fn test_channel_already_removed() {
         let scid = 456;
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         bucket.add_htlc(scid, 1).unwrap();
+        assert!(bucket.htlc_slots.iter().any(|b| *b));
+
+        // Removing a channel with htlcs in flight releases its slots.
         assert!(bucket.remove_channel(scid));
+        assert!(bucket.htlc_slots.iter().all(|b| !*b));
         assert!(!bucket.remove_channel(scid));
     }
 
@@ -480,13 +523,14 @@ mod tests {
                 slot_count: 200,
                 liquidity_msat: 100,
             },
+            0,
         );
         assert!(matches!(result, Err(ReputationError::ErrUnrecoverable(_))));
     }
 
     #[test]
     fn test_candidate_slots_existing() {
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         let scid = 456;
         let slots = [1, 2, 3, 4, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
         bucket
@@ -498,7 +542,7 @@ mod tests {
     #[test]
     fn test_candidate_slots_self() {
         let bucket_scid = 123;
-        let mut bucket = GeneralBucket::new(bucket_scid, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(bucket_scid, TEST_BUCKET_PARAMS, 0).unwrap();
         assert!(matches!(
             bucket.get_candidate_slots(bucket_scid),
             Err(ReputationError::ErrUnrecoverable(_))
@@ -507,7 +551,7 @@ mod tests {
 
     #[test]
     fn test_get_candidate_slots_consistency() {
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         let scid = 789;
         let slots1 = bucket.get_candidate_slots(scid).unwrap();
         let slots2 = bucket.get_candidate_slots(scid).unwrap();
@@ -516,7 +560,7 @@ mod tests {
 
     #[test]
     fn test_get_candidate_slots_within_bounds_and_unique() {
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         let scid = 789;
         let slots = bucket.get_candidate_slots(scid).unwrap();
         for &slot in &slots {
@@ -528,7 +572,7 @@ mod tests {
 
     #[test]
     fn test_add_htlc_successful_allocation() {
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         let scid = 456;
         let htlc_amt = 1000;
 
@@ -548,7 +592,7 @@ mod tests {
     /// Tests that a single HTLC is allowed to take up all liquidity for all slots.
     #[test]
     fn test_liquidity_one_htlc() {
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         let scid = 345;
 
         let max_htlc = bucket.slot_size_msat * ASSIGNED_SLOTS as u64;
@@ -563,7 +607,7 @@ mod tests {
     /// share that liquidity.
     #[test]
     fn test_partial_liquidity_usage() {
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         let scid = 345;
 
         // Add a HTLC that uses just over half of the of our liquidity, partially filling a bucket.
@@ -587,7 +631,7 @@ mod tests {
 
     #[test]
     fn test_insufficient_liquidity() {
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         let scid = 345;
         let htlc_too_big = bucket.slot_size_msat * ASSIGNED_SLOTS as u64 * 2;
 
@@ -596,7 +640,7 @@ mod tests {
 
     #[test]
     fn test_duplicate_remove_bug_addressed() {
-        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS).unwrap();
+        let mut bucket = GeneralBucket::new(123, TEST_BUCKET_PARAMS, 0).unwrap();
         let scid_1 = 345;
         let scid_2 = 678;
 
@@ -1,3 +1,4 @@
+pub mod baselines;
 mod decaying_average;
 pub mod forward_manager;
 pub use htlc_manager::ReputationParams;
@@ -40,6 +41,8 @@ pub enum ReputationError {
     ErrNegativeFee(u64, u64),
     /// Htlc has a negative cltv delta.
     ErrNegativeCltvDelta(u32, u32),
+    /// Htlc has already expired at the block height it was added at.
+    ErrExpiredHtlc(u32, u32),
     /// Channel has already been added.
     ErrChannelExists(u64),
     /// Channel has already been removed or was never tracked.
@@ -91,6 +94,9 @@ impl Display for ReputationError {
             ReputationError::ErrNegativeCltvDelta(incoming, outgoing) => {
                 write!(f, "incoming cltv: {incoming} < outgoing {outgoing}")
             }
+            ReputationError::ErrExpiredHtlc(expiry, height) => {
+                write!(f, "incoming cltv: {expiry} expired at height {height}")
+            }
             ReputationError::ErrChannelExists(chan_id) => {
                 write!(f, "channel {chan_id} already exists")
             }
@@ -183,6 +189,9 @@ pub enum ResourceBucketType {
     Protected,
     Congestion,
     General,
+    /// Pseudo-bucket for htlcs that would have been failed, but were forwarded by an observe-only manager. Htlcs in
+    /// this bucket are tracked for reputation and revenue, but don't occupy any of the channel's resources.
+    Overflow,
 }
 
 impl ResourceBucketType {
@@ -191,6 +200,7 @@ impl ResourceBucketType {
             ResourceBucketType::General => AccountableSignal::Unaccountable,
             ResourceBucketType::Congestion => AccountableSignal::Accountable,
             ResourceBucketType::Protected => AccountableSignal::Accountable,
+            ResourceBucketType::Overflow => AccountableSignal::Unaccountable,
         }
     }
 }
@@ -462,6 +472,8 @@ pub struct ProposedForward {
     pub amount_out_msat: u64,
     pub expiry_in_height: u32,
     pub expiry_out_height: u32,
+    /// The block height at which the htlc was added to the incoming channel.
+    pub added_height: u32,
     pub added_at: Instant,
     pub incoming_accountable: AccountableSignal,
     pub upgradable_accountability: bool,
@@ -505,6 +517,13 @@ impl ProposedForward {
             ));
         }
 
+        if self.expiry_in_height <= self.added_height {
+            return Err(ReputationError::ErrExpiredHtlc(
+                self.expiry_in_height,
+                self.added_height,
+            ));
+        }
+
         Ok(())
     }
 
@@ -512,6 +531,12 @@ impl ProposedForward {
     fn fee_msat(&self) -> u64 {
         self.amount_in_msat - self.amount_out_msat
     }
+
+    /// The number of blocks remaining until the incoming htlc expires, which is the longest that it can be held.
+    /// Only underflow safe after validation.
+    fn expiry_delta(&self) -> u32 {
+        self.expiry_in_height - self.added_height
+    }
 }
 
 /// Provides a snapshot of the reputation and revenue values tracked for a channel.
@@ -122,7 +122,7 @@ mod tests {
     ) -> InFlightHtlc {
         InFlightHtlc {
             outgoing_channel_id: 1,
-            hold_blocks: 1000,
+            expiry_in_height: 1000,
             incoming_amt_msat: 2000,
             fee_msat,
             added_instant: Instant::now(),
@@ -1,13 +1,17 @@
-use crate::BoxError;
+use crate::reputation_interceptor::ReputationMonitor;
+use crate::{count_reputation_pairs, BoxError};
 use async_trait::async_trait;
 use bitcoin::secp256k1::PublicKey;
 use csv::WriterBuilder;
-use ln_resource_mgr::{AllocationCheck, ForwardingOutcome, ProposedForward};
+use ln_resource_mgr::{
+    AccountableSignal, AllocationCheck, FailureReason, ForwardingOutcome, ProposedForward,
+};
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
-use std::collections::HashMap;
-use std::fs::{metadata, OpenOptions};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{metadata, File, OpenOptions};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 
 /// Implemented to report forwards for analytics and data recording.
@@ -19,6 +23,106 @@ pub trait ForwardReporter: Send + Sync {
         decision: AllocationCheck,
         forward: ProposedForward,
     ) -> Result<(), BoxError>;
+
+    /// Reports the outcomes that a node's shadow parameter sets would have given a forward, alongside the outcome that
+    /// was enforced by the node. Ignored by default.
+    async fn report_shadow_outcomes(
+        &mut self,
+        _forwarding_node: PublicKey,
+        _enforced: &ForwardingOutcome,
+        _shadows: &[ShadowOutcome],
+    ) -> Result<(), BoxError> {
+        Ok(())
+    }
+}
+
+/// The outcome that a shadow parameter set would have given a forward.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShadowOutcome {
+    pub name: String,
+    pub outcome: ForwardingOutcome,
+}
+
+/// The name used to report outcomes enforced by a node's primary forward manager.
+pub const PRIMARY_CONFIG: &str = "primary";
+
+/// Aggregates the outcomes that a single parameter set gave the forwards of a node.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct OutcomeBreakdown {
+    forwards: u64,
+    forwarded_unaccountable: u64,
+    forwarded_accountable: u64,
+    no_general_resources: u64,
+    no_resources: u64,
+    no_reputation: u64,
+    upgradable_signal_modified: u64,
+    /// The number of forwards where the outcome differs from the one enforced by the node.
+    disagreements: u64,
+}
+
+impl OutcomeBreakdown {
+    /// Records an outcome, counting a disagreement if it differs from the outcome that it's compared to.
+    pub(crate) fn record(&mut self, outcome: &ForwardingOutcome, enforced: &ForwardingOutcome) {
+        self.forwards += 1;
+        if outcome != enforced {
+            self.disagreements += 1;
+        }
+
+        match outcome {
+            ForwardingOutcome::Forward(AccountableSignal::Unaccountable) => {
+                self.forwarded_unaccountable += 1
+            }
+            ForwardingOutcome::Forward(AccountableSignal::Accountable) => {
+                self.forwarded_accountable += 1
+            }
+            ForwardingOutcome::Fail(FailureReason::NoGeneralResources) => {
+                self.no_general_resources += 1
+            }
+            ForwardingOutcome::Fail(FailureReason::NoResources) => self.no_resources += 1,
+            ForwardingOutcome::Fail(FailureReason::NoReputation) => self.no_reputation += 1,
+            ForwardingOutcome::Fail(FailureReason::UpgradableSignalModified) => {
+                self.upgradable_signal_modified += 1
+            }
+        }
+    }
+}
+
+/// A single row in an outcome breakdown file, flattened because csv can't write nested structs.
+#[derive(Serialize)]
+struct OutcomeRow<'a> {
+    node: &'a str,
+    config: &'a str,
+    forwards: u64,
+    forwarded_unaccountable: u64,
+    forwarded_accountable: u64,
+    no_general_resources: u64,
+    no_resources: u64,
+    no_reputation: u64,
+    upgradable_signal_modified: u64,
+    disagreements: u64,
+    disagreement_rate: f64,
+}
+
+impl<'a> OutcomeRow<'a> {
+    fn new(node: &'a str, config: &'a str, breakdown: &OutcomeBreakdown) -> Self {
+        OutcomeRow {
+            node,
+            config,
+            forwards: breakdown.forwards,
+            forwarded_unaccountable: breakdown.forwarded_unaccountable,
+            forwarded_accountable: breakdown.forwarded_accountable,
+            no_general_resources: breakdown.no_general_resources,
+            no_resources: breakdown.no_resources,
+            no_reputation: breakdown.no_reputation,
+            upgradable_signal_modified: breakdown.upgradable_signal_modified,
+            disagreements: breakdown.disagreements,
+            disagreement_rate: if breakdown.forwards == 0 {
+                0.0
+            } else {
+                breakdown.disagreements as f64 / breakdown.forwards as f64
+            },
+        }
+    }
 }
 
 struct Record {
@@ -36,7 +140,7 @@ impl Serialize for Record {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Record", 20)?;
+        let mut state = serializer.serialize_struct("Record", 27)?;
         state.serialize_field(
             "ts_offset_ns",
             &self
@@ -51,6 +155,7 @@ impl Serialize for Record {
         state.serialize_field("amount_out_msat", &self.forward.amount_out_msat)?;
         state.serialize_field("expiry_in_height", &self.forward.expiry_in_height)?;
         state.serialize_field("expiry_out_height", &self.forward.expiry_out_height)?;
+        state.serialize_field("added_height", &self.forward.added_height)?;
         state.serialize_field("incoming_accountable", &self.forward.incoming_accountable)?;
         let fwd_result = self.decision.inner_forwarding_outcome(
             self.forward.amount_in_msat,
@@ -81,10 +186,22 @@ impl Serialize for Record {
             "in_flight_risk",
             &self.decision.reputation_check.in_flight_total_risk,
         )?;
+        state.serialize_field(
+            "general_slots_used",
+            &self.decision.resource_check.general_bucket.slots_used,
+        )?;
         state.serialize_field(
             "general_slots_available",
             &self.decision.resource_check.general_bucket.slots_available,
         )?;
+        state.serialize_field(
+            "general_liquidity_used",
+            &self
+                .decision
+                .resource_check
+                .general_bucket
+                .liquidity_used_msat,
+        )?;
         state.serialize_field(
             "general_liquidity_available",
             &self
@@ -93,6 +210,10 @@ impl Serialize for Record {
                 .general_bucket
                 .liquidity_available_msat,
         )?;
+        state.serialize_field(
+            "congestion_slots_used",
+            &self.decision.resource_check.congestion_bucket.slots_used,
+        )?;
         state.serialize_field(
             "congestion_slots_available",
             &self
@@ -101,6 +222,14 @@ impl Serialize for Record {
                 .congestion_bucket
                 .slots_available,
         )?;
+        state.serialize_field(
+            "congestion_liquidity_used",
+            &self
+                .decision
+                .resource_check
+                .congestion_bucket
+                .liquidity_used_msat,
+        )?;
         state.serialize_field(
             "congestion_liquidity_available",
             &self
@@ -109,6 +238,10 @@ impl Serialize for Record {
                 .congestion_bucket
                 .liquidity_available_msat,
         )?;
+        state.serialize_field(
+            "protected_slots_used",
+            &self.decision.resource_check.protected_bucket.slots_used,
+        )?;
         state.serialize_field(
             "protected_slots_available",
             &self
@@ -117,6 +250,14 @@ impl Serialize for Record {
                 .protected_bucket
                 .slots_available,
         )?;
+        state.serialize_field(
+            "protected_liquidity_used",
+            &self
+                .decision
+                .resource_check
+                .protected_bucket
+                .liquidity_used_msat,
+        )?;
         state.serialize_field(
             "protected_liquidity_available",
             &self
@@ -138,6 +279,8 @@ pub struct BatchForwardWriter {
     record_count: u16,
     path: PathBuf,
     start_ins: Instant,
+    /// Outcome breakdowns for the enforced and shadow parameter sets of tracked nodes, keyed by (alias, config).
+    shadow_outcomes: BTreeMap<(String, String), OutcomeBreakdown>,
 }
 
 impl BatchForwardWriter {
@@ -157,6 +300,7 @@ impl BatchForwardWriter {
             record_count: 0,
             path,
             start_ins,
+            shadow_outcomes: BTreeMap::new(),
         }
     }
 
@@ -175,8 +319,35 @@ impl BatchForwardWriter {
         }
         self.record_count = 0;
 
+        // Shadow outcomes are aggregated over the whole run, so they're only written once we're forced to flush.
+        if force && !self.shadow_outcomes.is_empty() {
+            self.write_shadow_outcomes()?;
+        }
+
         Ok(())
     }
+
+    /// Writes the outcome breakdown for each tracked node's parameter sets to disk, overwriting any previous file.
+    fn write_shadow_outcomes(&self) -> Result<(), BoxError> {
+        write_outcome_breakdowns(&get_shadow_file(&self.path), &self.shadow_outcomes)
+    }
+}
+
+fn get_shadow_file(path: &Path) -> PathBuf {
+    path.join("shadow_outcomes.csv")
+}
+
+/// Writes outcome breakdowns keyed by (node, config) to the file provided, overwriting any previous file.
+pub(crate) fn write_outcome_breakdowns(
+    path: &Path,
+    breakdowns: &BTreeMap<(String, String), OutcomeBreakdown>,
+) -> Result<(), BoxError> {
+    let mut writer = WriterBuilder::new().from_writer(File::create(path)?);
+    for ((node, config), breakdown) in breakdowns.iter() {
+        writer.serialize(OutcomeRow::new(node, config, breakdown))?;
+    }
+
+    writer.flush().map_err(|e| e.into())
 }
 
 fn get_file(path: &Path, node: &PublicKey, alias: String) -> PathBuf {
@@ -219,19 +390,136 @@ impl ForwardReporter for BatchForwardWriter {
 
         Ok(())
     }
+
+    /// Aggregates the outcomes of a forward for each of a tracked node's parameter sets, including the one enforced.
+    async fn report_shadow_outcomes(
+        &mut self,
+        forwarding_node: PublicKey,
+        enforced: &ForwardingOutcome,
+        shadows: &[ShadowOutcome],
+    ) -> Result<(), BoxError> {
+        let alias = match self.nodes.get(&forwarding_node) {
+            Some((_, alias)) => alias.clone(),
+            None => return Ok(()),
+        };
+
+        self.shadow_outcomes
+            .entry((alias.clone(), PRIMARY_CONFIG.to_string()))
+            .or_default()
+            .record(enforced, enforced);
+
+        for shadow in shadows {
+            self.shadow_outcomes
+                .entry((alias.clone(), shadow.name.clone()))
+                .or_default()
+                .record(&shadow.outcome, enforced);
+        }
+
+        Ok(())
+    }
+}
+
+/// The name of the file that periodic reputation samples are written to in the results directory.
+pub const REPUTATION_SERIES_FILE: &str = "reputation_timeseries.csv";
+
+/// A single channel's reputation state when sampled, written in long format with one row per channel per sample.
+#[derive(Serialize)]
+struct ReputationSample<'a> {
+    ts_offset_ns: u128,
+    node: &'a str,
+    channel_id: u64,
+    outgoing_reputation: i64,
+    incoming_revenue: i64,
+    /// The number of the node's other channels that have sufficient reputation to use this channel as outgoing.
+    good_pairs: usize,
+    pair_count: usize,
+}
+
+/// Periodically samples the reputation of every channel for a set of nodes, so that reputation can be tracked over
+/// the course of a simulation for channels that aren't forwarding any traffic.
+pub struct ReputationSeriesWriter<R: ReputationMonitor> {
+    monitor: Arc<R>,
+    nodes: Vec<(PublicKey, String)>,
+    /// The risk that an incoming channel's revenue is increased by when assessing whether a pair has reputation.
+    risk_margin: u64,
+    path: PathBuf,
+    start_ins: Instant,
+}
+
+impl<R: ReputationMonitor> ReputationSeriesWriter<R> {
+    pub fn new(
+        monitor: Arc<R>,
+        nodes: Vec<(PublicKey, String)>,
+        risk_margin: u64,
+        path: PathBuf,
+        start_ins: Instant,
+    ) -> Self {
+        Self {
+            monitor,
+            nodes,
+            risk_margin,
+            path,
+            start_ins,
+        }
+    }
+
+    /// Appends a sample of every channel's reputation for each tracked node to disk.
+    pub async fn sample(&self, access_ins: Instant) -> Result<(), BoxError> {
+        let file_exists = metadata(&self.path).is_ok();
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+
+        let mut writer = WriterBuilder::new()
+            .has_headers(!file_exists)
+            .from_writer(file);
+
+        let ts_offset_ns = access_ins.duration_since(self.start_ins).as_nanos();
+        for (pubkey, alias) in self.nodes.iter() {
+            let channels = self.monitor.list_channels(*pubkey, access_ins).await?;
+
+            let mut scids: Vec<&u64> = channels.keys().collect();
+            scids.sort();
+
+            for scid in scids {
+                let snapshot = &channels[scid];
+                writer.serialize(ReputationSample {
+                    ts_offset_ns,
+                    node: alias,
+                    channel_id: *scid,
+                    outgoing_reputation: snapshot.outgoing_reputation,
+                    incoming_revenue: snapshot.incoming_revenue,
+                    good_pairs: count_reputation_pairs(&channels, *scid, self.risk_margin)?,
+                    pair_count: channels.len() - 1,
+                })?;
+            }
+        }
+
+        writer.flush().map_err(|e| e.into())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::fs::read_to_string;
     use std::path::{Path, PathBuf};
     use std::str::FromStr;
-    use std::time::{Instant, SystemTime};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant, SystemTime};
 
-    use crate::analysis::get_file;
-    use crate::test_utils::{get_random_keypair, test_allocation_check, test_proposed_forward};
+    use ln_resource_mgr::{AccountableSignal, ChannelSnapshot, FailureReason, ForwardingOutcome};
 
-    use super::{BatchForwardWriter, ForwardReporter};
+    use crate::analysis::{get_file, get_shadow_file};
+    use crate::test_utils::{
+        get_random_keypair, test_allocation_check, test_proposed_forward, MockReputationInterceptor,
+    };
+
+    use super::{
+        BatchForwardWriter, ForwardReporter, OutcomeBreakdown, ReputationSeriesWriter,
+        ShadowOutcome, PRIMARY_CONFIG,
+    };
 
     /// Tests that only forwards on nodes of interest are queued for writing.
     #[tokio::test]
@@ -361,4 +649,150 @@ mod tests {
 
         std::fs::remove_file(filename).unwrap();
     }
+
+    /// Tests aggregation of shadow outcomes for tracked nodes, and that they're only written to disk on a forced
+    /// write.
+    #[tokio::test]
+    async fn test_report_shadow_outcomes() {
+        let node_0 = get_random_keypair().1;
+        let node_1 = get_random_keypair().1;
+
+        let path = std::env::temp_dir().join(format!(
+            "shadow_test_{}",
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos(),
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+
+        let mut writer = BatchForwardWriter::new(
+            path.clone(),
+            &[(node_0, "0".to_string())],
+            5,
+            Instant::now(),
+        );
+
+        let forward = ForwardingOutcome::Forward(AccountableSignal::Unaccountable);
+        let fail = ForwardingOutcome::Fail(FailureReason::NoReputation);
+        let shadow = |outcome: &ForwardingOutcome| {
+            vec![ShadowOutcome {
+                name: "strict".to_string(),
+                outcome: outcome.clone(),
+            }]
+        };
+
+        // Shadow agrees, then disagrees with the enforced outcome.
+        writer
+            .report_shadow_outcomes(node_0, &forward, &shadow(&forward))
+            .await
+            .unwrap();
+        writer
+            .report_shadow_outcomes(node_0, &forward, &shadow(&fail))
+            .await
+            .unwrap();
+
+        // Non-tracked node ignored.
+        writer
+            .report_shadow_outcomes(node_1, &forward, &shadow(&fail))
+            .await
+            .unwrap();
+
+        assert_eq!(writer.shadow_outcomes.len(), 2);
+        assert_eq!(
+            writer.shadow_outcomes[&("0".to_string(), PRIMARY_CONFIG.to_string())],
+            OutcomeBreakdown {
+                forwards: 2,
+                forwarded_unaccountable: 2,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            writer.shadow_outcomes[&("0".to_string(), "strict".to_string())],
+            OutcomeBreakdown {
+                forwards: 2,
+                forwarded_unaccountable: 1,
+                no_reputation: 1,
+                disagreements: 1,
+                ..Default::default()
+            }
+        );
+
+        // Only written to disk on a forced write, with a header and a row per config.
+        let filename = get_shadow_file(&path);
+        writer.write(false).unwrap();
+        assert!(!filename.exists());
+
+        writer.write(true).unwrap();
+        let contents = read_to_string(&filename).unwrap();
+        assert_eq!(contents.lines().count(), 3);
+        assert!(contents.contains("0,strict,2,1,0,0,0,1,0,1,0.5"));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    /// Tests that reputation samples are appended to disk with a single header, and that good pairs are counted for
+    /// each channel as outgoing.
+    #[tokio::test]
+    async fn test_reputation_series_sample() {
+        let node = get_random_keypair().1;
+        let mut monitor = MockReputationInterceptor::new();
+        monitor.expect_list_channels().returning(|_, _| {
+            Ok(HashMap::from([
+                (
+                    1,
+                    ChannelSnapshot {
+                        capacity_msat: 200_000,
+                        outgoing_reputation: 100,
+                        incoming_revenue: 10,
+                    },
+                ),
+                (
+                    2,
+                    ChannelSnapshot {
+                        capacity_msat: 200_000,
+                        outgoing_reputation: 5,
+                        incoming_revenue: 50,
+                    },
+                ),
+            ]))
+        });
+
+        let path = PathBuf::from(format!(
+            "test_reputation_series_{}.csv",
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos(),
+        ));
+
+        let start_ins = Instant::now();
+        let writer = ReputationSeriesWriter::new(
+            Arc::new(monitor),
+            vec![(node, "0".to_string())],
+            0,
+            path.clone(),
+            start_ins,
+        );
+
+        writer.sample(start_ins).await.unwrap();
+        writer
+            .sample(start_ins + Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        let contents = read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            contents.lines().collect::<Vec<_>>(),
+            vec![
+                "ts_offset_ns,node,channel_id,outgoing_reputation,incoming_revenue,good_pairs,pair_count",
+                "0,0,1,100,10,1,1",
+                "0,0,2,5,50,0,1",
+                "1000000000,0,1,100,10,1,1",
+                "1000000000,0,2,5,50,0,1",
+            ]
+        );
+    }
 }
@@ -1,61 +1,187 @@
+use crate::attacks::costs::CostTracker;
 use crate::attacks::JammingAttack;
-use crate::reputation_interceptor::ReputationMonitor;
+use crate::clock::{BlockClock, InstantClock};
+use crate::reputation_interceptor::{
+    ChannelCloser, ChannelOpener, FailureReporter, ReputationMonitor,
+};
+use crate::revenue_interceptor::ForceCloseRecorder;
+use crate::topology::ChannelTopology;
+use crate::{print_request, BoxError};
 use async_trait::async_trait;
 use bitcoin::secp256k1::PublicKey;
+use ln_resource_mgr::HtlcRef;
 use simln_lib::sim_node::{
     CriticalError, CustomRecords, ForwardingError, InterceptRequest, InterceptResolution,
     Interceptor,
 };
 use std::sync::Arc;
+use tokio::select;
 
 /// Wraps an innner reputation interceptor (which is responsible for implementing a mitigation to
 /// channel jamming) in an outer interceptor which can be used to take custom actions for attacks.
 #[derive(Clone)]
 pub struct AttackInterceptor<R>
 where
-    R: Interceptor + ReputationMonitor,
+    R: Interceptor + ReputationMonitor + ChannelOpener + ChannelCloser,
 {
     attacker_pubkeys: Vec<PublicKey>,
     /// Inner reputation monitor that implements jamming mitigation.
     reputation_interceptor: Arc<R>,
     /// The attack that will be launched.
     attack: Arc<dyn JammingAttack + Send + Sync>,
+    /// Used to detect htlcs that the attacker holds close to their expiry height.
+    block_clock: BlockClock,
+    /// The number of blocks before a htlc held by the attacker expires that the upstream node will force close the
+    /// channel to resolve the htlc on-chain.
+    force_close_margin: u32,
+    /// Records the force closes caused by attackers.
+    cost_tracker: Arc<CostTracker>,
+    /// Charges the on-chain fees of force closes to the revenue of the closing node.
+    force_close_recorder: Arc<dyn ForceCloseRecorder + Send + Sync>,
+    /// Tracks channels that are closed, so that htlcs over them can be failed.
+    topology: Arc<ChannelTopology<R>>,
+    /// Optionally notified of htlcs that are failed because they're forwarded over a closed channel.
+    failure_reporter: Option<Arc<dyn FailureReporter>>,
 }
 
 impl<R> AttackInterceptor<R>
 where
-    R: Interceptor + ReputationMonitor,
+    R: Interceptor + ReputationMonitor + ChannelOpener + ChannelCloser,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         attacker_pubkeys: Vec<PublicKey>,
         reputation_interceptor: Arc<R>,
         attack: Arc<dyn JammingAttack + Send + Sync>,
+        block_clock: BlockClock,
+        force_close_margin: u32,
+        cost_tracker: Arc<CostTracker>,
+        force_close_recorder: Arc<dyn ForceCloseRecorder + Send + Sync>,
+        topology: Arc<ChannelTopology<R>>,
     ) -> Self {
         Self {
             attacker_pubkeys,
             reputation_interceptor,
             attack,
+            block_clock,
+            force_close_margin,
+            cost_tracker,
+            force_close_recorder,
+            topology,
+            failure_reporter: None,
+        }
+    }
+
+    /// Reports htlcs that are failed because they're forwarded over a closed channel to the reporter provided.
+    pub fn with_failure_reporter(mut self, reporter: Arc<dyn FailureReporter>) -> Self {
+        self.failure_reporter = Some(reporter);
+        self
+    }
+
+    /// Force closes a channel on which the attacker is holding a htlc close to its expiry, as the upstream node would
+    /// to resolve the htlc on-chain. The channel is only closed once, even if the attacker holds multiple htlcs on it,
+    /// and the on-chain fees for the close are charged to the upstream node.
+    async fn force_close_channel(
+        &self,
+        attacker: &PublicKey,
+        channel: u64,
+    ) -> Result<(), BoxError> {
+        let closing_node = self.topology.channel_peer(channel, attacker)?;
+        if !self.topology.close_channel(channel).await? {
+            return Ok(());
         }
+
+        let close_cost = self.cost_tracker.record_force_close(attacker)?;
+        self.force_close_recorder
+            .record_force_close(&closing_node, close_cost)
+            .await;
+
+        Ok(())
     }
 }
 
 #[async_trait]
 impl<R> Interceptor for AttackInterceptor<R>
 where
-    R: Interceptor + ReputationMonitor,
+    R: Interceptor + ReputationMonitor + ChannelOpener + ChannelCloser,
 {
     /// Implemented by HTLC interceptors that provide input on the resolution of HTLCs forwarded in the simulation.
+    /// If the attacker is still holding a htlc when it reaches the force close margin of its expiry height, the
+    /// upstream node force closes the channel and the htlc is failed back. As the attack no longer gets to resolve
+    /// the htlc, its hold is recorded here instead.
     async fn intercept_htlc(
         &self,
         req: InterceptRequest,
     ) -> Result<Result<CustomRecords, ForwardingError>, CriticalError> {
+        let incoming_channel: u64 = req.incoming_htlc.channel_id.into();
+        if self
+            .topology
+            .any_closed(&[
+                Some(incoming_channel),
+                req.outgoing_channel_id.map(|c| c.into()),
+            ])
+            .map_err(|e| CriticalError::InterceptorError(e.to_string()))?
+        {
+            if let Some(reporter) = &self.failure_reporter {
+                reporter.report_closed_channel(
+                    req.forwarding_node,
+                    HtlcRef {
+                        channel_id: incoming_channel,
+                        htlc_index: req.incoming_htlc.index,
+                    },
+                )?;
+            }
+
+            return Ok(Err(ForwardingError::InterceptorError(
+                "channel force closed".into(),
+            )));
+        }
+
         if self.attacker_pubkeys.contains(&req.forwarding_node) {
-            return match req.outgoing_channel_id {
+            let expiry_height = self
+                .block_clock
+                .current_height()
+                .saturating_add(req.incoming_expiry_height);
+            let close_height = expiry_height.saturating_sub(self.force_close_margin);
+            let attacker = req.forwarding_node;
+            let amount_msat = req.incoming_amount_msat;
+            let req_str = print_request(&req);
+            let start = InstantClock::now(&self.block_clock);
+
+            let attack_res = match req.outgoing_channel_id {
                 Some(_) => self.attack.intercept_attacker_htlc(req),
                 None => self.attack.intercept_attacker_receive(req),
-            }
-            .await
-            .map_err(|e| CriticalError::InterceptorError(e.to_string()));
+            };
+
+            // Prefer the attacker's decision if the htlc is resolved at the same time as the force close height is
+            // reached.
+            return select! {
+                biased;
+
+                res = attack_res => res.map_err(|e| CriticalError::InterceptorError(e.to_string())),
+                _ = self.block_clock.sleep_until_height(close_height) => {
+                    log::warn!(
+                        "Attacker held HTLC until height {close_height} with expiry {expiry_height}, force closing \
+                        channel {incoming_channel}: {req_str}"
+                    );
+
+                    self.cost_tracker
+                        .record_hold(
+                            &attacker,
+                            amount_msat,
+                            InstantClock::now(&self.block_clock).duration_since(start),
+                        )
+                        .map_err(|e| CriticalError::InterceptorError(e.to_string()))?;
+
+                    self.force_close_channel(&attacker, incoming_channel)
+                        .await
+                        .map_err(|e| CriticalError::InterceptorError(e.to_string()))?;
+
+                    Ok(Err(ForwardingError::InterceptorError(format!(
+                        "channel {incoming_channel} force closed at height {close_height}"
+                    ))))
+                }
+            };
         }
 
         // If attacker is not involved, use jamming interceptor to implement reputation and
@@ -72,6 +198,19 @@ where
             return Ok(());
         }
 
+        // Htlcs on force closed channels are resolved on-chain, and are no longer tracked by the reputation
+        // interceptor.
+        if self
+            .topology
+            .any_closed(&[
+                Some(res.incoming_htlc.channel_id.into()),
+                res.outgoing_channel_id.map(|c| c.into()),
+            ])
+            .map_err(|e| CriticalError::InterceptorError(e.to_string()))?
+        {
+            return Ok(());
+        }
+
         self.reputation_interceptor.notify_resolution(res).await
     }
 
@@ -85,17 +224,29 @@ mod tests {
     use std::collections::HashMap;
     use std::sync::Arc;
 
+    use crate::attacks::costs::{CostParams, CostTracker};
     use crate::attacks::JammingAttack;
-    use crate::test_utils::{get_random_keypair, setup_test_request, MockReputationInterceptor};
+    use crate::clock::BlockClock;
+    use crate::reputation_interceptor::FailureReporter;
+    use crate::test_utils::{
+        get_random_keypair, setup_test_edge, setup_test_request, MockPeacetimeMonitor,
+        MockReputationInterceptor,
+    };
+    use crate::topology::{ChannelTopology, TopologyController};
     use crate::NetworkReputation;
     use crate::{records_from_signal, BoxError};
     use async_trait::async_trait;
-    use ln_resource_mgr::AccountableSignal;
+    use bitcoin::secp256k1::PublicKey;
+    use ln_resource_mgr::{AccountableSignal, FailureReason, HtlcRef};
     use mockall::mock;
-    use mockall::predicate::function;
+    use mockall::predicate::{always, eq, function};
+    use simln_lib::clock::SimulationClock;
     use simln_lib::sim_node::{
-        CustomRecords, ForwardingError, InterceptRequest, Interceptor, SimGraph, SimNode,
+        CriticalError, CustomRecords, ForwardingError, InterceptRequest, InterceptResolution,
+        Interceptor, SimGraph, SimNode,
     };
+    use simln_lib::ShortChannelID;
+    use std::time::Duration;
     use triggered::Listener;
 
     use super::AttackInterceptor;
@@ -107,12 +258,46 @@ mod tests {
             fn validate(&self) -> Result<(), BoxError>;
             async fn intercept_attacker_htlc(&self, req: InterceptRequest) -> Result<Result<CustomRecords, ForwardingError>, BoxError>;
             async fn intercept_attacker_receive(&self,_req: InterceptRequest) -> Result<Result<CustomRecords, ForwardingError>, BoxError>;
-            async fn run_attack(&self, _start_reputation: NetworkReputation, attacker_nodes: HashMap<String, Arc<tokio::sync::Mutex<SimNode<SimGraph, simln_lib::clock::SimulationClock>>>>, shutdown_listener: Listener) -> Result<(), BoxError>;
+            async fn run_attack(&self, _start_reputation: HashMap<PublicKey, NetworkReputation>, attacker_nodes: HashMap<String, Arc<tokio::sync::Mutex<SimNode<SimGraph, simln_lib::clock::SimulationClock>>>>, shutdown_listener: Listener) -> Result<(), BoxError>;
             fn attack_statistics(&self) -> Result<crate::attacks::AttackStatisitcs, BoxError>;
         }
     }
 
-    fn setup_interceptor_test() -> AttackInterceptor<MockReputationInterceptor> {
+    const TEST_FORCE_CLOSE_MARGIN: u32 = 10;
+
+    fn test_cost_tracker(attacker_pubkey: PublicKey) -> Arc<CostTracker> {
+        Arc::new(CostTracker::new(
+            CostParams::default(),
+            &[],
+            &[("attacker".to_string(), attacker_pubkey)],
+        ))
+    }
+
+    fn test_block_clock() -> BlockClock {
+        BlockClock::new(Arc::new(SimulationClock::new(1).unwrap()), None)
+    }
+
+    /// Creates a topology containing a single channel with scid 0 between the upstream node and attacker provided,
+    /// with no scheduled events.
+    async fn test_topology(
+        mock: MockReputationInterceptor,
+        upstream_pubkey: PublicKey,
+        attacker_pubkey: PublicKey,
+    ) -> Arc<ChannelTopology<MockReputationInterceptor>> {
+        let edges = vec![setup_test_edge(
+            ShortChannelID::from(0),
+            upstream_pubkey,
+            attacker_pubkey,
+        )];
+
+        Arc::new(
+            ChannelTopology::new(Arc::new(mock), &edges, &[])
+                .await
+                .unwrap(),
+        )
+    }
+
+    async fn setup_interceptor_test() -> AttackInterceptor<MockReputationInterceptor> {
         let attacker_pubkey = get_random_keypair().1;
 
         let mock = MockReputationInterceptor::new();
@@ -120,6 +305,16 @@ mod tests {
             vec![attacker_pubkey],
             Arc::new(mock),
             Arc::new(MockAttack::new()),
+            test_block_clock(),
+            TEST_FORCE_CLOSE_MARGIN,
+            test_cost_tracker(attacker_pubkey),
+            Arc::new(MockPeacetimeMonitor::new()),
+            test_topology(
+                MockReputationInterceptor::new(),
+                get_random_keypair().1,
+                attacker_pubkey,
+            )
+            .await,
         )
     }
 
@@ -154,6 +349,16 @@ mod tests {
             vec![attacker_pubkey],
             Arc::new(MockReputationInterceptor::new()),
             Arc::new(mock_attack),
+            test_block_clock(),
+            TEST_FORCE_CLOSE_MARGIN,
+            test_cost_tracker(attacker_pubkey),
+            Arc::new(MockPeacetimeMonitor::new()),
+            test_topology(
+                MockReputationInterceptor::new(),
+                get_random_keypair().1,
+                attacker_pubkey,
+            )
+            .await,
         );
 
         // Intercepted on attacker: target -(0)-> attacker -(5)-> node.
@@ -199,6 +404,16 @@ mod tests {
             vec![attacker_pubkey],
             Arc::new(MockReputationInterceptor::new()),
             Arc::new(mock_attack),
+            test_block_clock(),
+            TEST_FORCE_CLOSE_MARGIN,
+            test_cost_tracker(attacker_pubkey),
+            Arc::new(MockPeacetimeMonitor::new()),
+            test_topology(
+                MockReputationInterceptor::new(),
+                get_random_keypair().1,
+                attacker_pubkey,
+            )
+            .await,
         );
 
         let mut attacker_receive = setup_test_request(
@@ -218,7 +433,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_peer_to_target_accountable() {
-        let mut interceptor = setup_interceptor_test();
+        let mut interceptor = setup_interceptor_test().await;
 
         // Intercepted on target's peer: node -(5) -> peer -(1)-> target, accountable payments just passed through.
         let peer_pubkey = get_random_keypair().1;
@@ -244,7 +459,7 @@ mod tests {
     /// sufficient reputation.
     #[tokio::test]
     async fn test_peer_to_target_upgraded() {
-        let mut interceptor = setup_interceptor_test();
+        let mut interceptor = setup_interceptor_test().await;
 
         let peer_pubkey = get_random_keypair().1;
         let peer_to_target =
@@ -270,7 +485,7 @@ mod tests {
     /// be upgraded to accountable.
     #[tokio::test]
     async fn test_peer_to_target_general_jammed() {
-        let mut interceptor = setup_interceptor_test();
+        let mut interceptor = setup_interceptor_test().await;
 
         let peer_pubkey = get_random_keypair().1;
         let peer_to_target =
@@ -295,7 +510,7 @@ mod tests {
     /// Tests that forwards through the target node to its peers will be upgraded to accountable.
     #[tokio::test]
     async fn test_target_to_peer() {
-        let mut interceptor = setup_interceptor_test();
+        let mut interceptor = setup_interceptor_test().await;
 
         let target_pubkey = get_random_keypair().1;
         let target_forward = setup_test_request(
@@ -327,7 +542,7 @@ mod tests {
     /// Tests that forwards through the target node to the attacker will be upgraded to accountable.
     #[tokio::test]
     async fn test_target_to_attacker() {
-        let mut interceptor = setup_interceptor_test();
+        let mut interceptor = setup_interceptor_test().await;
 
         let target_pubkey = get_random_keypair().1;
         let target_forward = setup_test_request(
@@ -359,7 +574,7 @@ mod tests {
     /// Tests that forwards by the target sent from attacker -> target are handled like any other target payment.
     #[tokio::test]
     async fn test_target_from_attacker() {
-        let mut interceptor = setup_interceptor_test();
+        let mut interceptor = setup_interceptor_test().await;
 
         let target_pubkey = get_random_keypair().1;
         let not_actually_attacker =
@@ -380,4 +595,243 @@ mod tests {
             .unwrap()
             .unwrap();
     }
+
+    /// Records the htlcs that are reported as failed over closed channels.
+    #[derive(Default)]
+    struct ClosedChannelReporter {
+        closed: std::sync::Mutex<Vec<(PublicKey, HtlcRef)>>,
+    }
+
+    impl FailureReporter for ClosedChannelReporter {
+        fn report_failure(
+            &self,
+            _forwarding_node: PublicKey,
+            _incoming_ref: HtlcRef,
+            _reason: &FailureReason,
+        ) -> Result<(), CriticalError> {
+            panic!("unexpected resource manager failure");
+        }
+
+        fn report_closed_channel(
+            &self,
+            forwarding_node: PublicKey,
+            incoming_ref: HtlcRef,
+        ) -> Result<(), CriticalError> {
+            self.closed
+                .lock()
+                .unwrap()
+                .push((forwarding_node, incoming_ref));
+            Ok(())
+        }
+    }
+
+    /// An attack that holds every htlc it intercepts indefinitely.
+    struct HoldingAttack {}
+
+    #[async_trait]
+    impl JammingAttack for HoldingAttack {
+        async fn intercept_attacker_htlc(
+            &self,
+            _req: InterceptRequest,
+        ) -> Result<Result<CustomRecords, ForwardingError>, BoxError> {
+            tokio::time::sleep(Duration::from_secs(60 * 60 * 24 * 365)).await;
+            Ok(Ok(CustomRecords::new()))
+        }
+
+        async fn run_attack(
+            &self,
+            _start_reputation: HashMap<PublicKey, NetworkReputation>,
+            _attacker_nodes: HashMap<
+                String,
+                Arc<tokio::sync::Mutex<SimNode<SimGraph, SimulationClock>>>,
+            >,
+            _shutdown_listener: Listener,
+        ) -> Result<(), BoxError> {
+            Ok(())
+        }
+
+        fn attack_statistics(&self) -> Result<crate::attacks::AttackStatisitcs, BoxError> {
+            Err("not implemented".into())
+        }
+    }
+
+    /// Tests that the upstream node force closes its channel with the attacker if the attacker holds a htlc within the
+    /// force close margin of its expiry height, that the hold is charged to the attacker, that the on-chain fees for
+    /// the close are charged to the upstream node and that htlcs over the closed channel are subsequently failed and
+    /// reported.
+    #[tokio::test(start_paused = true)]
+    async fn test_attacker_hold_force_close() {
+        let attacker_pubkey = get_random_keypair().1;
+        let upstream_pubkey = get_random_keypair().1;
+        let block_clock = test_block_clock();
+        let cost_tracker = test_cost_tracker(attacker_pubkey);
+
+        let mut mock = MockReputationInterceptor::new();
+        mock.expect_close_channel()
+            .with(eq(0))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let mut recorder = MockPeacetimeMonitor::new();
+        recorder
+            .expect_record_force_close()
+            .with(
+                eq(upstream_pubkey),
+                eq(CostParams::default().force_close_cost_msat()),
+            )
+            .times(1)
+            .return_const(());
+
+        let reporter = Arc::new(ClosedChannelReporter::default());
+        let interceptor = AttackInterceptor::new(
+            vec![attacker_pubkey],
+            Arc::new(MockReputationInterceptor::new()),
+            Arc::new(HoldingAttack {}),
+            block_clock.clone(),
+            TEST_FORCE_CLOSE_MARGIN,
+            cost_tracker.clone(),
+            Arc::new(recorder),
+            test_topology(mock, upstream_pubkey, attacker_pubkey).await,
+        )
+        .with_failure_reporter(reporter.clone());
+
+        let mut req = setup_test_request(attacker_pubkey, 0, 5, AccountableSignal::Accountable);
+        req.incoming_expiry_height = 20;
+        req.outgoing_expiry_height = 15;
+
+        let res = interceptor.intercept_htlc(req).await.unwrap();
+        assert!(matches!(res, Err(ForwardingError::InterceptorError(_))));
+        assert_eq!(block_clock.current_height(), 20 - TEST_FORCE_CLOSE_MARGIN);
+
+        // The htlc was held from height zero until the force close, with the default ten minute block speed.
+        let cost = cost_tracker.costs().unwrap().remove(0);
+        assert_eq!(cost.htlcs_held, 1);
+        assert_eq!(
+            cost.liquidity_locked_msat_secs,
+            100 * (20 - TEST_FORCE_CLOSE_MARGIN as u128) * 600
+        );
+        assert_eq!(cost.force_closes_caused, 1);
+        assert_eq!(
+            cost.force_close_cost_msat,
+            CostParams::default().force_close_cost_msat()
+        );
+
+        // Htlcs over the closed channel are failed without reaching the attacker, and their resolution is not passed
+        // on to the reputation interceptor.
+        let peer_pubkey = get_random_keypair().1;
+        let peer_req = setup_test_request(peer_pubkey, 3, 0, AccountableSignal::Unaccountable);
+        let res = interceptor.intercept_htlc(peer_req.clone()).await.unwrap();
+        assert!(matches!(res, Err(ForwardingError::InterceptorError(_))));
+        assert_eq!(
+            *reporter.closed.lock().unwrap(),
+            vec![(
+                peer_pubkey,
+                HtlcRef {
+                    channel_id: 3,
+                    htlc_index: peer_req.incoming_htlc.index,
+                }
+            )]
+        );
+
+        interceptor
+            .notify_resolution(InterceptResolution {
+                forwarding_node: peer_pubkey,
+                incoming_htlc: peer_req.incoming_htlc,
+                outgoing_channel_id: peer_req.outgoing_channel_id,
+                success: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    /// An attack that closes a channel through the topology controller, and reopens it after a delay.
+    struct ChurnAttack<T: TopologyController + Send + Sync> {
+        topology: Arc<T>,
+        channel: u64,
+        reopen_after: Duration,
+    }
+
+    #[async_trait]
+    impl<T: TopologyController + Send + Sync> JammingAttack for ChurnAttack<T> {
+        async fn run_attack(
+            &self,
+            _start_reputation: HashMap<PublicKey, NetworkReputation>,
+            _attacker_nodes: HashMap<
+                String,
+                Arc<tokio::sync::Mutex<SimNode<SimGraph, SimulationClock>>>,
+            >,
+            _shutdown_listener: Listener,
+        ) -> Result<(), BoxError> {
+            assert!(self.topology.close_channel(self.channel).await?);
+            tokio::time::sleep(self.reopen_after).await;
+            self.topology.open_channel(self.channel).await
+        }
+
+        fn attack_statistics(&self) -> Result<crate::attacks::AttackStatisitcs, BoxError> {
+            Err("not implemented".into())
+        }
+    }
+
+    /// Tests that an attack can churn a channel through the topology controller: the channel is removed from and
+    /// added back to the forward managers of both nodes, htlcs over it are failed while it's closed and are passed on
+    /// to the reputation interceptor once it's reopened.
+    #[tokio::test(start_paused = true)]
+    async fn test_attack_churns_channel() {
+        let attacker_pubkey = get_random_keypair().1;
+        let upstream_pubkey = get_random_keypair().1;
+
+        let mut channel_manager = MockReputationInterceptor::new();
+        channel_manager
+            .expect_close_channel()
+            .with(eq(0))
+            .times(1)
+            .returning(|_| Ok(()));
+        channel_manager
+            .expect_open_channel()
+            .with(eq(0), always(), eq([upstream_pubkey, attacker_pubkey]))
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let topology = test_topology(channel_manager, upstream_pubkey, attacker_pubkey).await;
+
+        let reopen_after = Duration::from_secs(60);
+        let attack = Arc::new(ChurnAttack {
+            topology: topology.clone(),
+            channel: 0,
+            reopen_after,
+        });
+
+        let peer_pubkey = get_random_keypair().1;
+        let peer_req = setup_test_request(peer_pubkey, 3, 0, AccountableSignal::Unaccountable);
+        let mut reputation_interceptor = MockReputationInterceptor::new();
+        mock_intercept_htlc(&mut reputation_interceptor, &peer_req).await;
+
+        let interceptor = AttackInterceptor::new(
+            vec![attacker_pubkey],
+            Arc::new(reputation_interceptor),
+            attack.clone(),
+            test_block_clock(),
+            TEST_FORCE_CLOSE_MARGIN,
+            test_cost_tracker(attacker_pubkey),
+            Arc::new(MockPeacetimeMonitor::new()),
+            topology.clone(),
+        );
+
+        let (_shutdown, listener) = triggered::trigger();
+        let attack_task = tokio::spawn(async move {
+            attack
+                .run_attack(HashMap::new(), HashMap::new(), listener)
+                .await
+        });
+
+        // Once the attack has closed the channel, htlcs over it are failed without reaching the reputation
+        // interceptor.
+        tokio::time::sleep(reopen_after / 2).await;
+        assert!(topology.any_closed(&[Some(0)]).unwrap());
+        let res = interceptor.intercept_htlc(peer_req.clone()).await.unwrap();
+        assert!(matches!(res, Err(ForwardingError::InterceptorError(_))));
+
+        attack_task.await.unwrap().unwrap();
+        assert!(!topology.any_closed(&[Some(0)]).unwrap());
+        interceptor.intercept_htlc(peer_req).await.unwrap().unwrap();
+    }
 }
@@ -0,0 +1,343 @@
+use bitcoin::secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+use sim_cli::parsing::NetworkParser;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::BoxError;
+
+/// The approximate size of a channel open transaction with a single input and change output.
+pub const DEFAULT_CHANNEL_OPEN_VBYTES: u64 = 154;
+
+/// The approximate size of the transactions required to force close a channel with a single htlc in flight: the
+/// commitment transaction, an anchor spend to bump its fee and a htlc timeout transaction.
+pub const DEFAULT_FORCE_CLOSE_VBYTES: u64 = 500;
+
+/// The default fee rate used to estimate on-chain costs, expressed in sat/vbyte.
+pub const DEFAULT_ONCHAIN_FEE_RATE: u64 = 10;
+
+/// The default unconditional fee charged for every htlc, expressed as a percentage of its success-case routing
+/// fee.
+pub const DEFAULT_UNCONDITIONAL_FEE_PERCENT: u64 = 1;
+
+/// Parameters used to estimate the costs of an attack that are not captured by the simulator itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CostParams {
+    /// The unconditional (upfront) fee that an attacker would pay for every htlc that it sends, regardless of
+    /// whether it succeeds, expressed as a percentage of the htlc's success-case routing fee.
+    pub unconditional_fee_percent: u64,
+
+    /// The fee rate used to estimate on-chain costs, in sat/vbyte.
+    pub onchain_fee_rate: u64,
+
+    /// The size of a channel open transaction, in vbytes.
+    pub channel_open_vbytes: u64,
+
+    /// The size of the transactions required to force close a channel, in vbytes.
+    pub force_close_vbytes: u64,
+}
+
+impl Default for CostParams {
+    fn default() -> Self {
+        Self {
+            unconditional_fee_percent: DEFAULT_UNCONDITIONAL_FEE_PERCENT,
+            onchain_fee_rate: DEFAULT_ONCHAIN_FEE_RATE,
+            channel_open_vbytes: DEFAULT_CHANNEL_OPEN_VBYTES,
+            force_close_vbytes: DEFAULT_FORCE_CLOSE_VBYTES,
+        }
+    }
+}
+
+impl CostParams {
+    /// The on-chain cost of opening a single channel, in msat.
+    pub fn channel_open_cost_msat(&self) -> u64 {
+        self.channel_open_vbytes * self.onchain_fee_rate * 1000
+    }
+
+    /// The on-chain cost of force closing a single channel, in msat.
+    pub fn force_close_cost_msat(&self) -> u64 {
+        self.force_close_vbytes * self.onchain_fee_rate * 1000
+    }
+
+    /// The unconditional fee paid for a htlc with the routing fee provided, in msat.
+    pub fn unconditional_fee_msat(&self, routing_fee_msat: u64) -> u64 {
+        routing_fee_msat * self.unconditional_fee_percent / 100
+    }
+}
+
+/// Summarizes the costs incurred by an individual attacking node.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct AttackerCost {
+    pub alias: String,
+
+    /// The total capacity of the node's channels, which is committed for the duration of the attack.
+    pub channel_capacity_msat: u64,
+
+    /// The number of channels that the node has opened.
+    pub channel_count: u64,
+
+    /// The estimated on-chain cost of opening the node's channels.
+    pub onchain_open_cost_msat: u64,
+
+    /// The number of htlcs that the node held to jam resources.
+    pub htlcs_held: u64,
+
+    /// The amount of liquidity locked in held htlcs multiplied by the time it was held for.
+    pub liquidity_locked_msat_secs: u128,
+
+    /// Routing fees paid by the node for payments that it sent and succeeded.
+    pub fees_paid_msat: u64,
+
+    /// Unconditional fees that the node would have paid for every htlc that it sent.
+    pub unconditional_fees_msat: u64,
+
+    /// Forwarding fees that the node would have earned for payments that it dropped rather than forwarding.
+    pub lost_revenue_msat: u64,
+
+    /// The number of channels that were force closed because the node held a htlc close to its expiry.
+    pub force_closes_caused: u64,
+
+    /// The on-chain fees paid by the closing parties of the force closes that the node caused. This cost is borne by
+    /// the node's peers rather than the node itself, and is deducted from their revenue.
+    pub force_close_cost_msat: u64,
+}
+
+impl AttackerCost {
+    /// The total monetary cost to the attacking node, in msat. Does not include liquidity costs, as these are
+    /// expressed in msat*seconds and depend on the attacker's cost of capital, or the cost of force closes that it
+    /// caused, as these are paid by its peers.
+    pub fn total_msat(&self) -> u64 {
+        self.onchain_open_cost_msat
+            + self.fees_paid_msat
+            + self.unconditional_fees_msat
+            + self.lost_revenue_msat
+    }
+}
+
+/// Collects costs for each attacking node over the course of an attack. Shared between attack implementations and
+/// helpers so that all attacker actions are accounted for in a single place.
+#[derive(Debug)]
+pub struct CostTracker {
+    params: CostParams,
+    costs: Mutex<HashMap<PublicKey, AttackerCost>>,
+}
+
+impl CostTracker {
+    /// Creates a cost tracker for the attackers provided, counting the capacity and on-chain costs of their
+    /// channels in the network.
+    pub fn new(
+        params: CostParams,
+        network: &[NetworkParser],
+        attackers: &[(String, PublicKey)],
+    ) -> Self {
+        let mut costs: HashMap<PublicKey, AttackerCost> = attackers
+            .iter()
+            .map(|(alias, pubkey)| {
+                (
+                    *pubkey,
+                    AttackerCost {
+                        alias: alias.clone(),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        for channel in network {
+            let node_1 = costs.contains_key(&channel.node_1.pubkey);
+            let node_2 = costs.contains_key(&channel.node_2.pubkey);
+
+            // We assume that the node_1 attacker opened the channel if both nodes are attackers, so that we don't
+            // double count costs.
+            let opener = match (node_1, node_2) {
+                (true, _) => channel.node_1.pubkey,
+                (false, true) => channel.node_2.pubkey,
+                (false, false) => continue,
+            };
+
+            if let Some(cost) = costs.get_mut(&opener) {
+                cost.channel_capacity_msat += channel.capacity_msat;
+                cost.channel_count += 1;
+                cost.onchain_open_cost_msat += params.channel_open_cost_msat();
+            }
+        }
+
+        Self {
+            params,
+            costs: Mutex::new(costs),
+        }
+    }
+
+    /// Records a htlc sent by an attacker with the routing fees provided. Unconditional fees are charged regardless
+    /// of outcome, routing fees are only charged if the payment succeeded.
+    pub fn record_payment(
+        &self,
+        sender: &PublicKey,
+        fees_msat: u64,
+        success: bool,
+    ) -> Result<(), BoxError> {
+        let unconditional_fees = self.params.unconditional_fee_msat(fees_msat);
+        self.update(sender, |cost| {
+            cost.unconditional_fees_msat += unconditional_fees;
+            if success {
+                cost.fees_paid_msat += fees_msat;
+            }
+        })
+    }
+
+    /// Records a htlc held by an attacker for the duration provided. Attacks should provide the incoming amount of
+    /// the htlc, as that is the liquidity locked in the channel that the attacker received it on.
+    pub fn record_hold(
+        &self,
+        node: &PublicKey,
+        amount_msat: u64,
+        hold_time: Duration,
+    ) -> Result<(), BoxError> {
+        self.update(node, |cost| {
+            cost.htlcs_held += 1;
+            cost.liquidity_locked_msat_secs += amount_msat as u128 * hold_time.as_secs() as u128;
+        })
+    }
+
+    /// Records a forward that was dropped by an attacker, giving up the fee that it would have earned.
+    pub fn record_dropped_forward(&self, node: &PublicKey, fee_msat: u64) -> Result<(), BoxError> {
+        self.update(node, |cost| cost.lost_revenue_msat += fee_msat)
+    }
+
+    /// Records a channel force closed by the attacker's peer because the attacker held a htlc close to its expiry,
+    /// returning the on-chain fees for the close that should be charged to the closing party.
+    pub fn record_force_close(&self, node: &PublicKey) -> Result<u64, BoxError> {
+        let close_cost = self.params.force_close_cost_msat();
+        self.update(node, |cost| {
+            cost.force_closes_caused += 1;
+            cost.force_close_cost_msat += close_cost;
+        })?;
+
+        Ok(close_cost)
+    }
+
+    /// Returns the costs incurred by each attacker, sorted by alias.
+    pub fn costs(&self) -> Result<Vec<AttackerCost>, BoxError> {
+        let mut costs: Vec<AttackerCost> = self
+            .costs
+            .lock()
+            .map_err(|e| format!("cost tracker lock poisoned: {e}"))?
+            .values()
+            .cloned()
+            .collect();
+        costs.sort_by(|a, b| a.alias.cmp(&b.alias));
+
+        Ok(costs)
+    }
+
+    fn update<F: FnOnce(&mut AttackerCost)>(
+        &self,
+        node: &PublicKey,
+        update: F,
+    ) -> Result<(), BoxError> {
+        let mut costs = self
+            .costs
+            .lock()
+            .map_err(|e| format!("cost tracker lock poisoned: {e}"))?;
+
+        let cost = costs
+            .get_mut(node)
+            .ok_or(format!("attacker {node} not tracked for costs"))?;
+        update(cost);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bitcoin::secp256k1::PublicKey;
+    use sim_cli::parsing::NetworkParser;
+
+    use crate::test_utils::{get_random_keypair, get_test_policy};
+
+    use super::{CostParams, CostTracker};
+
+    fn edge(scid: u64, node_1: PublicKey, node_2: PublicKey) -> NetworkParser {
+        NetworkParser {
+            scid: scid.into(),
+            capacity_msat: 100_000,
+            node_1: get_test_policy(node_1),
+            node_2: get_test_policy(node_2),
+        }
+    }
+
+    /// Tests that channel costs are only counted once for channels between attackers, and not at all for channels
+    /// between honest nodes.
+    #[test]
+    fn test_channel_costs() {
+        let honest = get_random_keypair().1;
+        let attackers = vec![
+            ("a0".to_string(), get_random_keypair().1),
+            ("a1".to_string(), get_random_keypair().1),
+        ];
+
+        let network = vec![
+            edge(0, honest, attackers[0].1),
+            edge(1, attackers[0].1, attackers[1].1),
+            edge(2, attackers[1].1, honest),
+            edge(3, honest, get_random_keypair().1),
+        ];
+
+        let params = CostParams::default();
+        let open_cost = params.channel_open_cost_msat();
+        let costs = CostTracker::new(params, &network, &attackers)
+            .costs()
+            .unwrap();
+
+        assert_eq!(costs[0].channel_count, 2);
+        assert_eq!(costs[0].channel_capacity_msat, 200_000);
+        assert_eq!(costs[0].onchain_open_cost_msat, open_cost * 2);
+        assert_eq!(costs[1].channel_count, 1);
+        assert_eq!(costs[1].onchain_open_cost_msat, open_cost);
+    }
+
+    #[test]
+    fn test_record_costs() {
+        let attacker = get_random_keypair().1;
+        let tracker = CostTracker::new(
+            CostParams {
+                unconditional_fee_percent: 10,
+                onchain_fee_rate: 1,
+                channel_open_vbytes: 100,
+                force_close_vbytes: 200,
+            },
+            &[edge(0, attacker, get_random_keypair().1)],
+            &[("a0".to_string(), attacker)],
+        );
+
+        tracker.record_payment(&attacker, 1_000, true).unwrap();
+        tracker.record_payment(&attacker, 2_000, false).unwrap();
+        tracker
+            .record_hold(&attacker, 5_000, Duration::from_secs(10))
+            .unwrap();
+        tracker.record_dropped_forward(&attacker, 50).unwrap();
+        assert_eq!(tracker.record_force_close(&attacker).unwrap(), 200_000);
+
+        // Unknown attackers are rejected.
+        assert!(tracker
+            .record_payment(&get_random_keypair().1, 1_000, true)
+            .is_err());
+
+        let cost = tracker.costs().unwrap().remove(0);
+        assert_eq!(cost.fees_paid_msat, 1_000);
+        assert_eq!(cost.unconditional_fees_msat, 300);
+        assert_eq!(cost.htlcs_held, 1);
+        assert_eq!(cost.liquidity_locked_msat_secs, 50_000);
+        assert_eq!(cost.lost_revenue_msat, 50);
+        assert_eq!(cost.onchain_open_cost_msat, 100_000);
+        assert_eq!(cost.force_closes_caused, 1);
+        assert_eq!(cost.force_close_cost_msat, 200_000);
+
+        // Force close costs are paid by the closing party, so aren't included in the attacker's total.
+        assert_eq!(cost.total_msat(), 101_350);
+    }
+}
@@ -1,24 +1,57 @@
 use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
+use bitcoin::secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
 use simln_lib::clock::SimulationClock;
 use simln_lib::sim_node::{CustomRecords, ForwardingError, InterceptRequest, SimGraph, SimNode};
 use tokio::sync::Mutex;
 use triggered::Listener;
 
 use crate::{accountable_from_records, records_from_signal, BoxError, NetworkReputation};
+use costs::AttackerCost;
 
+pub mod costs;
+pub mod revenue_inflation;
 pub mod sink;
 pub mod slow_jam;
+pub mod sybil;
 pub mod utils;
 
 /// Summarizes actions taken during the attack.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct AttackStatisitcs {
-    /// The number of channels general jammed using [`reputation_interceptor::ChannelJammer`].
+    /// The number of channels general jammed, either using [`reputation_interceptor::ChannelJammer`] or with htlcs
+    /// sent by the attacker.
     pub general_jammed_channels: usize,
 
-    /// The number of channels congestion jammed using [`reputation_interceptor::ChannelJammer`].
+    /// The number of channels congestion jammed, either using [`reputation_interceptor::ChannelJammer`] or with htlcs
+    /// sent by the attacker.
     pub congestion_jammed_channels: usize,
+
+    /// Costs incurred by each individual attacking node.
+    pub attacker_costs: Vec<AttackerCost>,
+
+    /// The reputation that the target's honest outgoing peers have with the target over the course of the attack,
+    /// if tracked by the attack.
+    pub honest_peer_reputation: Option<ReputationChange>,
+}
+
+/// The number of (incoming, outgoing) channel pairs at a target on which its honest outgoing peers have good
+/// reputation.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HonestPairReputation {
+    /// The number of pairs that honest outgoing peers have good reputation on.
+    pub good_pairs: usize,
+    /// The total number of pairs with an honest outgoing peer.
+    pub pair_count: usize,
+}
+
+/// Tracks the change in honest peers' reputation pairs over the course of an attack.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReputationChange {
+    pub start: HonestPairReputation,
+    pub end: HonestPairReputation,
 }
 
 // Defines an attack that can be mounted against the simulation framework.
@@ -65,10 +98,12 @@ pub trait JammingAttack {
     /// complete, return from the method to trigger the simulation to shutdown. It should also
     /// listen for shutdown signals and terminate to avoid blocking the simulation shutdown.
     ///
+    /// The reputation of each target at the start of the attack is provided, keyed by the target's public key.
+    ///
     /// [`SimNode::send_to_route`]: simln_lib::sim_node::SimNode::send_to_route
     async fn run_attack(
         &self,
-        _start_reputation: NetworkReputation,
+        _start_reputation: HashMap<PublicKey, NetworkReputation>,
         _attacker_nodes: HashMap<String, Arc<Mutex<SimNode<SimGraph, SimulationClock>>>>,
         _shutdown_listener: Listener,
     ) -> Result<(), BoxError>;
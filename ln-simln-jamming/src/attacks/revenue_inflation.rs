@@ -0,0 +1,579 @@
+use async_trait::async_trait;
+use bitcoin::secp256k1::PublicKey;
+use lightning::routing::gossip::NetworkGraph;
+use sim_cli::parsing::NetworkParser;
+use simln_lib::clock::{Clock, SimulationClock};
+use simln_lib::sim_node::{SimGraph, SimNode, WrappedLog};
+use simln_lib::{LightningNode, PaymentOutcome};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::Mutex;
+use triggered::Listener;
+
+use crate::clock::InstantClock;
+use crate::reputation_interceptor::ReputationMonitor;
+use crate::{count_reputation_pairs, BoxError, NetworkReputation, PaymentHashGenerator};
+
+use super::costs::CostTracker;
+use super::utils::build_custom_route;
+use super::{AttackStatisitcs, HonestPairReputation, JammingAttack, ReputationChange};
+
+type LdkNetworkGraph = NetworkGraph<Arc<WrappedLog>>;
+
+/// The percentage of the smallest attacker -> target channel's capacity that is used for each inflation payment.
+const INFLATION_PAYMENT_PERCENT: u64 = 10;
+
+/// The interval at which inflation payments are sent.
+const PAYMENT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The interval at which the attack checks the reputation of the target's honest peers.
+const STATUS_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Runs an attack that inflates the revenue threshold of chosen attacker channels with the target. Attacking nodes
+/// send fee-paying payments to each other through the target, which raises the incoming revenue of the attacker
+/// channel that the payment arrives on. As reputation is judged against the incoming channel's revenue, honest
+/// outgoing peers of the target lose access to protected resources for htlcs arriving on the inflated channels.
+///
+/// The attack tracks the number of (incoming, outgoing) pairs at the target that honest outgoing peers have
+/// reputation for, to measure the loss of protected access that the attack causes, and completes once honest peers
+/// have lost the configured percentage of the pairs they started with. This is reported as a single
+/// [`super::AttackStatisitcs::honest_peer_reputation`] value, so the attack only supports a single target.
+pub struct RevenueInflationAttack<R>
+where
+    R: ReputationMonitor + Send + Sync,
+{
+    clock: Arc<SimulationClock>,
+    target_pubkey: PublicKey,
+    /// Attacking nodes that have a channel with the target, sorted by alias so that payments are sent in a
+    /// consistent order across runs.
+    attackers: Vec<(String, PublicKey)>,
+    /// Channels that the target has with attackers, and the attacker's pubkey.
+    attacker_channels: HashMap<u64, PublicKey>,
+    /// The attacker channels with the target whose incoming revenue is inflated, sorted so that payments are sent in
+    /// a consistent order across runs.
+    inflated_channels: Vec<u64>,
+    /// Channels that the target has with honest peers, and the peer's pubkey.
+    honest_channels: HashMap<u64, PublicKey>,
+    payment_amount_msat: u64,
+    risk_margin: u64,
+    /// The percentage of the pairs that honest peers start with good reputation on that they must lose for the
+    /// attack to complete.
+    loss_percent: u8,
+    reputation_monitor: Arc<R>,
+    network_graph: Arc<LdkNetworkGraph>,
+    cost_tracker: Arc<CostTracker>,
+    payment_hashes: PaymentHashGenerator,
+    honest_reputation: StdMutex<Option<ReputationChange>>,
+}
+
+impl<R> RevenueInflationAttack<R>
+where
+    R: ReputationMonitor + Send + Sync,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        clock: Arc<SimulationClock>,
+        network: &[NetworkParser],
+        target_pubkey: PublicKey,
+        attackers: Vec<(String, PublicKey)>,
+        inflation_channels: &[u64],
+        risk_margin: u64,
+        loss_percent: u8,
+        reputation_monitor: Arc<R>,
+        network_graph: Arc<LdkNetworkGraph>,
+        cost_tracker: Arc<CostTracker>,
+        payment_hashes: PaymentHashGenerator,
+    ) -> Self {
+        let attacker_pubkeys: HashSet<PublicKey> = attackers.iter().map(|a| a.1).collect();
+
+        let mut honest_channels = HashMap::new();
+        let mut attacker_channels: HashMap<u64, PublicKey> = HashMap::new();
+        for channel in network {
+            let counterparty = if channel.node_1.pubkey == target_pubkey {
+                channel.node_2.pubkey
+            } else if channel.node_2.pubkey == target_pubkey {
+                channel.node_1.pubkey
+            } else {
+                continue;
+            };
+
+            if attacker_pubkeys.contains(&counterparty) {
+                attacker_channels.insert(channel.scid.into(), counterparty);
+            } else {
+                honest_channels.insert(channel.scid.into(), counterparty);
+            }
+        }
+
+        let payment_amount_msat = network
+            .iter()
+            .filter(|channel| attacker_channels.contains_key(&channel.scid.into()))
+            .map(|channel| channel.capacity_msat)
+            .min()
+            .unwrap_or(0)
+            * INFLATION_PAYMENT_PERCENT
+            / 100;
+
+        // Only attackers that have a channel with the target can participate in the attack.
+        let mut attackers: Vec<(String, PublicKey)> = attackers
+            .into_iter()
+            .filter(|a| attacker_channels.values().any(|pk| *pk == a.1))
+            .collect();
+        attackers.sort();
+
+        // If no channels are chosen, every attacker channel with the target is inflated.
+        let mut inflated_channels = if inflation_channels.is_empty() {
+            attacker_channels.keys().cloned().collect()
+        } else {
+            inflation_channels.to_vec()
+        };
+        inflated_channels.sort();
+        inflated_channels.dedup();
+
+        Self {
+            clock,
+            target_pubkey,
+            attackers,
+            attacker_channels,
+            inflated_channels,
+            honest_channels,
+            payment_amount_msat,
+            risk_margin,
+            loss_percent,
+            reputation_monitor,
+            network_graph,
+            cost_tracker,
+            payment_hashes,
+            honest_reputation: StdMutex::new(None),
+        }
+    }
+
+    /// Returns the (sender, receiver) pair for the round provided. Inflated channels take turns to have their
+    /// attacker send to the next attacker, so that the payment arrives at the target on the inflated channel.
+    fn payment_pair(
+        &self,
+        round: usize,
+    ) -> Result<(&(String, PublicKey), &(String, PublicKey)), BoxError> {
+        let channel = self.inflated_channels[round % self.inflated_channels.len()];
+        let sender_pubkey = self.attacker_channels.get(&channel).ok_or(format!(
+            "inflated channel: {channel} is not an attacker channel"
+        ))?;
+        let sender = self
+            .attackers
+            .iter()
+            .position(|a| a.1 == *sender_pubkey)
+            .ok_or(format!("attacker: {sender_pubkey} not found"))?;
+        let receiver = (sender + 1) % self.attackers.len();
+
+        Ok((&self.attackers[sender], &self.attackers[receiver]))
+    }
+
+    /// Sends a payment sender -> target -> receiver, recording the fees paid by the sender if it succeeds.
+    async fn send_inflation_payment(
+        &self,
+        sender: &(String, PublicKey),
+        receiver: &(String, PublicKey),
+        attacker_nodes: &HashMap<String, Arc<Mutex<SimNode<SimGraph, SimulationClock>>>>,
+        shutdown_listener: Listener,
+    ) -> Result<(), BoxError> {
+        let sender_node = attacker_nodes
+            .get(&sender.0)
+            .ok_or(format!("node {} not found in attacker nodes", sender.0))?;
+
+        let route = build_custom_route(
+            &sender.1,
+            self.payment_amount_msat,
+            &[self.target_pubkey, receiver.1],
+            &self.network_graph,
+        )
+        .map_err(|e| e.err)?;
+        let fees_msat = route.get_total_fees();
+
+        let payment_hash = self.payment_hashes.generate();
+        let mut sender_lock = sender_node.lock().await;
+        sender_lock
+            .send_to_route(route, payment_hash, None)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let payment_result = sender_lock
+            .track_payment(&payment_hash, shutdown_listener)
+            .await?;
+
+        // Fees are only earned by the target when the payment succeeds, so we only count them as inflation on
+        // success.
+        let success = matches!(payment_result.payment_outcome, PaymentOutcome::Success);
+        self.cost_tracker
+            .record_payment(&sender.1, fees_msat, success)?;
+
+        if !success {
+            log::warn!(
+                "Inflation payment {} -> {} failed: {:?}",
+                sender.0,
+                receiver.0,
+                payment_result.payment_outcome
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Gets the reputation that the target's honest peers have with the target, assessing the outgoing reputation of
+    /// each honest channel against every other incoming channel's revenue threshold on the target.
+    async fn get_honest_peer_reputation(&self) -> Result<HonestPairReputation, BoxError> {
+        let target_channels = self
+            .reputation_monitor
+            .list_channels(self.target_pubkey, InstantClock::now(&*self.clock))
+            .await?;
+
+        let mut reputation = HonestPairReputation::default();
+        for scid in self.honest_channels.keys() {
+            reputation.good_pairs +=
+                count_reputation_pairs(&target_channels, *scid, self.risk_margin)?;
+            reputation.pair_count += target_channels.len() - 1;
+        }
+
+        Ok(reputation)
+    }
+
+    /// Records the honest peers' reputation with the target, tracking the first reading as the start of the attack.
+    /// Returns a boolean indicating whether honest peers have lost enough reputation for the attack to complete.
+    fn record_honest_reputation(&self, reputation: HonestPairReputation) -> Result<bool, BoxError> {
+        let mut honest_reputation = self
+            .honest_reputation
+            .lock()
+            .map_err(|e| format!("honest reputation lock poisoned: {e}"))?;
+
+        let change = honest_reputation.get_or_insert(ReputationChange {
+            start: reputation,
+            end: reputation,
+        });
+        change.end = reputation;
+
+        Ok(honest_loss_reached(change, self.loss_percent))
+    }
+}
+
+/// Returns a boolean indicating whether honest peers have lost at least the percentage provided of the good
+/// reputation pairs that they started the attack with. If they started with no good pairs, there's no access to
+/// remove and the loss is considered reached.
+fn honest_loss_reached(change: &ReputationChange, loss_percent: u8) -> bool {
+    let lost = change
+        .start
+        .good_pairs
+        .saturating_sub(change.end.good_pairs);
+    lost * 100 >= change.start.good_pairs * loss_percent as usize
+}
+
+#[async_trait]
+impl<R> JammingAttack for RevenueInflationAttack<R>
+where
+    R: ReputationMonitor + Send + Sync,
+{
+    /// Validates that there are at least two attacking nodes with channels to the target, so that payments can be
+    /// routed between them, that the target has honest peers whose access can be measured and that every inflated
+    /// channel can be routed over.
+    fn validate(&self) -> Result<(), BoxError> {
+        if self.attackers.len() < 2 {
+            return Err(format!(
+                "revenue inflation attack requires at least two attackers with target channels, got: {}",
+                self.attackers.len()
+            )
+            .into());
+        }
+
+        if self.honest_channels.is_empty() {
+            return Err("revenue inflation attack requires target to have honest peers".into());
+        }
+
+        if self.payment_amount_msat == 0 {
+            return Err("attacker channels with target have zero capacity".into());
+        }
+
+        // Payments are routed by node, so an inflated channel's attacker must only have one channel with the target
+        // for payments to arrive on the channel.
+        for channel in self.inflated_channels.iter() {
+            let attacker = self.attacker_channels.get(channel).ok_or(format!(
+                "inflated channel: {channel} is not a channel between the target and an attacker"
+            ))?;
+
+            let attacker_channel_count = self
+                .attacker_channels
+                .values()
+                .filter(|pk| *pk == attacker)
+                .count();
+            if attacker_channel_count > 1 {
+                return Err(format!(
+                    "inflated channel: {channel} can't be routed over, attacker: {attacker} has {} channels with target",
+                    attacker_channel_count
+                )
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Continuously sends payments between attacking nodes through the target to inflate the incoming revenue of the
+    /// chosen attacker channels. Shuts down when honest peers have lost the configured percentage of the reputation
+    /// pairs that they had at the start of the attack.
+    async fn run_attack(
+        &self,
+        _start_reputation: HashMap<PublicKey, NetworkReputation>,
+        attacker_nodes: HashMap<String, Arc<Mutex<SimNode<SimGraph, SimulationClock>>>>,
+        shutdown_listener: Listener,
+    ) -> Result<(), BoxError> {
+        if self.record_honest_reputation(self.get_honest_peer_reputation().await?)? {
+            log::warn!("Honest peers have no reputation with target to lose, attack complete");
+            return Ok(());
+        }
+
+        let mut round = 0;
+        let mut last_status = InstantClock::now(&*self.clock);
+        loop {
+            select! {
+                _ = shutdown_listener.clone() => return Ok(()),
+                _ = self.clock.sleep(PAYMENT_INTERVAL) => {},
+            }
+
+            let (sender, receiver) = self.payment_pair(round)?;
+            round += 1;
+
+            if let Err(e) = self
+                .send_inflation_payment(
+                    sender,
+                    receiver,
+                    &attacker_nodes,
+                    shutdown_listener.clone(),
+                )
+                .await
+            {
+                log::warn!("Could not send inflation payment: {e}");
+            }
+
+            let now = InstantClock::now(&*self.clock);
+            if now.duration_since(last_status) < STATUS_INTERVAL {
+                continue;
+            }
+            last_status = now;
+
+            let honest_reputation = self.get_honest_peer_reputation().await?;
+            log::debug!(
+                "Honest peers have good reputation with target for: {}/{} pairs",
+                honest_reputation.good_pairs,
+                honest_reputation.pair_count
+            );
+
+            if self.record_honest_reputation(honest_reputation)? {
+                log::error!(
+                    "Honest peers lost at least {}% of their good reputation pairs with target",
+                    self.loss_percent,
+                );
+
+                return Ok(());
+            }
+        }
+    }
+
+    fn attack_statistics(&self) -> Result<AttackStatisitcs, BoxError> {
+        Ok(AttackStatisitcs {
+            general_jammed_channels: 0,
+            congestion_jammed_channels: 0,
+            attacker_costs: self.cost_tracker.costs()?,
+            honest_peer_reputation: self
+                .honest_reputation
+                .lock()
+                .map_err(|e| format!("honest reputation lock poisoned: {e}"))?
+                .clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bitcoin::secp256k1::PublicKey;
+    use sim_cli::parsing::NetworkParser;
+    use simln_lib::clock::SimulationClock;
+
+    use crate::attacks::costs::{CostParams, CostTracker};
+    use crate::attacks::JammingAttack;
+    use crate::attacks::{HonestPairReputation, ReputationChange};
+    use crate::test_utils::{get_random_keypair, get_test_policy, MockReputationInterceptor};
+    use crate::PaymentHashGenerator;
+
+    use super::{honest_loss_reached, RevenueInflationAttack};
+
+    /// Creates a test network with the following topology, returning the network, target and attackers:
+    ///
+    /// A0 --+         +-- P0
+    ///      +- target -+
+    /// A1 --+         +-- P1
+    fn setup_test_network() -> (Vec<NetworkParser>, PublicKey, Vec<(String, PublicKey)>) {
+        let target = get_random_keypair().1;
+        let peer_0 = get_random_keypair().1;
+        let peer_1 = get_random_keypair().1;
+        let attackers: Vec<(String, PublicKey)> = (0..2)
+            .map(|i| (format!("attacker_{i}"), get_random_keypair().1))
+            .collect();
+
+        let edge =
+            |scid: u64, capacity_msat: u64, node_1: PublicKey, node_2: PublicKey| NetworkParser {
+                scid: scid.into(),
+                capacity_msat,
+                node_1: get_test_policy(node_1),
+                node_2: get_test_policy(node_2),
+            };
+
+        let network = vec![
+            edge(0, 100_000, target, peer_0),
+            edge(1, 100_000, peer_1, target),
+            edge(2, 200_000, attackers[0].1, target),
+            edge(3, 500_000, target, attackers[1].1),
+        ];
+
+        (network, target, attackers)
+    }
+
+    fn setup_test_attack(
+        network: &[NetworkParser],
+        target: PublicKey,
+        attackers: Vec<(String, PublicKey)>,
+        inflation_channels: &[u64],
+    ) -> RevenueInflationAttack<MockReputationInterceptor> {
+        let graph = crate::parsing::network_graph(network.to_vec()).unwrap();
+        let cost_tracker = Arc::new(CostTracker::new(CostParams::default(), network, &attackers));
+
+        RevenueInflationAttack::new(
+            Arc::new(SimulationClock::new(1).unwrap()),
+            network,
+            target,
+            attackers,
+            inflation_channels,
+            0,
+            50,
+            Arc::new(MockReputationInterceptor::new()),
+            graph,
+            cost_tracker,
+            PaymentHashGenerator::new(0),
+        )
+    }
+
+    #[test]
+    fn test_setup_attack() {
+        let (network, target, attackers) = setup_test_network();
+        let attack = setup_test_attack(&network, target, attackers.clone(), &[]);
+        attack.validate().unwrap();
+
+        assert_eq!(attack.honest_channels.len(), 2);
+        assert_eq!(attack.attackers.len(), 2);
+        assert_eq!(attack.inflated_channels, vec![2, 3]);
+
+        // Payments are sized using the smallest attacker channel.
+        assert_eq!(attack.payment_amount_msat, 20_000);
+
+        // Attackers take turns sending to each other.
+        assert_eq!(
+            attack.payment_pair(0).unwrap(),
+            (&attackers[0], &attackers[1])
+        );
+        assert_eq!(
+            attack.payment_pair(1).unwrap(),
+            (&attackers[1], &attackers[0])
+        );
+        assert_eq!(
+            attack.payment_pair(2).unwrap(),
+            (&attackers[0], &attackers[1])
+        );
+
+        // A single attacker with a channel to the target can't route payments to itself.
+        let attack = setup_test_attack(&network[0..3], target, attackers, &[]);
+        assert!(attack.validate().is_err());
+    }
+
+    /// Tests that only the chosen channels are inflated, by having their attacker send every payment, and that
+    /// channels that aren't attacker channels with the target can't be chosen.
+    #[test]
+    fn test_inflation_channels() {
+        let (network, target, attackers) = setup_test_network();
+        let attack = setup_test_attack(&network, target, attackers.clone(), &[3]);
+        attack.validate().unwrap();
+
+        for round in 0..3 {
+            assert_eq!(
+                attack.payment_pair(round).unwrap(),
+                (&attackers[1], &attackers[0])
+            );
+        }
+
+        // Honest and unknown channels can't be inflated.
+        for channel in [0, 5] {
+            let attack = setup_test_attack(&network, target, attackers.clone(), &[channel]);
+            assert!(attack.validate().is_err());
+        }
+
+        // Payments are routed by node, so channels can't be chosen if their attacker has multiple channels with the
+        // target.
+        let mut network = network;
+        network.push(NetworkParser {
+            scid: 4.into(),
+            capacity_msat: 100_000,
+            node_1: get_test_policy(attackers[1].1),
+            node_2: get_test_policy(target),
+        });
+        let attack = setup_test_attack(&network, target, attackers, &[3]);
+        assert!(attack.validate().is_err());
+    }
+
+    /// Tests that the first honest reputation reading is tracked as the start of the attack, subsequent readings
+    /// update the end and that the attack completes once honest peers have lost the configured share of their pairs.
+    #[test]
+    fn test_record_honest_reputation() {
+        let (network, target, attackers) = setup_test_network();
+        let attack = setup_test_attack(&network, target, attackers, &[]);
+
+        let reputation = |good_pairs: usize| HonestPairReputation {
+            good_pairs,
+            pair_count: 6,
+        };
+
+        assert!(!attack.record_honest_reputation(reputation(6)).unwrap());
+        assert!(!attack.record_honest_reputation(reputation(4)).unwrap());
+        assert!(attack.record_honest_reputation(reputation(2)).unwrap());
+
+        let change = attack
+            .attack_statistics()
+            .unwrap()
+            .honest_peer_reputation
+            .unwrap();
+        assert_eq!(change.start, reputation(6));
+        assert_eq!(change.end, reputation(2));
+    }
+
+    #[test]
+    fn test_honest_loss_reached() {
+        let change = |start: usize, end: usize| ReputationChange {
+            start: HonestPairReputation {
+                good_pairs: start,
+                pair_count: 10,
+            },
+            end: HonestPairReputation {
+                good_pairs: end,
+                pair_count: 10,
+            },
+        };
+
+        assert!(!honest_loss_reached(&change(10, 6), 50));
+        assert!(honest_loss_reached(&change(10, 5), 50));
+        assert!(honest_loss_reached(&change(10, 0), 100));
+        assert!(!honest_loss_reached(&change(10, 1), 100));
+
+        // Reputation may recover over the course of the attack.
+        assert!(!honest_loss_reached(&change(4, 8), 50));
+
+        // There's nothing to lose if honest peers start without reputation.
+        assert!(honest_loss_reached(&change(0, 0), 50));
+    }
+}
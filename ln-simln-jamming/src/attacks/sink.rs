@@ -4,21 +4,23 @@ use ln_resource_mgr::AccountableSignal;
 use sim_cli::parsing::NetworkParser;
 use simln_lib::clock::{Clock, SimulationClock};
 use simln_lib::sim_node::{CustomRecords, ForwardingError, InterceptRequest, SimGraph, SimNode};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::select;
 use tokio::sync::Mutex;
 use triggered::Listener;
 
-use crate::clock::InstantClock;
+use crate::clock::{BlockClock, InstantClock};
 use crate::reputation_interceptor::{ChannelJammer, ReputationMonitor};
 use crate::revenue_interceptor::PeacetimeRevenueMonitor;
+use crate::topology::TopologyController;
 use crate::{
-    accountable_from_records, get_network_reputation, print_request, records_from_signal, BoxError,
-    NetworkReputation,
+    accountable_from_records, get_network_reputation, node_channels, print_request,
+    records_from_signal, BoxError, NetworkReputation,
 };
 
+use super::costs::CostTracker;
 use super::{AttackStatisitcs, JammingAttack};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -28,66 +30,167 @@ pub enum TargetChannelType {
 }
 
 #[derive(Clone)]
-pub struct SinkAttack<R, M, J>
+pub struct SinkAttack<R, M, J, T>
 where
     R: ReputationMonitor + Send + Sync,
     M: PeacetimeRevenueMonitor + Send + Sync,
     J: ChannelJammer + Send + Sync,
+    T: TopologyController + Send + Sync,
 {
     clock: Arc<SimulationClock>,
-    target_pubkey: PublicKey,
+    block_clock: BlockClock,
+    /// The number of blocks before a held htlc expires that the attack releases it. Set to the target's force close
+    /// margin to hold htlcs for as long as possible without causing a force close, or zero to hold htlcs to their
+    /// deadline.
+    release_margin: u32,
     attacker_pubkey: PublicKey,
-    target_channels: HashMap<u64, (PublicKey, String)>,
+    /// The channels of each target node that is attacked, keyed by the target's public key.
+    target_channels: HashMap<PublicKey, HashMap<u64, (PublicKey, String)>>,
     risk_margin: u64,
+    /// Whether the mitigation tracks reputation. If it doesn't, the attack only completes when targets lose revenue.
+    tracks_reputation: bool,
     reputation_monitor: Arc<R>,
     peacetime_revenue: Arc<M>,
     channel_jammer: Arc<J>,
+    cost_tracker: Arc<CostTracker>,
+    /// Used to detect when the attacker's channel with a target has been force closed.
+    topology: Arc<T>,
 }
 
 impl<
         R: ReputationMonitor + Send + Sync,
         M: PeacetimeRevenueMonitor + Send + Sync,
         J: ChannelJammer + Send + Sync,
-    > SinkAttack<R, M, J>
+        T: TopologyController + Send + Sync,
+    > SinkAttack<R, M, J, T>
 {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         clock: Arc<SimulationClock>,
+        block_clock: BlockClock,
+        release_margin: u32,
         network: &[NetworkParser],
-        target_pubkey: PublicKey,
+        target_pubkeys: Vec<PublicKey>,
         attacker_pubkeys: Vec<PublicKey>,
         risk_margin: u64,
+        tracks_reputation: bool,
         reputation_monitor: Arc<R>,
         peacetime_revenue: Arc<M>,
         channel_jammer: Arc<J>,
+        cost_tracker: Arc<CostTracker>,
+        topology: Arc<T>,
     ) -> Self {
         // For sink attack we only use one attacker node.
         assert!(attacker_pubkeys.len() == 1);
 
         Self {
             clock,
-            target_pubkey,
+            block_clock,
+            release_margin,
             attacker_pubkey: attacker_pubkeys[0],
-            target_channels: HashMap::from_iter(network.iter().filter_map(|channel| {
-                if channel.node_1.pubkey == target_pubkey {
-                    Some((
-                        channel.scid.into(),
-                        (channel.node_2.pubkey, channel.node_2.alias.clone()),
-                    ))
-                } else if channel.node_2.pubkey == target_pubkey {
-                    Some((
-                        channel.scid.into(),
-                        (channel.node_1.pubkey, channel.node_1.alias.clone()),
-                    ))
-                } else {
-                    None
-                }
-            })),
+            target_channels: target_pubkeys
+                .into_iter()
+                .map(|target| (target, node_channels(network, target)))
+                .collect(),
             risk_margin,
+            tracks_reputation,
             reputation_monitor,
             peacetime_revenue,
             channel_jammer,
+            cost_tracker,
+            topology,
+        }
+    }
+
+    /// Returns true if the channel provided belongs to any of the targets.
+    fn is_target_channel(&self, scid: u64) -> bool {
+        self.target_channels
+            .values()
+            .any(|channels| channels.contains_key(&scid))
+    }
+
+    /// Returns the directed edges that are general jammed: both directions of every channel that a target has with
+    /// a node that is not the attacker. Channels between two targets are only included once.
+    fn jammed_edges(&self) -> HashSet<(PublicKey, u64)> {
+        let attacker = self.attacker_pubkey;
+        self.target_channels
+            .iter()
+            .flat_map(|(target, channels)| {
+                channels
+                    .iter()
+                    .filter(move |(_, (pk, _))| *pk != attacker)
+                    .flat_map(move |(scid, (pk, _))| [(*pk, *scid), (*target, *scid)])
+            })
+            .collect()
+    }
+
+    /// Returns true if the attack on a target is complete, because it has lost revenue compared to its projected
+    /// peacetime revenue or the attacker has lost its reputation with the target. Reputation is only checked if the
+    /// mitigation tracks it, as baseline mitigations report no reputation for any channel. If the target has force
+    /// closed its channel with the attacker, the attacker has no reputation left to check.
+    async fn target_completed(
+        &self,
+        target: &PublicKey,
+        start_reputation: &NetworkReputation,
+    ) -> Result<bool, BoxError> {
+        let snapshot = self.peacetime_revenue.get_revenue_difference(target).await;
+        if snapshot.peacetime_revenue_msat > snapshot.simulation_revenue_msat {
+            log::error!(
+                "Target {target} peacetime revenue: {} exceeds simulation revenue: {} after: {:?}",
+                snapshot.peacetime_revenue_msat,
+                snapshot.simulation_revenue_msat,
+                snapshot.runtime
+            );
+
+            return Ok(true);
         }
+
+        log::trace!(
+            "Target {target} peacetime revenue: {} less than simulation revenue: {} after: {:?}",
+            snapshot.peacetime_revenue_msat,
+            snapshot.simulation_revenue_msat,
+            snapshot.runtime
+        );
+
+        if !self.tracks_reputation {
+            return Ok(false);
+        }
+
+        let target_channels = self
+            .target_channels
+            .get(target)
+            .ok_or(format!("target {target} not found"))?;
+
+        for (scid, (pk, _)) in target_channels.iter() {
+            if *pk == self.attacker_pubkey && self.topology.is_closed(*scid)? {
+                log::error!("Target {target} force closed channel {scid} with the attacker");
+                return Ok(true);
+            }
+        }
+
+        let current_reputation = get_network_reputation(
+            self.reputation_monitor.clone(),
+            *target,
+            &[self.attacker_pubkey],
+            &target_channels.iter().map(|(k, v)| (*k, v.0)).collect(),
+            self.risk_margin,
+            InstantClock::now(&*self.clock),
+        )
+        .await?;
+
+        log::debug!(
+            "Attacker has good reputation with target {target} for: {}/{} pairs",
+            current_reputation.attacker_reputation,
+            current_reputation.attacker_pair_count
+        );
+
+        log::debug!(
+            "Target {target} has good reputation with peers for: {}/{} pairs",
+            current_reputation.target_reputation,
+            current_reputation.target_pair_count
+        );
+
+        inner_simulation_completed(start_reputation, &current_reputation)
     }
 
     /// Intercepts payments flowing from target -> attacker, holding the htlc for the maximum allowable time to
@@ -109,22 +212,40 @@ impl<
             return Ok(records_from_signal(AccountableSignal::Unaccountable));
         }
 
-        // Get maximum hold time assuming 10 minute blocks, assuming a zero block height (simulator doesn't track
-        // height).
-        let max_hold_secs = Duration::from_secs((req.incoming_expiry_height * 10 * 60).into());
+        // Hold the htlc until the block before the release margin, which is the longest that we can hold it without
+        // it being failed back on-chain when the margin is the target's force close margin. The expiry height is
+        // relative to the current height.
+        let expiry_height = self
+            .block_clock
+            .current_height()
+            .saturating_add(req.incoming_expiry_height);
+        let max_hold = self
+            .block_clock
+            .max_hold(expiry_height, self.release_margin);
 
         log::info!(
             "HTLC from target -> attacker accountable, holding for {:?}: {}",
-            max_hold_secs,
+            max_hold,
             print_request(&req),
         );
 
         // If the htlc is accountable, then we go ahead and hold the htlc for as long as we can only exiting if we
         // get a shutdown signal elsewhere.
-        select! {
+        let start = InstantClock::now(&*self.clock);
+        let res = select! {
             _ = req.shutdown_listener.clone() => Err(ForwardingError::InterceptorError("shutdown signal received".to_string())),
-            _ = self.clock.sleep(max_hold_secs) => Ok(records_from_signal(AccountableSignal::Accountable))
+            _ = self.clock.sleep(max_hold) => Ok(records_from_signal(AccountableSignal::Accountable))
+        };
+
+        if let Err(e) = self.cost_tracker.record_hold(
+            &self.attacker_pubkey,
+            req.incoming_amount_msat,
+            InstantClock::now(&*self.clock).duration_since(start),
+        ) {
+            log::error!("Could not record attacker hold cost: {e}");
         }
+
+        res
     }
 }
 
@@ -149,38 +270,38 @@ fn inner_simulation_completed(
 }
 
 #[async_trait]
-impl<R, M, J> JammingAttack for SinkAttack<R, M, J>
+impl<R, M, J, T> JammingAttack for SinkAttack<R, M, J, T>
 where
     R: ReputationMonitor + Send + Sync,
     M: PeacetimeRevenueMonitor + Send + Sync,
     J: ChannelJammer + Send + Sync,
+    T: TopologyController + Send + Sync,
 {
-    /// Validates that there's only one channel between the target and the attacking node.
+    /// Validates that there's only one channel between each target and the attacking node.
     fn validate(&self) -> Result<(), BoxError> {
-        let target_to_attacker_len = self
-            .target_channels
-            .iter()
-            .filter_map(|(scid, (pk, _))| {
-                if *pk == self.attacker_pubkey {
-                    Some(*scid)
-                } else {
-                    None
-                }
-            })
-            .count();
+        if self.target_channels.is_empty() {
+            return Err("sink attack requires at least one target".into());
+        }
 
-        if target_to_attacker_len != 1 {
-            return Err(format!(
-                "expected one target -> attacker channel, got: {}",
-                target_to_attacker_len,
-            )
-            .into());
+        for (target, channels) in self.target_channels.iter() {
+            let target_to_attacker_len = channels
+                .values()
+                .filter(|(pk, _)| *pk == self.attacker_pubkey)
+                .count();
+
+            if target_to_attacker_len != 1 {
+                return Err(format!(
+                    "expected one target {target} -> attacker channel, got: {}",
+                    target_to_attacker_len,
+                )
+                .into());
+            }
         }
 
         Ok(())
     }
 
-    /// Intercepts attacker forwads from the target node to jam them, otherwise forwards unrelated
+    /// Intercepts attacker forwads from the target nodes to jam them, otherwise forwards unrelated
     /// traffic in the hopes of continuing to be chosen in pathfinding (it can't hurt).
     async fn intercept_attacker_htlc(
         &self,
@@ -195,16 +316,19 @@ where
         }
 
         // If the htlc is incoming to the attacker, we're interested in hodling it.
-        if self
-            .target_channels
-            .contains_key(&req.incoming_htlc.channel_id.into())
-        {
+        if self.is_target_channel(req.incoming_htlc.channel_id.into()) {
             return Ok(self.intercept_attacker_incoming(req).await);
         }
 
-        // If the payment is going to the target node, we'll drop it to deprive them of the revenue.
+        // If the payment is going to a target node, we'll drop it to deprive them of the revenue.
         if let Some(outgoing_channel) = req.outgoing_channel_id {
-            if self.target_channels.contains_key(&outgoing_channel.into()) {
+            if self.is_target_channel(outgoing_channel.into()) {
+                self.cost_tracker.record_dropped_forward(
+                    &self.attacker_pubkey,
+                    req.incoming_amount_msat
+                        .saturating_sub(req.outgoing_amount_msat),
+                )?;
+
                 return Ok(Err(ForwardingError::InterceptorError(
                     "attacker failing".into(),
                 )));
@@ -227,75 +351,41 @@ where
         return Err("HTLC receive not expected in passive sink attack".into());
     }
 
-    /// Shuts down the simulation if the target node has lost revenue compared to its projected
-    /// peacetime revenue, or the attacker has lost reputation without being able to compromise
-    /// the target's reputation.
+    /// Shuts down the simulation once every target node has lost revenue compared to its projected peacetime
+    /// revenue, or the attacker has lost reputation with it without being able to compromise the target's reputation.
     async fn run_attack(
         &self,
-        start_reputation: NetworkReputation,
+        start_reputation: HashMap<PublicKey, NetworkReputation>,
         _attacker_nodes: HashMap<String, Arc<Mutex<SimNode<SimGraph, SimulationClock>>>>,
         shutdown_listener: Listener,
     ) -> Result<(), BoxError> {
-        // Jam all non-attacking channels with the target in both directions.
-        for (scid, (pk, _)) in self
-            .target_channels
-            .iter()
-            .filter(|(_, (pk, _))| *pk != self.attacker_pubkey)
-        {
-            self.channel_jammer.jam_general_resources(pk, *scid).await?;
-            self.channel_jammer
-                .jam_general_resources(&self.target_pubkey, *scid)
-                .await?;
+        // Jam all non-attacking channels with the targets in both directions.
+        for (pk, scid) in self.jammed_edges() {
+            self.channel_jammer.jam_general_resources(&pk, scid).await?;
         }
 
-        // Poll every 5 minutes to check if the attack is done.
+        // Poll every 5 minutes to check if the attack is done, only checking targets that aren't complete yet.
         let interval = Duration::from_secs(300);
+        let mut remaining: Vec<PublicKey> = self.target_channels.keys().copied().collect();
         loop {
             select! {
                 _ = shutdown_listener.clone() => break,
                 _ = self.clock.sleep(interval) => {
-                    let snapshot = self.peacetime_revenue.get_revenue_difference().await;
-                    if snapshot.peacetime_revenue_msat > snapshot.simulation_revenue_msat {
-                        log::error!(
-                            "Peacetime revenue: {} exceeds simulation revenue: {} after: {:?}",
-                            snapshot.peacetime_revenue_msat,
-                            snapshot.simulation_revenue_msat,
-                            snapshot.runtime
-                        );
+                    let mut still_remaining = vec![];
+                    for target in remaining {
+                        let start = start_reputation
+                            .get(&target)
+                            .ok_or(format!("start reputation not found for target {target}"))?;
 
-                        return Ok(());
+                        if !self.target_completed(&target, start).await? {
+                            still_remaining.push(target);
+                        }
                     }
 
-                    log::trace!(
-                        "Peacetime revenue: {} less than simulation revenue: {} after: {:?}",
-                        snapshot.peacetime_revenue_msat,
-                        snapshot.simulation_revenue_msat,
-                        snapshot.runtime
-                    );
-
-                    let current_reputation = get_network_reputation(
-                        self.reputation_monitor.clone(),
-                        self.target_pubkey,
-                        &[self.attacker_pubkey],
-                        &self
-                            .target_channels
-                            .iter()
-                            .map(|(k, v)| (*k, v.0))
-                            .collect(),
-                        self.risk_margin,
-                        InstantClock::now(&*self.clock),
-                    )
-                    .await?;
-
-                    log::debug!("Attacker has good reputation with target for: {}/{} pairs",
-                        current_reputation.attacker_reputation, current_reputation.attacker_pair_count);
-
-                    log::debug!("Target has good reputation with peers for: {}/{} pairs",
-                        current_reputation.target_reputation, current_reputation.target_pair_count);
-
-                    if inner_simulation_completed(&start_reputation, &current_reputation)? {
-                        return Ok(())
+                    if still_remaining.is_empty() {
+                        return Ok(());
                     }
+                    remaining = still_remaining;
                 }
             }
         }
@@ -305,9 +395,11 @@ where
 
     fn attack_statistics(&self) -> Result<AttackStatisitcs, BoxError> {
         Ok(AttackStatisitcs {
-            // We jam the target's channels in both directions.
-            general_jammed_channels: self.target_channels.len() * 2,
+            // We jam the targets' channels in both directions.
+            general_jammed_channels: self.jammed_edges().len(),
             congestion_jammed_channels: 0,
+            attacker_costs: self.cost_tracker.costs()?,
+            honest_peer_reputation: None,
         })
     }
 }
@@ -316,35 +408,55 @@ where
 mod tests {
     use std::sync::Arc;
 
+    use crate::attack_interceptor::AttackInterceptor;
+    use crate::attacks::costs::{CostParams, CostTracker};
     use crate::attacks::sink::inner_simulation_completed;
     use crate::attacks::JammingAttack;
+    use crate::clock::BlockClock;
+    use crate::parsing::DEFAULT_FORCE_CLOSE_MARGIN_BLOCKS;
+    use crate::revenue_interceptor::RevenueSnapshot;
     use crate::test_utils::{
         get_random_keypair, get_test_policy, setup_test_request, MockJammer, MockPeacetimeMonitor,
-        MockReputationInterceptor,
+        MockReputationInterceptor, MockTopology,
     };
+    use crate::topology::ChannelTopology;
     use crate::{accountable_from_records, NetworkReputation};
     use bitcoin::secp256k1::PublicKey;
     use ln_resource_mgr::AccountableSignal;
+    use mockall::predicate::eq;
     use sim_cli::parsing::NetworkParser;
     use simln_lib::clock::SimulationClock;
-    use simln_lib::sim_node::ForwardingError;
+    use simln_lib::sim_node::{ForwardingError, Interceptor};
+    use std::time::Duration;
 
     use super::SinkAttack;
 
     fn setup_test_attack(
-        target: PublicKey,
+        targets: &[PublicKey],
         attacker: PublicKey,
         network: &[NetworkParser],
-    ) -> SinkAttack<MockReputationInterceptor, MockPeacetimeMonitor, MockJammer> {
+    ) -> SinkAttack<MockReputationInterceptor, MockPeacetimeMonitor, MockJammer, MockTopology> {
+        let cost_tracker = Arc::new(CostTracker::new(
+            CostParams::default(),
+            network,
+            &[("attacker".to_string(), attacker)],
+        ));
+
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
         SinkAttack::new(
-            Arc::new(SimulationClock::new(1).unwrap()),
+            Arc::clone(&clock),
+            BlockClock::new(clock, None),
+            0,
             network,
-            target,
+            targets.to_vec(),
             vec![attacker],
             0,
+            true,
             Arc::new(MockReputationInterceptor::new()),
             Arc::new(MockPeacetimeMonitor::new()),
             Arc::new(MockJammer::new()),
+            cost_tracker,
+            Arc::new(MockTopology::new()),
         )
     }
 
@@ -356,7 +468,7 @@ mod tests {
     ///      |
     /// P2 --+
     fn setup_test_network() -> (
-        SinkAttack<MockReputationInterceptor, MockPeacetimeMonitor, MockJammer>,
+        SinkAttack<MockReputationInterceptor, MockPeacetimeMonitor, MockJammer, MockTopology>,
         u64,
     ) {
         let target = get_random_keypair().1;
@@ -385,7 +497,7 @@ mod tests {
             },
         ];
 
-        (setup_test_attack(target, attacker, &network), 2)
+        (setup_test_attack(&[target], attacker, &network), 2)
     }
 
     #[test]
@@ -429,16 +541,101 @@ mod tests {
         ];
 
         // Two target <--> attacker channels should fail.
-        let attack = setup_test_attack(target, attacker, &network);
+        let attack = setup_test_attack(&[target], attacker, &network);
         assert!(attack.validate().is_err());
 
         // No target <--> attacker should fail.
-        let attack = setup_test_attack(target, attacker, &network[0..1]);
+        let attack = setup_test_attack(&[target], attacker, &network[0..1]);
         assert!(attack.validate().is_err());
 
         // It's okay for the target to have multiple channels with other nodes.
-        let attack = setup_test_attack(target, attacker, &network[0..3]);
+        let attack = setup_test_attack(&[target], attacker, &network[0..3]);
         attack.validate().unwrap();
+
+        // Every target needs a channel with the attacker.
+        let attack = setup_test_attack(&[target, regular_1], attacker, &network[0..3]);
+        assert!(attack.validate().is_err());
+    }
+
+    /// Tests attacking multiple targets that share a channel, with the following topology:
+    /// P1 -- target_1 -- target_2 -- P2
+    ///           |          |
+    ///           +- attacker -+
+    #[tokio::test]
+    async fn test_multiple_targets() {
+        let target_1 = get_random_keypair().1;
+        let target_2 = get_random_keypair().1;
+        let attacker = get_random_keypair().1;
+        let regular_1 = get_random_keypair().1;
+        let regular_2 = get_random_keypair().1;
+
+        let network = vec![
+            NetworkParser {
+                scid: 0.into(),
+                capacity_msat: 100_000,
+                node_1: get_test_policy(target_1),
+                node_2: get_test_policy(regular_1),
+            },
+            NetworkParser {
+                scid: 1.into(),
+                capacity_msat: 100_000,
+                node_1: get_test_policy(target_1),
+                node_2: get_test_policy(target_2),
+            },
+            NetworkParser {
+                scid: 2.into(),
+                capacity_msat: 100_000,
+                node_1: get_test_policy(target_2),
+                node_2: get_test_policy(regular_2),
+            },
+            NetworkParser {
+                scid: 3.into(),
+                capacity_msat: 100_000,
+                node_1: get_test_policy(target_1),
+                node_2: get_test_policy(attacker),
+            },
+            NetworkParser {
+                scid: 4.into(),
+                capacity_msat: 100_000,
+                node_1: get_test_policy(attacker),
+                node_2: get_test_policy(target_2),
+            },
+        ];
+
+        let attack = setup_test_attack(&[target_1, target_2], attacker, &network);
+        attack.validate().unwrap();
+
+        // Each of the targets' channels with honest nodes is jammed in both directions, with the channel shared by
+        // the targets only jammed once.
+        assert_eq!(attack.jammed_edges().len(), 6);
+        assert_eq!(
+            attack.attack_statistics().unwrap().general_jammed_channels,
+            6
+        );
+
+        // Forwards to either target are dropped.
+        for scid in [3, 4] {
+            let request = setup_test_request(attacker, 100, scid, AccountableSignal::Unaccountable);
+            assert!(attack
+                .intercept_attacker_htlc(request)
+                .await
+                .unwrap()
+                .is_err());
+        }
+
+        // Htlcs incoming from either target are intercepted rather than forwarded as unrelated traffic.
+        for scid in [3, 4] {
+            let mut request =
+                setup_test_request(attacker, scid, 100, AccountableSignal::Accountable);
+            request.incoming_expiry_height = 0;
+
+            let res = attack
+                .intercept_attacker_htlc(request)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(accountable_from_records(&res) == AccountableSignal::Accountable);
+        }
     }
 
     /// Tests that bad requests to the attacking interceptor will fail.
@@ -474,6 +671,12 @@ mod tests {
             ForwardingError::InterceptorError(_)
         ));
 
+        // The attacker gives up the fee it would have earned by forwarding.
+        assert_eq!(
+            attack.attack_statistics().unwrap().attacker_costs[0].lost_revenue_msat,
+            50
+        );
+
         // Receives to the attacker or forwards unrelated to the target succeed.
         request.outgoing_channel_id = None;
         assert!(
@@ -532,6 +735,304 @@ mod tests {
         assert!(accountable_from_records(&res) == AccountableSignal::Accountable);
     }
 
+    /// Tests that accountable HTLCs are held until the block before their expiry height.
+    #[tokio::test(start_paused = true)]
+    async fn test_intercept_incoming_hold_expiry() {
+        let (attack, attacker_scid) = setup_test_network();
+        let mut request = setup_test_request(
+            attack.attacker_pubkey,
+            attacker_scid,
+            100,
+            AccountableSignal::Accountable,
+        );
+        request.incoming_expiry_height = 10;
+
+        let res = attack
+            .intercept_attacker_htlc(request)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(accountable_from_records(&res) == AccountableSignal::Accountable);
+        assert_eq!(attack.block_clock.current_height(), 9);
+    }
+
+    /// Tests that with the default force close margin, the sink releases accountable htlcs in the block before the
+    /// target would force close its channel with the attacker.
+    #[tokio::test(start_paused = true)]
+    async fn test_hold_default_force_close_margin() {
+        let force_close_margin: u32 = DEFAULT_FORCE_CLOSE_MARGIN_BLOCKS.parse().unwrap();
+        let target = get_random_keypair().1;
+        let attacker = get_random_keypair().1;
+        let network = vec![NetworkParser {
+            scid: 0.into(),
+            capacity_msat: 100_000,
+            node_1: get_test_policy(target),
+            node_2: get_test_policy(attacker),
+        }];
+
+        let cost_tracker = Arc::new(CostTracker::new(
+            CostParams::default(),
+            &network,
+            &[("attacker".to_string(), attacker)],
+        ));
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
+        let block_clock = BlockClock::new(clock.clone(), None);
+        let attack = SinkAttack::new(
+            clock,
+            block_clock.clone(),
+            force_close_margin,
+            &network,
+            vec![target],
+            vec![attacker],
+            0,
+            true,
+            Arc::new(MockReputationInterceptor::new()),
+            Arc::new(MockPeacetimeMonitor::new()),
+            Arc::new(MockJammer::new()),
+            cost_tracker.clone(),
+            Arc::new(MockTopology::new()),
+        );
+
+        // The channel manager has no expectations, so a force close will panic.
+        let interceptor = AttackInterceptor::new(
+            vec![attacker],
+            Arc::new(MockReputationInterceptor::new()),
+            Arc::new(attack),
+            block_clock.clone(),
+            force_close_margin,
+            cost_tracker.clone(),
+            Arc::new(MockPeacetimeMonitor::new()),
+            Arc::new(
+                ChannelTopology::new(Arc::new(MockReputationInterceptor::new()), &network, &[])
+                    .await
+                    .unwrap(),
+            ),
+        );
+
+        let mut request = setup_test_request(attacker, 0, 100, AccountableSignal::Accountable);
+        request.incoming_expiry_height = 40;
+
+        let res = interceptor.intercept_htlc(request).await.unwrap().unwrap();
+        assert!(accountable_from_records(&res) == AccountableSignal::Accountable);
+        assert_eq!(block_clock.current_height(), 40 - force_close_margin - 1);
+
+        let cost = cost_tracker.costs().unwrap().remove(0);
+        assert_eq!(cost.htlcs_held, 1);
+        assert_eq!(cost.force_closes_caused, 0);
+    }
+
+    /// Tests that when the sink holds htlcs to their expiry, the target force closes its channel with the attacker at
+    /// the default force close margin and the close is counted in the attacker's costs.
+    #[tokio::test(start_paused = true)]
+    async fn test_hold_to_expiry_force_close() {
+        let force_close_margin: u32 = DEFAULT_FORCE_CLOSE_MARGIN_BLOCKS.parse().unwrap();
+        let target = get_random_keypair().1;
+        let attacker = get_random_keypair().1;
+        let network = vec![NetworkParser {
+            scid: 0.into(),
+            capacity_msat: 100_000,
+            node_1: get_test_policy(target),
+            node_2: get_test_policy(attacker),
+        }];
+
+        let cost_tracker = Arc::new(CostTracker::new(
+            CostParams::default(),
+            &network,
+            &[("attacker".to_string(), attacker)],
+        ));
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
+        let block_clock = BlockClock::new(clock.clone(), None);
+        let attack = SinkAttack::new(
+            clock,
+            block_clock.clone(),
+            0,
+            &network,
+            vec![target],
+            vec![attacker],
+            0,
+            true,
+            Arc::new(MockReputationInterceptor::new()),
+            Arc::new(MockPeacetimeMonitor::new()),
+            Arc::new(MockJammer::new()),
+            cost_tracker.clone(),
+            Arc::new(MockTopology::new()),
+        );
+
+        let mut channel_manager = MockReputationInterceptor::new();
+        channel_manager
+            .expect_close_channel()
+            .with(eq(0))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let mut recorder = MockPeacetimeMonitor::new();
+        recorder
+            .expect_record_force_close()
+            .with(
+                eq(target),
+                eq(CostParams::default().force_close_cost_msat()),
+            )
+            .times(1)
+            .return_const(());
+
+        let interceptor = AttackInterceptor::new(
+            vec![attacker],
+            Arc::new(MockReputationInterceptor::new()),
+            Arc::new(attack),
+            block_clock.clone(),
+            force_close_margin,
+            cost_tracker.clone(),
+            Arc::new(recorder),
+            Arc::new(
+                ChannelTopology::new(Arc::new(channel_manager), &network, &[])
+                    .await
+                    .unwrap(),
+            ),
+        );
+
+        let mut request = setup_test_request(attacker, 0, 100, AccountableSignal::Accountable);
+        request.incoming_expiry_height = 40;
+
+        let res = interceptor.intercept_htlc(request).await.unwrap();
+        assert!(matches!(res, Err(ForwardingError::InterceptorError(_))));
+        assert_eq!(block_clock.current_height(), 40 - force_close_margin);
+
+        let cost = cost_tracker.costs().unwrap().remove(0);
+        assert_eq!(cost.htlcs_held, 1);
+        assert_eq!(cost.force_closes_caused, 1);
+        assert_eq!(
+            cost.force_close_cost_msat,
+            CostParams::default().force_close_cost_msat()
+        );
+    }
+
+    /// Tests that the attack doesn't check reputation when the mitigation doesn't track it, as baselines report no
+    /// reputation for any channel, and only completes once the target has lost revenue.
+    #[tokio::test]
+    async fn test_target_completed_without_reputation() {
+        let target = get_random_keypair().1;
+        let attacker = get_random_keypair().1;
+        let network = vec![NetworkParser {
+            scid: 0.into(),
+            capacity_msat: 100_000,
+            node_1: get_test_policy(target),
+            node_2: get_test_policy(attacker),
+        }];
+
+        let mut peacetime = MockPeacetimeMonitor::new();
+        let mut simulation_revenue = vec![50, 200];
+        peacetime
+            .expect_get_revenue_difference()
+            .times(2)
+            .returning(move |_| RevenueSnapshot {
+                peacetime_revenue_msat: 100,
+                simulation_revenue_msat: simulation_revenue.pop().unwrap(),
+                force_close_cost_msat: 0,
+                runtime: Duration::from_secs(300),
+            });
+
+        // The reputation monitor has no expectations, so any reputation checks will panic.
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
+        let attack = SinkAttack::new(
+            Arc::clone(&clock),
+            BlockClock::new(clock, None),
+            0,
+            &network,
+            vec![target],
+            vec![attacker],
+            0,
+            false,
+            Arc::new(MockReputationInterceptor::new()),
+            Arc::new(peacetime),
+            Arc::new(MockJammer::new()),
+            Arc::new(CostTracker::new(
+                CostParams::default(),
+                &network,
+                &[("attacker".to_string(), attacker)],
+            )),
+            Arc::new(MockTopology::new()),
+        );
+
+        let no_reputation = NetworkReputation {
+            target_reputation: 0,
+            target_pair_count: 1,
+            attacker_reputation: 0,
+            attacker_pair_count: 1,
+        };
+        assert!(!attack
+            .target_completed(&target, &no_reputation)
+            .await
+            .unwrap());
+        assert!(attack
+            .target_completed(&target, &no_reputation)
+            .await
+            .unwrap());
+    }
+
+    /// Tests that the attack on a target completes without checking reputation once the target has force closed its
+    /// channel with the attacker.
+    #[tokio::test]
+    async fn test_target_completed_channel_closed() {
+        let target = get_random_keypair().1;
+        let attacker = get_random_keypair().1;
+        let network = vec![NetworkParser {
+            scid: 0.into(),
+            capacity_msat: 100_000,
+            node_1: get_test_policy(target),
+            node_2: get_test_policy(attacker),
+        }];
+
+        let mut peacetime = MockPeacetimeMonitor::new();
+        peacetime
+            .expect_get_revenue_difference()
+            .returning(|_| RevenueSnapshot {
+                peacetime_revenue_msat: 100,
+                simulation_revenue_msat: 200,
+                force_close_cost_msat: 0,
+                runtime: Duration::from_secs(300),
+            });
+
+        let mut topology = MockTopology::new();
+        topology
+            .expect_is_closed()
+            .with(eq(0))
+            .times(1)
+            .returning(|_| Ok(true));
+
+        // The reputation monitor has no expectations, so any reputation checks will panic.
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
+        let attack = SinkAttack::new(
+            Arc::clone(&clock),
+            BlockClock::new(clock, None),
+            0,
+            &network,
+            vec![target],
+            vec![attacker],
+            0,
+            true,
+            Arc::new(MockReputationInterceptor::new()),
+            Arc::new(peacetime),
+            Arc::new(MockJammer::new()),
+            Arc::new(CostTracker::new(
+                CostParams::default(),
+                &network,
+                &[("attacker".to_string(), attacker)],
+            )),
+            Arc::new(topology),
+        );
+
+        let start_reputation = NetworkReputation {
+            target_reputation: 1,
+            target_pair_count: 1,
+            attacker_reputation: 1,
+            attacker_pair_count: 1,
+        };
+        assert!(attack
+            .target_completed(&target, &start_reputation)
+            .await
+            .unwrap());
+    }
+
     /// Tests stop conditions for simulation. Does not cover simulation_completed to avoid needing
     /// to do complicated mocking for the get_network_reputation call.
     #[tokio::test]
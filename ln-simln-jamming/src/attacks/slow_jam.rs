@@ -1,8 +1,9 @@
 use crate::{
     attacks::JammingAttack,
+    clock::{BlockClock, InstantClock},
     print_request,
     reputation_interceptor::{ChannelJammer, ReputationMonitor},
-    BoxError, NetworkReputation,
+    BoxError, NetworkReputation, PaymentHashGenerator,
 };
 
 use async_trait::async_trait;
@@ -27,6 +28,7 @@ use tokio::{select, sync::Mutex};
 use triggered::{trigger, Listener, Trigger};
 
 use super::{
+    costs::CostTracker,
     utils::{build_custom_route, build_reputation, BuildReputationParams},
     AttackStatisitcs,
 };
@@ -36,12 +38,20 @@ use super::{
 
 type LdkNetworkGraph = NetworkGraph<Arc<WrappedLog>>;
 
+/// Slowly jams a single channel of the target, building reputation on a route chosen for the ln_slow_jam network. The
+/// attacking nodes, target peer and jammed channel are fixed for that network, so the attack only supports a single
+/// target.
 pub struct SlowJam<R, J>
 where
     R: ReputationMonitor + Send + Sync + 'static,
     J: ChannelJammer + Send + Sync + 'static,
 {
     clock: Arc<SimulationClock>,
+    block_clock: BlockClock,
+    /// The number of blocks before a held htlc expires that the attack releases it. Set to the target's force close
+    /// margin to hold htlcs for as long as possible without causing a force close, or zero to hold htlcs to their
+    /// deadline.
+    release_margin: u32,
     target_pubkey: PublicKey,
     attacker_sender: (String, PublicKey),
     attacker_receiver: (String, PublicKey),
@@ -60,6 +70,8 @@ where
     jamming_payments: Arc<Mutex<HashSet<PaymentHash>>>,
     reputation_params: ForwardManagerParams,
     payment_trigger: (Trigger, Listener),
+    cost_tracker: Arc<CostTracker>,
+    payment_hashes: PaymentHashGenerator,
 }
 
 impl<R, J> SlowJam<R, J>
@@ -77,12 +89,21 @@ where
         honest_sender: (String, PublicKey),
         honest_receiver: (String, PublicKey),
         channel_to_jam: (PublicKey, u64),
+        reputation_params: ForwardManagerParams,
+        release_margin: u32,
         reputation_monitor: Arc<R>,
         channel_jammer: Arc<J>,
         network_graph: Arc<LdkNetworkGraph>,
+        cost_tracker: Arc<CostTracker>,
+        payment_hashes: PaymentHashGenerator,
     ) -> Self {
         Self {
+            block_clock: BlockClock::new(
+                Arc::clone(&clock),
+                reputation_params.reputation_params.expected_block_speed,
+            ),
             clock,
+            release_margin,
             target_pubkey,
             attacker_sender,
             attacker_receiver,
@@ -102,8 +123,10 @@ where
             channel_jammer,
             network_graph,
             jamming_payments: Arc::new(Mutex::new(HashSet::new())),
-            reputation_params: ForwardManagerParams::default(),
+            reputation_params,
             payment_trigger: trigger(),
+            cost_tracker,
+            payment_hashes,
         }
     }
 
@@ -133,7 +156,7 @@ where
             htlcs: vec![900_000_000; 1],
             target_channel,
             reputation_monitor: Arc::clone(&self.reputation_monitor),
-            payment_hash: PaymentHash(rand::random()),
+            payment_hash: self.payment_hashes.generate(),
             reputation_params: self.reputation_params,
             clock: Arc::clone(&self.clock),
             shutdown_listener: trigger().1,
@@ -173,7 +196,8 @@ where
         // Here attacker receiver must have enough reputation to jam the entire protected bucket.
         // So that a subsequent honest payment going to an honest receiver can't use the channel
         // even if it has reputation because all protected resources are taken.
-        let payment_hash = PaymentHash(rand::random());
+        let fees_msat = route.get_total_fees();
+        let payment_hash = self.payment_hashes.generate();
         self.jamming_payments.lock().await.insert(payment_hash);
         if let Err(e) = attacker_node_sender
             .lock()
@@ -185,6 +209,10 @@ where
             return Err(e.to_string().into());
         }
 
+        // The jamming payment will be failed back once we're done holding it, so we only pay unconditional fees.
+        self.cost_tracker
+            .record_payment(&self.attacker_sender.1, fees_msat, false)?;
+
         Ok(())
     }
 }
@@ -290,7 +318,15 @@ where
         {
             Ok(Ok(req.incoming_custom_records))
         } else {
-            let hold_time = Duration::from_secs((2016 * 10 * 60) as u64);
+            // Hold until the block before the release margin, as the expiry height is relative to the current
+            // height.
+            let expiry_height = self
+                .block_clock
+                .current_height()
+                .saturating_add(req.incoming_expiry_height);
+            let hold_time = self
+                .block_clock
+                .max_hold(expiry_height, self.release_margin);
 
             log::info!(
                 "Jamming HTLC from target -> attacker accountable, holding for {:?}: {}",
@@ -301,7 +337,8 @@ where
             self.payment_trigger.0.trigger();
 
             // If this is one of our jamming payments, hold it
-            select! {
+            let start = InstantClock::now(&*self.clock);
+            let res = select! {
                 _ = req.shutdown_listener.clone() => Ok(Err(ForwardingError::InterceptorError("shutdown signal received".to_string()))),
                 _ = self.clock.sleep(hold_time) => {
                     self.jamming_payments.lock().await.remove(&req.payment_hash);
@@ -309,7 +346,15 @@ where
                         "failing from jamming interceptor".into(),
                     )))
                 }
-            }
+            };
+
+            self.cost_tracker.record_hold(
+                &self.attacker_receiver.1,
+                req.incoming_amount_msat,
+                InstantClock::now(&*self.clock).duration_since(start),
+            )?;
+
+            res
         }
     }
 
@@ -320,7 +365,7 @@ where
     /// entire expiry time.
     async fn run_attack(
         &self,
-        _start_reputation: NetworkReputation,
+        _start_reputation: HashMap<PublicKey, NetworkReputation>,
         attacker_nodes: HashMap<String, Arc<Mutex<SimNode<SimGraph, SimulationClock>>>>,
         shutdown_listener: Listener,
     ) -> Result<(), BoxError> {
@@ -350,7 +395,7 @@ where
             )
             .map_err(|e| e.err)?;
 
-            let payment_hash = PaymentHash(rand::random());
+            let payment_hash = self.payment_hashes.generate();
             if let Err(e) = sender
                 .lock()
                 .await
@@ -387,7 +432,9 @@ where
 
         check_payment(true).await?;
 
-        let _ = self.build_reputation(&attacker_nodes).await?;
+        let reputation_fees = self.build_reputation(&attacker_nodes).await?;
+        self.cost_tracker
+            .record_payment(&self.attacker_sender.1, reputation_fees, true)?;
 
         self.channel_jammer
             .jam_general_resources(&self.target_pubkey, self.channel_to_jam.1)
@@ -424,6 +471,8 @@ where
         Ok(AttackStatisitcs {
             general_jammed_channels: 1,
             congestion_jammed_channels: 1,
+            attacker_costs: self.cost_tracker.costs()?,
+            honest_peer_reputation: None,
         })
     }
 }
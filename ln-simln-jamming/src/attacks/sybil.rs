@@ -0,0 +1,1005 @@
+use async_trait::async_trait;
+use bitcoin::secp256k1::PublicKey;
+use lightning::ln::PaymentHash;
+use lightning::routing::gossip::NetworkGraph;
+use ln_resource_mgr::forward_manager::ForwardManagerParams;
+use ln_resource_mgr::AccountableSignal;
+use sim_cli::parsing::NetworkParser;
+use simln_lib::clock::{Clock, SimulationClock};
+use simln_lib::sim_node::{
+    CustomRecords, ForwardingError, InterceptRequest, SimGraph, SimNode, WrappedLog,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::select;
+use tokio::sync::Mutex;
+use triggered::Listener;
+
+use crate::clock::InstantClock;
+use crate::reputation_interceptor::ReputationMonitor;
+use crate::revenue_interceptor::PeacetimeRevenueMonitor;
+use crate::{
+    accountable_from_records, get_network_reputation, print_request, records_from_signal, BoxError,
+    NetworkReputation, PaymentHashGenerator,
+};
+
+use super::costs::CostTracker;
+use super::utils::build_custom_route;
+use super::{AttackStatisitcs, JammingAttack};
+
+type LdkNetworkGraph = NetworkGraph<Arc<WrappedLog>>;
+
+/// The amount used for jamming htlcs. The attack checks that it fits in a single congestion slot on each of the
+/// target's honest channels, so that every htlc that falls back to congestion resources occupies a slot.
+const JAMMING_HTLC_MSAT: u64 = 1_000_000;
+
+/// The margin below the resolution period that jamming htlcs are released at, so that they're
+/// never considered slow by the target (which would mark the channel as misusing congestion
+/// resources and impact its reputation).
+const RESOLUTION_MARGIN: Duration = Duration::from_secs(10);
+
+/// The interval at which the controller dispatches new jamming htlcs. Each dispatch sends one htlc to every idle
+/// sybil via each of the target's peers, so this must be short enough that sybils fill the general slots available
+/// to them before their first htlcs are released.
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The interval at which the attack checks whether the target has lost revenue.
+const STATUS_INTERVAL: Duration = Duration::from_secs(300);
+
+/// A sybil node participating in the attack.
+#[derive(Debug)]
+struct SybilNode {
+    /// Whether the sybil has a channel with the target, and is able to receive jamming htlcs.
+    has_target_channel: bool,
+    /// Whether the sybil is holding a jamming htlc that occupies a congestion slot. The target only allows each
+    /// outgoing channel to use one congestion slot at a time, so no htlcs are dispatched to the sybil until it has
+    /// been released.
+    holding_congestion: bool,
+}
+
+/// The bucket that a jamming htlc occupies on the target's incoming channel. Sybils don't have reputation with the
+/// target, so this can be inferred from the accountable signal that the receiving sybil sees: unaccountable htlcs
+/// were assigned general resources and accountable htlcs were upgraded to use congestion resources.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum JammedBucket {
+    General,
+    Congestion,
+}
+
+impl From<AccountableSignal> for JammedBucket {
+    fn from(signal: AccountableSignal) -> Self {
+        match signal {
+            AccountableSignal::Unaccountable => JammedBucket::General,
+            AccountableSignal::Accountable => JammedBucket::Congestion,
+        }
+    }
+}
+
+/// A jamming htlc that has been dispatched by the controller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct JammingHtlc {
+    sender: PublicKey,
+    peer: PublicKey,
+    receiver: PublicKey,
+    payment_hash: PaymentHash,
+    dispatched_at: Instant,
+    /// The target's channel with the peer that the htlc was routed over, set once the htlc has been sent.
+    target_channel: Option<u64>,
+    /// The bucket that the htlc occupies on the target, set once the receiving sybil has received it.
+    bucket: Option<JammedBucket>,
+}
+
+/// Coordinates jamming htlcs between sybil nodes. Sybils keep sending htlcs through each of the target's peers to
+/// fill the general slots that the target has assigned to their channels, so that further htlcs fall back to the
+/// target's congestion resources. Every sybil's channel with the target holds at most one htlc in congestion
+/// resources at a time, as the target only allows an outgoing channel to occupy a single congestion slot, and htlcs
+/// are released before the resolution period, so no single channel will reach the misuse or reputation thresholds.
+#[derive(Debug)]
+struct SybilController {
+    sybils: HashMap<PublicKey, SybilNode>,
+    /// Pairs of (sybil, target peer) that can be used to send jamming htlcs towards the target.
+    send_paths: Vec<(PublicKey, PublicKey)>,
+    /// Index of the next send path to be used, to spread htlcs evenly over the sending sybils.
+    next_path: usize,
+    /// Jamming htlcs that have been dispatched and not yet released, keyed by payment hash.
+    in_flight: HashMap<PaymentHash, JammingHtlc>,
+    /// Dispatched htlcs that are not received within this period are assumed to have failed.
+    dispatch_timeout: Duration,
+    payment_hashes: PaymentHashGenerator,
+    /// The target's channels that jamming htlcs have occupied general and congestion resources on.
+    general_jammed: HashSet<u64>,
+    congestion_jammed: HashSet<u64>,
+}
+
+impl SybilController {
+    fn new(
+        network: &[NetworkParser],
+        target_pubkey: PublicKey,
+        sybils: &[(String, PublicKey)],
+        dispatch_timeout: Duration,
+        payment_hashes: PaymentHashGenerator,
+    ) -> Self {
+        let sybil_pubkeys: HashSet<PublicKey> = sybils.iter().map(|s| s.1).collect();
+        let target_peers: HashSet<PublicKey> = network
+            .iter()
+            .filter_map(|channel| {
+                if channel.node_1.pubkey == target_pubkey {
+                    Some(channel.node_2.pubkey)
+                } else if channel.node_2.pubkey == target_pubkey {
+                    Some(channel.node_1.pubkey)
+                } else {
+                    None
+                }
+            })
+            .filter(|pk| !sybil_pubkeys.contains(pk))
+            .collect();
+
+        let mut sybil_nodes: HashMap<PublicKey, SybilNode> = sybils
+            .iter()
+            .map(|(_, pubkey)| {
+                (
+                    *pubkey,
+                    SybilNode {
+                        has_target_channel: false,
+                        holding_congestion: false,
+                    },
+                )
+            })
+            .collect();
+
+        let mut send_paths = Vec::new();
+        for channel in network {
+            for (sybil, counterparty) in [
+                (channel.node_1.pubkey, channel.node_2.pubkey),
+                (channel.node_2.pubkey, channel.node_1.pubkey),
+            ] {
+                let node = match sybil_nodes.get_mut(&sybil) {
+                    Some(n) => n,
+                    None => continue,
+                };
+
+                if counterparty == target_pubkey {
+                    node.has_target_channel = true;
+                } else if target_peers.contains(&counterparty) {
+                    send_paths.push((sybil, counterparty));
+                }
+            }
+        }
+
+        // Sort so that htlcs are dispatched in a consistent order across runs.
+        send_paths.sort();
+
+        Self {
+            sybils: sybil_nodes,
+            send_paths,
+            next_path: 0,
+            in_flight: HashMap::new(),
+            dispatch_timeout,
+            payment_hashes,
+            general_jammed: HashSet::new(),
+            congestion_jammed: HashSet::new(),
+        }
+    }
+
+    /// Stops tracking any htlc that has not been received within the dispatch timeout, as it has most likely been
+    /// failed back before reaching the receiving sybil.
+    fn expire(&mut self, now: Instant) {
+        self.in_flight.retain(|_, htlc| {
+            htlc.bucket.is_some() || now.duration_since(htlc.dispatched_at) <= self.dispatch_timeout
+        });
+    }
+
+    /// Assigns a jamming htlc via each of the target's peers to every sybil that has a channel with the target and
+    /// isn't holding a htlc in congestion resources. Sending sybils are chosen in a round-robin manner, and are never
+    /// the receiver.
+    fn dispatch(&mut self, now: Instant) -> Vec<JammingHtlc> {
+        self.expire(now);
+
+        if self.send_paths.is_empty() {
+            return vec![];
+        }
+
+        let mut receivers: Vec<PublicKey> = self
+            .sybils
+            .iter()
+            .filter(|(_, node)| node.has_target_channel && !node.holding_congestion)
+            .map(|(pk, _)| *pk)
+            .collect();
+        receivers.sort();
+
+        let mut peers: Vec<PublicKey> = self.send_paths.iter().map(|p| p.1).collect();
+        peers.sort();
+        peers.dedup();
+
+        let mut dispatched = Vec::new();
+        for receiver in receivers {
+            for peer in peers.iter() {
+                let path = (0..self.send_paths.len())
+                    .map(|i| (self.next_path + i) % self.send_paths.len())
+                    .find(|i| {
+                        let (sender, path_peer) = self.send_paths[*i];
+                        sender != receiver && path_peer == *peer
+                    });
+
+                let (sender, peer) = match path {
+                    Some(i) => {
+                        self.next_path = (i + 1) % self.send_paths.len();
+                        self.send_paths[i]
+                    }
+                    None => continue,
+                };
+
+                let htlc = JammingHtlc {
+                    sender,
+                    peer,
+                    receiver,
+                    payment_hash: self.payment_hashes.generate(),
+                    dispatched_at: now,
+                    target_channel: None,
+                    bucket: None,
+                };
+
+                self.in_flight.insert(htlc.payment_hash, htlc.clone());
+                dispatched.push(htlc);
+            }
+        }
+
+        dispatched
+    }
+
+    /// Records the target channel that a jamming htlc was routed over once it has been sent.
+    fn sent(&mut self, payment_hash: &PaymentHash, target_channel: u64) {
+        if let Some(htlc) = self.in_flight.get_mut(payment_hash) {
+            htlc.target_channel = Some(target_channel);
+        }
+    }
+
+    /// Records that a jamming htlc has been received by its sybil, occupying the bucket provided on the target. Returns
+    /// false if the payment hash does not belong to a jamming htlc that the controller dispatched.
+    fn receive(&mut self, payment_hash: &PaymentHash, bucket: JammedBucket) -> bool {
+        let htlc = match self.in_flight.get_mut(payment_hash) {
+            Some(htlc) => htlc,
+            None => return false,
+        };
+        htlc.bucket = Some(bucket);
+
+        if bucket == JammedBucket::Congestion {
+            if let Some(node) = self.sybils.get_mut(&htlc.receiver) {
+                node.holding_congestion = true;
+            }
+        }
+
+        if let Some(channel) = htlc.target_channel {
+            match bucket {
+                JammedBucket::General => self.general_jammed.insert(channel),
+                JammedBucket::Congestion => self.congestion_jammed.insert(channel),
+            };
+        }
+
+        true
+    }
+
+    /// Releases a jamming htlc once the receiving sybil has finished holding it (or it could not be sent). If the htlc
+    /// occupied congestion resources, its receiver is freed up to be dispatched more htlcs.
+    fn release(&mut self, payment_hash: &PaymentHash) -> Option<JammingHtlc> {
+        let htlc = self.in_flight.remove(payment_hash)?;
+        if htlc.bucket == Some(JammedBucket::Congestion) {
+            if let Some(node) = self.sybils.get_mut(&htlc.receiver) {
+                node.holding_congestion = false;
+            }
+        }
+
+        Some(htlc)
+    }
+}
+
+/// Runs a sybil attack where many low-capacity attacker nodes open channels with the target and its peers. The
+/// sybils coordinate to send htlcs through the target's honest peers that fill the general resources of the target's
+/// channels, then fall back to its congestion resources. Htlcs are released just before the resolution period, so
+/// that no individual sybil channel loses reputation or is marked as misusing congestion resources.
+///
+/// The attack only supports a single target. Jamming htlcs are sized to the congestion slots of one target's
+/// channels, and each sybil can only hold one htlc in the target's congestion resources, so sybils would need to be
+/// split between targets to attack several at once.
+pub struct SybilAttack<R, M>
+where
+    R: ReputationMonitor + Send + Sync,
+    M: PeacetimeRevenueMonitor + Send + Sync,
+{
+    clock: Arc<SimulationClock>,
+    target_pubkey: PublicKey,
+    sybils: Vec<(String, PublicKey)>,
+    /// Channels that the target has with honest peers, and the peer's pubkey.
+    honest_channels: HashMap<u64, PublicKey>,
+    /// The largest htlc that fits in a single congestion slot on each of the target's honest channels.
+    congestion_limits: HashMap<u64, u64>,
+    /// Channels that the target has with sybils, and the sybil's pubkey.
+    sybil_channels: HashMap<u64, PublicKey>,
+    hold_time: Duration,
+    risk_margin: u64,
+    controller: StdMutex<SybilController>,
+    reputation_monitor: Arc<R>,
+    peacetime_revenue: Arc<M>,
+    network_graph: Arc<LdkNetworkGraph>,
+    cost_tracker: Arc<CostTracker>,
+}
+
+impl<R, M> SybilAttack<R, M>
+where
+    R: ReputationMonitor + Send + Sync,
+    M: PeacetimeRevenueMonitor + Send + Sync,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        clock: Arc<SimulationClock>,
+        network: &[NetworkParser],
+        target_pubkey: PublicKey,
+        sybils: Vec<(String, PublicKey)>,
+        forward_params: ForwardManagerParams,
+        risk_margin: u64,
+        reputation_monitor: Arc<R>,
+        peacetime_revenue: Arc<M>,
+        network_graph: Arc<LdkNetworkGraph>,
+        cost_tracker: Arc<CostTracker>,
+        payment_hashes: PaymentHashGenerator,
+    ) -> Self {
+        let sybil_pubkeys: HashSet<PublicKey> = sybils.iter().map(|s| s.1).collect();
+
+        let mut honest_channels = HashMap::new();
+        let mut sybil_channels = HashMap::new();
+        let mut congestion_limits = HashMap::new();
+        for channel in network {
+            let counterparty = if channel.node_1.pubkey == target_pubkey {
+                channel.node_2.pubkey
+            } else if channel.node_2.pubkey == target_pubkey {
+                channel.node_1.pubkey
+            } else {
+                continue;
+            };
+
+            if sybil_pubkeys.contains(&counterparty) {
+                sybil_channels.insert(channel.scid.into(), counterparty);
+            } else {
+                honest_channels.insert(channel.scid.into(), counterparty);
+                congestion_limits.insert(
+                    channel.scid.into(),
+                    forward_params.congestion_htlc_limit(channel.capacity_msat),
+                );
+            }
+        }
+
+        let hold_time = forward_params
+            .reputation_params
+            .resolution_period
+            .saturating_sub(RESOLUTION_MARGIN);
+
+        Self {
+            clock,
+            target_pubkey,
+            controller: StdMutex::new(SybilController::new(
+                network,
+                target_pubkey,
+                &sybils,
+                hold_time * 2,
+                payment_hashes,
+            )),
+            sybils,
+            honest_channels,
+            congestion_limits,
+            sybil_channels,
+            hold_time,
+            risk_margin,
+            reputation_monitor,
+            peacetime_revenue,
+            network_graph,
+            cost_tracker,
+        }
+    }
+
+    /// Sends a jamming htlc sybil -> peer -> target -> sybil, returning the target's channel with the peer that it was
+    /// routed over.
+    async fn send_jamming_htlc(
+        &self,
+        htlc: &JammingHtlc,
+        attacker_nodes: &HashMap<String, Arc<Mutex<SimNode<SimGraph, SimulationClock>>>>,
+    ) -> Result<u64, BoxError> {
+        let sender_alias = &self
+            .sybils
+            .iter()
+            .find(|s| s.1 == htlc.sender)
+            .ok_or(format!("sybil sender {} not found", htlc.sender))?
+            .0;
+
+        let sender = attacker_nodes
+            .get(sender_alias)
+            .ok_or(format!("node {} not found in attacker nodes", sender_alias))?;
+
+        let route = build_custom_route(
+            &htlc.sender,
+            JAMMING_HTLC_MSAT,
+            &[htlc.peer, self.target_pubkey, htlc.receiver],
+            &self.network_graph,
+        )
+        .map_err(|e| e.err)?;
+        let target_channel = route.paths[0].hops[1].short_channel_id;
+        let fees_msat = route.get_total_fees();
+
+        sender
+            .lock()
+            .await
+            .send_to_route(route, htlc.payment_hash, None)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // Jamming htlcs are always failed back, so the sybil will only pay unconditional fees.
+        self.cost_tracker
+            .record_payment(&htlc.sender, fees_msat, false)?;
+
+        Ok(target_channel)
+    }
+
+    fn lock_controller(&self) -> Result<std::sync::MutexGuard<'_, SybilController>, BoxError> {
+        self.controller
+            .lock()
+            .map_err(|e| format!("sybil controller lock poisoned: {e}").into())
+    }
+}
+
+#[async_trait]
+impl<R, M> JammingAttack for SybilAttack<R, M>
+where
+    R: ReputationMonitor + Send + Sync,
+    M: PeacetimeRevenueMonitor + Send + Sync,
+{
+    /// Validates that at least one sybil can receive jamming htlcs from the target, that sybils have channels
+    /// with the target's peers to send them and that jamming htlcs fit in the congestion slots of the target's
+    /// honest channels.
+    fn validate(&self) -> Result<(), BoxError> {
+        if self.sybil_channels.is_empty() {
+            return Err("sybil attack requires at least one sybil -> target channel".into());
+        }
+
+        let controller = self.lock_controller()?;
+        if controller.send_paths.is_empty() {
+            return Err("sybil attack requires at least one sybil -> target peer channel".into());
+        }
+
+        // A sybil can't send a jamming htlc to itself, so we need at least one sending sybil that is distinct from
+        // a receiving sybil.
+        let has_distinct_path = controller.send_paths.iter().any(|(sender, _)| {
+            self.sybil_channels
+                .values()
+                .any(|receiver| receiver != sender)
+        });
+        if !has_distinct_path {
+            return Err("sybil attack requires distinct sending and receiving sybils".into());
+        }
+
+        for (scid, limit) in self.congestion_limits.iter() {
+            if JAMMING_HTLC_MSAT > *limit {
+                return Err(format!(
+                    "jamming htlc amount {JAMMING_HTLC_MSAT} exceeds congestion slot limit {limit} on channel {scid}"
+                )
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops payments that are headed to the target to deprive it of revenue, and forwards all other payments.
+    async fn intercept_attacker_htlc(
+        &self,
+        req: InterceptRequest,
+    ) -> Result<Result<CustomRecords, ForwardingError>, BoxError> {
+        if let Some(outgoing_channel) = req.outgoing_channel_id {
+            if self.sybil_channels.contains_key(&outgoing_channel.into()) {
+                self.cost_tracker.record_dropped_forward(
+                    &req.forwarding_node,
+                    req.incoming_amount_msat
+                        .saturating_sub(req.outgoing_amount_msat),
+                )?;
+
+                return Ok(Err(ForwardingError::InterceptorError(
+                    "sybil failing".into(),
+                )));
+            }
+        }
+
+        Ok(Ok(records_from_signal(accountable_from_records(
+            &req.incoming_custom_records,
+        ))))
+    }
+
+    /// Holds jamming htlcs dispatched by the controller for just under the target's resolution period, then fails
+    /// them back. Any other payments received by sybils are settled.
+    async fn intercept_attacker_receive(
+        &self,
+        req: InterceptRequest,
+    ) -> Result<Result<CustomRecords, ForwardingError>, BoxError> {
+        let bucket = accountable_from_records(&req.incoming_custom_records).into();
+        if !self.lock_controller()?.receive(&req.payment_hash, bucket) {
+            return Ok(Ok(req.incoming_custom_records));
+        }
+
+        log::info!(
+            "Sybil holding jamming HTLC for {:?}: {}",
+            self.hold_time,
+            print_request(&req)
+        );
+
+        let start = InstantClock::now(&*self.clock);
+        select! {
+            _ = req.shutdown_listener.clone() => {},
+            _ = self.clock.sleep(self.hold_time) => {},
+        };
+
+        self.lock_controller()?.release(&req.payment_hash);
+        self.cost_tracker.record_hold(
+            &req.forwarding_node,
+            req.incoming_amount_msat,
+            InstantClock::now(&*self.clock).duration_since(start),
+        )?;
+
+        Ok(Err(ForwardingError::InterceptorError(
+            "failing sybil jamming htlc".into(),
+        )))
+    }
+
+    /// Continuously dispatches jamming htlcs to occupy the target's general and congestion resources. Shuts down when
+    /// the target has lost revenue compared to its projected peacetime revenue.
+    async fn run_attack(
+        &self,
+        _start_reputation: HashMap<PublicKey, NetworkReputation>,
+        attacker_nodes: HashMap<String, Arc<Mutex<SimNode<SimGraph, SimulationClock>>>>,
+        shutdown_listener: Listener,
+    ) -> Result<(), BoxError> {
+        let sybil_pubkeys: Vec<PublicKey> = self.sybils.iter().map(|s| s.1).collect();
+        let target_channels: HashMap<u64, PublicKey> = self
+            .honest_channels
+            .iter()
+            .chain(self.sybil_channels.iter())
+            .map(|(k, v)| (*k, *v))
+            .collect();
+
+        let mut last_status = InstantClock::now(&*self.clock);
+        loop {
+            select! {
+                _ = shutdown_listener.clone() => return Ok(()),
+                _ = self.clock.sleep(DISPATCH_INTERVAL) => {},
+            }
+
+            let now = InstantClock::now(&*self.clock);
+            let dispatched = self.lock_controller()?.dispatch(now);
+            for htlc in dispatched {
+                match self.send_jamming_htlc(&htlc, &attacker_nodes).await {
+                    Ok(target_channel) => self
+                        .lock_controller()?
+                        .sent(&htlc.payment_hash, target_channel),
+                    Err(e) => {
+                        log::warn!("Could not send sybil jamming htlc: {e}");
+                        self.lock_controller()?.release(&htlc.payment_hash);
+                    }
+                }
+            }
+
+            if now.duration_since(last_status) < STATUS_INTERVAL {
+                continue;
+            }
+            last_status = now;
+
+            let snapshot = self
+                .peacetime_revenue
+                .get_revenue_difference(&self.target_pubkey)
+                .await;
+            if snapshot.peacetime_revenue_msat > snapshot.simulation_revenue_msat {
+                log::error!(
+                    "Peacetime revenue: {} exceeds simulation revenue: {} after: {:?}",
+                    snapshot.peacetime_revenue_msat,
+                    snapshot.simulation_revenue_msat,
+                    snapshot.runtime
+                );
+
+                return Ok(());
+            }
+
+            let current_reputation = get_network_reputation(
+                self.reputation_monitor.clone(),
+                self.target_pubkey,
+                &sybil_pubkeys,
+                &target_channels,
+                self.risk_margin,
+                now,
+            )
+            .await?;
+
+            log::debug!(
+                "Sybils have good reputation with target for: {}/{} pairs",
+                current_reputation.attacker_reputation,
+                current_reputation.attacker_pair_count
+            );
+
+            log::debug!(
+                "Target has good reputation with peers for: {}/{} pairs",
+                current_reputation.target_reputation,
+                current_reputation.target_pair_count
+            );
+        }
+    }
+
+    fn attack_statistics(&self) -> Result<AttackStatisitcs, BoxError> {
+        let controller = self.lock_controller()?;
+        Ok(AttackStatisitcs {
+            general_jammed_channels: controller.general_jammed.len(),
+            congestion_jammed_channels: controller.congestion_jammed.len(),
+            attacker_costs: self.cost_tracker.costs()?,
+            honest_peer_reputation: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use bitcoin::secp256k1::PublicKey;
+    use ln_resource_mgr::forward_manager::ForwardManagerParams;
+    use ln_resource_mgr::AccountableSignal;
+    use sim_cli::parsing::NetworkParser;
+    use simln_lib::clock::SimulationClock;
+    use simln_lib::sim_node::{ForwardingError, InterceptRequest};
+
+    use crate::attacks::costs::{CostParams, CostTracker};
+    use crate::attacks::JammingAttack;
+    use crate::test_utils::{
+        get_random_keypair, get_test_policy, setup_test_request, MockPeacetimeMonitor,
+        MockReputationInterceptor,
+    };
+    use crate::{accountable_from_records, PaymentHashGenerator};
+
+    use super::{JammedBucket, JammingHtlc, SybilAttack, SybilController, RESOLUTION_MARGIN};
+
+    /// Creates a network where the target has two honest peers, and three sybils have channels with the target.
+    /// Sybils 0 and 1 also have channels with the first peer.
+    ///
+    /// S0 --+
+    ///      P0 -- target -- P1
+    /// S1 --+       |
+    ///          S0, S1, S2
+    fn setup_test_network() -> (Vec<NetworkParser>, PublicKey, Vec<(String, PublicKey)>) {
+        let target = get_random_keypair().1;
+        let peer_0 = get_random_keypair().1;
+        let peer_1 = get_random_keypair().1;
+        let sybils: Vec<(String, PublicKey)> = (0..3)
+            .map(|i| (format!("sybil_{i}"), get_random_keypair().1))
+            .collect();
+
+        let edge = |scid: u64, node_1: PublicKey, node_2: PublicKey| NetworkParser {
+            scid: scid.into(),
+            capacity_msat: 100_000,
+            node_1: get_test_policy(node_1),
+            node_2: get_test_policy(node_2),
+        };
+
+        let network = vec![
+            edge(0, target, peer_0),
+            edge(1, target, peer_1),
+            edge(2, sybils[0].1, target),
+            edge(3, sybils[1].1, target),
+            edge(4, sybils[2].1, target),
+            edge(5, sybils[0].1, peer_0),
+            edge(6, peer_0, sybils[1].1),
+        ];
+
+        (network, target, sybils)
+    }
+
+    fn setup_test_attack(
+        network: &[NetworkParser],
+        target: PublicKey,
+        sybils: Vec<(String, PublicKey)>,
+    ) -> SybilAttack<MockReputationInterceptor, MockPeacetimeMonitor> {
+        let graph = crate::parsing::network_graph(network.to_vec()).unwrap();
+        let cost_tracker = Arc::new(CostTracker::new(CostParams::default(), network, &sybils));
+
+        SybilAttack::new(
+            Arc::new(SimulationClock::new(1).unwrap()),
+            network,
+            target,
+            sybils,
+            ForwardManagerParams::default(),
+            0,
+            Arc::new(MockReputationInterceptor::new()),
+            Arc::new(MockPeacetimeMonitor::new()),
+            graph,
+            cost_tracker,
+            PaymentHashGenerator::new(0),
+        )
+    }
+
+    /// Dispatches a round of jamming htlcs and records them as sent over the target's channel with the first peer,
+    /// returning the first htlc and a request for it arriving at its receiving sybil with the signal provided.
+    fn dispatch_test_htlc(
+        attack: &SybilAttack<MockReputationInterceptor, MockPeacetimeMonitor>,
+        signal: AccountableSignal,
+    ) -> (JammingHtlc, InterceptRequest) {
+        let mut controller = attack.lock_controller().unwrap();
+        let htlc = controller.dispatch(Instant::now()).remove(0);
+        controller.sent(&htlc.payment_hash, 0);
+
+        let receiver_channel = *attack
+            .sybil_channels
+            .iter()
+            .find(|(_, pk)| **pk == htlc.receiver)
+            .unwrap()
+            .0;
+
+        let mut request = setup_test_request(htlc.receiver, receiver_channel, 0, signal);
+        request.payment_hash = htlc.payment_hash;
+        request.outgoing_channel_id = None;
+
+        (htlc, request)
+    }
+
+    /// Tests that a jamming htlc received with an accountable signal is tracked as occupying the target's congestion
+    /// resources while it's held, and is failed back before the target's resolution period less our margin.
+    #[tokio::test(start_paused = true)]
+    async fn test_intercept_receive_congestion_hold() {
+        let (network, target, sybils) = setup_test_network();
+        let attack = Arc::new(setup_test_attack(&network, target, sybils));
+        let resolution_period = ForwardManagerParams::default()
+            .reputation_params
+            .resolution_period;
+
+        let (htlc, request) = dispatch_test_htlc(&attack, AccountableSignal::Accountable);
+
+        let start = tokio::time::Instant::now();
+        let attack_clone = Arc::clone(&attack);
+        let hold =
+            tokio::spawn(async move { attack_clone.intercept_attacker_receive(request).await });
+
+        // While the htlc is held, its receiver is paused and the target channel is counted as congestion jammed.
+        tokio::time::sleep(resolution_period / 2).await;
+        {
+            let controller = attack.lock_controller().unwrap();
+            assert_eq!(
+                controller.in_flight.get(&htlc.payment_hash).unwrap().bucket,
+                Some(JammedBucket::Congestion)
+            );
+            assert!(
+                controller
+                    .sybils
+                    .get(&htlc.receiver)
+                    .unwrap()
+                    .holding_congestion
+            );
+            assert_eq!(controller.congestion_jammed, HashSet::from([0]));
+            assert!(controller.general_jammed.is_empty());
+        }
+
+        let res = hold.await.unwrap().unwrap();
+        assert!(matches!(res, Err(ForwardingError::InterceptorError(_))));
+
+        let held = tokio::time::Instant::now().duration_since(start);
+        assert!(held >= attack.hold_time);
+        assert!(held <= resolution_period - RESOLUTION_MARGIN);
+
+        // Once released, the receiver can be dispatched htlcs again.
+        let controller = attack.lock_controller().unwrap();
+        assert!(!controller.in_flight.contains_key(&htlc.payment_hash));
+        assert!(
+            !controller
+                .sybils
+                .get(&htlc.receiver)
+                .unwrap()
+                .holding_congestion
+        );
+    }
+
+    /// Tests that a jamming htlc received with an unaccountable signal is tracked as occupying the target's general
+    /// resources, and does not pause further dispatches to its receiver.
+    #[tokio::test(start_paused = true)]
+    async fn test_intercept_receive_general_hold() {
+        let (network, target, sybils) = setup_test_network();
+        let attack = Arc::new(setup_test_attack(&network, target, sybils));
+
+        let (htlc, request) = dispatch_test_htlc(&attack, AccountableSignal::Unaccountable);
+
+        let attack_clone = Arc::clone(&attack);
+        let hold =
+            tokio::spawn(async move { attack_clone.intercept_attacker_receive(request).await });
+
+        tokio::time::sleep(attack.hold_time / 2).await;
+        {
+            let controller = attack.lock_controller().unwrap();
+            assert_eq!(
+                controller.in_flight.get(&htlc.payment_hash).unwrap().bucket,
+                Some(JammedBucket::General)
+            );
+            assert!(
+                !controller
+                    .sybils
+                    .get(&htlc.receiver)
+                    .unwrap()
+                    .holding_congestion
+            );
+            assert_eq!(controller.general_jammed, HashSet::from([0]));
+            assert!(controller.congestion_jammed.is_empty());
+        }
+
+        let res = hold.await.unwrap().unwrap();
+        assert!(matches!(res, Err(ForwardingError::InterceptorError(_))));
+        assert!(!attack
+            .lock_controller()
+            .unwrap()
+            .in_flight
+            .contains_key(&htlc.payment_hash));
+    }
+
+    /// Tests that payments received by sybils that weren't dispatched by the controller are settled immediately.
+    #[tokio::test(start_paused = true)]
+    async fn test_intercept_receive_unknown() {
+        let (network, target, sybils) = setup_test_network();
+        let attack = setup_test_attack(&network, target, sybils.clone());
+
+        let start = tokio::time::Instant::now();
+        let request = setup_test_request(sybils[0].1, 2, 0, AccountableSignal::Accountable);
+        let res = attack
+            .intercept_attacker_receive(request)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(accountable_from_records(&res) == AccountableSignal::Accountable);
+        assert_eq!(tokio::time::Instant::now(), start);
+
+        let costs = attack.cost_tracker.costs().unwrap();
+        assert!(costs.iter().all(|c| c.htlcs_held == 0));
+    }
+
+    /// Tests that holds are recorded against the receiving sybil, and dropped forwards against the forwarding sybil,
+    /// without charging any other sybil.
+    #[tokio::test(start_paused = true)]
+    async fn test_intercept_costs() {
+        let (network, target, sybils) = setup_test_network();
+        let attack = setup_test_attack(&network, target, sybils.clone());
+
+        let (htlc, request) = dispatch_test_htlc(&attack, AccountableSignal::Accountable);
+        let amount_msat = request.incoming_amount_msat;
+        attack
+            .intercept_attacker_receive(request)
+            .await
+            .unwrap()
+            .unwrap_err();
+
+        // Sybils drop payments that they're asked to forward to the target.
+        let request = setup_test_request(sybils[0].1, 5, 2, AccountableSignal::Unaccountable);
+        let dropped_fee = request.incoming_amount_msat - request.outgoing_amount_msat;
+        attack
+            .intercept_attacker_htlc(request)
+            .await
+            .unwrap()
+            .unwrap_err();
+
+        // Payments that don't go to the target are forwarded with their signal unchanged.
+        let request = setup_test_request(sybils[1].1, 6, 0, AccountableSignal::Accountable);
+        let res = attack
+            .intercept_attacker_htlc(request)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(accountable_from_records(&res) == AccountableSignal::Accountable);
+
+        for cost in attack.cost_tracker.costs().unwrap() {
+            let pubkey = sybils.iter().find(|s| s.0 == cost.alias).unwrap().1;
+
+            if pubkey == htlc.receiver {
+                assert_eq!(cost.htlcs_held, 1);
+                assert_eq!(
+                    cost.liquidity_locked_msat_secs,
+                    amount_msat as u128 * attack.hold_time.as_secs() as u128
+                );
+            } else {
+                assert_eq!(cost.htlcs_held, 0);
+                assert_eq!(cost.liquidity_locked_msat_secs, 0);
+            }
+
+            if pubkey == sybils[0].1 {
+                assert_eq!(cost.lost_revenue_msat, dropped_fee);
+            } else {
+                assert_eq!(cost.lost_revenue_msat, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_controller_setup() {
+        let (network, target, sybils) = setup_test_network();
+        let controller = SybilController::new(
+            &network,
+            target,
+            &sybils,
+            Duration::from_secs(10),
+            PaymentHashGenerator::new(0),
+        );
+
+        assert_eq!(controller.send_paths.len(), 2);
+        assert!(controller.sybils.values().all(|s| s.has_target_channel));
+    }
+
+    /// Tests that sybils keep being dispatched htlcs while they occupy general resources, and are only paused while
+    /// they hold a htlc in congestion resources.
+    #[test]
+    fn test_controller_dispatch_receive_release() {
+        let (network, target, sybils) = setup_test_network();
+        let mut controller = SybilController::new(
+            &network,
+            target,
+            &sybils,
+            Duration::from_secs(10),
+            PaymentHashGenerator::new(0),
+        );
+
+        let now = Instant::now();
+        let dispatched = controller.dispatch(now);
+        assert_eq!(dispatched.len(), 3);
+        assert!(dispatched.iter().all(|h| h.sender != h.receiver));
+
+        // Htlcs that occupy general resources don't stop further dispatches to their receiver.
+        controller.sent(&dispatched[0].payment_hash, 0);
+        assert!(controller.receive(&dispatched[0].payment_hash, JammedBucket::General));
+        assert_eq!(controller.dispatch(now).len(), 3);
+        assert_eq!(controller.general_jammed, HashSet::from([0]));
+
+        // Once a receiver holds a congestion htlc, it is not dispatched any more htlcs until it's released.
+        controller.sent(&dispatched[1].payment_hash, 0);
+        assert!(controller.receive(&dispatched[1].payment_hash, JammedBucket::Congestion));
+        assert_eq!(controller.congestion_jammed, HashSet::from([0]));
+
+        let paused = controller.dispatch(now);
+        assert_eq!(paused.len(), 2);
+        assert!(paused.iter().all(|h| h.receiver != dispatched[1].receiver));
+
+        assert_eq!(
+            controller
+                .release(&dispatched[1].payment_hash)
+                .unwrap()
+                .receiver,
+            dispatched[1].receiver
+        );
+        assert_eq!(controller.dispatch(now).len(), 3);
+
+        // Unknown htlcs are not treated as jamming htlcs.
+        assert!(controller.release(&dispatched[1].payment_hash).is_none());
+        assert!(!controller.receive(&dispatched[1].payment_hash, JammedBucket::General));
+    }
+
+    /// Tests that htlcs that are never received are expired after the dispatch timeout, while htlcs that are being
+    /// held by sybils are kept until they're released.
+    #[test]
+    fn test_controller_expire() {
+        let (network, target, sybils) = setup_test_network();
+        let mut controller = SybilController::new(
+            &network,
+            target,
+            &sybils,
+            Duration::from_secs(10),
+            PaymentHashGenerator::new(0),
+        );
+
+        let now = Instant::now();
+        let dispatched = controller.dispatch(now);
+        assert!(controller.receive(&dispatched[0].payment_hash, JammedBucket::General));
+
+        controller.expire(now + Duration::from_secs(5));
+        assert_eq!(controller.in_flight.len(), 3);
+
+        controller.expire(now + Duration::from_secs(11));
+        assert_eq!(controller.in_flight.len(), 1);
+        assert!(controller
+            .in_flight
+            .contains_key(&dispatched[0].payment_hash));
+    }
+}
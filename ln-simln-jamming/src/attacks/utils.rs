@@ -203,7 +203,7 @@ fn fee_to_build_reputation(
                 .position(|hop| hop.pubkey == target_hop_pubkey)
             {
                 // Add up the cltv_delta(s) from the target hop to get the total number of blocks
-                // until the htlc can be resolved. Here we assume the current height is 0.
+                // until the htlc can be resolved. Route expiries are relative to the current height.
                 //
                 // We use `build_route_from_hops` from LDK to build the routes for the
                 // payments. However, that method adds a random CLTV offset so the route we got
@@ -269,6 +269,7 @@ mod tests {
         attacks::utils::{
             build_custom_route, build_reputation, BuildReputationParams, CLTV_OFFSET_LDK,
         },
+        deployment::NetworkDeployment,
         records_from_signal,
         reputation_interceptor::{ChannelJammer, ReputationInterceptor},
         test_utils::{get_random_keypair, setup_test_edge},
@@ -433,8 +434,15 @@ mod tests {
 
         let clock = Arc::new(SimulationClock::new(1).unwrap());
         let reputation_interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
-            ReputationInterceptor::new_for_network(params, &edges, Arc::clone(&clock), None)
-                .unwrap();
+            ReputationInterceptor::new_for_network(
+                params,
+                &NetworkDeployment::default(),
+                0,
+                &edges,
+                Arc::clone(&clock),
+                None,
+            )
+            .unwrap();
 
         let network_graph = {
             let channels = edges
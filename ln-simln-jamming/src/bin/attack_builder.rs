@@ -0,0 +1,92 @@
+use clap::Parser;
+use ln_simln_jamming::network_gen::{
+    add_attackers, write_attack, GeneratedAttack, PlacementParams,
+};
+use ln_simln_jamming::parsing::{AttackType, NetworkParams, NetworkType, DEFAULT_SEED};
+use ln_simln_jamming::BoxError;
+use log::LevelFilter;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use simple_logger::SimpleLogger;
+
+/// Builds the attack time graph for an attack by adding attacking nodes to a network's peacetime graph, and writes it
+/// along with the attack's attacker.csv.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    network: NetworkParams,
+
+    /// The attack to build a graph for.
+    #[arg(long, value_enum)]
+    attack_type: AttackType,
+
+    #[command(flatten)]
+    placement: PlacementParams,
+
+    /// Overwrite the attack's graph if it already exists.
+    #[arg(long)]
+    overwrite: bool,
+
+    /// The seed that all random number generation is derived from.
+    #[arg(long, default_value = DEFAULT_SEED)]
+    seed: u64,
+}
+
+fn main() -> Result<(), BoxError> {
+    SimpleLogger::new()
+        .with_level(LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+
+    let attack_graph = cli
+        .network
+        .network_dir
+        .join("attacks")
+        .join(format!("{:?}", cli.attack_type))
+        .join("attacktime_network.json");
+    if attack_graph.exists() && !cli.overwrite {
+        return Err(format!(
+            "attack graph {} already exists, run with --overwrite to replace it",
+            attack_graph.to_string_lossy()
+        )
+        .into());
+    }
+
+    let network = NetworkType::new(&cli.network, None, None)?;
+    let targets = network.targets();
+
+    let mut rng = StdRng::seed_from_u64(cli.seed);
+    let (attacktime, attackers) = add_attackers(
+        network.active_network(),
+        &targets.iter().map(|t| t.1).collect::<Vec<_>>(),
+        &cli.placement,
+        &mut rng,
+    )?;
+
+    write_attack(
+        &cli.network.network_dir,
+        &GeneratedAttack {
+            attack_type: cli.attack_type.clone(),
+            attacktime,
+            attackers,
+        },
+    )?;
+
+    // Load the attack back to check that its graph only differs from the peacetime graph by attacker channels.
+    let attack_network = NetworkType::new(&cli.network, Some(cli.attack_type), None)?;
+    log::info!(
+        "Wrote attack graph to {} with attackers {:?} for targets {:?}",
+        attack_graph.to_string_lossy(),
+        attack_network
+            .attackers()
+            .iter()
+            .map(|a| a.0.as_str())
+            .collect::<Vec<_>>(),
+        targets.iter().map(|t| t.0.as_str()).collect::<Vec<_>>(),
+    );
+
+    Ok(())
+}
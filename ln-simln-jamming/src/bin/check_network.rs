@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use clap::Parser;
+use ln_resource_mgr::forward_manager::ForwardManagerParams;
+use ln_simln_jamming::network_check::{check_network, Severity};
+use ln_simln_jamming::parsing::{parse_duration, NetworkParams, ReputationParams};
+use ln_simln_jamming::BoxError;
+
+/// Checks that a network directory has all the files that simulations require, and that they're consistent with each
+/// other, reporting any problems found along with how to fix them.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    network: NetworkParams,
+
+    /// The parameters that simulations will be run with, used to check that traffic spans the reputation window.
+    #[command(flatten)]
+    reputation_params: ReputationParams,
+
+    /// The duration that attacker reputation will be bootstrapped for, expressed as human readable values (eg: 1w,
+    /// 3d). Each attack is checked for the traffic, reputation snapshot and revenue files that it requires.
+    #[arg(long, value_parser = parse_duration)]
+    attacker_bootstrap: Option<Duration>,
+
+    /// Only print warnings and errors.
+    #[arg(long)]
+    quiet: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    let cli = Cli::parse();
+    let forward_params: ForwardManagerParams = cli.reputation_params.into();
+
+    let report = check_network(
+        &cli.network,
+        forward_params.reputation_params.reputation_window(),
+        cli.attacker_bootstrap,
+    )
+    .await;
+
+    for finding in report.findings.iter() {
+        if !cli.quiet || finding.severity != Severity::Ok {
+            println!("{finding}");
+        }
+    }
+
+    let errors = report.count(Severity::Error);
+    println!(
+        "\n{} checks passed, {} warnings, {errors} errors",
+        report.count(Severity::Ok),
+        report.count(Severity::Warning),
+    );
+
+    if errors > 0 {
+        return Err(format!("network check found {errors} errors").into());
+    }
+
+    Ok(())
+}
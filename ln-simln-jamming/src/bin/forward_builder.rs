@@ -1,19 +1,25 @@
 use async_trait::async_trait;
 use bitcoin::secp256k1::PublicKey;
 use clap::Parser;
+use ln_resource_mgr::forward_manager::ForwardManager;
 use ln_resource_mgr::{AllocationCheck, ProposedForward};
 use ln_simln_jamming::analysis::ForwardReporter;
 use ln_simln_jamming::clock::InstantClock;
+use ln_simln_jamming::deployment::NetworkDeployment;
+use ln_simln_jamming::latency_interceptor::LatencyInterceptor;
 use ln_simln_jamming::parsing::{
-    parse_duration, AttackType, NetworkParams, NetworkType, ReputationParams,
+    parse_duration, AttackType, NetworkParams, NetworkType, ReputationParams, DEFAULT_SEED,
+};
+use ln_simln_jamming::payments::{
+    payment_stats_file, payments_file, write_payment_stats, write_payments, PaymentStats,
+    PaymentTracker,
 };
 use ln_simln_jamming::reputation_interceptor::{BootstrapForward, ReputationInterceptor};
-use ln_simln_jamming::{BoxError, ACCOUNTABLE_TYPE, UPGRADABLE_TYPE};
+use ln_simln_jamming::{BoxError, SeedComponent, ACCOUNTABLE_TYPE, UPGRADABLE_TYPE};
 use log::LevelFilter;
 use sim_cli::parsing::{create_simulation_with_network, SimParams};
 use simln_lib::batched_writer::BatchedWriter;
 use simln_lib::clock::{Clock, SimulationClock};
-use simln_lib::latency_interceptor::LatencyIntercepor;
 use simln_lib::sim_node::CustomRecords;
 use simln_lib::SimulationCfg;
 use simple_logger::SimpleLogger;
@@ -41,6 +47,10 @@ struct Cli {
     /// The attack that we're interested in running.
     #[arg(long, value_enum)]
     pub attack_type: Option<AttackType>,
+
+    /// The seed that all random number generation is derived from.
+    #[arg(long, default_value = DEFAULT_SEED)]
+    pub seed: u64,
 }
 
 #[tokio::main]
@@ -66,40 +76,58 @@ async fn main() -> Result<(), BoxError> {
     let clock = Arc::new(SimulationClock::new(1000)?);
     let tasks = TaskTracker::new();
 
+    // Track honest payments so that simulations run on this traffic can compare against them.
+    let attacker_pubkeys: Vec<PublicKey> = network.attackers().iter().map(|a| a.1).collect();
+    let payment_tracker = Arc::new(PaymentTracker::new(
+        clock.clone(),
+        sim_network,
+        &attacker_pubkeys,
+    ));
+
     // Create a reputation interceptor without any bootstrap (since here we're creating the
     // bootstrap itself, we just want to run with reputation active).
     let traffic_file = network.traffic_file();
-    let reputation_interceptor = Arc::new(ReputationInterceptor::new_for_network(
-        cli.reputation_params.into(),
-        sim_network,
-        clock.clone(),
-        Some(Arc::new(Mutex::new(BootstrapWriter::new(
+    let reputation_interceptor: Arc<ReputationInterceptor<_, ForwardManager>> = Arc::new(
+        ReputationInterceptor::new_for_network(
+            cli.reputation_params.into(),
+            &NetworkDeployment::default(),
+            SeedComponent::ForwardManagers.seed(cli.seed),
+            sim_network,
             clock.clone(),
-            // TODO: change API in SimLN so that we can just pass a path in here.
-            traffic_file
-                .parent()
-                .ok_or("could not get traffic file directory")?
-                .to_path_buf(),
-            traffic_file
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-                .to_string(),
-        )?))),
+            Some(Arc::new(Mutex::new(BootstrapWriter::new(
+                clock.clone(),
+                // TODO: change API in SimLN so that we can just pass a path in here.
+                traffic_file
+                    .parent()
+                    .ok_or("could not get traffic file directory")?
+                    .to_path_buf(),
+                traffic_file
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+            )?))),
+        )?
+        .with_failure_reporter(payment_tracker.clone()),
+    );
+    let latency_interceptor = Arc::new(LatencyInterceptor::new_poisson(
+        300.0,
+        SeedComponent::Latency.seed(cli.seed),
     )?);
-    let latency_interceptor = Arc::new(LatencyIntercepor::new_poisson(300.0)?);
 
     let sim_cfg = SimulationCfg::new(
         Some(cli.duration.as_secs() as u32),
         3_800_000,
         2.0,
         None,
-        Some(13995354354227336701),
+        Some(SeedComponent::Activity.seed(cli.seed)),
     );
 
-    let exclude_pubkeys = [network.target().1]
-        .into_iter()
-        .chain(network.attackers().iter().map(|a| a.1))
+    let exclude_pubkeys = network
+        .targets()
+        .iter()
+        .chain(network.attackers().iter())
+        .map(|n| n.1)
         .collect();
 
     let custom_records =
@@ -117,13 +145,24 @@ async fn main() -> Result<(), BoxError> {
         &sim_params,
         clock,
         tasks,
-        vec![reputation_interceptor, latency_interceptor],
+        vec![
+            reputation_interceptor,
+            latency_interceptor,
+            payment_tracker.clone(),
+        ],
         custom_records,
     )
     .await?;
 
     simulation.run(&validated_activities).await?;
 
+    let payments = payment_tracker.payments()?;
+    write_payments(&payments_file(&traffic_file), &payments)?;
+    write_payment_stats(
+        &payment_stats_file(&traffic_file),
+        &PaymentStats::new(&payments),
+    )?;
+
     Ok(())
 }
 
@@ -164,8 +203,14 @@ impl ForwardReporter for BootstrapWriter {
             .queue(BootstrapForward {
                 incoming_amt: forward.amount_in_msat,
                 outgoing_amt: forward.amount_out_msat,
-                incoming_expiry: forward.expiry_in_height,
-                outgoing_expiry: forward.expiry_out_height,
+                // Expiry heights are stored relative to the height the htlc was added at, as they are replayed from a
+                // zero block height.
+                incoming_expiry: forward
+                    .expiry_in_height
+                    .saturating_sub(forward.added_height),
+                outgoing_expiry: forward
+                    .expiry_out_height
+                    .saturating_sub(forward.added_height),
                 added_ns: settled_ns - nanos_since_added,
                 settled_ns,
                 forwarding_node,
@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Parser;
+use ln_simln_jamming::graph_import::{import_graph, write_imported, GraphFormat, ImportFilters};
+use ln_simln_jamming::BoxError;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+
+/// Converts a graph dump from a lightning node into a network directory's peacetime_network.json.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The implementation that produced the graph dump.
+    #[arg(long, value_enum)]
+    format: GraphFormat,
+
+    /// The json output of LND's describegraph or CLN's listchannels.
+    #[arg(long)]
+    graph_file: PathBuf,
+
+    /// The json output of CLN's listnodes, used to set node aliases.
+    #[arg(long)]
+    cln_nodes_file: Option<PathBuf>,
+
+    /// The directory that the network is written to.
+    #[arg(long)]
+    network_dir: PathBuf,
+
+    #[command(flatten)]
+    filters: ImportFilters,
+}
+
+fn main() -> Result<(), BoxError> {
+    SimpleLogger::new()
+        .with_level(LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+    if cli.cln_nodes_file.is_some() && cli.format != GraphFormat::Cln {
+        return Err("cln nodes file can only be used with cln format".into());
+    }
+
+    let dump = fs::read_to_string(&cli.graph_file)?;
+    let cln_nodes = cli
+        .cln_nodes_file
+        .as_ref()
+        .map(fs::read_to_string)
+        .transpose()?;
+
+    let imported = import_graph(cli.format, &dump, cln_nodes.as_deref(), &cli.filters)?;
+    write_imported(
+        &cli.network_dir,
+        &imported.graph,
+        cli.filters.target.as_deref(),
+    )?;
+
+    log::info!(
+        "Imported {} channels to {}, skipped {} channels with missing or disabled policies",
+        imported.graph.len(),
+        cli.network_dir.to_string_lossy(),
+        imported.skipped_channels,
+    );
+
+    Ok(())
+}
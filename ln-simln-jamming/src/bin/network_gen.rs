@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use ln_simln_jamming::network_gen::{generate_network, write_network, NetworkGenParams};
+use ln_simln_jamming::parsing::{AttackType, DEFAULT_SEED};
+use ln_simln_jamming::BoxError;
+use log::LevelFilter;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use simple_logger::SimpleLogger;
+
+/// Generates a synthetic network directory, with a peacetime graph, a target picked by centrality and optionally the
+/// files required to run an attack against it.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The directory that the network is written to.
+    #[arg(long)]
+    network_dir: PathBuf,
+
+    #[command(flatten)]
+    params: NetworkGenParams,
+
+    /// The attack to generate attacker channels for.
+    #[arg(long, value_enum)]
+    attack_type: Option<AttackType>,
+
+    /// The number of attacking nodes to add, defaults to one for sink attacks, two for revenue inflation attacks and
+    /// five for sybil attacks.
+    #[arg(long, requires = "attack_type")]
+    attacker_count: Option<usize>,
+
+    /// The seed that all random number generation is derived from.
+    #[arg(long, default_value = DEFAULT_SEED)]
+    seed: u64,
+}
+
+fn main() -> Result<(), BoxError> {
+    SimpleLogger::new()
+        .with_level(LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+    if cli.network_dir.join("peacetime_network.json").exists() {
+        return Err(format!(
+            "network already exists in {}",
+            cli.network_dir.to_string_lossy()
+        )
+        .into());
+    }
+
+    let mut rng = StdRng::seed_from_u64(cli.seed);
+    let network = generate_network(
+        &cli.params,
+        cli.attack_type.map(|a| (a, cli.attacker_count)),
+        &mut rng,
+    )?;
+    write_network(&cli.network_dir, &network)?;
+
+    log::info!(
+        "Generated {:?} network with {} channels and target {} in {}",
+        cli.params.topology,
+        network.peacetime.len(),
+        network.target,
+        cli.network_dir.to_string_lossy()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Parser;
+use ln_simln_jamming::plot::{
+    average_chart, bucket_utilisation, pair_chart, read_forwards, register_font, render,
+    reputation_series, utilisation_chart, ImageFormat,
+};
+use ln_simln_jamming::BoxError;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+
+/// Plots reputation and bucket utilisation for outgoing channels from a node's simulation results file.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// A node's results file, written to the results directory as {alias}_{pubkey prefix}.csv.
+    #[arg(long)]
+    results_file: PathBuf,
+
+    /// The outgoing channel to plot, if not set every outgoing channel in the results file is plotted.
+    #[arg(long)]
+    outgoing_channel: Option<u64>,
+
+    /// The directory to write charts to, defaults to the directory containing the results file. Charts for each
+    /// outgoing channel are written to {outgoing channel}_reputation within it.
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// The image format to render charts in.
+    #[arg(long, value_enum, default_value = "png")]
+    format: ImageFormat,
+
+    /// The number of samples that each pair's reputation is interpolated over to calculate the average.
+    #[arg(long, default_value = "1000")]
+    samples: usize,
+
+    /// The TrueType font file that chart text is rendered with.
+    #[arg(
+        long,
+        default_value = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"
+    )]
+    font_file: PathBuf,
+}
+
+fn main() -> Result<(), BoxError> {
+    SimpleLogger::new()
+        .with_level(LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+    register_font(&cli.font_file)?;
+    let rows = read_forwards(&cli.results_file)?;
+
+    let outgoing_channels: BTreeSet<u64> = match cli.outgoing_channel {
+        Some(channel) => BTreeSet::from([channel]),
+        None => rows.iter().map(|r| r.outgoing_channel_id).collect(),
+    };
+
+    let output_dir = match cli.output_dir {
+        Some(dir) => dir,
+        None => cli
+            .results_file
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default(),
+    };
+    let ext = cli.format.extension();
+
+    for outgoing in outgoing_channels {
+        let series = reputation_series(&rows, outgoing);
+        if series.is_empty() {
+            log::warn!("No forwards found for outgoing channel {outgoing}");
+            continue;
+        }
+
+        let channel_dir = output_dir.join(format!("{outgoing}_reputation"));
+        fs::create_dir_all(&channel_dir)?;
+
+        for (incoming, points) in series.iter() {
+            let path = channel_dir.join(format!("incoming_{incoming}.{ext}"));
+            render(&pair_chart(outgoing, *incoming, points), &path, cli.format)?;
+        }
+
+        render(
+            &average_chart(outgoing, &series, cli.samples),
+            &channel_dir.join(format!("average.{ext}")),
+            cli.format,
+        )?;
+
+        match bucket_utilisation(&rows, outgoing) {
+            Ok(points) => render(
+                &utilisation_chart(outgoing, &points),
+                &channel_dir.join(format!("utilisation.{ext}")),
+                cli.format,
+            )?,
+            Err(e) => log::warn!("Skipping bucket utilisation for {outgoing}: {e}"),
+        }
+
+        log::info!(
+            "Wrote charts for {} incoming channels to {}",
+            series.len(),
+            channel_dir.to_string_lossy()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+use ln_resource_mgr::forward_manager::ForwardManagerParams;
+use ln_simln_jamming::analysis::PRIMARY_CONFIG;
+use ln_simln_jamming::parsing::{
+    find_pubkey_by_alias, history_from_file, parse_duration, shadow_params_from_file, AttackType,
+    NetworkParams, NetworkType, ReputationParams, DEFAULT_SEED,
+};
+use ln_simln_jamming::replay::{
+    replay, replay_htlcs_from_results, write_replay_results, ReplayHtlc,
+};
+use ln_simln_jamming::{BoxError, SeedComponent};
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+
+/// The default directory that replay results are written to.
+pub const DEFAULT_OUTPUT_DIR: &str = "replay";
+
+/// Replays recorded htlc forwards through fresh forward managers with alternative parameters, without running a
+/// simulation, and reports how each parameter set's decisions differ.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    network: NetworkParams,
+
+    /// The attack whose graph the recorded forwards were made on, if any.
+    #[arg(long, value_enum)]
+    attack_type: Option<AttackType>,
+
+    /// A traffic file of forwards to replay, defaults to the network's traffic file.
+    #[arg(long, conflicts_with = "results_file")]
+    traffic_file: Option<PathBuf>,
+
+    /// A node's results file from a simulation to replay forwards from.
+    #[arg(long, requires_all = ["node", "resolve_after"])]
+    results_file: Option<PathBuf>,
+
+    /// The alias of the node that forwarded the htlcs in the results file.
+    #[arg(long, requires = "results_file")]
+    node: Option<String>,
+
+    /// The time that htlcs from a results file are held for before they are settled, as results files don't record
+    /// resolutions. Expressed as human readable values (eg: 90s, 10m).
+    #[arg(long, value_parser = parse_duration, requires = "results_file")]
+    resolve_after: Option<Duration>,
+
+    /// The parameters that decisions are compared against.
+    #[command(flatten)]
+    reputation_params: ReputationParams,
+
+    /// A json file of named parameter sets to replay forwards with, in the same format as shadow parameters.
+    #[arg(long)]
+    params_file: PathBuf,
+
+    /// The seed that all random number generation is derived from.
+    #[arg(long, default_value = DEFAULT_SEED)]
+    seed: u64,
+
+    /// The directory that per-htlc decisions and outcome breakdowns are written to.
+    #[arg(long, default_value = DEFAULT_OUTPUT_DIR)]
+    output_dir: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    SimpleLogger::new()
+        .with_level(LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+    let forward_params: ForwardManagerParams = cli.reputation_params.into();
+
+    let network = NetworkType::new(&cli.network, cli.attack_type, None)?;
+    let edges = network.active_network();
+
+    let htlcs: Vec<ReplayHtlc> = match (&cli.results_file, &cli.node, cli.resolve_after) {
+        (Some(results_file), Some(node), Some(resolve_after)) => replay_htlcs_from_results(
+            results_file,
+            find_pubkey_by_alias(node, edges)?,
+            resolve_after,
+        )?,
+        (None, _, _) => {
+            let traffic_file = cli.traffic_file.unwrap_or(network.traffic_file());
+            history_from_file(&traffic_file, None)
+                .await?
+                .iter()
+                .map(ReplayHtlc::from)
+                .collect()
+        }
+        _ => return Err("results file requires node and resolve after".into()),
+    };
+
+    let mut configs = vec![(PRIMARY_CONFIG.to_string(), forward_params)];
+    configs.extend(shadow_params_from_file(&cli.params_file, forward_params)?);
+
+    log::info!(
+        "Replaying {} htlcs with {} parameter sets",
+        htlcs.len(),
+        configs.len()
+    );
+    let decisions = replay(
+        &htlcs,
+        edges,
+        &configs,
+        SeedComponent::ForwardManagers.seed(cli.seed),
+    )?;
+
+    fs::create_dir_all(&cli.output_dir)?;
+    write_replay_results(&cli.output_dir, &decisions, &configs, edges)?;
+
+    log::info!(
+        "Finished writing replay results to {}",
+        cli.output_dir.to_string_lossy()
+    );
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use std::{
-    collections::HashSet,
-    fs::{File, OpenOptions},
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
     io::Write,
     sync::Arc,
     time::Duration,
@@ -13,12 +13,13 @@ use ln_resource_mgr::forward_manager::{ForwardManager, ForwardManagerParams};
 use ln_simln_jamming::{
     analysis::BatchForwardWriter,
     clock::InstantClock,
+    deployment::NetworkDeployment,
     parsing::{
         get_history_for_bootstrap, history_from_file, parse_duration, AttackType, NetworkParams,
-        NetworkType, ReputationParams,
+        NetworkType, ReputationParams, DEFAULT_SEED,
     },
     reputation_interceptor::{BootstrapRecords, ReputationInterceptor, ReputationMonitor},
-    BoxError,
+    BoxError, SeedComponent,
 };
 use log::LevelFilter;
 use simln_lib::clock::SimulationClock;
@@ -41,6 +42,10 @@ struct Cli {
     /// for, expressed as human readable values (eg: 1w, 3d).
     #[arg(long, value_parser = parse_duration, requires = "attack_type")]
     pub attacker_bootstrap: Option<Duration>,
+
+    /// The seed that all random number generation is derived from.
+    #[arg(long, default_value = DEFAULT_SEED)]
+    pub seed: u64,
 }
 
 #[tokio::main]
@@ -60,7 +65,7 @@ async fn main() -> Result<(), BoxError> {
     }
 
     let active_network = network.active_network();
-    let target_pubkey = network.target().1;
+    let target_pubkeys: Vec<PublicKey> = network.targets().iter().map(|t| t.1).collect();
     let traffic_file = network.traffic_file();
 
     let unfiltered_history = history_from_file(
@@ -76,10 +81,10 @@ async fn main() -> Result<(), BoxError> {
         let target_to_attacker_channels: HashSet<u64> = active_network
             .iter()
             .filter(|&channel| {
-                (channel.node_1.pubkey == target_pubkey
+                (target_pubkeys.contains(&channel.node_1.pubkey)
                     && attacker_pubkeys.contains(&channel.node_2.pubkey))
                     || (attacker_pubkeys.contains(&channel.node_1.pubkey)
-                        && channel.node_2.pubkey == target_pubkey)
+                        && target_pubkeys.contains(&channel.node_2.pubkey))
             })
             .map(|channel| u64::from(channel.scid))
             .collect();
@@ -90,13 +95,12 @@ async fn main() -> Result<(), BoxError> {
             target_to_attacker_channels,
         )?;
 
-        let revenue = bootstrap.forwards.iter().fold(0, |acc, item| {
-            if item.forwarding_node == target_pubkey {
-                acc + item.incoming_amt - item.outgoing_amt
-            } else {
-                acc
+        let mut revenue: HashMap<PublicKey, u64> = target_pubkeys.iter().map(|t| (*t, 0)).collect();
+        for item in bootstrap.forwards.iter() {
+            if let Some(target_revenue) = revenue.get_mut(&item.forwarding_node) {
+                *target_revenue += item.incoming_amt - item.outgoing_amt;
             }
-        });
+        }
 
         (bootstrap, revenue)
     } else {
@@ -111,7 +115,7 @@ async fn main() -> Result<(), BoxError> {
             last_timestamp_nanos,
         };
 
-        (bootstrap_records, 0)
+        (bootstrap_records, HashMap::new())
     };
 
     let clock = Arc::new(SimulationClock::new(1)?);
@@ -119,6 +123,8 @@ async fn main() -> Result<(), BoxError> {
     let mut reputation_interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
         ReputationInterceptor::new_for_network(
             forward_params,
+            &NetworkDeployment::default(),
+            SeedComponent::ForwardManagers.seed(cli.seed),
             active_network,
             reputation_clock,
             None,
@@ -137,9 +143,21 @@ async fn main() -> Result<(), BoxError> {
     let reputation_file = network.reputation_file();
     let revenue_file = network.revenue_file();
 
+    if let Some(reputation_dir) = reputation_file.parent() {
+        fs::create_dir_all(reputation_dir)?;
+    }
+
+    // Revenue is written as one pubkey,revenue line per target, in the order that they're listed in target.txt.
     if let Some(revenue_file_path) = &revenue_file {
         let mut target_revenue = File::create(revenue_file_path)?;
-        write!(target_revenue, "{}", bootstrap_revenue)?;
+        for target in target_pubkeys.iter() {
+            writeln!(
+                target_revenue,
+                "{},{}",
+                target,
+                bootstrap_revenue.get(target).copied().unwrap_or_default()
+            )?;
+        }
     }
 
     let snapshot_file = OpenOptions::new()
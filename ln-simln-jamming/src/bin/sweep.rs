@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+
+use clap::Parser;
+use csv::Writer;
+use ln_simln_jamming::summary::{SimulationSummary, SUMMARY_JSON_FILE};
+use ln_simln_jamming::sweep::{ComparisonRow, SweepConfig, SweepJob};
+use ln_simln_jamming::BoxError;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// The default number of simulations that are run at the same time.
+pub const DEFAULT_MAX_PARALLEL: &str = "2";
+
+/// The default directory that sweep results are written to.
+pub const DEFAULT_OUTPUT_DIR: &str = "sweep";
+
+/// Runs a simulation for every parameter set described in a sweep file, building any reputation snapshots that the
+/// parameters require, and writes a comparison of the results.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The directory containing all files required for the simulation.
+    #[arg(long)]
+    network_dir: PathBuf,
+
+    /// A json file describing the parameters to sweep over.
+    #[arg(long)]
+    sweep_file: PathBuf,
+
+    /// The directory that snapshots, run results and the comparison table are written to.
+    #[arg(long, default_value = DEFAULT_OUTPUT_DIR)]
+    output_dir: PathBuf,
+
+    /// The maximum number of simulations or reputation builds that run at the same time.
+    #[arg(long, default_value = DEFAULT_MAX_PARALLEL)]
+    max_parallel: usize,
+
+    /// The simulator binary, defaults to ln-simln-jamming in the same directory as this binary.
+    #[arg(long)]
+    simulation_bin: Option<PathBuf>,
+
+    /// The reputation builder binary, defaults to reputation-builder in the same directory as this binary.
+    #[arg(long)]
+    reputation_builder_bin: Option<PathBuf>,
+
+    /// Additional arguments passed to every simulation, provided after --.
+    #[arg(last = true)]
+    simulation_args: Vec<String>,
+}
+
+fn sibling_bin(name: &str) -> Result<PathBuf, BoxError> {
+    let exe = std::env::current_exe()?;
+    let dir = exe
+        .parent()
+        .ok_or("could not get directory of current binary")?;
+    Ok(dir.join(name))
+}
+
+/// Runs the command provided, writing its output to the log file provided.
+async fn run_logged(mut command: Command, log_file: &Path) -> Result<(), String> {
+    let log = File::create(log_file).map_err(|e| format!("could not create log file: {e}"))?;
+    let log_err = log
+        .try_clone()
+        .map_err(|e| format!("could not clone log file: {e}"))?;
+
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(log)
+        .stderr(log_err)
+        .status()
+        .await
+        .map_err(|e| format!("could not run command: {e}"))?;
+
+    if !status.success() {
+        return Err(format!("exited with {status}, see {}", log_file.display()));
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    SimpleLogger::new()
+        .with_level(LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let cli = Cli::parse();
+    if cli.max_parallel == 0 {
+        return Err("max parallel must be at least 1".into());
+    }
+
+    let simulation_bin = match cli.simulation_bin {
+        Some(b) => b,
+        None => sibling_bin("ln-simln-jamming")?,
+    };
+    let reputation_builder_bin = match cli.reputation_builder_bin {
+        Some(b) => b,
+        None => sibling_bin("reputation-builder")?,
+    };
+
+    let config: SweepConfig = serde_json::from_reader(File::open(&cli.sweep_file)?)?;
+    let jobs = config.jobs()?;
+    log::info!("Running sweep with {} simulations", jobs.len());
+
+    let reputation_dir = cli.output_dir.join("reputation");
+    let runs_dir = cli.output_dir.join("runs");
+    fs::create_dir_all(&reputation_dir)?;
+    fs::create_dir_all(&runs_dir)?;
+
+    let semaphore = Arc::new(Semaphore::new(cli.max_parallel));
+
+    // Build each reputation snapshot required once, before any simulations are run. Jobs that share a snapshot all
+    // fail if their build fails.
+    let mut builds: HashMap<PathBuf, &SweepJob> = HashMap::new();
+    for job in jobs.iter() {
+        if let Some(subdir) = job.reputation_subdir() {
+            builds.entry(subdir).or_insert(job);
+        }
+    }
+
+    let mut build_tasks = JoinSet::new();
+    for (subdir, job) in builds {
+        let dir = reputation_dir.join(&subdir);
+        fs::create_dir_all(&dir)?;
+
+        let mut command = Command::new(&reputation_builder_bin);
+        command
+            .arg("--network-dir")
+            .arg(&cli.network_dir)
+            .arg("--reputation-dir")
+            .arg(&dir)
+            .args(job.reputation_builder_args());
+
+        let semaphore = semaphore.clone();
+        build_tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            log::info!("Building reputation snapshot in {}", dir.display());
+            let result = run_logged(command, &dir.join("reputation_builder.log")).await;
+            (subdir, result)
+        });
+    }
+
+    let mut build_results = HashMap::new();
+    while let Some(res) = build_tasks.join_next().await {
+        let (subdir, result) = res?;
+        if let Err(e) = &result {
+            log::error!("Reputation build for {} failed: {e}", subdir.display());
+        }
+        build_results.insert(subdir, result);
+    }
+
+    let mut sim_tasks = JoinSet::new();
+    for job in jobs.iter().cloned() {
+        let results_dir = runs_dir.join(&job.name);
+        fs::create_dir_all(&results_dir)?;
+
+        let mut command = Command::new(&simulation_bin);
+        command
+            .arg("--network-dir")
+            .arg(&cli.network_dir)
+            .arg("--results-dir")
+            .arg(&results_dir);
+
+        if let Some(subdir) = job.reputation_subdir() {
+            if let Some(Err(e)) = build_results.get(&subdir) {
+                let err = format!("reputation build failed: {e}");
+                sim_tasks.spawn(async move { (job, Err(err)) });
+                continue;
+            }
+            command
+                .arg("--reputation-dir")
+                .arg(reputation_dir.join(subdir));
+        }
+        command
+            .args(job.simulation_args())
+            .args(cli.simulation_args.iter());
+
+        let semaphore = semaphore.clone();
+        sim_tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            log::info!("Starting simulation {}", job.name);
+
+            let result = match run_logged(command, &results_dir.join("simulation.log")).await {
+                Ok(()) => read_summary(&results_dir.join(SUMMARY_JSON_FILE)),
+                Err(e) => Err(e),
+            };
+
+            match &result {
+                Ok(_) => log::info!("Simulation {} completed", job.name),
+                Err(e) => log::error!("Simulation {} failed: {e}", job.name),
+            }
+
+            (job, result)
+        });
+    }
+
+    let mut rows = vec![];
+    while let Some(res) = sim_tasks.join_next().await {
+        let (job, result) = res?;
+        rows.push(ComparisonRow::new(&job, &result));
+    }
+    rows.sort_by(|a, b| a.run.cmp(&b.run));
+
+    let comparison_file = cli.output_dir.join("comparison.csv");
+    let mut writer = Writer::from_path(&comparison_file)?;
+    for row in rows.iter() {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+
+    let failed = rows
+        .iter()
+        .filter(|r| r.outcome.starts_with("run_failed"))
+        .count();
+    log::info!(
+        "Sweep complete with {} of {} runs failed, comparison written to {}",
+        failed,
+        rows.len(),
+        comparison_file.display()
+    );
+
+    Ok(())
+}
+
+fn read_summary(path: &Path) -> Result<SimulationSummary, String> {
+    let file = File::open(path).map_err(|e| format!("could not open summary: {e}"))?;
+    serde_json::from_reader(file).map_err(|e| format!("could not read summary: {e}"))
+}
@@ -1,16 +1,229 @@
-use simln_lib::clock::SimulationClock;
+use simln_lib::clock::{Clock, SimulationClock};
+use std::io;
 use std::ops::Add;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Runtime};
 
 pub trait InstantClock {
     fn now(&self) -> Instant;
 }
 
 impl InstantClock for SimulationClock {
+    /// Reads the current time from tokio's clock rather than the system clock, so that simulated time follows tokio
+    /// when its clock is paused (see [`simulation_runtime`]).
     fn now(&self) -> Instant {
         let start_instant_std = self.get_start_instant().into();
-        let elapsed = Instant::now().duration_since(start_instant_std);
+        let elapsed = tokio::time::Instant::now()
+            .into_std()
+            .duration_since(start_instant_std);
 
         start_instant_std.add(elapsed * self.get_speedup_multiplier().into())
     }
 }
+
+/// The time between blocks used when no expected block speed is configured, matching the default used by
+/// [`ln_resource_mgr::ReputationParams`].
+const DEFAULT_BLOCK_SPEED: Duration = Duration::from_secs(60 * 10);
+
+/// Tracks the block height of the simulated network, which starts at zero when the simulation clock is started and
+/// advances by one block every block_speed of simulated time.
+///
+/// sim-ln builds routes as if the chain is at height zero, so the expiry heights on an intercepted htlc are
+/// interpreted as relative to the height at which the htlc was intercepted.
+#[derive(Clone)]
+pub struct BlockClock {
+    clock: Arc<SimulationClock>,
+    block_speed: Duration,
+}
+
+impl BlockClock {
+    pub fn new(clock: Arc<SimulationClock>, block_speed: Option<Duration>) -> Self {
+        Self {
+            clock,
+            block_speed: block_speed.unwrap_or(DEFAULT_BLOCK_SPEED),
+        }
+    }
+
+    /// Returns the height of the most recent block mined in the simulation.
+    pub fn current_height(&self) -> u32 {
+        let elapsed = InstantClock::now(&*self.clock).duration_since(self.start_instant());
+        u32::try_from(elapsed.as_nanos() / self.block_speed.as_nanos()).unwrap_or(u32::MAX)
+    }
+
+    /// Returns the amount of simulated time until the block at the height provided is mined, or zero if it has
+    /// already been mined.
+    pub fn time_until_height(&self, height: u32) -> Duration {
+        let height_ins = self.start_instant().add(self.block_speed * height);
+        height_ins.saturating_duration_since(InstantClock::now(&*self.clock))
+    }
+
+    /// Returns the amount of simulated time that a htlc expiring at the height provided can be held for before the
+    /// block before the margin provided is reached. With the upstream node's force close margin, this is the longest
+    /// that the htlc can be held without the channel being force closed.
+    pub fn max_hold(&self, expiry_height: u32, margin: u32) -> Duration {
+        self.time_until_height(expiry_height.saturating_sub(margin).saturating_sub(1))
+    }
+
+    /// Sleeps until the block at the height provided is mined.
+    pub async fn sleep_until_height(&self, height: u32) {
+        self.clock.sleep(self.time_until_height(height)).await
+    }
+
+    fn start_instant(&self) -> Instant {
+        self.clock.get_start_instant().into()
+    }
+}
+
+impl InstantClock for BlockClock {
+    fn now(&self) -> Instant {
+        InstantClock::now(&*self.clock)
+    }
+}
+
+/// Builds the runtime that a simulation runs on.
+///
+/// When fast forwarding, the simulation is run as a discrete-event simulation: tasks run on a single thread with
+/// tokio's clock paused, and whenever every task is idle the clock jumps straight to the next scheduled sleep rather
+/// than waiting for it in real time. As [`SimulationClock`] sleeps on tokio's timer and [`InstantClock`] reads
+/// tokio's clock, simulated time advances as fast as events can be processed and timestamps are not affected by
+/// load on the machine.
+///
+/// Pausing tokio's clock requires its `test-util` feature, which is only enabled by the non-default `fast-forward`
+/// feature, and an error is returned if fast forwarding is requested without it. Tokio only advances a paused clock when the runtime has no work left, which holds for every way that the simulation waits:
+/// - sim-ln and attacks sleep on [`SimulationClock`], which uses tokio's timer, so their sleeps are skipped.
+/// - std mutexes and file writes block the runtime's only thread, so the clock can't advance while they're in
+///   progress.
+/// - tokio's file operations run on the blocking pool, and tokio doesn't auto-advance while blocking tasks are in
+///   flight.
+///
+/// Otherwise, a multi-threaded runtime is used and the simulation runs against the (sped up) wall clock.
+pub fn simulation_runtime(fast_forward: bool) -> Result<Runtime, io::Error> {
+    if fast_forward {
+        fast_forward_runtime()
+    } else {
+        Builder::new_multi_thread().enable_all().build()
+    }
+}
+
+#[cfg(feature = "fast-forward")]
+fn fast_forward_runtime() -> Result<Runtime, io::Error> {
+    Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+}
+
+#[cfg(not(feature = "fast-forward"))]
+fn fast_forward_runtime() -> Result<Runtime, io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "fast forwarding requires building with the fast-forward feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use simln_lib::clock::{Clock, SimulationClock};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime};
+    use tokio::select;
+
+    use super::{BlockClock, InstantClock};
+
+    /// Tests that a clock running on a paused runtime jumps straight to the end of a sleep, and reports the time slept
+    /// for (within tokio's millisecond timer resolution).
+    #[tokio::test(start_paused = true)]
+    async fn test_fast_forward_sleep() {
+        let clock = SimulationClock::new(1).unwrap();
+        let real_start = std::time::Instant::now();
+        let start = InstantClock::now(&clock);
+
+        let sleep = Duration::from_secs(60 * 60 * 24 * 14);
+        clock.sleep(sleep).await;
+
+        let elapsed = InstantClock::now(&clock).duration_since(start);
+        assert!(elapsed >= sleep && elapsed < sleep + Duration::from_millis(2));
+        assert!(real_start.elapsed() < Duration::from_secs(10));
+    }
+
+    /// Tests that concurrent tasks sleeping on a paused runtime wake in order of their simulated deadlines, and that
+    /// time does not advance while a task holds a std mutex or writes to a file.
+    #[tokio::test(start_paused = true)]
+    async fn test_fast_forward_concurrent_tasks() {
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
+        let start = InstantClock::now(&*clock);
+        let path = std::env::temp_dir().join(format!(
+            "clock_test_{}",
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos(),
+        ));
+        let file = Arc::new(Mutex::new(std::fs::File::create(&path).unwrap()));
+
+        let mut tasks = vec![];
+        for (i, wait) in [300, 100, 200].into_iter().enumerate() {
+            let clock = clock.clone();
+            let file = file.clone();
+            tasks.push(tokio::spawn(async move {
+                clock.sleep(Duration::from_secs(wait)).await;
+                let mut file = file.lock().unwrap();
+                let woke = InstantClock::now(&*clock).duration_since(start);
+                for _ in 0..1_000 {
+                    writeln!(file, "{i}").unwrap();
+                }
+                file.flush().unwrap();
+
+                assert_eq!(InstantClock::now(&*clock).duration_since(start), woke);
+                woke
+            }));
+        }
+
+        let mut woke = vec![];
+        for task in tasks {
+            woke.push(task.await.unwrap().as_secs());
+        }
+        assert_eq!(woke, vec![300, 100, 200]);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let order: Vec<&str> = written.lines().step_by(1_000).collect();
+        assert_eq!(order, vec!["1", "2", "0"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Tests that a paused runtime does not skip ahead to a pending sleep while work is in progress on tokio's
+    /// blocking pool, which is used for tokio's file operations.
+    #[tokio::test(start_paused = true)]
+    async fn test_fast_forward_blocking_io() {
+        let clock = SimulationClock::new(1).unwrap();
+        let start = InstantClock::now(&clock);
+
+        select! {
+            res = tokio::task::spawn_blocking(|| std::thread::sleep(Duration::from_millis(100))) => res.unwrap(),
+            _ = clock.sleep(Duration::from_secs(60 * 60)) => panic!("clock advanced during blocking work"),
+        }
+
+        assert!(InstantClock::now(&clock).duration_since(start) < Duration::from_secs(1));
+    }
+
+    /// Tests that block height advances at the block speed provided.
+    #[tokio::test(start_paused = true)]
+    async fn test_block_clock() {
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
+        let block_speed = Duration::from_secs(60);
+        let block_clock = BlockClock::new(clock.clone(), Some(block_speed));
+
+        assert_eq!(block_clock.current_height(), 0);
+        assert!(block_clock.time_until_height(0).is_zero());
+        assert_eq!(block_clock.time_until_height(10), block_speed * 10);
+
+        clock.sleep(block_speed * 5 / 2).await;
+        assert_eq!(block_clock.current_height(), 2);
+
+        block_clock.sleep_until_height(10).await;
+        assert_eq!(block_clock.current_height(), 10);
+        assert!(block_clock.time_until_height(10).is_zero());
+    }
+}
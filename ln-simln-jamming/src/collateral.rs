@@ -0,0 +1,248 @@
+use crate::BoxError;
+use bitcoin::secp256k1::PublicKey;
+use clap::ValueEnum;
+use csv::WriterBuilder;
+use serde::{Deserialize, Serialize};
+use sim_cli::parsing::NetworkParser;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::path::Path;
+
+/// The name of the file that per-node revenue is written to in the results directory.
+pub const NODE_REVENUE_FILE: &str = "node_revenue.csv";
+
+/// The set of nodes that revenue is compared against peacetime for, to measure collateral damage from an attack.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorScope {
+    /// Only the target nodes.
+    #[default]
+    Target,
+    /// The targets and their direct peers.
+    Peers,
+    /// The targets and every node within two hops of them.
+    TwoHop,
+    /// Every node in the network.
+    Network,
+}
+
+impl MonitorScope {
+    fn max_hops(&self) -> Option<usize> {
+        match self {
+            MonitorScope::Target => Some(0),
+            MonitorScope::Peers => Some(1),
+            MonitorScope::TwoHop => Some(2),
+            MonitorScope::Network => None,
+        }
+    }
+}
+
+/// A node whose revenue is monitored, along with its distance from the closest target.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MonitoredNode {
+    pub pubkey: PublicKey,
+    pub alias: String,
+    pub hops: usize,
+}
+
+/// Returns the nodes within the scope provided, sorted by distance from the closest target and then alias. Attacking
+/// nodes are never monitored, and are not traversed when calculating distance from the targets.
+pub fn monitored_nodes(
+    scope: MonitorScope,
+    sim_network: &[NetworkParser],
+    targets: &[PublicKey],
+    attackers: &[PublicKey],
+) -> Vec<MonitoredNode> {
+    let mut peers: HashMap<PublicKey, Vec<(PublicKey, &str)>> = HashMap::new();
+    let mut aliases: HashMap<PublicKey, &str> = HashMap::new();
+    for channel in sim_network {
+        for (node, peer) in [
+            (&channel.node_1, &channel.node_2),
+            (&channel.node_2, &channel.node_1),
+        ] {
+            aliases.insert(node.pubkey, &node.alias);
+            peers
+                .entry(node.pubkey)
+                .or_default()
+                .push((peer.pubkey, &peer.alias));
+        }
+    }
+
+    let mut monitored: Vec<MonitoredNode> = targets
+        .iter()
+        .map(|target| MonitoredNode {
+            pubkey: *target,
+            alias: aliases.get(target).copied().unwrap_or_default().to_string(),
+            hops: 0,
+        })
+        .collect();
+    let mut visited: HashSet<PublicKey> = targets.iter().copied().collect();
+    let mut queue: VecDeque<(PublicKey, usize)> = targets.iter().map(|t| (*t, 0)).collect();
+
+    while let Some((node, hops)) = queue.pop_front() {
+        if scope.max_hops().is_some_and(|max| hops >= max) {
+            continue;
+        }
+
+        for (peer, alias) in peers.get(&node).into_iter().flatten() {
+            if attackers.contains(peer) || !visited.insert(*peer) {
+                continue;
+            }
+
+            monitored.push(MonitoredNode {
+                pubkey: *peer,
+                alias: alias.to_string(),
+                hops: hops + 1,
+            });
+            queue.push_back((*peer, hops + 1));
+        }
+    }
+
+    monitored.sort_by(|a, b| (a.hops, &a.alias).cmp(&(b.hops, &b.alias)));
+    monitored
+}
+
+/// The revenue that a monitored node earned in the simulation, and in the same period of peacetime.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NodeRevenueChange {
+    pub peacetime_revenue_msat: u64,
+    /// Revenue earned in the simulation, less the on-chain fees paid to force close channels.
+    pub simulation_revenue_msat: u64,
+    pub force_close_cost_msat: u64,
+}
+
+impl NodeRevenueChange {
+    pub fn change_msat(&self) -> i64 {
+        self.simulation_revenue_msat as i64 - self.peacetime_revenue_msat as i64
+    }
+}
+
+#[derive(Serialize)]
+struct NodeRevenueRow<'a> {
+    node: &'a str,
+    hops: usize,
+    peacetime_revenue_msat: u64,
+    simulation_revenue_msat: u64,
+    force_close_cost_msat: u64,
+    change_msat: i64,
+}
+
+/// Writes the revenue of each monitored node compared to peacetime to the directory provided.
+pub fn write_node_revenue(
+    dir: &Path,
+    nodes: &[MonitoredNode],
+    revenue: &HashMap<PublicKey, NodeRevenueChange>,
+) -> Result<(), BoxError> {
+    let mut writer = WriterBuilder::new().from_writer(File::create(dir.join(NODE_REVENUE_FILE))?);
+    for node in nodes {
+        let change = revenue.get(&node.pubkey).cloned().unwrap_or_default();
+        writer.serialize(NodeRevenueRow {
+            node: &node.alias,
+            hops: node.hops,
+            peacetime_revenue_msat: change.peacetime_revenue_msat,
+            simulation_revenue_msat: change.simulation_revenue_msat,
+            force_close_cost_msat: change.force_close_cost_msat,
+            change_msat: change.change_msat(),
+        })?;
+    }
+
+    writer.flush().map_err(|e| e.into())
+}
+
+/// The collateral damage that an attack caused to the nodes monitored, excluding the targets.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CollateralDamage {
+    pub scope: MonitorScope,
+    /// The number of nodes monitored, excluding the targets.
+    pub monitored_nodes: usize,
+    /// The number of monitored nodes that earned less revenue than in peacetime.
+    pub nodes_with_loss: usize,
+    pub peacetime_revenue_msat: u64,
+    pub simulation_revenue_msat: u64,
+    /// On-chain fees that monitored nodes paid to force close channels, which are deducted from their simulation
+    /// revenue.
+    pub force_close_cost_msat: u64,
+}
+
+impl CollateralDamage {
+    pub fn new(
+        scope: MonitorScope,
+        targets: &[PublicKey],
+        revenue: &HashMap<PublicKey, NodeRevenueChange>,
+    ) -> Self {
+        let collateral: Vec<&NodeRevenueChange> = revenue
+            .iter()
+            .filter(|(pubkey, _)| !targets.contains(pubkey))
+            .map(|(_, change)| change)
+            .collect();
+
+        CollateralDamage {
+            scope,
+            monitored_nodes: collateral.len(),
+            nodes_with_loss: collateral.iter().filter(|c| c.change_msat() < 0).count(),
+            peacetime_revenue_msat: collateral.iter().map(|c| c.peacetime_revenue_msat).sum(),
+            simulation_revenue_msat: collateral.iter().map(|c| c.simulation_revenue_msat).sum(),
+            force_close_cost_msat: collateral.iter().map(|c| c.force_close_cost_msat).sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::secp256k1::PublicKey;
+    use simln_lib::ShortChannelID;
+
+    use crate::test_utils::{get_random_keypair, setup_test_edge};
+
+    use super::{monitored_nodes, MonitorScope};
+
+    fn hops(nodes: &[super::MonitoredNode]) -> Vec<(PublicKey, usize)> {
+        nodes.iter().map(|n| (n.pubkey, n.hops)).collect()
+    }
+
+    /// Tests selection of monitored nodes on the following network, where node D is only reachable via the attacker:
+    /// Attacker -- Target -- A -- B -- C
+    ///    |
+    ///    D
+    #[test]
+    fn test_monitored_nodes() {
+        let target = get_random_keypair().1;
+        let attacker = get_random_keypair().1;
+        let (node_a, node_b, node_c, node_d) = (
+            get_random_keypair().1,
+            get_random_keypair().1,
+            get_random_keypair().1,
+            get_random_keypair().1,
+        );
+
+        let edges = vec![
+            setup_test_edge(ShortChannelID::from(0), attacker, target),
+            setup_test_edge(ShortChannelID::from(1), target, node_a),
+            setup_test_edge(ShortChannelID::from(2), node_b, node_a),
+            setup_test_edge(ShortChannelID::from(3), node_b, node_c),
+            setup_test_edge(ShortChannelID::from(4), attacker, node_d),
+        ];
+
+        let target_only = monitored_nodes(MonitorScope::Target, &edges, &[target], &[attacker]);
+        assert_eq!(hops(&target_only), vec![(target, 0)]);
+
+        let peers = monitored_nodes(MonitorScope::Peers, &edges, &[target], &[attacker]);
+        assert_eq!(hops(&peers), vec![(target, 0), (node_a, 1)]);
+
+        let two_hop = monitored_nodes(MonitorScope::TwoHop, &edges, &[target], &[attacker]);
+        assert_eq!(hops(&two_hop), vec![(target, 0), (node_a, 1), (node_b, 2)]);
+
+        let network = monitored_nodes(MonitorScope::Network, &edges, &[target], &[attacker]);
+        assert_eq!(
+            hops(&network),
+            vec![(target, 0), (node_a, 1), (node_b, 2), (node_c, 3)]
+        );
+
+        // With multiple targets, nodes are monitored by their distance from the closest target.
+        let multiple = monitored_nodes(MonitorScope::Peers, &edges, &[target, node_c], &[attacker]);
+        assert_eq!(
+            hops(&multiple),
+            vec![(target, 0), (node_c, 0), (node_a, 1), (node_b, 1)]
+        );
+    }
+}
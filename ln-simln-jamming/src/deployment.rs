@@ -0,0 +1,284 @@
+use crate::mitigation::Mitigation;
+use crate::parsing::{find_pubkey_by_alias, validate_forward_params, ReputationParams};
+use crate::BoxError;
+use bitcoin::secp256k1::PublicKey;
+use ln_resource_mgr::forward_manager::ForwardManagerParams;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use sim_cli::parsing::NetworkParser;
+use std::collections::{HashMap, HashSet};
+
+/// Describes whether a single node has deployed the mitigation, and any parameters that it uses that differ from the
+/// network's defaults. Read from a deployment file, keyed by node alias.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NodeDeploymentConfig {
+    #[serde(default = "default_deployed")]
+    pub deployed: bool,
+    /// Overrides for the network's default parameters, using the same names as the equivalent cli options.
+    #[serde(default)]
+    pub params: Option<ReputationParams>,
+    /// Nodes that observe only track reputation and resources as usual, but forward every htlc.
+    #[serde(default)]
+    pub observe_only: bool,
+}
+
+fn default_deployed() -> bool {
+    true
+}
+
+/// Tracks which nodes in the network have deployed the mitigation, and the parameters that each node uses.
+///
+/// Nodes that have not deployed the mitigation forward all htlcs without bucketing and relay the accountable signal
+/// that they receive. By default, every node deploys the reputation mitigation with the network's default parameters.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkDeployment {
+    mitigation: Mitigation,
+    not_deployed: HashSet<PublicKey>,
+    observe_only: HashSet<PublicKey>,
+    params: HashMap<PublicKey, ForwardManagerParams>,
+}
+
+impl NetworkDeployment {
+    /// Creates a deployment of the mitigation provided for the network from per-node configuration, keyed by alias,
+    /// and an optional fraction of nodes that deploy the mitigation.
+    ///
+    /// Nodes that are configured use the configuration provided. If a deployment fraction is set, that fraction of the
+    /// remaining nodes (excluding those in always_deployed) is picked at random using the seed provided to deploy the
+    /// mitigation, and the others do not deploy it.
+    pub fn new(
+        config: HashMap<String, NodeDeploymentConfig>,
+        graph: &[NetworkParser],
+        default_params: ForwardManagerParams,
+        mitigation: Mitigation,
+        deployment_fraction: Option<f64>,
+        seed: u64,
+        always_deployed: &[PublicKey],
+    ) -> Result<Self, BoxError> {
+        let mut deployment = NetworkDeployment {
+            mitigation,
+            ..Default::default()
+        };
+        let mut configured = HashSet::with_capacity(config.len());
+        for (alias, node_config) in config {
+            let pubkey = find_pubkey_by_alias(&alias, graph)?;
+            configured.insert(pubkey);
+
+            if !node_config.deployed {
+                deployment.not_deployed.insert(pubkey);
+            } else if node_config.observe_only {
+                deployment.set_observe_only(pubkey)?;
+            }
+            if let Some(params) = node_config.params {
+                let node_params = params.apply(default_params);
+                validate_forward_params(&node_params)
+                    .map_err(|e| format!("invalid params for {alias}: {e}"))?;
+                deployment.params.insert(pubkey, node_params);
+            }
+        }
+
+        if let Some(fraction) = deployment_fraction {
+            if !(0.0..=1.0).contains(&fraction) {
+                return Err(format!("deployment fraction: {fraction} not in [0, 1]").into());
+            }
+
+            // Sort candidates so that the nodes picked only depend on the seed, and not the order of the graph.
+            let mut candidates: Vec<PublicKey> = graph
+                .iter()
+                .flat_map(|c| [c.node_1.pubkey, c.node_2.pubkey])
+                .filter(|p| !configured.contains(p) && !always_deployed.contains(p))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            candidates.sort();
+            candidates.shuffle(&mut StdRng::seed_from_u64(seed));
+
+            let deployed_count = (fraction * candidates.len() as f64).round() as usize;
+            deployment
+                .not_deployed
+                .extend(candidates.into_iter().skip(deployed_count));
+        }
+
+        Ok(deployment)
+    }
+
+    /// Returns the mitigation that nodes which have deployed a mitigation use.
+    pub fn mitigation(&self) -> Mitigation {
+        self.mitigation
+    }
+
+    /// Returns a boolean indicating whether the node has deployed the mitigation.
+    pub fn is_deployed(&self, node: &PublicKey) -> bool {
+        !self.not_deployed.contains(node)
+    }
+
+    /// Returns a boolean indicating whether the node forwards every htlc, only observing the decisions that its
+    /// mitigation would have made.
+    pub fn is_observe_only(&self, node: &PublicKey) -> bool {
+        self.observe_only.contains(node)
+    }
+
+    /// Sets a node to observe its mitigation's decisions without failing any htlcs. Only nodes that have deployed a
+    /// mitigation that tracks reputation can observe.
+    pub fn set_observe_only(&mut self, node: PublicKey) -> Result<(), BoxError> {
+        if !self.mitigation.tracks_reputation() {
+            return Err(format!("mitigation: {:?} can't observe only", self.mitigation).into());
+        }
+        if !self.is_deployed(&node) {
+            return Err(format!("node: {node} has not deployed the mitigation to observe").into());
+        }
+
+        self.observe_only.insert(node);
+        Ok(())
+    }
+
+    /// Returns the parameters that a node uses, falling back to the default parameters provided if the node has not
+    /// been configured with its own.
+    pub fn params(
+        &self,
+        node: &PublicKey,
+        default_params: ForwardManagerParams,
+    ) -> ForwardManagerParams {
+        self.params.get(node).copied().unwrap_or(default_params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ln_resource_mgr::forward_manager::ForwardManagerParams;
+    use simln_lib::ShortChannelID;
+
+    use crate::mitigation::Mitigation;
+    use crate::test_utils::{get_random_keypair, setup_test_edge};
+
+    use super::NetworkDeployment;
+
+    /// Tests reading per-node deployment and parameters from deployment configuration.
+    #[test]
+    fn test_deployment_config() {
+        let mut edges = vec![
+            setup_test_edge(
+                ShortChannelID::from(1),
+                get_random_keypair().1,
+                get_random_keypair().1,
+            ),
+            setup_test_edge(
+                ShortChannelID::from(2),
+                get_random_keypair().1,
+                get_random_keypair().1,
+            ),
+        ];
+        edges[0].node_1.alias = "alice".to_string();
+        edges[0].node_2.alias = "bob".to_string();
+
+        let config = serde_json::from_str(
+            r#"{"alice": {"deployed": false}, "bob": {"params": {"general_slot_portion": 50}}}"#,
+        )
+        .unwrap();
+
+        let default_params = ForwardManagerParams::default();
+        let deployment = NetworkDeployment::new(
+            config,
+            &edges,
+            default_params,
+            Mitigation::None,
+            None,
+            0,
+            &[],
+        )
+        .unwrap();
+
+        assert!(!deployment.is_deployed(&edges[0].node_1.pubkey));
+        assert!(deployment.is_deployed(&edges[0].node_2.pubkey));
+        assert!(deployment.is_deployed(&edges[1].node_1.pubkey));
+        assert_eq!(deployment.mitigation(), Mitigation::None);
+
+        let bob_params = deployment.params(&edges[0].node_2.pubkey, default_params);
+        assert_eq!(bob_params.general_slot_portion, 50);
+        assert_eq!(
+            bob_params.reputation_params,
+            default_params.reputation_params
+        );
+        assert_eq!(
+            deployment.params(&edges[0].node_1.pubkey, default_params),
+            default_params
+        );
+    }
+
+    /// Tests that nodes can be configured to observe only, which is only possible for mitigations that track
+    /// reputation.
+    #[test]
+    fn test_observe_only_config() {
+        let mut edges = vec![setup_test_edge(
+            ShortChannelID::from(1),
+            get_random_keypair().1,
+            get_random_keypair().1,
+        )];
+        edges[0].node_1.alias = "alice".to_string();
+        edges[0].node_2.alias = "bob".to_string();
+
+        let deployment = |mitigation: Mitigation| {
+            NetworkDeployment::new(
+                serde_json::from_str(
+                    r#"{"alice": {"deployed": false, "observe_only": true}, "bob": {"observe_only": true}}"#,
+                )
+                .unwrap(),
+                &edges,
+                ForwardManagerParams::default(),
+                mitigation,
+                None,
+                0,
+                &[],
+            )
+        };
+
+        // Nodes that haven't deployed the mitigation have nothing to observe.
+        let reputation = deployment(Mitigation::Reputation).unwrap();
+        assert!(!reputation.is_observe_only(&edges[0].node_1.pubkey));
+        assert!(reputation.is_observe_only(&edges[0].node_2.pubkey));
+
+        assert!(deployment(Mitigation::None).is_err());
+    }
+
+    /// Tests that a deployment fraction picks the same nodes for the same seed, and never picks nodes that are always
+    /// deployed.
+    #[test]
+    fn test_deployment_fraction() {
+        let target = get_random_keypair().1;
+        let edges: Vec<_> = (0..10)
+            .map(|i| setup_test_edge(ShortChannelID::from(i), target, get_random_keypair().1))
+            .collect();
+
+        let deployment = |seed: u64| {
+            NetworkDeployment::new(
+                HashMap::new(),
+                &edges,
+                ForwardManagerParams::default(),
+                Mitigation::Reputation,
+                Some(0.3),
+                seed,
+                &[target],
+            )
+            .unwrap()
+        };
+
+        let first = deployment(1);
+        assert!(first.is_deployed(&target));
+        assert_eq!(first.not_deployed.len(), 7);
+        assert_eq!(first.not_deployed, deployment(1).not_deployed);
+
+        assert!(NetworkDeployment::new(
+            HashMap::new(),
+            &edges,
+            ForwardManagerParams::default(),
+            Mitigation::Reputation,
+            Some(1.5),
+            1,
+            &[target],
+        )
+        .is_err());
+    }
+}
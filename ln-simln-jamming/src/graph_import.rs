@@ -0,0 +1,627 @@
+use crate::network_gen::write_graph;
+use crate::BoxError;
+use bitcoin::secp256k1::PublicKey;
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Deserializer};
+use sim_cli::parsing::NetworkParser;
+use simln_lib::sim_node::ChannelPolicy;
+use simln_lib::ShortChannelID;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The protocol limit on the number of htlcs a channel can have in flight, used because gossip doesn't advertise it.
+const MAX_HTLC_COUNT: u64 = 483;
+
+/// The implementation that a graph dump was produced by.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum GraphFormat {
+    /// The output of LND's `lncli describegraph`.
+    Lnd,
+    /// The output of CLN's `lightning-cli listchannels`, optionally with `listnodes` for aliases.
+    Cln,
+}
+
+/// Filters applied to an imported graph, in the order that they're listed.
+#[derive(Clone, Debug, Default, Parser)]
+pub struct ImportFilters {
+    /// Drop channels that are smaller than this capacity, expressed in sats.
+    #[arg(long)]
+    pub min_capacity_sat: Option<u64>,
+
+    /// Only keep nodes within this many hops of the target, which must be set.
+    #[arg(long, requires = "target")]
+    pub max_hops: Option<usize>,
+
+    /// Only keep the largest connected component of the graph.
+    #[arg(long)]
+    pub largest_component: bool,
+
+    /// The alias or pubkey of the target node, written to target.txt.
+    #[arg(long)]
+    pub target: Option<String>,
+}
+
+/// Reads a u64 that may be encoded as a json number, a string or a string with a `msat` suffix, as the
+/// implementations differ in how they encode large integers.
+fn de_u64<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(n) => Ok(n),
+        NumberOrString::String(s) => s
+            .trim_end_matches("msat")
+            .parse()
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+#[derive(Deserialize)]
+struct LndGraph {
+    nodes: Vec<LndNode>,
+    edges: Vec<LndEdge>,
+}
+
+#[derive(Deserialize)]
+struct LndNode {
+    pub_key: String,
+    #[serde(default)]
+    alias: String,
+}
+
+#[derive(Deserialize)]
+struct LndEdge {
+    #[serde(deserialize_with = "de_u64")]
+    channel_id: u64,
+    node1_pub: String,
+    node2_pub: String,
+    #[serde(deserialize_with = "de_u64")]
+    capacity: u64,
+    node1_policy: Option<LndPolicy>,
+    node2_policy: Option<LndPolicy>,
+}
+
+#[derive(Deserialize)]
+struct LndPolicy {
+    time_lock_delta: u32,
+    #[serde(deserialize_with = "de_u64")]
+    min_htlc: u64,
+    #[serde(deserialize_with = "de_u64")]
+    fee_base_msat: u64,
+    #[serde(deserialize_with = "de_u64")]
+    fee_rate_milli_msat: u64,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default, deserialize_with = "de_u64")]
+    max_htlc_msat: u64,
+}
+
+#[derive(Deserialize)]
+struct ClnChannels {
+    channels: Vec<ClnChannel>,
+}
+
+/// A single direction of a channel, as CLN lists each direction separately.
+#[derive(Deserialize)]
+struct ClnChannel {
+    source: String,
+    destination: String,
+    short_channel_id: String,
+    #[serde(deserialize_with = "de_u64")]
+    amount_msat: u64,
+    #[serde(default = "default_active")]
+    active: bool,
+    #[serde(deserialize_with = "de_u64")]
+    base_fee_millisatoshi: u64,
+    #[serde(deserialize_with = "de_u64")]
+    fee_per_millionth: u64,
+    delay: u32,
+    #[serde(deserialize_with = "de_u64")]
+    htlc_minimum_msat: u64,
+    #[serde(default, deserialize_with = "de_u64")]
+    htlc_maximum_msat: u64,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct ClnNodes {
+    nodes: Vec<ClnNode>,
+}
+
+#[derive(Deserialize)]
+struct ClnNode {
+    nodeid: String,
+    #[serde(default)]
+    alias: String,
+}
+
+/// A node's forwarding policy for a channel, common to both implementations.
+struct ImportPolicy {
+    pubkey: PublicKey,
+    min_htlc_msat: u64,
+    max_htlc_msat: u64,
+    cltv_expiry_delta: u32,
+    base_fee: u64,
+    fee_rate_prop: u64,
+}
+
+struct ImportChannel {
+    scid: u64,
+    capacity_msat: u64,
+    node_1: ImportPolicy,
+    node_2: ImportPolicy,
+}
+
+/// The channels and aliases read from a graph dump, before filters are applied.
+struct ParsedDump {
+    channels: Vec<ImportChannel>,
+    aliases: HashMap<PublicKey, String>,
+    skipped: usize,
+}
+
+/// The result of importing a graph, along with the number of channels that couldn't be imported.
+pub struct ImportedGraph {
+    pub graph: Vec<NetworkParser>,
+    /// Channels that were skipped because one of their policies was missing or disabled.
+    pub skipped_channels: usize,
+}
+
+/// Parses a CLN short channel id in the `{block}x{tx}x{output}` format.
+fn parse_cln_scid(scid: &str) -> Result<u64, BoxError> {
+    let parts: Vec<u64> = scid
+        .split('x')
+        .map(|p| p.parse::<u64>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("invalid short channel id {scid}: {e}"))?;
+
+    match parts[..] {
+        [block, tx, output] => Ok((block << 40) | (tx << 16) | output),
+        _ => Err(format!("invalid short channel id: {scid}").into()),
+    }
+}
+
+/// Converts a LND policy, returning None if the policy is missing or disabled.
+fn lnd_policy(pubkey: &str, policy: Option<LndPolicy>) -> Result<Option<ImportPolicy>, BoxError> {
+    Ok(match policy {
+        Some(p) if !p.disabled => Some(ImportPolicy {
+            pubkey: PublicKey::from_str(pubkey)?,
+            min_htlc_msat: p.min_htlc,
+            max_htlc_msat: p.max_htlc_msat,
+            cltv_expiry_delta: p.time_lock_delta,
+            base_fee: p.fee_base_msat,
+            fee_rate_prop: p.fee_rate_milli_msat,
+        }),
+        _ => None,
+    })
+}
+
+fn lnd_channels(dump: &str) -> Result<ParsedDump, BoxError> {
+    let graph: LndGraph = serde_json::from_str(dump)?;
+
+    let mut aliases = HashMap::with_capacity(graph.nodes.len());
+    for node in graph.nodes {
+        aliases.insert(PublicKey::from_str(&node.pub_key)?, node.alias);
+    }
+
+    let mut channels = Vec::with_capacity(graph.edges.len());
+    let mut skipped = 0;
+    for edge in graph.edges {
+        match (
+            lnd_policy(&edge.node1_pub, edge.node1_policy)?,
+            lnd_policy(&edge.node2_pub, edge.node2_policy)?,
+        ) {
+            (Some(node_1), Some(node_2)) => channels.push(ImportChannel {
+                scid: edge.channel_id,
+                capacity_msat: edge.capacity * 1000,
+                node_1,
+                node_2,
+            }),
+            _ => skipped += 1,
+        }
+    }
+
+    Ok(ParsedDump {
+        channels,
+        aliases,
+        skipped,
+    })
+}
+
+fn cln_channels(dump: &str, nodes: Option<&str>) -> Result<ParsedDump, BoxError> {
+    let listing: ClnChannels = serde_json::from_str(dump)?;
+
+    let mut aliases = HashMap::new();
+    if let Some(nodes) = nodes {
+        let nodes: ClnNodes = serde_json::from_str(nodes)?;
+        for node in nodes.nodes {
+            aliases.insert(PublicKey::from_str(&node.nodeid)?, node.alias);
+        }
+    }
+
+    // Pair up the two directions of each channel, ordered by scid so that output is deterministic.
+    let mut directions: BTreeMap<u64, Vec<ClnChannel>> = BTreeMap::new();
+    for channel in listing.channels {
+        directions
+            .entry(parse_cln_scid(&channel.short_channel_id)?)
+            .or_default()
+            .push(channel);
+    }
+
+    let mut channels = Vec::with_capacity(directions.len());
+    let mut skipped = 0;
+    for (scid, mut sides) in directions {
+        sides.retain(|c| c.active);
+        let (Some(first), Some(second)) = (sides.first(), sides.get(1)) else {
+            skipped += 1;
+            continue;
+        };
+        if first.source != second.destination || first.destination != second.source {
+            return Err(format!("channel {scid} has mismatched directions").into());
+        }
+
+        let policy = |c: &ClnChannel| -> Result<ImportPolicy, BoxError> {
+            Ok(ImportPolicy {
+                pubkey: PublicKey::from_str(&c.source)?,
+                min_htlc_msat: c.htlc_minimum_msat,
+                max_htlc_msat: c.htlc_maximum_msat,
+                cltv_expiry_delta: c.delay,
+                base_fee: c.base_fee_millisatoshi,
+                fee_rate_prop: c.fee_per_millionth,
+            })
+        };
+
+        channels.push(ImportChannel {
+            scid,
+            capacity_msat: first.amount_msat,
+            node_1: policy(first)?,
+            node_2: policy(second)?,
+        });
+    }
+
+    Ok(ParsedDump {
+        channels,
+        aliases,
+        skipped,
+    })
+}
+
+/// Imports a graph dump into the format that sim-ln reads, applying the filters provided. As gossip doesn't advertise
+/// them, every channel allows the protocol maximum number of htlcs and its full capacity in flight. Channels where
+/// either side's policy is missing or disabled are skipped. Nodes without a unique alias are given one derived from
+/// their pubkey, so that they can be referred to by alias.
+pub fn import_graph(
+    format: GraphFormat,
+    dump: &str,
+    cln_nodes: Option<&str>,
+    filters: &ImportFilters,
+) -> Result<ImportedGraph, BoxError> {
+    let ParsedDump {
+        mut channels,
+        aliases,
+        skipped,
+    } = match format {
+        GraphFormat::Lnd => lnd_channels(dump)?,
+        GraphFormat::Cln => cln_channels(dump, cln_nodes)?,
+    };
+    let aliases = unique_aliases(&channels, aliases);
+
+    if let Some(min_capacity) = filters.min_capacity_sat {
+        channels.retain(|c| c.capacity_msat >= min_capacity * 1000);
+    }
+
+    if let Some(max_hops) = filters.max_hops {
+        let target_alias = filters
+            .target
+            .as_ref()
+            .ok_or("target required for max hops")?;
+        let target = find_node(&aliases, target_alias)?;
+        let neighbourhood = reachable(&channels, target, Some(max_hops));
+        channels.retain(|c| {
+            neighbourhood.contains(&c.node_1.pubkey) && neighbourhood.contains(&c.node_2.pubkey)
+        });
+    }
+
+    if filters.largest_component {
+        let mut seen = HashSet::new();
+        let mut largest = HashSet::new();
+        for channel in channels.iter() {
+            if seen.contains(&channel.node_1.pubkey) {
+                continue;
+            }
+
+            let component = reachable(&channels, channel.node_1.pubkey, None);
+            seen.extend(component.iter().copied());
+            if component.len() > largest.len() {
+                largest = component;
+            }
+        }
+        channels.retain(|c| largest.contains(&c.node_1.pubkey));
+    }
+
+    if let Some(target) = &filters.target {
+        let target = find_node(&aliases, target)?;
+        if !channels
+            .iter()
+            .any(|c| c.node_1.pubkey == target || c.node_2.pubkey == target)
+        {
+            return Err(format!("target {target} has no channels once filters are applied").into());
+        }
+    }
+
+    let policy = |p: &ImportPolicy, capacity_msat: u64| ChannelPolicy {
+        pubkey: p.pubkey,
+        alias: aliases[&p.pubkey].clone(),
+        max_htlc_count: MAX_HTLC_COUNT,
+        max_in_flight_msat: capacity_msat,
+        min_htlc_size_msat: p.min_htlc_msat,
+        // Some implementations advertise a maximum larger than the channel, or none at all.
+        max_htlc_size_msat: match p.max_htlc_msat {
+            0 => capacity_msat,
+            max => max.min(capacity_msat),
+        },
+        cltv_expiry_delta: p.cltv_expiry_delta,
+        base_fee: p.base_fee,
+        fee_rate_prop: p.fee_rate_prop,
+    };
+
+    Ok(ImportedGraph {
+        graph: channels
+            .iter()
+            .map(|c| NetworkParser {
+                scid: ShortChannelID::from(c.scid),
+                capacity_msat: c.capacity_msat,
+                node_1: policy(&c.node_1, c.capacity_msat),
+                node_2: policy(&c.node_2, c.capacity_msat),
+            })
+            .collect(),
+        skipped_channels: skipped,
+    })
+}
+
+/// Assigns each node in the graph an alias, falling back to the start of its pubkey when it has no alias or shares its
+/// alias with another node.
+fn unique_aliases(
+    channels: &[ImportChannel],
+    aliases: HashMap<PublicKey, String>,
+) -> HashMap<PublicKey, String> {
+    let nodes: HashSet<PublicKey> = channels
+        .iter()
+        .flat_map(|c| [c.node_1.pubkey, c.node_2.pubkey])
+        .collect();
+
+    let mut alias_count: HashMap<&str, usize> = HashMap::new();
+    for node in nodes.iter() {
+        if let Some(alias) = aliases.get(node).filter(|a| !a.is_empty()) {
+            *alias_count.entry(alias.as_str()).or_default() += 1;
+        }
+    }
+
+    nodes
+        .iter()
+        .map(|node| {
+            let alias = match aliases.get(node) {
+                Some(alias) if alias_count.get(alias.as_str()) == Some(&1) => alias.clone(),
+                _ => node.to_string()[..16].to_string(),
+            };
+            (*node, alias)
+        })
+        .collect()
+}
+
+/// Finds a node by alias or pubkey.
+fn find_node(aliases: &HashMap<PublicKey, String>, node: &str) -> Result<PublicKey, BoxError> {
+    if let Ok(pubkey) = PublicKey::from_str(node) {
+        return Ok(pubkey);
+    }
+
+    aliases
+        .iter()
+        .find(|(_, alias)| alias.as_str() == node)
+        .map(|(pubkey, _)| *pubkey)
+        .ok_or(format!("node {node} not found in graph").into())
+}
+
+/// Returns the nodes reachable from the node provided, optionally limited to a number of hops.
+fn reachable(
+    channels: &[ImportChannel],
+    start: PublicKey,
+    max_hops: Option<usize>,
+) -> HashSet<PublicKey> {
+    let mut adjacency: HashMap<PublicKey, Vec<PublicKey>> = HashMap::new();
+    for channel in channels {
+        adjacency
+            .entry(channel.node_1.pubkey)
+            .or_default()
+            .push(channel.node_2.pubkey);
+        adjacency
+            .entry(channel.node_2.pubkey)
+            .or_default()
+            .push(channel.node_1.pubkey);
+    }
+
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((node, hops)) = queue.pop_front() {
+        if max_hops.is_some_and(|max| hops >= max) {
+            continue;
+        }
+
+        for peer in adjacency.get(&node).into_iter().flatten() {
+            if seen.insert(*peer) {
+                queue.push_back((*peer, hops + 1));
+            }
+        }
+    }
+
+    seen
+}
+
+/// Writes an imported graph to `peacetime_network.json` in the network directory provided, along with `target.txt`
+/// if a target is provided.
+pub fn write_imported(
+    network_dir: &Path,
+    graph: &[NetworkParser],
+    target: Option<&str>,
+) -> Result<(), BoxError> {
+    fs::create_dir_all(network_dir)?;
+    write_graph(&network_dir.join("peacetime_network.json"), graph)?;
+
+    if let Some(target) = target {
+        let alias = graph
+            .iter()
+            .flat_map(|c| [&c.node_1, &c.node_2])
+            .find(|p| p.alias == target || p.pubkey.to_string() == target)
+            .map(|p| p.alias.clone())
+            .ok_or(format!("target {target} not found in graph"))?;
+        fs::write(network_dir.join("target.txt"), alias)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::get_random_keypair;
+
+    use super::{import_graph, parse_cln_scid, GraphFormat, ImportFilters};
+
+    fn lnd_edge(channel_id: u64, node_1: &str, node_2: &str, capacity: u64) -> String {
+        let policy = r#"{"time_lock_delta": 40, "min_htlc": "1000", "fee_base_msat": "1000",
+            "fee_rate_milli_msat": "10", "disabled": false, "max_htlc_msat": "0"}"#;
+        format!(
+            r#"{{"channel_id": "{channel_id}", "node1_pub": "{node_1}", "node2_pub": "{node_2}",
+            "capacity": "{capacity}", "node1_policy": {policy}, "node2_policy": {policy}}}"#
+        )
+    }
+
+    #[test]
+    fn test_parse_cln_scid() {
+        assert_eq!(parse_cln_scid("1x2x3").unwrap(), (1 << 40) | (2 << 16) | 3);
+        assert!(parse_cln_scid("1x2").is_err());
+        assert!(parse_cln_scid("axbxc").is_err());
+    }
+
+    /// Tests importing a LND graph with duplicate aliases, a channel with a missing policy and two components, and
+    /// that each filter is applied.
+    #[test]
+    fn test_import_lnd() {
+        let nodes: Vec<String> = (0..5).map(|_| get_random_keypair().1.to_string()).collect();
+        let mut edges = vec![
+            lnd_edge(1, &nodes[0], &nodes[1], 1_000_000),
+            lnd_edge(2, &nodes[1], &nodes[2], 2_000_000),
+            lnd_edge(3, &nodes[3], &nodes[4], 3_000_000),
+        ];
+        edges.push(format!(
+            r#"{{"channel_id": "4", "node1_pub": "{}", "node2_pub": "{}", "capacity": "1000",
+            "node1_policy": null, "node2_policy": null}}"#,
+            nodes[0], nodes[2]
+        ));
+
+        let dump = format!(
+            r#"{{"nodes": [{{"pub_key": "{}", "alias": "alice"}}, {{"pub_key": "{}", "alias": "bob"}},
+            {{"pub_key": "{}", "alias": "bob"}}], "edges": [{}]}}"#,
+            nodes[0],
+            nodes[1],
+            nodes[2],
+            edges.join(",")
+        );
+
+        let imported =
+            import_graph(GraphFormat::Lnd, &dump, None, &ImportFilters::default()).unwrap();
+        assert_eq!(imported.graph.len(), 3);
+        assert_eq!(imported.skipped_channels, 1);
+
+        let first = &imported.graph[0];
+        assert_eq!(first.capacity_msat, 1_000_000_000);
+        assert_eq!(first.node_1.alias, "alice");
+        assert_eq!(first.node_1.max_htlc_count, 483);
+        assert_eq!(first.node_1.max_in_flight_msat, 1_000_000_000);
+        assert_eq!(first.node_1.max_htlc_size_msat, 1_000_000_000);
+        assert_eq!(first.node_1.fee_rate_prop, 10);
+        // Duplicate aliases are replaced with pubkeys.
+        assert_eq!(first.node_2.alias, nodes[1][..16]);
+
+        let scids = |filters: &ImportFilters| -> Vec<u64> {
+            import_graph(GraphFormat::Lnd, &dump, None, filters)
+                .unwrap()
+                .graph
+                .iter()
+                .map(|c| u64::from(c.scid))
+                .collect()
+        };
+
+        let filters = ImportFilters {
+            largest_component: true,
+            ..Default::default()
+        };
+        assert_eq!(scids(&filters), vec![1, 2]);
+
+        let filters = ImportFilters {
+            min_capacity_sat: Some(1_500_000),
+            ..Default::default()
+        };
+        assert_eq!(scids(&filters), vec![2, 3]);
+
+        let filters = ImportFilters {
+            max_hops: Some(1),
+            target: Some("alice".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(scids(&filters), vec![1]);
+    }
+
+    /// Tests that CLN's per-direction listings are combined into channels, and that channels with a single active
+    /// direction are skipped.
+    #[test]
+    fn test_import_cln() {
+        let alice = get_random_keypair().1.to_string();
+        let bob = get_random_keypair().1.to_string();
+        let direction = |source: &str, destination: &str, scid: &str, active: bool| {
+            format!(
+                r#"{{"source": "{source}", "destination": "{destination}", "short_channel_id": "{scid}",
+                "amount_msat": "5000000msat", "active": {active}, "base_fee_millisatoshi": 1,
+                "fee_per_millionth": 100, "delay": 6, "htlc_minimum_msat": 0,
+                "htlc_maximum_msat": 9000000}}"#
+            )
+        };
+
+        let dump = format!(
+            r#"{{"channels": [{}, {}, {}, {}]}}"#,
+            direction(&alice, &bob, "100x1x0", true),
+            direction(&bob, &alice, "100x1x0", true),
+            direction(&alice, &bob, "101x1x1", true),
+            direction(&bob, &alice, "101x1x1", false),
+        );
+        let nodes = format!(r#"{{"nodes": [{{"nodeid": "{alice}", "alias": "alice"}}]}}"#);
+
+        let imported = import_graph(
+            GraphFormat::Cln,
+            &dump,
+            Some(&nodes),
+            &ImportFilters::default(),
+        )
+        .unwrap();
+        assert_eq!(imported.graph.len(), 1);
+        assert_eq!(imported.skipped_channels, 1);
+
+        let channel = &imported.graph[0];
+        assert_eq!(u64::from(channel.scid), (100 << 40) | (1 << 16));
+        assert_eq!(channel.capacity_msat, 5_000_000);
+        assert_eq!(channel.node_1.alias, "alice");
+        assert_eq!(channel.node_2.alias, bob[..16]);
+        assert_eq!(channel.node_1.max_htlc_size_msat, 5_000_000);
+        assert_eq!(channel.node_2.cltv_expiry_delta, 6);
+    }
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use lightning::ln::PaymentHash;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use simln_lib::sim_node::{
+    CriticalError, CustomRecords, ForwardingError, InterceptRequest, InterceptResolution,
+    Interceptor,
+};
+use std::time::Duration;
+use tokio::select;
+
+use crate::{derive_seed, BoxError};
+
+/// The largest mean latency that can be sampled. The probability of sampling zero is e^-lambda, which underflows an
+/// f64 for larger values.
+const MAX_LAMBDA_MS: f64 = 700.0;
+
+/// Adds latency to every htlc forwarded in the simulation, sampled from a poisson distribution. Unlike the latency
+/// interceptor provided by sim-ln, each sample is drawn from a random number generator seeded by the htlc itself, so
+/// that the latency applied to a htlc is reproducible between runs regardless of the order that htlcs arrive in.
+#[derive(Debug)]
+pub struct LatencyInterceptor {
+    lambda_ms: f64,
+    seed: u64,
+}
+
+impl LatencyInterceptor {
+    pub fn new_poisson(lambda_ms: f64, seed: u64) -> Result<Self, BoxError> {
+        if lambda_ms <= 0.0 || lambda_ms > MAX_LAMBDA_MS {
+            return Err(
+                format!("poisson lambda: {lambda_ms} must be in (0, {MAX_LAMBDA_MS}]").into(),
+            );
+        }
+
+        Ok(Self { lambda_ms, seed })
+    }
+
+    /// Samples the latency for a htlc identified by its payment hash and incoming channel from the interceptor's
+    /// distribution using inverse transform sampling, which is linear in lambda but adequate for the small values used
+    /// for network latency.
+    fn sample_latency(&self, payment_hash: &PaymentHash, incoming_channel: u64) -> Duration {
+        let mut rng = StdRng::seed_from_u64(derive_seed(
+            self.seed,
+            &[&payment_hash.0, &incoming_channel.to_be_bytes()],
+        ));
+        let u: f64 = rng.random();
+
+        let mut k = 0u64;
+        let mut p = (-self.lambda_ms).exp();
+        let mut cumulative = p;
+        while u > cumulative && p > 0.0 {
+            k += 1;
+            p *= self.lambda_ms / k as f64;
+            cumulative += p;
+        }
+
+        Duration::from_millis(k)
+    }
+}
+
+#[async_trait]
+impl Interceptor for LatencyInterceptor {
+    /// Delays the htlc by a sampled latency, returning early if the simulation shuts down.
+    async fn intercept_htlc(
+        &self,
+        req: InterceptRequest,
+    ) -> Result<Result<CustomRecords, ForwardingError>, CriticalError> {
+        let latency = self.sample_latency(&req.payment_hash, req.incoming_htlc.channel_id.into());
+
+        select! {
+            _ = req.shutdown_listener => log::debug!("Latency interceptor exiting due to shutdown signal"),
+            _ = tokio::time::sleep(latency) => {}
+        }
+
+        Ok(Ok(CustomRecords::default()))
+    }
+
+    async fn notify_resolution(&self, _res: InterceptResolution) -> Result<(), CriticalError> {
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        "latency interceptor".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lightning::ln::PaymentHash;
+
+    use super::LatencyInterceptor;
+
+    #[test]
+    fn test_seeded_latency() {
+        assert!(LatencyInterceptor::new_poisson(0.0, 1).is_err());
+        assert!(LatencyInterceptor::new_poisson(1000.0, 1).is_err());
+
+        let htlcs: Vec<(PaymentHash, u64)> = (0..100u8)
+            .map(|i| (PaymentHash([i; 32]), i as u64 % 3))
+            .collect();
+
+        let sample = |seed, htlcs: &[(PaymentHash, u64)]| {
+            let interceptor = LatencyInterceptor::new_poisson(150.0, seed).unwrap();
+            htlcs
+                .iter()
+                .map(|(hash, channel)| interceptor.sample_latency(hash, *channel).as_millis())
+                .collect::<Vec<u128>>()
+        };
+
+        let latencies = sample(7, &htlcs);
+        assert_eq!(latencies, sample(7, &htlcs));
+        assert_ne!(latencies, sample(8, &htlcs));
+
+        // Latency depends on the htlc, not the order that htlcs are sampled in.
+        let reversed: Vec<(PaymentHash, u64)> = htlcs.iter().rev().cloned().collect();
+        let mut reversed_latencies = sample(7, &reversed);
+        reversed_latencies.reverse();
+        assert_eq!(latencies, reversed_latencies);
+
+        // The same payment crossing different channels is sampled independently.
+        let interceptor = LatencyInterceptor::new_poisson(150.0, 7).unwrap();
+        assert!((0..10)
+            .map(|c| interceptor.sample_latency(&htlcs[0].0, c))
+            .any(|l| l != interceptor.sample_latency(&htlcs[0].0, 10)));
+
+        // The sample mean should be close to lambda.
+        let mean = latencies.iter().sum::<u128>() / latencies.len() as u128;
+        assert!((130..170).contains(&mean), "mean: {mean}");
+    }
+}
@@ -1,9 +1,16 @@
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin::hashes::Hash;
 use bitcoin::secp256k1::PublicKey;
+use lightning::ln::PaymentHash;
 use ln_resource_mgr::{AccountableSignal, ChannelSnapshot};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sim_cli::parsing::NetworkParser;
 use simln_lib::sim_node::{CustomRecords, InterceptRequest};
 use std::collections::HashMap;
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::Instant;
 
 use self::reputation_interceptor::ReputationMonitor;
@@ -12,10 +19,24 @@ pub mod analysis;
 pub mod attack_interceptor;
 pub mod attacks;
 pub mod clock;
+pub mod collateral;
+pub mod deployment;
+pub mod graph_import;
+pub mod latency_interceptor;
+pub mod mitigation;
+pub mod network_check;
+pub mod network_gen;
 pub mod parsing;
+pub mod payments;
+pub mod plot;
+pub mod replay;
 pub mod reputation_interceptor;
 pub mod revenue_interceptor;
+pub mod simulation;
+pub mod summary;
+pub mod sweep;
 pub(crate) mod test_utils;
+pub mod topology;
 
 /// Error type for errors that can be erased, includes 'static so that down-casting is possible.
 pub type BoxError = Box<dyn Error + Send + Sync + 'static>;
@@ -27,6 +48,75 @@ pub const ACCOUNTABLE_TYPE: u64 = 106823;
 /// the onion.
 pub const UPGRADABLE_TYPE: u64 = 106825;
 
+/// The components of a simulation that draw random numbers. Each component is seeded separately from the simulation's
+/// seed so that the values sampled by one component don't depend on how many draws another has made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeedComponent {
+    /// Payment activity generated by sim-ln.
+    Activity,
+    /// Latency added to htlcs by [`latency_interceptor::LatencyInterceptor`].
+    Latency,
+    /// Payment hashes used by attacks.
+    PaymentHashes,
+    /// Slot assignment in each node's forward manager.
+    ForwardManagers,
+    /// Slot assignment in shadow forward managers.
+    ShadowManagers,
+    /// Selection of the nodes that deploy the mitigation.
+    Deployment,
+}
+
+impl SeedComponent {
+    /// Derives the seed for this component from the simulation's seed.
+    pub fn seed(&self, seed: u64) -> u64 {
+        let tag: &[u8] = match self {
+            SeedComponent::Activity => b"activity",
+            SeedComponent::Latency => b"latency",
+            SeedComponent::PaymentHashes => b"payment_hashes",
+            SeedComponent::ForwardManagers => b"forward_managers",
+            SeedComponent::ShadowManagers => b"shadow_managers",
+            SeedComponent::Deployment => b"deployment",
+        };
+
+        derive_seed(seed, &[tag])
+    }
+}
+
+/// Derives a seed from the seed and data provided by hashing them together.
+pub fn derive_seed(seed: u64, data: &[&[u8]]) -> u64 {
+    let mut preimage = seed.to_be_bytes().to_vec();
+    for d in data {
+        preimage.extend_from_slice(d);
+    }
+
+    let hash = Sha256Hash::hash(&preimage).to_byte_array();
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Generates payment hashes for payments sent by attacks from a seeded random number generator, so that runs with the
+/// same seed use the same hashes.
+#[derive(Debug)]
+pub struct PaymentHashGenerator(Mutex<StdRng>);
+
+impl PaymentHashGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self(Mutex::new(StdRng::seed_from_u64(seed)))
+    }
+
+    /// Returns the next payment hash. A poisoned lock is recovered because the generator's state can't be left
+    /// inconsistent by a panic.
+    pub fn generate(&self) -> PaymentHash {
+        PaymentHash(
+            self.0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .random(),
+        )
+    }
+}
+
 /// Converts a set of custom tlv records to an accountable signal.
 pub fn accountable_from_records(records: &CustomRecords) -> AccountableSignal {
     match records.get(&ACCOUNTABLE_TYPE) {
@@ -64,7 +154,7 @@ pub fn records_from_signal(signal: AccountableSignal) -> CustomRecords {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct NetworkReputation {
     pub target_reputation: usize,
     pub target_pair_count: usize,
@@ -72,6 +162,32 @@ pub struct NetworkReputation {
     pub attacker_pair_count: usize,
 }
 
+/// Returns a node's channels in the network, keyed by short channel id, with the pubkey and alias of the peer that the
+/// channel is with.
+pub fn node_channels(
+    network: &[NetworkParser],
+    node: PublicKey,
+) -> HashMap<u64, (PublicKey, String)> {
+    network
+        .iter()
+        .filter_map(|channel| {
+            if channel.node_1.pubkey == node {
+                Some((
+                    channel.scid.into(),
+                    (channel.node_2.pubkey, channel.node_2.alias.clone()),
+                ))
+            } else if channel.node_2.pubkey == node {
+                Some((
+                    channel.scid.into(),
+                    (channel.node_1.pubkey, channel.node_1.alias.clone()),
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn get_network_reputation<R: ReputationMonitor>(
     reputation_monitor: Arc<R>,
@@ -122,7 +238,7 @@ pub async fn get_network_reputation<R: ReputationMonitor>(
 }
 
 /// Counts the number of pairs that the outgoing channel has reputation for.
-fn count_reputation_pairs(
+pub(crate) fn count_reputation_pairs(
     channels: &HashMap<u64, ChannelSnapshot>,
     outgoing_channel: u64,
     risk_margin: u64,
@@ -167,7 +283,7 @@ mod tests {
     use crate::reputation_interceptor::ReputationMonitor;
     use crate::test_utils::get_random_keypair;
     use crate::{count_reputation_pairs, get_network_reputation};
-    use crate::{BoxError, NetworkReputation};
+    use crate::{BoxError, NetworkReputation, PaymentHashGenerator, SeedComponent};
     use async_trait::async_trait;
     use bitcoin::secp256k1::PublicKey;
     use ln_resource_mgr::ChannelSnapshot;
@@ -185,6 +301,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_payment_hash_generator() {
+        let generator_1 = PaymentHashGenerator::new(1);
+        let generator_2 = PaymentHashGenerator::new(1);
+
+        let hash = generator_1.generate();
+        assert_eq!(hash, generator_2.generate());
+        assert_ne!(hash, generator_1.generate());
+        assert_ne!(hash, PaymentHashGenerator::new(2).generate());
+    }
+
+    /// Tests that each component is given a distinct seed that only depends on the simulation's seed.
+    #[test]
+    fn test_seed_components() {
+        let components = [
+            SeedComponent::Activity,
+            SeedComponent::Latency,
+            SeedComponent::PaymentHashes,
+            SeedComponent::ForwardManagers,
+            SeedComponent::ShadowManagers,
+            SeedComponent::Deployment,
+        ];
+
+        let seeds: Vec<u64> = components.iter().map(|c| c.seed(1)).collect();
+        for (i, seed) in seeds.iter().enumerate() {
+            assert!(!seeds[i + 1..].contains(seed));
+            assert_ne!(*seed, 1);
+        }
+
+        assert_eq!(SeedComponent::Latency.seed(1), seeds[1]);
+        assert_ne!(SeedComponent::Latency.seed(2), seeds[1]);
+    }
+
     /// Tests counting the number of pairs that an outgoing channel has good reputation on. Uses a zero risk margin
     /// to simplify test values.
     #[test]
@@ -0,0 +1,210 @@
+use crate::reputation_interceptor::{NodeManager, ObservingManager};
+use crate::BoxError;
+use ln_resource_mgr::baselines::{BaselineManager, BaselinePolicy};
+use ln_resource_mgr::forward_manager::{
+    ForwardManager, ForwardManagerParams, ObservedOutcome, SimulationDebugManager,
+};
+use ln_resource_mgr::{
+    AllocationCheck, ChannelSnapshot, ForwardResolution, ForwardingOutcome, HtlcRef,
+    ProposedForward, ReputationError, ReputationManager,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// The mitigation that nodes which have deployed a mitigation use to manage their resources.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Mitigation {
+    /// Outgoing reputation and resource bucketing, as implemented by [`ForwardManager`].
+    #[default]
+    Reputation,
+    /// Forwards every htlc that a channel has space for.
+    None,
+    /// Limits the htlcs that each incoming channel may have in flight.
+    CircuitBreaker {
+        max_pending_htlcs: u16,
+        max_pending_liquidity_portion: u8,
+    },
+    /// Reserves protected resources for accountable htlcs, using the general bucket portions of the forward
+    /// manager's parameters for unaccountable htlcs.
+    StaticBuckets,
+}
+
+impl Mitigation {
+    /// Returns a boolean indicating whether the mitigation tracks reputation.
+    pub fn tracks_reputation(&self) -> bool {
+        *self == Mitigation::Reputation
+    }
+
+    fn baseline_policy(&self, params: &ForwardManagerParams) -> Option<BaselinePolicy> {
+        match self {
+            Mitigation::Reputation => None,
+            Mitigation::None => Some(BaselinePolicy::NoMitigation),
+            Mitigation::CircuitBreaker {
+                max_pending_htlcs,
+                max_pending_liquidity_portion,
+            } => Some(BaselinePolicy::CircuitBreaker {
+                max_pending_htlcs: *max_pending_htlcs,
+                max_pending_liquidity_portion: *max_pending_liquidity_portion,
+            }),
+            Mitigation::StaticBuckets => Some(BaselinePolicy::StaticBuckets {
+                general_slot_portion: params.general_slot_portion,
+                general_liquidity_portion: params.general_liquidity_portion,
+            }),
+        }
+    }
+}
+
+/// Dispatches to the resource manager for the mitigation that a run is configured with, so that the same attack can
+/// be compared across mitigations.
+pub enum MitigationManager {
+    Reputation(Box<ForwardManager>),
+    Baseline(BaselineManager),
+}
+
+impl NodeManager for MitigationManager {
+    fn new_node_manager(
+        mitigation: Mitigation,
+        params: ForwardManagerParams,
+        seed: u64,
+        observe_only: bool,
+    ) -> Result<Self, BoxError> {
+        match mitigation.baseline_policy(&params) {
+            Some(_) if observe_only => {
+                Err(format!("mitigation: {mitigation:?} can't observe only").into())
+            }
+            Some(policy) => Ok(MitigationManager::Baseline(BaselineManager::new(policy))),
+            None => Ok(MitigationManager::Reputation(Box::new(
+                ForwardManager::new_node_manager(mitigation, params, seed, observe_only)?,
+            ))),
+        }
+    }
+}
+
+macro_rules! dispatch {
+    ($self:expr, $manager:ident => $call:expr) => {
+        match $self {
+            MitigationManager::Reputation($manager) => $call,
+            MitigationManager::Baseline($manager) => $call,
+        }
+    };
+}
+
+impl ObservingManager for BaselineManager {}
+
+impl ObservingManager for MitigationManager {
+    fn add_observed_htlc(
+        &self,
+        forward: &ProposedForward,
+    ) -> Result<ObservedOutcome, ReputationError> {
+        dispatch!(self, m => m.add_observed_htlc(forward))
+    }
+}
+
+impl SimulationDebugManager for MitigationManager {
+    fn general_jam_channel(&self, channel: u64) -> Result<(), ReputationError> {
+        dispatch!(self, m => m.general_jam_channel(channel))
+    }
+
+    fn congestion_jam_channel(&self, channel: u64) -> Result<(), ReputationError> {
+        dispatch!(self, m => m.congestion_jam_channel(channel))
+    }
+}
+
+impl ReputationManager for MitigationManager {
+    fn add_channel(
+        &self,
+        channel_id: u64,
+        capacity_msat: u64,
+        add_ins: Instant,
+        channel_reputation: Option<ChannelSnapshot>,
+    ) -> Result<(), ReputationError> {
+        dispatch!(self, m => m.add_channel(channel_id, capacity_msat, add_ins, channel_reputation))
+    }
+
+    fn remove_channel(&self, channel_id: u64) -> Result<(), ReputationError> {
+        dispatch!(self, m => m.remove_channel(channel_id))
+    }
+
+    fn get_allocation_snapshot(
+        &self,
+        forward: &ProposedForward,
+    ) -> Result<AllocationCheck, ReputationError> {
+        dispatch!(self, m => m.get_allocation_snapshot(forward))
+    }
+
+    fn add_htlc(&self, forward: &ProposedForward) -> Result<ForwardingOutcome, ReputationError> {
+        dispatch!(self, m => m.add_htlc(forward))
+    }
+
+    fn resolve_htlc(
+        &self,
+        outgoing_channel: u64,
+        incoming_ref: HtlcRef,
+        resolution: ForwardResolution,
+        resolved_instant: Instant,
+    ) -> Result<(), ReputationError> {
+        dispatch!(self, m => m.resolve_htlc(outgoing_channel, incoming_ref, resolution, resolved_instant))
+    }
+
+    fn list_channels(
+        &self,
+        access_ins: Instant,
+    ) -> Result<HashMap<u64, ChannelSnapshot>, ReputationError> {
+        dispatch!(self, m => m.list_channels(access_ins))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use ln_resource_mgr::forward_manager::{ForwardManager, ForwardManagerParams};
+    use ln_resource_mgr::ReputationManager;
+
+    use crate::reputation_interceptor::NodeManager;
+
+    use super::{Mitigation, MitigationManager};
+
+    /// Tests that each mitigation creates the expected manager, and that a forward manager can only be created for
+    /// the reputation mitigation.
+    #[test]
+    fn test_new_node_manager() {
+        let params = ForwardManagerParams::default();
+        let mitigations = [
+            Mitigation::Reputation,
+            Mitigation::None,
+            Mitigation::CircuitBreaker {
+                max_pending_htlcs: 10,
+                max_pending_liquidity_portion: 50,
+            },
+            Mitigation::StaticBuckets,
+        ];
+
+        for mitigation in mitigations {
+            let manager =
+                MitigationManager::new_node_manager(mitigation, params, 0, false).unwrap();
+            assert_eq!(
+                matches!(manager, MitigationManager::Reputation(_)),
+                mitigation.tracks_reputation()
+            );
+
+            manager
+                .add_channel(0, 100_000, Instant::now(), None)
+                .unwrap();
+            assert_eq!(manager.list_channels(Instant::now()).unwrap().len(), 1);
+
+            assert_eq!(
+                ForwardManager::new_node_manager(mitigation, params, 0, false).is_ok(),
+                mitigation.tracks_reputation()
+            );
+
+            // Only managers that track reputation can observe only.
+            assert_eq!(
+                MitigationManager::new_node_manager(mitigation, params, 0, true).is_ok(),
+                mitigation.tracks_reputation()
+            );
+        }
+    }
+}
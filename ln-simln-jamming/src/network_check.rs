@@ -0,0 +1,608 @@
+use crate::parsing::{
+    bootstrap_revenue_from_file, find_alias_by_pubkey, history_from_file,
+    reputation_snapshot_from_file, AttackType, NetworkParams, NetworkType,
+};
+use crate::reputation_interceptor::BootstrapForward;
+use bitcoin::secp256k1::PublicKey;
+use clap::ValueEnum;
+use ln_resource_mgr::ChannelSnapshot;
+use sim_cli::parsing::NetworkParser;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The severity of a finding in a network check.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Ok => write!(f, "ok"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The outcome of a single check on a network directory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// The file or directory that the finding relates to.
+    pub subject: String,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.subject, self.message)
+    }
+}
+
+/// A report of all the checks run on a network directory.
+#[derive(Clone, Debug, Default)]
+pub struct CheckReport {
+    pub findings: Vec<Finding>,
+}
+
+impl CheckReport {
+    fn add(&mut self, severity: Severity, subject: impl Display, message: impl Into<String>) {
+        self.findings.push(Finding {
+            severity,
+            subject: subject.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Returns the number of findings with the severity provided.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == severity)
+            .count()
+    }
+}
+
+/// Checks that a network directory has everything that simulations require, so that problems are reported up front
+/// rather than part way through a run:
+/// - The targets in `target.txt` are in the peacetime graph.
+/// - The reputation snapshot has an entry for every node and channel in the peacetime graph, with matching capacities.
+/// - The peacetime traffic file is readable and spans the reputation window.
+/// - Each attack directory loads, and its graph only differs from the peacetime graph by attacker channels.
+/// - If an attacker bootstrap is provided, each attack has traffic that covers it, along with a reputation snapshot
+///   and revenue file for the bootstrap duration. Otherwise, any bootstrapped snapshots found have revenue files.
+pub async fn check_network(
+    params: &NetworkParams,
+    reputation_window: Duration,
+    attacker_bootstrap: Option<Duration>,
+) -> CheckReport {
+    let mut report = CheckReport::default();
+
+    let network = match NetworkType::new(params, None, None) {
+        Ok(n) => n,
+        Err(e) => {
+            report.add(
+                Severity::Error,
+                params.network_dir.to_string_lossy(),
+                format!("could not load peacetime network, check peacetime_network.json and target.txt: {e}"),
+            );
+            return report;
+        }
+    };
+
+    let target_aliases: Vec<&str> = network.targets().iter().map(|t| t.0.as_str()).collect();
+    let peacetime_graph = network.active_network();
+    report.add(
+        Severity::Ok,
+        "target.txt",
+        format!(
+            "targets {} found in peacetime graph with {} channels",
+            target_aliases.join(", "),
+            peacetime_graph.len()
+        ),
+    );
+
+    check_snapshot_file(
+        &mut report,
+        &network.reputation_file(),
+        peacetime_graph,
+        "reputation-builder --network-dir {network dir}",
+    );
+    check_traffic_file(
+        &mut report,
+        &network.traffic_file(),
+        reputation_window,
+        "reputation window",
+        false,
+    )
+    .await;
+
+    let attacks_dir = params.network_dir.join("attacks");
+    let attack_dirs = match fs::read_dir(&attacks_dir) {
+        Ok(dirs) => dirs,
+        Err(_) => {
+            report.add(
+                Severity::Warning,
+                attacks_dir.to_string_lossy(),
+                "no attacks directory found, only peacetime runs are possible",
+            );
+            return report;
+        }
+    };
+
+    let mut names: Vec<String> = attack_dirs
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+
+    for name in names {
+        let attack = match attack_type_for_dir(&name) {
+            Ok(attack) => attack,
+            Err(e) => {
+                report.add(
+                    Severity::Error,
+                    attacks_dir.join(&name).to_string_lossy(),
+                    e,
+                );
+                continue;
+            }
+        };
+
+        check_attack(&mut report, params, attack, attacker_bootstrap).await;
+    }
+
+    report
+}
+
+/// Returns the attack type that a directory in `attacks` is for. Directories must be named exactly after the attack
+/// type's variant, so directories that only differ in case are reported with the name they should have.
+fn attack_type_for_dir(name: &str) -> Result<AttackType, String> {
+    for attack in AttackType::value_variants() {
+        let expected = format!("{attack:?}");
+        if expected == name {
+            return Ok(attack.clone());
+        }
+
+        if expected.eq_ignore_ascii_case(name) {
+            return Err(format!(
+                "attack directory should be named {expected} to be found for --attack-type"
+            ));
+        }
+    }
+
+    Err("directory does not match any attack type".to_string())
+}
+
+async fn check_attack(
+    report: &mut CheckReport,
+    params: &NetworkParams,
+    attack: AttackType,
+    attacker_bootstrap: Option<Duration>,
+) {
+    let subject = format!("attacks/{attack:?}");
+    let network = match NetworkType::new(params, Some(attack.clone()), attacker_bootstrap) {
+        Ok(n) => n,
+        Err(e) => {
+            report.add(
+                Severity::Error,
+                &subject,
+                format!(
+                    "could not load attack, check attacktime_network.json and attacker.csv: {e}"
+                ),
+            );
+            return;
+        }
+    };
+
+    let attackers: Vec<&str> = network.attackers().iter().map(|a| a.0.as_str()).collect();
+    report.add(
+        Severity::Ok,
+        &subject,
+        format!(
+            "attack graph matches peacetime graph with attackers {}",
+            attackers.join(", ")
+        ),
+    );
+
+    let Some(bootstrap) = attacker_bootstrap else {
+        let snapshot_dir = params.reputation_dir.clone().unwrap_or(
+            params
+                .network_dir
+                .join("attacks")
+                .join(format!("{attack:?}")),
+        );
+        check_bootstrap_pairs(report, &snapshot_dir, &subject);
+        return;
+    };
+
+    let builder = format!(
+        "forward-builder and reputation-builder with --attack-type {} --attacker-bootstrap {}",
+        attack
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default(),
+        humantime::format_duration(bootstrap)
+    );
+
+    check_traffic_file(
+        report,
+        &network.traffic_file(),
+        bootstrap,
+        "attacker bootstrap",
+        true,
+    )
+    .await;
+
+    // When the attacker's reputation is bootstrapped, every node in the attack graph is expected to have reputation.
+    check_snapshot_file(
+        report,
+        &network.reputation_file(),
+        network.active_network(),
+        &builder,
+    );
+
+    if let Some(revenue_file) = network.revenue_file() {
+        let subject = revenue_file.to_string_lossy();
+        if !revenue_file.exists() {
+            report.add(
+                Severity::Error,
+                subject,
+                format!("could not read revenue: file not found, generate with {builder}"),
+            );
+            return;
+        }
+
+        match bootstrap_revenue_from_file(&revenue_file, network.targets()) {
+            Ok(revenue) => report.add(
+                Severity::Ok,
+                subject,
+                format!(
+                    "target bootstrap revenue: {}",
+                    network
+                        .targets()
+                        .iter()
+                        .map(|(alias, pubkey)| format!(
+                            "{alias}={} msat",
+                            revenue.get(pubkey).copied().unwrap_or_default()
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ),
+            Err(e) => report.add(
+                Severity::Error,
+                subject,
+                format!("could not parse revenue: {e}, regenerate with {builder}"),
+            ),
+        }
+    }
+}
+
+/// Checks that every bootstrapped reputation snapshot found for an attack has a matching revenue file, reporting the
+/// bootstrap durations that are available.
+fn check_bootstrap_pairs(report: &mut CheckReport, dir: &Path, subject: &str) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut durations: Vec<u64> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .strip_prefix("reputation_")?
+                .strip_suffix(".csv")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    durations.sort();
+
+    for secs in durations {
+        let bootstrap = humantime::format_duration(Duration::from_secs(secs));
+        if dir.join(format!("revenue_{secs}.csv")).exists() {
+            report.add(
+                Severity::Ok,
+                subject,
+                format!("bootstrap snapshot available for --attacker-bootstrap {bootstrap}"),
+            );
+        } else {
+            report.add(
+                Severity::Warning,
+                subject,
+                format!(
+                    "reputation_{secs}.csv has no revenue_{secs}.csv, rerun reputation-builder with \
+                     --attacker-bootstrap {bootstrap}"
+                ),
+            );
+        }
+    }
+}
+
+fn check_snapshot_file(
+    report: &mut CheckReport,
+    path: &PathBuf,
+    edges: &[NetworkParser],
+    builder: &str,
+) {
+    let subject = path.to_string_lossy();
+    let snapshot = match reputation_snapshot_from_file(path) {
+        Ok(s) => s,
+        Err(e) => {
+            report.add(
+                Severity::Error,
+                &subject,
+                format!("could not read reputation snapshot: {e}, generate one with {builder}"),
+            );
+            return;
+        }
+    };
+
+    let findings = check_snapshot(edges, &snapshot, &HashSet::new());
+    if findings.is_empty() {
+        report.add(
+            Severity::Ok,
+            &subject,
+            format!("snapshot matches graph for {} nodes", snapshot.len()),
+        );
+    }
+
+    for (severity, message) in findings {
+        let message = match severity {
+            Severity::Error => format!("{message}, regenerate with {builder}"),
+            _ => message,
+        };
+        report.add(severity, &subject, message);
+    }
+}
+
+/// Checks a reputation snapshot against the graph that it will be loaded for, reporting the same mismatches that
+/// would fail [`crate::reputation_interceptor::ReputationInterceptor::new_from_snapshot`] as errors. Channels in the
+/// snapshot that aren't in the graph are reported as warnings.
+fn check_snapshot(
+    edges: &[NetworkParser],
+    snapshot: &HashMap<PublicKey, HashMap<u64, ChannelSnapshot>>,
+    no_reputation: &HashSet<PublicKey>,
+) -> Vec<(Severity, String)> {
+    let mut findings = vec![];
+    let mut reported_nodes = HashSet::new();
+    let mut graph_channels: HashSet<(PublicKey, u64)> = HashSet::new();
+
+    for channel in edges {
+        let scid = u64::from(channel.scid);
+        for (node, counterparty) in [
+            (&channel.node_1, &channel.node_2),
+            (&channel.node_2, &channel.node_1),
+        ] {
+            graph_channels.insert((node.pubkey, scid));
+            let node_snapshot = snapshot.get(&node.pubkey);
+
+            if no_reputation.contains(&node.pubkey) {
+                if node_snapshot.is_some() && reported_nodes.insert(node.pubkey) {
+                    findings.push((
+                        Severity::Error,
+                        format!("node {} should not have a snapshot", node.alias),
+                    ));
+                }
+                continue;
+            }
+
+            let Some(node_snapshot) = node_snapshot else {
+                if reported_nodes.insert(node.pubkey) {
+                    findings.push((
+                        Severity::Error,
+                        format!("node {} ({}) has no snapshot", node.alias, node.pubkey),
+                    ));
+                }
+                continue;
+            };
+
+            if no_reputation.contains(&counterparty.pubkey) {
+                continue;
+            }
+
+            match node_snapshot.get(&scid) {
+                Some(channel_snapshot) => {
+                    if channel_snapshot.capacity_msat != channel.capacity_msat {
+                        findings.push((
+                            Severity::Error,
+                            format!(
+                                "channel {scid} has capacity {} in graph but {} in snapshot for node {}",
+                                channel.capacity_msat, channel_snapshot.capacity_msat, node.alias
+                            ),
+                        ));
+                    }
+                }
+                None => findings.push((
+                    Severity::Error,
+                    format!(
+                        "channel {scid} not found in snapshot for node {}",
+                        node.alias
+                    ),
+                )),
+            }
+        }
+    }
+
+    let mut stale: Vec<(String, u64)> = snapshot
+        .iter()
+        .flat_map(|(pubkey, channels)| channels.keys().map(move |scid| (*pubkey, *scid)))
+        .filter(|channel| !graph_channels.contains(channel))
+        .map(|(pubkey, scid)| {
+            (
+                find_alias_by_pubkey(&pubkey, edges).unwrap_or(pubkey.to_string()),
+                scid,
+            )
+        })
+        .collect();
+    stale.sort();
+
+    findings.extend(stale.into_iter().map(|(node, scid)| {
+        (
+            Severity::Warning,
+            format!("snapshot has channel {scid} for node {node} which is not in the graph"),
+        )
+    }));
+
+    findings
+}
+
+/// Checks that a traffic file is readable, and that it spans at least the duration provided. If required, a traffic
+/// file that is missing or too short is reported as an error rather than a warning.
+async fn check_traffic_file(
+    report: &mut CheckReport,
+    path: &PathBuf,
+    duration: Duration,
+    duration_name: &str,
+    required: bool,
+) {
+    let subject = path.to_string_lossy();
+    let severity = if required {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+
+    let forwards = match history_from_file(path, None).await {
+        Ok(f) => f,
+        Err(e) => {
+            report.add(
+                severity,
+                subject,
+                format!("could not read traffic: {e}, generate with forward-builder"),
+            );
+            return;
+        }
+    };
+
+    let span = traffic_span(&forwards);
+    if span < duration {
+        report.add(
+            severity,
+            subject,
+            format!(
+                "traffic spans {} which is shorter than the {duration_name} of {}",
+                humantime::format_duration(span),
+                humantime::format_duration(duration)
+            ),
+        );
+    } else {
+        report.add(
+            Severity::Ok,
+            subject,
+            format!(
+                "{} forwards spanning {}",
+                forwards.len(),
+                humantime::format_duration(span)
+            ),
+        );
+    }
+}
+
+/// Returns the time between the first forward being added and the last being settled.
+fn traffic_span(forwards: &[BootstrapForward]) -> Duration {
+    let start = forwards.iter().map(|f| f.added_ns).min();
+    let end = forwards.iter().map(|f| f.settled_ns).max();
+
+    match (start, end) {
+        (Some(start), Some(end)) => Duration::from_nanos(end.saturating_sub(start)),
+        _ => Duration::ZERO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::time::Duration;
+
+    use ln_resource_mgr::ChannelSnapshot;
+    use simln_lib::ShortChannelID;
+
+    use crate::parsing::AttackType;
+    use crate::test_utils::{get_random_keypair, setup_test_edge, test_bootstrap_forward};
+
+    use super::{attack_type_for_dir, check_snapshot, traffic_span, Severity};
+
+    #[test]
+    fn test_attack_type_for_dir() {
+        assert_eq!(attack_type_for_dir("SlowJam").unwrap(), AttackType::SlowJam);
+        assert!(attack_type_for_dir("sink").unwrap_err().contains("Sink"));
+        assert!(attack_type_for_dir("unknown").is_err());
+    }
+
+    /// Tests that missing nodes and channels and mismatched capacities are reported as errors, and that stale
+    /// snapshot channels are reported as warnings.
+    #[test]
+    fn test_check_snapshot() {
+        let alice = get_random_keypair().1;
+        let bob = get_random_keypair().1;
+        let carol = get_random_keypair().1;
+        let edges = vec![
+            setup_test_edge(ShortChannelID::from(1), alice, bob),
+            setup_test_edge(ShortChannelID::from(2), bob, carol),
+        ];
+
+        let channel = |capacity_msat: u64| ChannelSnapshot {
+            capacity_msat,
+            outgoing_reputation: 0,
+            incoming_revenue: 0,
+        };
+        let capacity = edges[0].capacity_msat;
+
+        let mut snapshot = HashMap::from([
+            (alice, HashMap::from([(1, channel(capacity))])),
+            (
+                bob,
+                HashMap::from([(1, channel(capacity)), (2, channel(capacity))]),
+            ),
+            (carol, HashMap::from([(2, channel(capacity))])),
+        ]);
+        assert!(check_snapshot(&edges, &snapshot, &HashSet::new()).is_empty());
+
+        // Nodes that aren't expected to have reputation must not be in the snapshot, and their channels aren't
+        // required for their peers.
+        let findings = check_snapshot(&edges, &snapshot, &HashSet::from([carol]));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].0, Severity::Error);
+
+        snapshot.remove(&carol);
+        snapshot.get_mut(&bob).unwrap().remove(&2);
+        assert!(check_snapshot(&edges, &snapshot, &HashSet::from([carol])).is_empty());
+
+        // Carol has no snapshot, bob is missing a channel and alice has the wrong capacity and a stale channel.
+        snapshot
+            .get_mut(&alice)
+            .unwrap()
+            .insert(1, channel(capacity + 1));
+        snapshot
+            .get_mut(&alice)
+            .unwrap()
+            .insert(3, channel(capacity));
+        let findings = check_snapshot(&edges, &snapshot, &HashSet::new());
+        assert_eq!(
+            findings.iter().filter(|f| f.0 == Severity::Error).count(),
+            3
+        );
+        assert_eq!(findings.last().unwrap().0, Severity::Warning);
+    }
+
+    #[test]
+    fn test_traffic_span() {
+        assert_eq!(traffic_span(&[]), Duration::ZERO);
+
+        let forwards = vec![
+            test_bootstrap_forward(10, 20, 1, 2),
+            test_bootstrap_forward(5, 15, 1, 2),
+        ];
+        assert_eq!(traffic_span(&forwards), Duration::from_nanos(15));
+    }
+}
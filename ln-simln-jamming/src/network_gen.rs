@@ -0,0 +1,929 @@
+use crate::parsing::{AttackType, SimNetwork};
+use crate::BoxError;
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+use clap::{Parser, ValueEnum};
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sim_cli::parsing::NetworkParser;
+use simln_lib::sim_node::ChannelPolicy;
+use simln_lib::ShortChannelID;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+/// The block height that generated short channel ids start at.
+const START_BLOCK_HEIGHT: u64 = 300_000;
+
+/// The shape of the graph that is generated.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+pub enum Topology {
+    /// Preferential attachment, where each new node opens channels to existing nodes with a probability proportional
+    /// to their channel count.
+    ScaleFree,
+    /// A ring lattice with a portion of its channels randomly rewired.
+    SmallWorld,
+    /// A fully connected core of hubs, with every other node connected to a number of hubs.
+    HubAndSpoke,
+}
+
+/// Parameters used to generate a synthetic network.
+#[derive(Clone, Debug, Parser)]
+pub struct NetworkGenParams {
+    /// The number of honest nodes in the network.
+    #[arg(long, default_value_t = 50)]
+    pub node_count: usize,
+
+    /// The shape of the graph that is generated.
+    #[arg(long, value_enum, default_value_t = Topology::ScaleFree)]
+    pub topology: Topology,
+
+    /// For scale-free networks, the number of channels each new node opens. For small-world networks, the number of
+    /// neighbours that each node has on either side of the ring. For hub-and-spoke networks, the number of hubs that
+    /// each spoke connects to.
+    #[arg(long, default_value_t = 2)]
+    pub degree: usize,
+
+    /// The probability that each channel in a small-world network is rewired to a random node.
+    #[arg(long, default_value_t = 0.1)]
+    pub rewire_probability: f64,
+
+    /// The number of hubs in a hub-and-spoke network.
+    #[arg(long, default_value_t = 5)]
+    pub hub_count: usize,
+
+    /// The smallest channel capacity, expressed in sats. Capacities are picked log-uniformly between the minimum and
+    /// maximum so that small channels are more common than large ones.
+    #[arg(long, default_value_t = 1_000_000)]
+    pub min_capacity_sat: u64,
+
+    /// The largest channel capacity, expressed in sats.
+    #[arg(long, default_value_t = 100_000_000)]
+    pub max_capacity_sat: u64,
+
+    /// The smallest base fee charged by a node, expressed in msat.
+    #[arg(long, default_value_t = 0)]
+    pub min_base_fee_msat: u64,
+
+    /// The largest base fee charged by a node, expressed in msat.
+    #[arg(long, default_value_t = 1_000)]
+    pub max_base_fee_msat: u64,
+
+    /// The smallest proportional fee charged by a node, expressed in parts per million.
+    #[arg(long, default_value_t = 1)]
+    pub min_fee_rate_ppm: u64,
+
+    /// The largest proportional fee charged by a node, expressed in parts per million.
+    #[arg(long, default_value_t = 2_000)]
+    pub max_fee_rate_ppm: u64,
+
+    /// The cltv deltas that nodes pick from, comma separated.
+    #[arg(long, value_delimiter = ',', default_value = "40,80,144")]
+    pub cltv_deltas: Vec<u32>,
+}
+
+impl NetworkGenParams {
+    fn validate(&self) -> Result<(), BoxError> {
+        if self.degree == 0 {
+            return Err("degree must be at least 1".into());
+        }
+
+        let min_nodes = match self.topology {
+            Topology::ScaleFree => self.degree + 1,
+            Topology::SmallWorld => self.degree * 2 + 1,
+            Topology::HubAndSpoke => {
+                if self.hub_count < self.degree {
+                    return Err(format!(
+                        "hub count: {} must be at least degree: {}",
+                        self.hub_count, self.degree
+                    )
+                    .into());
+                }
+                self.hub_count + 1
+            }
+        };
+        if self.node_count < min_nodes {
+            return Err(format!(
+                "{:?} network with degree {} requires at least {min_nodes} nodes, got: {}",
+                self.topology, self.degree, self.node_count
+            )
+            .into());
+        }
+
+        if !(0.0..=1.0).contains(&self.rewire_probability) {
+            return Err(format!(
+                "rewire probability: {} must be in [0, 1]",
+                self.rewire_probability
+            )
+            .into());
+        }
+
+        if self.min_capacity_sat == 0 || self.min_capacity_sat > self.max_capacity_sat {
+            return Err(format!(
+                "capacity range: {}-{} must be non-zero and ordered",
+                self.min_capacity_sat, self.max_capacity_sat
+            )
+            .into());
+        }
+
+        if self.min_base_fee_msat > self.max_base_fee_msat
+            || self.min_fee_rate_ppm > self.max_fee_rate_ppm
+        {
+            return Err("fee ranges must be ordered".into());
+        }
+
+        if self.cltv_deltas.is_empty() {
+            return Err("at least one cltv delta required".into());
+        }
+
+        Ok(())
+    }
+}
+
+/// A node in a generated network, along with the forwarding policy that it applies to all of its channels.
+#[derive(Clone, Debug)]
+struct GenNode {
+    pubkey: PublicKey,
+    alias: String,
+    min_htlc_size_msat: u64,
+    cltv_expiry_delta: u32,
+    base_fee: u64,
+    fee_rate_prop: u64,
+}
+
+impl From<&ChannelPolicy> for GenNode {
+    fn from(policy: &ChannelPolicy) -> Self {
+        GenNode {
+            pubkey: policy.pubkey,
+            alias: policy.alias.clone(),
+            min_htlc_size_msat: policy.min_htlc_size_msat,
+            cltv_expiry_delta: policy.cltv_expiry_delta,
+            base_fee: policy.base_fee,
+            fee_rate_prop: policy.fee_rate_prop,
+        }
+    }
+}
+
+/// A synthetic network, along with any attacker channels that were generated for it.
+#[derive(Clone)]
+pub struct GeneratedNetwork {
+    pub peacetime: Vec<NetworkParser>,
+    pub target: String,
+    pub attack: Option<GeneratedAttack>,
+}
+
+/// The channels and aliases of the attacking nodes added to a generated network for an attack.
+#[derive(Clone)]
+pub struct GeneratedAttack {
+    pub attack_type: AttackType,
+    pub attacktime: Vec<NetworkParser>,
+    pub attackers: Vec<String>,
+}
+
+/// Generates a network from the parameters provided, picking the node with the highest betweenness centrality as the
+/// target. If an attack is provided, attacking nodes are added with the channels that the attack requires.
+pub fn generate_network(
+    params: &NetworkGenParams,
+    attack: Option<(AttackType, Option<usize>)>,
+    rng: &mut StdRng,
+) -> Result<GeneratedNetwork, BoxError> {
+    params.validate()?;
+
+    let nodes: Vec<GenNode> = (0..params.node_count)
+        .map(|i| gen_node(i.to_string(), params, rng))
+        .collect();
+
+    let pairs = match params.topology {
+        Topology::ScaleFree => scale_free_pairs(params.node_count, params.degree, rng),
+        Topology::SmallWorld => small_world_pairs(
+            params.node_count,
+            params.degree,
+            params.rewire_probability,
+            rng,
+        ),
+        Topology::HubAndSpoke => {
+            hub_and_spoke_pairs(params.node_count, params.hub_count, params.degree, rng)
+        }
+    };
+
+    let mut scids = ScidGenerator(START_BLOCK_HEIGHT);
+    let peacetime: Vec<NetworkParser> = pairs
+        .iter()
+        .map(|(a, b)| {
+            gen_channel(
+                scids.next_scid(),
+                random_capacity(params, rng),
+                &nodes[*a],
+                &nodes[*b],
+            )
+        })
+        .collect();
+
+    let centrality = betweenness_centrality(params.node_count, &pairs);
+    let target = (0..params.node_count)
+        .max_by(|a, b| centrality[*a].total_cmp(&centrality[*b]).then(b.cmp(a)))
+        .ok_or("network has no nodes")?;
+
+    let attack = match attack {
+        Some((attack_type, attacker_count)) => Some(gen_attack(
+            attack_type,
+            attacker_count,
+            &peacetime,
+            nodes[target].pubkey,
+            params,
+            rng,
+        )?),
+        None => None,
+    };
+
+    Ok(GeneratedNetwork {
+        peacetime,
+        target: nodes[target].alias.clone(),
+        attack,
+    })
+}
+
+/// Adds attacking nodes to a copy of the peacetime network, with the channels that the attack requires:
+/// - Sink: a single attacker with a channel to the target and channels to `degree` of the target's largest peers.
+/// - Sybil: sybils that each have a channel to the target and a channel to one of the target's peers.
+/// - RevenueInflation: at least two attackers that each have a channel with the target.
+fn gen_attack(
+    attack_type: AttackType,
+    attacker_count: Option<usize>,
+    peacetime: &[NetworkParser],
+    target: PublicKey,
+    params: &NetworkGenParams,
+    rng: &mut StdRng,
+) -> Result<GeneratedAttack, BoxError> {
+    let (placement, count, min_count) = match attack_type {
+        AttackType::Sink => (
+            AttackerPlacement::TargetAndPeers,
+            attacker_count.unwrap_or(1),
+            1,
+        ),
+        AttackType::Sybil => (AttackerPlacement::Sybils, attacker_count.unwrap_or(5), 2),
+        AttackType::RevenueInflation => (
+            AttackerPlacement::TargetChannel,
+            attacker_count.unwrap_or(2),
+            2,
+        ),
+        AttackType::SlowJam => {
+            return Err("slow jam attack relies on fixed aliases and can't be generated".into())
+        }
+    };
+
+    if count < min_count || (attack_type == AttackType::Sink && count != 1) {
+        return Err(format!("{attack_type:?} attack can't be run with {count} attackers").into());
+    }
+
+    // Attackers open the largest channels allowed so that they have liquidity to spare.
+    let (attacktime, attackers) = add_attackers(
+        peacetime,
+        &[target],
+        &PlacementParams {
+            placement,
+            attacker_count: count,
+            peer_count: params.degree,
+            capacity_sat: Some(params.max_capacity_sat),
+        },
+        rng,
+    )?;
+
+    Ok(GeneratedAttack {
+        attack_type,
+        attacktime,
+        attackers,
+    })
+}
+
+/// The way that attacking nodes are connected to a peacetime graph.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+pub enum AttackerPlacement {
+    /// Each attacker opens a single channel with each target.
+    TargetChannel,
+    /// Each attacker opens a channel with each target and with each of the targets' largest peers.
+    TargetAndPeers,
+    /// Each attacker opens a channel with each target and with one of the targets' peers, picked at random.
+    Sybils,
+}
+
+/// Parameters describing how attacking nodes are added to a peacetime graph.
+#[derive(Clone, Debug, Parser)]
+pub struct PlacementParams {
+    /// The channels that each attacker opens.
+    #[arg(long, value_enum)]
+    pub placement: AttackerPlacement,
+
+    /// The number of attacking nodes to add.
+    #[arg(long, default_value_t = 1)]
+    pub attacker_count: usize,
+
+    /// The number of the targets' peers, by total capacity, that each attacker opens channels with when placed with
+    /// target-and-peers.
+    #[arg(long, default_value_t = 5)]
+    pub peer_count: usize,
+
+    /// The capacity of attacker channels, expressed in sats. Defaults to the capacity of the targets' largest channel.
+    #[arg(long)]
+    pub capacity_sat: Option<u64>,
+}
+
+/// Adds attacking nodes to a copy of the peacetime graph provided, returning the attack time graph and the aliases of
+/// the attackers. Each attacker opens a channel with every target, and any peer channels are picked from the targets'
+/// combined peers. Attackers use the median forwarding policy of the graph, and are given numeric aliases following the
+/// largest numeric alias in the graph. Their channels are confirmed after every channel in the graph.
+pub fn add_attackers(
+    peacetime: &[NetworkParser],
+    targets: &[PublicKey],
+    params: &PlacementParams,
+    rng: &mut StdRng,
+) -> Result<(Vec<NetworkParser>, Vec<String>), BoxError> {
+    if params.attacker_count == 0 {
+        return Err("at least one attacker required".into());
+    }
+
+    if targets.is_empty() {
+        return Err("at least one target required".into());
+    }
+
+    let policies: Vec<&ChannelPolicy> = peacetime
+        .iter()
+        .flat_map(|c| [&c.node_1, &c.node_2])
+        .collect();
+
+    let target_nodes = targets
+        .iter()
+        .map(|target| {
+            policies
+                .iter()
+                .find(|p| p.pubkey == *target)
+                .map(|p| GenNode::from(*p))
+                .ok_or(format!("target: {target} not found in graph"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Rank the targets' peers by their total capacity, breaking ties by pubkey so that the order is deterministic.
+    // Targets are not picked as peers, because every attacker already has a channel with them.
+    let mut peer_capacity: HashMap<PublicKey, (u64, GenNode)> = HashMap::new();
+    let mut target_capacity = 0;
+    for channel in peacetime.iter() {
+        let peer = match (
+            targets.contains(&channel.node_1.pubkey),
+            targets.contains(&channel.node_2.pubkey),
+        ) {
+            (true, true) => {
+                target_capacity = target_capacity.max(channel.capacity_msat);
+                continue;
+            }
+            (true, _) => &channel.node_2,
+            (_, true) => &channel.node_1,
+            _ => continue,
+        };
+
+        target_capacity = target_capacity.max(channel.capacity_msat);
+        peer_capacity.insert(peer.pubkey, (0, GenNode::from(peer)));
+    }
+    for channel in peacetime.iter() {
+        for node in [&channel.node_1, &channel.node_2] {
+            if let Some((capacity, _)) = peer_capacity.get_mut(&node.pubkey) {
+                *capacity += channel.capacity_msat;
+            }
+        }
+    }
+    let mut peers: Vec<(u64, GenNode)> = peer_capacity.into_values().collect();
+    peers.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.pubkey.cmp(&b.1.pubkey)));
+
+    if peers.is_empty() && params.placement != AttackerPlacement::TargetChannel {
+        return Err("targets have no peers to place attackers with".into());
+    }
+
+    let capacity_msat = params
+        .capacity_sat
+        .map(|sat| sat * 1000)
+        .unwrap_or(target_capacity);
+
+    let median = |mut values: Vec<u64>| -> u64 {
+        values.sort();
+        values[values.len() / 2]
+    };
+    let median_policy = |pubkey: PublicKey, alias: String| GenNode {
+        pubkey,
+        alias,
+        min_htlc_size_msat: median(policies.iter().map(|p| p.min_htlc_size_msat).collect()),
+        cltv_expiry_delta: median(
+            policies
+                .iter()
+                .map(|p| p.cltv_expiry_delta as u64)
+                .collect(),
+        ) as u32,
+        base_fee: median(policies.iter().map(|p| p.base_fee).collect()),
+        fee_rate_prop: median(policies.iter().map(|p| p.fee_rate_prop).collect()),
+    };
+
+    let first_alias = policies
+        .iter()
+        .filter_map(|p| p.alias.parse::<u64>().ok())
+        .max()
+        .map(|alias| alias + 1)
+        .unwrap_or(0);
+
+    let last_height = peacetime
+        .iter()
+        .map(|c| u64::from(c.scid) >> 40)
+        .max()
+        .unwrap_or(START_BLOCK_HEIGHT);
+    let mut scids = ScidGenerator(last_height + 1);
+
+    let mut attacktime = peacetime.to_vec();
+    let mut attackers = Vec::with_capacity(params.attacker_count);
+    for i in 0..params.attacker_count {
+        let attacker = median_policy(random_pubkey(rng), (first_alias + i as u64).to_string());
+        for target_node in target_nodes.iter() {
+            attacktime.push(gen_channel(
+                scids.next_scid(),
+                capacity_msat,
+                &attacker,
+                target_node,
+            ));
+        }
+
+        let attacker_peers: Vec<&(u64, GenNode)> = match params.placement {
+            AttackerPlacement::TargetChannel => vec![],
+            AttackerPlacement::TargetAndPeers => peers.iter().take(params.peer_count).collect(),
+            AttackerPlacement::Sybils => peers.choose(rng).into_iter().collect(),
+        };
+        for (_, peer) in attacker_peers {
+            attacktime.push(gen_channel(
+                scids.next_scid(),
+                capacity_msat,
+                &attacker,
+                peer,
+            ));
+        }
+
+        attackers.push(attacker.alias);
+    }
+
+    Ok((attacktime, attackers))
+}
+
+/// Writes an attack time graph and its attackers to the attack's directory in the network directory provided.
+pub fn write_attack(network_dir: &Path, attack: &GeneratedAttack) -> Result<(), BoxError> {
+    let attack_dir = network_dir
+        .join("attacks")
+        .join(format!("{:?}", attack.attack_type));
+    fs::create_dir_all(&attack_dir)?;
+
+    write_graph(
+        &attack_dir.join("attacktime_network.json"),
+        &attack.attacktime,
+    )?;
+    fs::write(attack_dir.join("attacker.csv"), attack.attackers.join(","))?;
+
+    Ok(())
+}
+
+/// Writes a generated network to the network directory provided, in the layout expected by the simulator.
+pub fn write_network(network_dir: &Path, network: &GeneratedNetwork) -> Result<(), BoxError> {
+    fs::create_dir_all(network_dir)?;
+    write_graph(
+        &network_dir.join("peacetime_network.json"),
+        &network.peacetime,
+    )?;
+    fs::write(network_dir.join("target.txt"), &network.target)?;
+
+    if let Some(attack) = &network.attack {
+        write_attack(network_dir, attack)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a graph to the path provided in the format that sim-ln reads, overwriting any existing file.
+pub(crate) fn write_graph(path: &Path, graph: &[NetworkParser]) -> Result<(), BoxError> {
+    let network = SimNetwork {
+        sim_network: graph.to_vec(),
+    };
+    fs::write(path, serde_json::to_string_pretty(&network)?)?;
+    Ok(())
+}
+
+fn random_pubkey(rng: &mut StdRng) -> PublicKey {
+    let secret_key = loop {
+        if let Ok(sk) = SecretKey::from_slice(&rng.random::<[u8; 32]>()) {
+            break sk;
+        }
+    };
+
+    PublicKey::from_secret_key(&Secp256k1::new(), &secret_key)
+}
+
+fn gen_node(alias: String, params: &NetworkGenParams, rng: &mut StdRng) -> GenNode {
+    GenNode {
+        pubkey: random_pubkey(rng),
+        alias,
+        min_htlc_size_msat: 1,
+        cltv_expiry_delta: *params
+            .cltv_deltas
+            .choose(rng)
+            .expect("cltv deltas validated"),
+        base_fee: rng.random_range(params.min_base_fee_msat..=params.max_base_fee_msat),
+        fee_rate_prop: rng.random_range(params.min_fee_rate_ppm..=params.max_fee_rate_ppm),
+    }
+}
+
+fn gen_channel(
+    scid: ShortChannelID,
+    capacity_msat: u64,
+    node_1: &GenNode,
+    node_2: &GenNode,
+) -> NetworkParser {
+    let policy = |node: &GenNode| ChannelPolicy {
+        pubkey: node.pubkey,
+        alias: node.alias.clone(),
+        max_htlc_count: 483,
+        max_in_flight_msat: capacity_msat,
+        min_htlc_size_msat: node.min_htlc_size_msat,
+        max_htlc_size_msat: capacity_msat,
+        cltv_expiry_delta: node.cltv_expiry_delta,
+        base_fee: node.base_fee,
+        fee_rate_prop: node.fee_rate_prop,
+    };
+
+    NetworkParser {
+        scid,
+        capacity_msat,
+        node_1: policy(node_1),
+        node_2: policy(node_2),
+    }
+}
+
+/// Picks a capacity log-uniformly between the minimum and maximum, expressed in msat.
+fn random_capacity(params: &NetworkGenParams, rng: &mut StdRng) -> u64 {
+    let min = (params.min_capacity_sat as f64).ln();
+    let max = (params.max_capacity_sat as f64).ln();
+    let sat = if max > min {
+        rng.random_range(min..=max).exp().round() as u64
+    } else {
+        params.min_capacity_sat
+    };
+
+    sat.clamp(params.min_capacity_sat, params.max_capacity_sat) * 1000
+}
+
+/// Generates short channel ids with one channel confirmed per block, starting at the block height provided.
+struct ScidGenerator(u64);
+
+impl ScidGenerator {
+    fn next_scid(&mut self) -> ShortChannelID {
+        let scid = self.0 << 40;
+        self.0 += 1;
+        ShortChannelID::from(scid)
+    }
+}
+
+/// Generates a scale-free graph with preferential attachment (Barabasi-Albert). The first `degree + 1` nodes are fully
+/// connected, and each subsequent node opens `degree` channels to distinct existing nodes, picked with probability
+/// proportional to their channel count.
+fn scale_free_pairs(node_count: usize, degree: usize, rng: &mut StdRng) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    // Each node appears once for every channel it has, so picking uniformly from this list is proportional to degree.
+    let mut endpoints = vec![];
+
+    for a in 0..=degree {
+        for b in (a + 1)..=degree {
+            pairs.push((a, b));
+            endpoints.extend([a, b]);
+        }
+    }
+
+    for node in (degree + 1)..node_count {
+        let mut peers = BTreeSet::new();
+        while peers.len() < degree {
+            peers.insert(*endpoints.choose(rng).expect("initial nodes connected"));
+        }
+
+        for peer in peers {
+            pairs.push((peer, node));
+            endpoints.extend([peer, node]);
+        }
+    }
+
+    pairs
+}
+
+/// Generates a small-world graph (Watts-Strogatz). Nodes are placed in a ring and connected to the `degree` nodes on
+/// either side of them, then each channel is rewired to a random node with the probability provided. Channels between
+/// direct neighbours in the ring are never rewired, so that the graph remains connected.
+fn small_world_pairs(
+    node_count: usize,
+    degree: usize,
+    rewire_probability: f64,
+    rng: &mut StdRng,
+) -> Vec<(usize, usize)> {
+    let mut pairs: BTreeSet<(usize, usize)> = BTreeSet::new();
+    let ordered = |a: usize, b: usize| (a.min(b), a.max(b));
+
+    for node in 0..node_count {
+        pairs.insert(ordered(node, (node + 1) % node_count));
+    }
+
+    for distance in 2..=degree {
+        for node in 0..node_count {
+            let mut peer = (node + distance) % node_count;
+            if rng.random_bool(rewire_probability) {
+                // Give up on rewiring if we don't find a free peer quickly, which can happen in dense graphs.
+                for _ in 0..node_count {
+                    let candidate = rng.random_range(0..node_count);
+                    if candidate != node && !pairs.contains(&ordered(node, candidate)) {
+                        peer = candidate;
+                        break;
+                    }
+                }
+            }
+
+            pairs.insert(ordered(node, peer));
+        }
+    }
+
+    pairs.into_iter().collect()
+}
+
+/// Generates a hub-and-spoke graph, where the first `hub_count` nodes are fully connected to each other and every
+/// other node connects to `degree` distinct hubs.
+fn hub_and_spoke_pairs(
+    node_count: usize,
+    hub_count: usize,
+    degree: usize,
+    rng: &mut StdRng,
+) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    for a in 0..hub_count {
+        for b in (a + 1)..hub_count {
+            pairs.push((a, b));
+        }
+    }
+
+    let hubs: Vec<usize> = (0..hub_count).collect();
+    for spoke in hub_count..node_count {
+        let mut spoke_hubs: Vec<usize> = hubs.choose_multiple(rng, degree).copied().collect();
+        spoke_hubs.sort();
+        pairs.extend(spoke_hubs.into_iter().map(|hub| (hub, spoke)));
+    }
+
+    pairs
+}
+
+/// Calculates the betweenness centrality of each node in an unweighted, undirected graph using Brandes' algorithm.
+fn betweenness_centrality(node_count: usize, pairs: &[(usize, usize)]) -> Vec<f64> {
+    let mut adjacency: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (a, b) in pairs {
+        adjacency.entry(*a).or_default().push(*b);
+        adjacency.entry(*b).or_default().push(*a);
+    }
+
+    let mut centrality = vec![0.0; node_count];
+    for source in 0..node_count {
+        let mut stack = vec![];
+        let mut predecessors: Vec<Vec<usize>> = vec![vec![]; node_count];
+        let mut paths = vec![0.0; node_count];
+        let mut distance: Vec<Option<usize>> = vec![None; node_count];
+        paths[source] = 1.0;
+        distance[source] = Some(0);
+
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            stack.push(node);
+            let node_distance = distance[node].expect("queued nodes have distance");
+
+            for peer in adjacency.get(&node).into_iter().flatten() {
+                if distance[*peer].is_none() {
+                    distance[*peer] = Some(node_distance + 1);
+                    queue.push_back(*peer);
+                }
+
+                if distance[*peer] == Some(node_distance + 1) {
+                    paths[*peer] += paths[node];
+                    predecessors[*peer].push(node);
+                }
+            }
+        }
+
+        let mut dependency = vec![0.0; node_count];
+        while let Some(node) = stack.pop() {
+            for predecessor in predecessors[node].iter() {
+                dependency[*predecessor] +=
+                    paths[*predecessor] / paths[node] * (1.0 + dependency[node]);
+            }
+
+            if node != source {
+                centrality[node] += dependency[node];
+            }
+        }
+    }
+
+    centrality
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashSet, VecDeque};
+
+    use clap::Parser;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::parsing::{diff_peacetime_attacktime, AttackType};
+
+    use super::{
+        add_attackers, betweenness_centrality, generate_network, hub_and_spoke_pairs,
+        scale_free_pairs, small_world_pairs, AttackerPlacement, NetworkGenParams, PlacementParams,
+        Topology,
+    };
+
+    /// Returns the set of nodes reachable from the first node in a graph, used to check that generated graphs are
+    /// connected.
+    fn reachable(pairs: &[(usize, usize)]) -> HashSet<usize> {
+        let mut seen = HashSet::from([0]);
+        let mut queue = VecDeque::from([0]);
+        while let Some(node) = queue.pop_front() {
+            for (a, b) in pairs {
+                let peer = match (*a == node, *b == node) {
+                    (true, _) => *b,
+                    (_, true) => *a,
+                    _ => continue,
+                };
+                if seen.insert(peer) {
+                    queue.push_back(peer);
+                }
+            }
+        }
+
+        seen
+    }
+
+    /// Tests that every topology produces a connected graph with no duplicate channels or channels to self.
+    #[test]
+    fn test_topologies_connected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for pairs in [
+            scale_free_pairs(40, 2, &mut rng),
+            small_world_pairs(40, 3, 0.5, &mut rng),
+            hub_and_spoke_pairs(40, 4, 2, &mut rng),
+        ] {
+            assert_eq!(reachable(&pairs).len(), 40);
+            assert!(pairs.iter().all(|(a, b)| a != b));
+
+            let unique: HashSet<(usize, usize)> =
+                pairs.iter().map(|(a, b)| (*a.min(b), *a.max(b))).collect();
+            assert_eq!(unique.len(), pairs.len());
+        }
+    }
+
+    /// Tests that the center of a star has the highest centrality, and that leaves have none.
+    #[test]
+    fn test_betweenness_centrality() {
+        let centrality = betweenness_centrality(4, &[(0, 1), (0, 2), (0, 3)]);
+        assert_eq!(centrality, vec![6.0, 0.0, 0.0, 0.0]);
+    }
+
+    /// Tests that generation is deterministic for a seed, picks the most central node of a hub-and-spoke network
+    /// as its target and only adds channels for attackers to the attack time graph.
+    #[test]
+    fn test_generate_network() {
+        let params = NetworkGenParams::try_parse_from([
+            "network-gen",
+            "--topology",
+            "hub-and-spoke",
+            "--hub-count",
+            "1",
+            "--degree",
+            "1",
+            "--node-count",
+            "10",
+        ])
+        .unwrap();
+        assert_eq!(params.topology, Topology::HubAndSpoke);
+
+        let network = generate_network(
+            &params,
+            Some((AttackType::Sybil, Some(3))),
+            &mut StdRng::seed_from_u64(0),
+        )
+        .unwrap();
+        assert_eq!(network.target, "0");
+        assert_eq!(network.peacetime.len(), 9);
+
+        let attack = network.attack.as_ref().unwrap();
+        assert_eq!(attack.attackers, vec!["10", "11", "12"]);
+        assert_eq!(
+            serde_json::to_string(&attack.attacktime[..9]).unwrap(),
+            serde_json::to_string(&network.peacetime).unwrap()
+        );
+        for channel in attack.attacktime[9..].iter() {
+            assert!(attack.attackers.contains(&channel.node_1.alias));
+        }
+        // Each sybil has a channel with the target and one of its peers.
+        assert_eq!(attack.attacktime.len(), 9 + 6);
+
+        let regenerated = generate_network(
+            &params,
+            Some((AttackType::Sybil, Some(3))),
+            &mut StdRng::seed_from_u64(0),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&regenerated.peacetime).unwrap(),
+            serde_json::to_string(&network.peacetime).unwrap()
+        );
+
+        // Slow jam relies on hard-coded aliases, and sink attacks only support a single attacker.
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(generate_network(&params, Some((AttackType::SlowJam, None)), &mut rng).is_err());
+        assert!(generate_network(&params, Some((AttackType::Sink, Some(2))), &mut rng).is_err());
+    }
+
+    /// Tests placing attackers with a target and its largest peers in an existing graph, using the median policy of
+    /// the graph and the capacity of the target's largest channel.
+    #[test]
+    fn test_add_attackers() {
+        let params =
+            NetworkGenParams::try_parse_from(["network-gen", "--node-count", "20"]).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        let network = generate_network(&params, None, &mut rng).unwrap();
+        let target = network
+            .peacetime
+            .iter()
+            .flat_map(|c| [&c.node_1, &c.node_2])
+            .find(|p| p.alias == network.target)
+            .unwrap()
+            .pubkey;
+
+        let placement = PlacementParams {
+            placement: AttackerPlacement::TargetAndPeers,
+            attacker_count: 2,
+            peer_count: 2,
+            capacity_sat: None,
+        };
+        let (attacktime, attackers) =
+            add_attackers(&network.peacetime, &[target], &placement, &mut rng).unwrap();
+        assert_eq!(attackers, vec!["20", "21"]);
+        assert_eq!(attacktime.len(), network.peacetime.len() + 6);
+        diff_peacetime_attacktime(
+            &network.peacetime,
+            &attacktime,
+            HashSet::from_iter(attackers.iter().cloned()),
+        )
+        .unwrap();
+
+        let target_capacity = network
+            .peacetime
+            .iter()
+            .filter(|c| c.node_1.pubkey == target || c.node_2.pubkey == target)
+            .map(|c| c.capacity_msat)
+            .max()
+            .unwrap();
+        let last_scid = network.peacetime.iter().map(|c| u64::from(c.scid)).max();
+
+        let attacker_channels = &attacktime[network.peacetime.len()..];
+        for channel in attacker_channels {
+            assert_eq!(channel.capacity_msat, target_capacity);
+            assert!(Some(u64::from(channel.scid)) > last_scid);
+        }
+        // Both attackers connect to the same peers, and use the same policy.
+        assert_eq!(attacker_channels[0].node_2.pubkey, target);
+        assert_eq!(
+            attacker_channels[1].node_2.pubkey,
+            attacker_channels[4].node_2.pubkey
+        );
+        assert_eq!(
+            attacker_channels[0].node_1.fee_rate_prop,
+            attacker_channels[3].node_1.fee_rate_prop
+        );
+
+        // Unknown targets can't have attackers placed with them.
+        let (_, unknown) = crate::test_utils::get_random_keypair();
+        assert!(add_attackers(&network.peacetime, &[unknown], &placement, &mut rng).is_err());
+
+        // With multiple targets, each attacker has a channel with every target.
+        let second_target = attacker_channels[1].node_2.pubkey;
+        let placement = PlacementParams {
+            placement: AttackerPlacement::TargetChannel,
+            attacker_count: 2,
+            peer_count: 0,
+            capacity_sat: None,
+        };
+        let (attacktime, _) = add_attackers(
+            &network.peacetime,
+            &[target, second_target],
+            &placement,
+            &mut rng,
+        )
+        .unwrap();
+        let attacker_channels = &attacktime[network.peacetime.len()..];
+        assert_eq!(attacker_channels.len(), 4);
+        assert_eq!(attacker_channels[0].node_2.pubkey, target);
+        assert_eq!(attacker_channels[1].node_2.pubkey, second_target);
+    }
+}
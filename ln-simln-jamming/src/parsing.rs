@@ -1,11 +1,23 @@
+use crate::analysis::PRIMARY_CONFIG;
+use crate::attacks::costs::{
+    CostParams, CostTracker, DEFAULT_FORCE_CLOSE_VBYTES, DEFAULT_ONCHAIN_FEE_RATE,
+    DEFAULT_UNCONDITIONAL_FEE_PERCENT,
+};
+use crate::attacks::revenue_inflation::RevenueInflationAttack;
 use crate::attacks::sink::SinkAttack;
 use crate::attacks::slow_jam::SlowJam;
+use crate::attacks::sybil::SybilAttack;
 use crate::attacks::JammingAttack;
+use crate::clock::BlockClock;
+use crate::collateral::MonitorScope;
+use crate::deployment::NodeDeploymentConfig;
+use crate::mitigation::Mitigation;
 use crate::reputation_interceptor::{
     BootstrapForward, BootstrapRecords, ChannelJammer, ReputationMonitor,
 };
 use crate::revenue_interceptor::{PeacetimeRevenueMonitor, RevenueEvent};
-use crate::BoxError;
+use crate::topology::{TopologyController, TopologyEvent};
+use crate::{BoxError, PaymentHashGenerator, SeedComponent};
 use bitcoin::secp256k1::PublicKey;
 use clap::{Parser, ValueEnum};
 use csv::{ReaderBuilder, StringRecord};
@@ -19,7 +31,7 @@ use sim_cli::parsing::NetworkParser;
 use simln_lib::clock::SimulationClock;
 use simln_lib::sim_node::{populate_network_graph, SimulatedChannel, WrappedLog};
 use simln_lib::SimulationError;
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufReader, Read, Seek};
 use std::ops::Add;
@@ -46,10 +58,28 @@ pub const DEFAULT_REPUTATION_MARGIN_MSAT: &str = "10000000";
 /// Default htlc expiry used for calculating reputation margin htlc's risk.
 pub const DEFAULT_REPUTATION_MARGIN_EXIPRY: &str = "200";
 
+/// Default number of blocks before the expiry of a htlc held by an attacker that the upstream node will force close
+/// the channel, matching LND's outgoing broadcast delta.
+pub const DEFAULT_FORCE_CLOSE_MARGIN_BLOCKS: &str = "10";
+
+/// Default number of htlcs that each incoming channel may have in flight when using the circuit breaker mitigation.
+pub const DEFAULT_CIRCUIT_BREAKER_MAX_PENDING_HTLCS: u16 = 20;
+
+/// Default percentage of each incoming channel's liquidity that may be in flight when using the circuit breaker
+/// mitigation.
+pub const DEFAULT_CIRCUIT_BREAKER_LIQUIDITY_PORTION: u8 = 40;
+
+/// Default percentage of the good reputation pairs that honest peers start with that they must lose for the revenue
+/// inflation attack to complete.
+pub const DEFAULT_INFLATION_LOSS_PERCENT: &str = "50";
+
 /// The default batch size for writing results to disk.
 pub const DEFAULT_RESULT_BATCH_SIZE: &str = "500";
 
-#[derive(Clone, Parser)]
+/// The default seed used for all random number generation in the simulation.
+pub const DEFAULT_SEED: &str = "13995354354227336701";
+
+#[derive(Clone, Debug, Default, Deserialize, Parser, Serialize)]
 pub struct ReputationParams {
     /// The window over which the value of a link's revenue to our node is calculated.
     #[arg(long)]
@@ -58,26 +88,149 @@ pub struct ReputationParams {
     /// The multiplier applied to revenue_window_seconds to get the duration over which reputation is bootstrapped.
     #[arg(long)]
     pub reputation_multiplier: Option<u8>,
+
+    /// The period after which htlcs are considered slow to resolve, and penalized.
+    #[arg(long)]
+    pub resolution_period_seconds: Option<u64>,
+
+    /// The percentage of each channel's slots that are reserved for the general bucket.
+    #[arg(long)]
+    pub general_slot_portion: Option<u8>,
+
+    /// The percentage of each channel's liquidity that is reserved for the general bucket.
+    #[arg(long)]
+    pub general_liquidity_portion: Option<u8>,
+
+    /// The percentage of each channel's slots that are reserved for the congestion bucket.
+    #[arg(long)]
+    pub congestion_slot_portion: Option<u8>,
+
+    /// The percentage of each channel's liquidity that is reserved for the congestion bucket.
+    #[arg(long)]
+    pub congestion_liquidity_portion: Option<u8>,
 }
 
-impl From<ReputationParams> for ForwardManagerParams {
-    fn from(cli: ReputationParams) -> Self {
-        let mut forward_params = ForwardManagerParams::default();
-        if let Some(revenue_window) = cli.revenue_window_seconds {
+impl ReputationParams {
+    /// Returns the parameters provided with any values that are set overridden.
+    pub fn apply(&self, mut forward_params: ForwardManagerParams) -> ForwardManagerParams {
+        if let Some(revenue_window) = self.revenue_window_seconds {
             forward_params.reputation_params.revenue_window = Duration::from_secs(revenue_window);
         }
-        if let Some(multiplier) = cli.reputation_multiplier {
+        if let Some(multiplier) = self.reputation_multiplier {
             forward_params.reputation_params.reputation_multiplier = multiplier;
         }
+        if let Some(resolution_period) = self.resolution_period_seconds {
+            forward_params.reputation_params.resolution_period =
+                Duration::from_secs(resolution_period);
+        }
+        if let Some(portion) = self.general_slot_portion {
+            forward_params.general_slot_portion = portion;
+        }
+        if let Some(portion) = self.general_liquidity_portion {
+            forward_params.general_liquidity_portion = portion;
+        }
+        if let Some(portion) = self.congestion_slot_portion {
+            forward_params.congestion_slot_portion = portion;
+        }
+        if let Some(portion) = self.congestion_liquidity_portion {
+            forward_params.congestion_liquidity_portion = portion;
+        }
         forward_params
     }
 }
 
+impl From<ReputationParams> for ForwardManagerParams {
+    fn from(cli: ReputationParams) -> Self {
+        cli.apply(ForwardManagerParams::default())
+    }
+}
+
+#[derive(Clone, Parser)]
+pub struct AttackCostParams {
+    /// The unconditional fee that attackers pay for every htlc they send, expressed as a percentage of the htlc's
+    /// routing fee.
+    #[arg(long, default_value_t = DEFAULT_UNCONDITIONAL_FEE_PERCENT)]
+    pub unconditional_fee_percent: u64,
+
+    /// The fee rate used to estimate the on-chain cost of opening attacker channels, expressed in sat/vbyte.
+    #[arg(long, default_value_t = DEFAULT_ONCHAIN_FEE_RATE)]
+    pub onchain_fee_rate: u64,
+
+    /// The size of a force close transaction, used to estimate the on-chain cost of force closes that attackers
+    /// cause, expressed in vbytes.
+    #[arg(long, default_value_t = DEFAULT_FORCE_CLOSE_VBYTES)]
+    pub force_close_vbytes: u64,
+}
+
+impl From<AttackCostParams> for CostParams {
+    fn from(cli: AttackCostParams) -> Self {
+        CostParams {
+            unconditional_fee_percent: cli.unconditional_fee_percent,
+            onchain_fee_rate: cli.onchain_fee_rate,
+            force_close_vbytes: cli.force_close_vbytes,
+            ..Default::default()
+        }
+    }
+}
+
+/// The mitigations that can be selected from the command line.
+#[derive(Clone, Copy, Debug, Default, Deserialize, ValueEnum, PartialEq, Eq, Hash, Serialize)]
+pub enum MitigationType {
+    /// Outgoing reputation and resource bucketing.
+    #[default]
+    Reputation,
+    /// No mitigation, forwarding every htlc that a channel has space for.
+    None,
+    /// A per-peer limit on the htlcs and liquidity in flight, regardless of accountable signal.
+    CircuitBreaker,
+    /// A static split of resources between unaccountable and accountable htlcs, without reputation.
+    StaticBuckets,
+}
+
+#[derive(Clone, Parser)]
+pub struct MitigationParams {
+    /// The mitigation used by nodes that have deployed a mitigation. Baselines that don't track reputation can be used
+    /// to compare the protection that reputation provides to doing nothing or to simpler defences. Static buckets use
+    /// the general slot and liquidity portions for unaccountable htlcs.
+    #[arg(long, value_enum, default_value_t = MitigationType::Reputation)]
+    pub mitigation: MitigationType,
+
+    /// The maximum number of htlcs that each incoming channel may have in flight when using the circuit breaker
+    /// mitigation.
+    #[arg(long, default_value_t = DEFAULT_CIRCUIT_BREAKER_MAX_PENDING_HTLCS)]
+    pub circuit_breaker_max_pending_htlcs: u16,
+
+    /// The percentage of each incoming channel's liquidity that may be in flight when using the circuit breaker
+    /// mitigation.
+    #[arg(long, default_value_t = DEFAULT_CIRCUIT_BREAKER_LIQUIDITY_PORTION)]
+    pub circuit_breaker_liquidity_portion: u8,
+}
+
+impl From<&MitigationParams> for Mitigation {
+    fn from(cli: &MitigationParams) -> Self {
+        match cli.mitigation {
+            MitigationType::Reputation => Mitigation::Reputation,
+            MitigationType::None => Mitigation::None,
+            MitigationType::CircuitBreaker => Mitigation::CircuitBreaker {
+                max_pending_htlcs: cli.circuit_breaker_max_pending_htlcs,
+                max_pending_liquidity_portion: cli.circuit_breaker_liquidity_portion,
+            },
+            MitigationType::StaticBuckets => Mitigation::StaticBuckets,
+        }
+    }
+}
+
 #[derive(Clone, Parser)]
 pub struct NetworkParams {
     /// The directory containing all files required for the simulation.
     #[arg(long)]
     pub network_dir: PathBuf,
+
+    /// The directory that reputation snapshots are read from and written to. Defaults to the network directory, or
+    /// the attack's directory when bootstrapping attacker reputation. Used to keep snapshots built with different
+    /// reputation parameters apart.
+    #[arg(long)]
+    pub reputation_dir: Option<PathBuf>,
 }
 
 pub enum NetworkType {
@@ -92,7 +245,8 @@ impl NetworkType {
         attack_type: Option<AttackType>,
         attacker_bootstrap: Option<Duration>,
     ) -> Result<Self, BoxError> {
-        let peacetime_network = PeacetimeNetwork::new(params.network_dir.clone())?;
+        let peacetime_network =
+            PeacetimeNetwork::new(params.network_dir.clone(), params.reputation_dir.clone())?;
         if let Some(attack) = attack_type {
             let attacktime_network = AttacktimeNetwork::new(
                 params
@@ -152,25 +306,43 @@ impl NetworkType {
         }
     }
 
+    /// Returns the location of the file that describes which nodes have deployed the mitigation, if it exists.
+    pub fn deployment_file(&self) -> Option<PathBuf> {
+        match self {
+            NetworkType::Peacetime(p)
+            | NetworkType::AttackTime(p, _)
+            | NetworkType::BootstrapAttackTime(p, _, _) => {
+                let deployment_file = p.network_dir.join(PeacetimeNetwork::DEPLOYMENT);
+                deployment_file.exists().then_some(deployment_file)
+            }
+        }
+    }
+
     /// Returns the location of a file that holds reputation summaries for our active graph.
     pub fn reputation_file(&self) -> PathBuf {
         match self {
             // For both a peacetime network and an attack time one without bootstrap we use
             // reputation build in our peacetime network.
-            NetworkType::Peacetime(p) | NetworkType::AttackTime(p, _) => {
-                p.network_dir.join(PeacetimeNetwork::REPUTATION)
-            }
-            NetworkType::BootstrapAttackTime(_, a, duration) => a
-                .attack_dir
+            NetworkType::Peacetime(p) | NetworkType::AttackTime(p, _) => p
+                .reputation_dir
+                .as_ref()
+                .unwrap_or(&p.network_dir)
+                .join(PeacetimeNetwork::REPUTATION),
+            NetworkType::BootstrapAttackTime(p, a, duration) => p
+                .reputation_dir
+                .as_ref()
+                .unwrap_or(&a.attack_dir)
                 .join(format!("reputation_{}.csv", duration.as_secs())),
         }
     }
 
-    /// Returns the location that revenue built by the target during setup should be written.
+    /// Returns the location that revenue built by the targets during setup should be written.
     pub fn revenue_file(&self) -> Option<PathBuf> {
         match self {
-            NetworkType::BootstrapAttackTime(_, a, duration) => Some(
-                a.attack_dir
+            NetworkType::BootstrapAttackTime(p, a, duration) => Some(
+                p.reputation_dir
+                    .as_ref()
+                    .unwrap_or(&a.attack_dir)
                     .join(format!("revenue_{}.csv", duration.as_secs())),
             ),
             _ => None,
@@ -194,12 +366,12 @@ impl NetworkType {
         }
     }
 
-    /// Returns the target node in our network.
-    pub fn target(&self) -> (String, PublicKey) {
+    /// Returns the target nodes in our network, in the order that they're listed in `target.txt`.
+    pub fn targets(&self) -> &[(String, PublicKey)] {
         match self {
             NetworkType::Peacetime(p)
             | NetworkType::AttackTime(p, _)
-            | NetworkType::BootstrapAttackTime(p, _, _) => p.target.clone(),
+            | NetworkType::BootstrapAttackTime(p, _, _) => &p.targets,
         }
     }
 
@@ -226,8 +398,9 @@ impl NetworkType {
 
 pub struct PeacetimeNetwork {
     network_dir: PathBuf,
+    reputation_dir: Option<PathBuf>,
     graph: Vec<NetworkParser>,
-    target: (String, PublicKey),
+    targets: Vec<(String, PublicKey)>,
 }
 
 impl PeacetimeNetwork {
@@ -235,10 +408,11 @@ impl PeacetimeNetwork {
     const TARGET: &'static str = "target.txt";
     const REPUTATION: &'static str = "reputation.csv";
     const TRAFFIC: &'static str = "peacetime_traffic.csv";
+    const DEPLOYMENT: &'static str = "deployment.json";
 
     /// Creates a new peacetime network representation, failing if the files we expect to exist
     /// are not present.
-    fn new(network_dir: PathBuf) -> Result<Self, BoxError> {
+    fn new(network_dir: PathBuf, reputation_dir: Option<PathBuf>) -> Result<Self, BoxError> {
         let peacetime_graph = network_dir.join(Self::PEACETIME_NETWORK);
         if !peacetime_graph.exists() {
             return Err(format!(
@@ -257,23 +431,50 @@ impl PeacetimeNetwork {
                 )
             })?)?;
 
-        // We only allow one target node, but if there are multiple aliases in this file we'll
-        // fail to find the pubkey by alias below.
-        let target_alias = fs::read_to_string(network_dir.join(Self::TARGET))
-            .map_err(|e| format!("target.txt file containing target alias not found: {}", e))?
-            .trim()
-            .to_owned();
+        let target_aliases =
+            parse_target_aliases(&fs::read_to_string(network_dir.join(Self::TARGET)).map_err(
+                |e| format!("target.txt file containing target aliases not found: {}", e),
+            )?)?;
 
-        let target_pubkey = find_pubkey_by_alias(&target_alias, &peacetime_network.sim_network)?;
+        let targets = target_aliases
+            .into_iter()
+            .map(|alias| {
+                let pubkey = find_pubkey_by_alias(&alias, &peacetime_network.sim_network)?;
+                Ok((alias, pubkey))
+            })
+            .collect::<Result<_, BoxError>>()?;
 
         Ok(Self {
             network_dir,
+            reputation_dir,
             graph: peacetime_network.sim_network,
-            target: (target_alias, target_pubkey),
+            targets,
         })
     }
 }
 
+/// Parses the aliases of target nodes from the contents of a target file, which may list several aliases separated by
+/// commas or newlines.
+fn parse_target_aliases(contents: &str) -> Result<Vec<String>, BoxError> {
+    let mut aliases: Vec<String> = vec![];
+    for alias in contents
+        .split([',', '\n'])
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+    {
+        if aliases.iter().any(|a| a == alias) {
+            return Err(format!("target {alias} listed more than once").into());
+        }
+        aliases.push(alias.to_string());
+    }
+
+    if aliases.is_empty() {
+        return Err("at least one target alias required".into());
+    }
+
+    Ok(aliases)
+}
+
 pub struct AttacktimeNetwork {
     attack_dir: PathBuf,
     graph: Vec<NetworkParser>,
@@ -319,7 +520,7 @@ impl AttacktimeNetwork {
 
 /// Checks that the only difference in the peacetime and attack time channel graphs are attacker
 /// owned channels.
-fn diff_peacetime_attacktime(
+pub(crate) fn diff_peacetime_attacktime(
     peacetime: &[NetworkParser],
     attacktime: &[NetworkParser],
     attacker_aliases: HashSet<String>,
@@ -405,6 +606,18 @@ pub struct Cli {
     #[arg(long, default_value = DEFAULT_CLOCK_SPEEDUP)]
     pub clock_speedup: u16,
 
+    /// Run the simulation as a discrete-event simulation, skipping straight to the next scheduled event whenever the
+    /// simulation is idle rather than waiting on the wall clock. Runs the simulation on a single thread, and is only
+    /// available when built with the `fast-forward` feature.
+    #[cfg(feature = "fast-forward")]
+    #[arg(long, conflicts_with = "clock_speedup")]
+    pub fast_forward: bool,
+
+    /// Run the target node's mitigation in observe-only mode, forwarding every htlc while tracking the decisions that
+    /// it would have made. Only supported for the reputation mitigation.
+    #[arg(long)]
+    pub observe_only_target: bool,
+
     /// The htlc amount that a peer must be able to get accountable to be considered as having a good reputation, expressed
     /// in msat. This will be converted to a fee using a base fee of 1000 msat and a proportional charge of 0.01% of the
     /// amount.
@@ -416,10 +629,30 @@ pub struct Cli {
     #[arg(long, default_value = DEFAULT_REPUTATION_MARGIN_EXIPRY)]
     pub reputation_margin_expiry_blocks: u32,
 
+    /// The number of blocks before a htlc held by an attacker expires that the upstream node will force close the
+    /// channel to resolve the htlc on-chain.
+    #[arg(long, default_value = DEFAULT_FORCE_CLOSE_MARGIN_BLOCKS)]
+    pub force_close_margin_blocks: u32,
+
+    /// Have the sink and slow jam attacks hold accountable htlcs until the block before they expire, causing the
+    /// upstream node to force close the channel, rather than releasing them before the force close margin is reached.
+    #[arg(long)]
+    pub hold_to_expiry: bool,
+
     /// The attack that we're interested in running.
     #[arg(long, value_enum)]
     pub attack_type: AttackType,
 
+    /// A comma separated list of the scids of attacker channels with the target whose revenue the revenue inflation
+    /// attack inflates. If not set, every attacker channel with the target is inflated.
+    #[arg(long, value_delimiter = ',')]
+    pub inflation_channels: Vec<u64>,
+
+    /// The percentage of the good reputation pairs that the target's honest peers start the revenue inflation attack
+    /// with that they must lose for the attack to complete.
+    #[arg(long, default_value = DEFAULT_INFLATION_LOSS_PERCENT)]
+    pub inflation_loss_percent: u8,
+
     /// The duration of time that reputation of the attacking node's reputation will be bootstrapped
     /// for, expressed as human readable values (eg: 1w, 3d). Requires that a reputation bootstrap
     /// file has been created in advance for this duration.
@@ -430,42 +663,119 @@ pub struct Cli {
     #[arg(long, default_value = DEFAULT_RESULT_BATCH_SIZE)]
     pub result_batch_size: u16,
 
+    /// The maximum amount of simulated time to run the attack for, expressed as human readable values (eg: 1w, 3d).
+    /// Mitigations that don't track reputation default to the revenue window, because attacks can't rely on the
+    /// attacker or target's reputation to decide when they've completed.
+    #[arg(long, value_parser = parse_duration)]
+    pub max_runtime: Option<Duration>,
+
+    /// The directory to write simulation results to. Defaults to results/{attack type}/{start timestamp}.
+    #[arg(long)]
+    pub results_dir: Option<PathBuf>,
+
+    /// The interval at which the reputation of every channel is sampled and written to reputation_timeseries.csv in
+    /// the results directory, expressed as human readable values in simulated time (eg: 1h, 30m). If not set,
+    /// reputation is only sampled at the start and end of the simulation.
+    #[arg(long, value_parser = parse_duration)]
+    pub reputation_interval: Option<Duration>,
+
+    /// A comma separated list of aliases of the nodes whose channels are sampled for the reputation time series. If
+    /// not set, every node in the network is sampled.
+    #[arg(long, value_delimiter = ',', requires = "reputation_interval")]
+    pub reputation_nodes: Vec<String>,
+
+    /// The nodes whose revenue is compared to peacetime to measure collateral damage from the attack, written to
+    /// node_revenue.csv in the results directory. Attacking nodes are never included.
+    #[arg(long, value_enum, default_value = "target")]
+    pub revenue_monitor_scope: MonitorScope,
+
+    /// A csv file of channel opens and closes to apply during the simulation, with columns offset_secs, scid and
+    /// action (open or close). Channels that are opened must be present in the simulation's graph.
+    #[arg(long)]
+    pub topology_file: Option<PathBuf>,
+
+    /// A json file of named parameter sets, using the same names as the equivalent cli options, that are run as
+    /// shadows alongside each node's forward manager. Shadows see the same htlcs, but don't decide forwarding outcomes.
+    #[arg(long)]
+    pub shadow_params_file: Option<PathBuf>,
+
+    /// The fraction of nodes that deploy the mitigation, picked at random using the simulation's seed. The target and
+    /// attacking nodes, and any nodes listed in the network's deployment.json, are not picked. If not set, all nodes
+    /// that are not listed in deployment.json deploy the mitigation.
+    #[arg(long)]
+    pub deployment_fraction: Option<f64>,
+
+    /// The seed that all random number generation in the simulation is derived from, including payment generation,
+    /// latency, general bucket slot assignment and payment hashes used by attacks. Results are only reproducible for a
+    /// seed when run with `--fast-forward`, as wall clock timing can change the order of events.
+    #[arg(long, default_value = DEFAULT_SEED)]
+    pub seed: u64,
+
     #[command(flatten)]
     pub reputation_params: ReputationParams,
 
+    #[command(flatten)]
+    pub mitigation_params: MitigationParams,
+
+    #[command(flatten)]
+    pub cost_params: AttackCostParams,
+
     #[clap(long, default_value = "debug")]
     pub log_level: LevelFilter,
 }
 
-#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, ValueEnum, PartialEq, Eq, Serialize)]
 pub enum AttackType {
     Sink,
     SlowJam,
+    Sybil,
+    RevenueInflation,
     // NOTE: add your attack that you want to run here.
 }
 
-pub fn setup_attack<R, M, J>(
+/// The parts of the simulation that attacks use to monitor and act on the network.
+pub struct AttackResources<R, M, J, T> {
+    pub reputation_monitor: Arc<R>,
+    pub revenue_monitor: Arc<M>,
+    pub channel_jammer: Arc<J>,
+    pub cost_tracker: Arc<CostTracker>,
+    /// Used by attacks to check, open and close channels during the simulation.
+    pub topology: Arc<T>,
+}
+
+pub fn setup_attack<R, M, J, T>(
     cli: &Cli,
     network: &NetworkType,
     clock: Arc<SimulationClock>,
-    reputation_monitor: Arc<R>,
-    revenue_monitor: Arc<M>,
-    channel_jammer: Arc<J>,
+    resources: AttackResources<R, M, J, T>,
 ) -> Result<Arc<dyn JammingAttack + Send + Sync>, BoxError>
 where
     R: ReputationMonitor + Send + Sync + 'static,
     M: PeacetimeRevenueMonitor + Send + Sync + 'static,
     J: ChannelJammer + Send + Sync + 'static,
+    T: TopologyController + Send + Sync + 'static,
 {
+    let AttackResources {
+        reputation_monitor,
+        revenue_monitor,
+        channel_jammer,
+        cost_tracker,
+        topology,
+    } = resources;
     let forward_params: ForwardManagerParams = cli.reputation_params.clone().into();
     let sim_network = network.active_network();
+    let payment_hashes = PaymentHashGenerator::new(SeedComponent::PaymentHashes.seed(cli.seed));
+    let block_clock = BlockClock::new(
+        Arc::clone(&clock),
+        forward_params.reputation_params.expected_block_speed,
+    );
 
     // NOTE: If you are implementing your own attack and have added the variant to AttackType, you can
     // then do any setup specific to your attack here and return.
-    match network
+    let attack_type = network
         .attack_type()
-        .ok_or("attack type must be set for simulation")?
-    {
+        .ok_or("attack type must be set for simulation")?;
+    match attack_type {
         AttackType::Sink => {
             // Reputation is assessed for a channel pair and a specific HTLC that's being proposed. To assess whether pairs
             // have reputation, we'll use LND's default fee policy to get the HTLC risk for our configured htlc size and hold
@@ -477,13 +787,18 @@ where
 
             let attack = Arc::new(SinkAttack::new(
                 clock,
+                block_clock,
+                cli.attack_release_margin(),
                 sim_network,
-                network.target().1,
+                network.targets().iter().map(|t| t.1).collect(),
                 network.attackers().iter().map(|a| a.1).collect(),
                 risk_margin,
+                Mitigation::from(&cli.mitigation_params).tracks_reputation(),
                 reputation_monitor,
                 revenue_monitor,
                 channel_jammer,
+                cost_tracker,
+                topology,
             ));
 
             Ok(attack)
@@ -515,15 +830,63 @@ where
             let attack = Arc::new(SlowJam::new(
                 Arc::clone(&clock),
                 sim_network,
-                network.target().1,
+                single_target(network, &attack_type)?,
                 attacker_sender.clone(),
                 attacker_pubkey_1.clone(),
                 honest_sender,
                 honest_receiver,
                 channel_to_jam,
+                forward_params,
+                cli.attack_release_margin(),
                 Arc::clone(&reputation_monitor),
                 Arc::clone(&channel_jammer),
                 network_graph,
+                cost_tracker,
+                payment_hashes,
+            ));
+
+            Ok(attack)
+        }
+        AttackType::Sybil => {
+            let risk_margin = forward_params.htlc_opportunity_cost(
+                1000 + (0.0001 * cli.reputation_margin_msat as f64) as u64,
+                cli.reputation_margin_expiry_blocks,
+            );
+
+            let attack = Arc::new(SybilAttack::new(
+                Arc::clone(&clock),
+                sim_network,
+                single_target(network, &attack_type)?,
+                network.attackers().to_vec(),
+                forward_params,
+                risk_margin,
+                reputation_monitor,
+                revenue_monitor,
+                network_graph(sim_network.clone())?,
+                cost_tracker,
+                payment_hashes,
+            ));
+
+            Ok(attack)
+        }
+        AttackType::RevenueInflation => {
+            let risk_margin = forward_params.htlc_opportunity_cost(
+                1000 + (0.0001 * cli.reputation_margin_msat as f64) as u64,
+                cli.reputation_margin_expiry_blocks,
+            );
+
+            let attack = Arc::new(RevenueInflationAttack::new(
+                clock,
+                sim_network,
+                single_target(network, &attack_type)?,
+                network.attackers().to_vec(),
+                &cli.inflation_channels,
+                risk_margin,
+                cli.inflation_loss_percent,
+                reputation_monitor,
+                network_graph(sim_network.clone())?,
+                cost_tracker,
+                payment_hashes,
             ));
 
             Ok(attack)
@@ -531,7 +894,20 @@ where
     }
 }
 
-fn network_graph(
+/// Returns the network's target for attacks that can only be run against a single node. The sink attack receives
+/// every target, see each attack's docs for why it only supports one.
+fn single_target(network: &NetworkType, attack_type: &AttackType) -> Result<PublicKey, BoxError> {
+    match network.targets() {
+        [(_, pubkey)] => Ok(*pubkey),
+        targets => Err(format!(
+            "{attack_type:?} attack only supports a single target, got: {}",
+            targets.len()
+        )
+        .into()),
+    }
+}
+
+pub(crate) fn network_graph(
     network: Vec<NetworkParser>,
 ) -> Result<Arc<NetworkGraph<Arc<WrappedLog>>>, BoxError> {
     let channels = network
@@ -557,6 +933,27 @@ fn network_graph(
 }
 
 impl Cli {
+    /// Returns a boolean indicating whether the simulation should be fast forwarded, which is only possible when built
+    /// with the `fast-forward` feature.
+    #[cfg(feature = "fast-forward")]
+    pub fn fast_forward(&self) -> bool {
+        self.fast_forward
+    }
+
+    #[cfg(not(feature = "fast-forward"))]
+    pub fn fast_forward(&self) -> bool {
+        false
+    }
+
+    /// Returns the number of blocks before a held htlc expires that the sink and slow jam attacks release it.
+    fn attack_release_margin(&self) -> u32 {
+        if self.hold_to_expiry {
+            0
+        } else {
+            self.force_close_margin_blocks
+        }
+    }
+
     pub fn validate(&self) -> Result<ForwardManagerParams, BoxError> {
         if self.target_reputation_percent == 0 || self.target_reputation_percent > 100 {
             return Err(format!(
@@ -576,7 +973,45 @@ impl Cli {
             }
         }
 
+        if self.inflation_loss_percent == 0 || self.inflation_loss_percent > 100 {
+            return Err(format!(
+                "inflation loss percent {} must be in (0;100]",
+                self.inflation_loss_percent
+            )
+            .into());
+        }
+
         let forward_params: ForwardManagerParams = self.reputation_params.clone().into();
+        validate_forward_params(&forward_params)?;
+
+        if self.mitigation_params.circuit_breaker_max_pending_htlcs == 0
+            || self.mitigation_params.circuit_breaker_max_pending_htlcs > 483
+        {
+            return Err(format!(
+                "circuit breaker max pending htlcs {} must be in (0;483]",
+                self.mitigation_params.circuit_breaker_max_pending_htlcs,
+            )
+            .into());
+        }
+
+        if self.mitigation_params.circuit_breaker_liquidity_portion == 0
+            || self.mitigation_params.circuit_breaker_liquidity_portion > 100
+        {
+            return Err(format!(
+                "circuit breaker liquidity portion {} must be in (0;100]",
+                self.mitigation_params.circuit_breaker_liquidity_portion,
+            )
+            .into());
+        }
+
+        if self.reputation_interval.is_some_and(|i| i.is_zero()) {
+            return Err("zero reputation_interval is invalid, do not specify option".into());
+        }
+
+        if self.max_runtime.is_some_and(|r| r.is_zero()) {
+            return Err("zero max_runtime is invalid, do not specify option".into());
+        }
+
         if let Some(bootstrap) = self.attacker_bootstrap {
             if bootstrap.is_zero() {
                 return Err("zero attacker_bootstrap is invalid, do not specify option".into());
@@ -596,6 +1031,17 @@ impl Cli {
 
         Ok(forward_params)
     }
+
+    /// Returns the maximum amount of simulated time to run the attack for, if any.
+    pub fn max_runtime(&self, forward_params: &ForwardManagerParams) -> Option<Duration> {
+        if self.max_runtime.is_some()
+            || Mitigation::from(&self.mitigation_params).tracks_reputation()
+        {
+            return self.max_runtime;
+        }
+
+        Some(forward_params.reputation_params.revenue_window)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -786,11 +1232,145 @@ pub fn reputation_snapshot_from_file(
     Ok(reputation_snapshot)
 }
 
-/// Reads from a CSV (generated by simln) and populates all forwards belonging to the target node in min heap by
+/// Reads the revenue that each target earned during the attacker's bootstrap period, written by reputation-builder
+/// as one `pubkey,revenue` line per target.
+pub fn bootstrap_revenue_from_file(
+    file_path: &PathBuf,
+    targets: &[(String, PublicKey)],
+) -> Result<HashMap<PublicKey, u64>, BoxError> {
+    parse_bootstrap_revenue(&fs::read_to_string(file_path)?, targets)
+}
+
+/// Parses per-target bootstrap revenue. Files that only contain a single value, written before multiple targets were
+/// supported, are accepted for networks with one target.
+fn parse_bootstrap_revenue(
+    contents: &str,
+    targets: &[(String, PublicKey)],
+) -> Result<HashMap<PublicKey, u64>, BoxError> {
+    if let ([(_, pubkey)], Ok(revenue)) = (targets, contents.trim().parse::<u64>()) {
+        return Ok(HashMap::from([(*pubkey, revenue)]));
+    }
+
+    let mut revenue: HashMap<PublicKey, u64> = HashMap::new();
+    for line in contents.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let (pubkey, msat) = line
+            .split_once(',')
+            .ok_or(format!("expected pubkey,revenue got: {line}"))?;
+        revenue.insert(
+            PublicKey::from_str(pubkey.trim())?,
+            msat.trim().parse::<u64>()?,
+        );
+    }
+
+    for (alias, pubkey) in targets {
+        if !revenue.contains_key(pubkey) {
+            return Err(format!("bootstrap revenue not found for target {alias}").into());
+        }
+    }
+
+    Ok(revenue)
+}
+
+/// Checks that forward manager parameters are valid, which may have been set for the whole network or a single node.
+pub(crate) fn validate_forward_params(
+    forward_params: &ForwardManagerParams,
+) -> Result<(), BoxError> {
+    if forward_params.general_slot_portion as u16 + forward_params.congestion_slot_portion as u16
+        >= 100
+    {
+        return Err(format!(
+            "general slot portion {} + congestion slot portion {} must be < 100",
+            forward_params.general_slot_portion, forward_params.congestion_slot_portion,
+        )
+        .into());
+    }
+
+    if forward_params.general_liquidity_portion as u16
+        + forward_params.congestion_liquidity_portion as u16
+        >= 100
+    {
+        return Err(format!(
+            "general liquidity portion {} + congestion liquidity portion {} must be < 100",
+            forward_params.general_liquidity_portion, forward_params.congestion_liquidity_portion,
+        )
+        .into());
+    }
+
+    if forward_params.reputation_params.resolution_period.is_zero() {
+        return Err("resolution period must be non-zero".into());
+    }
+
+    Ok(())
+}
+
+/// Reads per-node deployment configuration, keyed by node alias, from a json file.
+pub fn deployment_config_from_file(
+    file_path: &PathBuf,
+) -> Result<HashMap<String, NodeDeploymentConfig>, BoxError> {
+    serde_json::from_str(&fs::read_to_string(file_path).map_err(|e| {
+        format!(
+            "could not read deployment file {}: {e}",
+            file_path.to_string_lossy()
+        )
+    })?)
+    .map_err(|e| e.into())
+}
+
+/// Reads named shadow parameter sets from a json file, applying each set's overrides to the default parameters
+/// provided. Parameter sets are returned in order of name.
+pub fn shadow_params_from_file(
+    file_path: &PathBuf,
+    default_params: ForwardManagerParams,
+) -> Result<Vec<(String, ForwardManagerParams)>, BoxError> {
+    let config: BTreeMap<String, ReputationParams> =
+        serde_json::from_str(&fs::read_to_string(file_path).map_err(|e| {
+            format!(
+                "could not read shadow params file {}: {e}",
+                file_path.to_string_lossy()
+            )
+        })?)?;
+
+    shadow_params(config, default_params)
+}
+
+fn shadow_params(
+    config: BTreeMap<String, ReputationParams>,
+    default_params: ForwardManagerParams,
+) -> Result<Vec<(String, ForwardManagerParams)>, BoxError> {
+    let mut shadows = Vec::with_capacity(config.len());
+    for (name, params) in config {
+        if name == PRIMARY_CONFIG {
+            return Err(format!("shadow params name: {name} is reserved").into());
+        }
+
+        let forward_params = params.apply(default_params);
+        validate_forward_params(&forward_params)
+            .map_err(|e| format!("invalid shadow params {name}: {e}"))?;
+        shadows.push((name, forward_params));
+    }
+
+    Ok(shadows)
+}
+
+/// Reads a set of scheduled channel opens and closes from a CSV file.
+pub fn topology_events_from_file(file_path: &PathBuf) -> Result<Vec<TopologyEvent>, BoxError> {
+    let file = File::open(file_path)?;
+    let reader = BufReader::new(file);
+    let mut csv_reader = csv::Reader::from_reader(reader);
+
+    let mut events = Vec::new();
+    for result in csv_reader.deserialize() {
+        events.push(result?);
+    }
+
+    Ok(events)
+}
+
+/// Reads from a CSV (generated by simln) and populates all forwards belonging to the nodes provided in min heap by
 /// timestamp.
 pub async fn peacetime_from_file(
     file_path: &PathBuf,
-    target_pubkey: PublicKey,
+    nodes: &HashSet<PublicKey>,
 ) -> Result<BinaryHeap<RevenueEvent>, BoxError> {
     let num_chunks = Handle::current().metrics().num_workers().div_ceil(2);
     let breakpoints = calc_file_chunks(file_path, num_chunks as u8)?;
@@ -809,6 +1389,7 @@ pub async fn peacetime_from_file(
         };
         let path_clone = file_path.clone();
         let heap_clone = Arc::clone(&heap);
+        let nodes = nodes.clone();
 
         tasks.spawn(async move {
             let mut file = File::open(path_clone)?;
@@ -820,7 +1401,7 @@ pub async fn peacetime_from_file(
                 let record: StringRecord = result?;
 
                 let forwarding_node = PublicKey::from_slice(&hex::decode(&record[6])?)?;
-                if forwarding_node != target_pubkey {
+                if !nodes.contains(&forwarding_node) {
                     continue;
                 }
 
@@ -828,8 +1409,11 @@ pub async fn peacetime_from_file(
                 let outgoing_amt: u64 = record[1].parse()?;
 
                 heap_clone.lock().unwrap().push(RevenueEvent {
+                    forwarding_node,
                     timestamp_ns: record[5].parse()?,
                     fee_msat: incoming_amt - outgoing_amt,
+                    incoming_channel: record[7].parse()?,
+                    outgoing_channel: record[8].parse()?,
                 })
             }
             Ok(())
@@ -892,8 +1476,14 @@ mod tests {
     use std::ops::Add;
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-    use crate::parsing::get_history_for_bootstrap;
-    use crate::test_utils::test_bootstrap_forward;
+    use clap::Parser;
+    use ln_resource_mgr::forward_manager::ForwardManagerParams;
+
+    use crate::parsing::{
+        get_history_for_bootstrap, parse_bootstrap_revenue, parse_target_aliases, shadow_params,
+        Cli,
+    };
+    use crate::test_utils::{get_random_keypair, test_bootstrap_forward};
 
     /// Tests the cases where filtering bootstrap data fails.
     #[test]
@@ -973,4 +1563,111 @@ mod tests {
         assert_eq!(filtered_history.forwards.len(), 4);
         assert_eq!(filtered_history.last_timestamp_nanos, start_time.add(13));
     }
+
+    /// Tests that shadow parameter sets are applied to the default parameters in order of name, and that invalid or
+    /// reserved sets are rejected.
+    #[test]
+    fn test_shadow_params() {
+        let default_params = ForwardManagerParams::default();
+        let shadows = shadow_params(
+            serde_json::from_str(
+                r#"{"strict": {"general_slot_portion": 10}, "lenient": {"reputation_multiplier": 2}}"#,
+            )
+            .unwrap(),
+            default_params,
+        )
+        .unwrap();
+
+        assert_eq!(shadows.len(), 2);
+        assert_eq!(shadows[0].0, "lenient");
+        assert_eq!(shadows[0].1.reputation_params.reputation_multiplier, 2);
+        assert_eq!(
+            shadows[0].1.general_slot_portion,
+            default_params.general_slot_portion
+        );
+        assert_eq!(shadows[1].0, "strict");
+        assert_eq!(shadows[1].1.general_slot_portion, 10);
+
+        assert!(shadow_params(
+            serde_json::from_str(r#"{"primary": {}}"#).unwrap(),
+            default_params,
+        )
+        .is_err());
+
+        assert!(shadow_params(
+            serde_json::from_str(r#"{"invalid": {"general_slot_portion": 100}}"#).unwrap(),
+            default_params,
+        )
+        .is_err());
+    }
+
+    /// Tests that baseline mitigations default to running for the revenue window, while mitigations that track
+    /// reputation only have a maximum runtime if one is set.
+    #[test]
+    fn test_max_runtime() {
+        let cli = |args: &[&str]| {
+            let mut all = vec![
+                "sim-ln-jam",
+                "--network-dir",
+                "/tmp/network",
+                "--attack-type",
+                "sink",
+            ];
+            all.extend(args);
+            Cli::try_parse_from(all).unwrap()
+        };
+        let params = ForwardManagerParams::default();
+
+        assert_eq!(cli(&[]).max_runtime(&params), None);
+        assert_eq!(
+            cli(&["--max-runtime", "1d"]).max_runtime(&params),
+            Some(Duration::from_secs(60 * 60 * 24))
+        );
+
+        for mitigation in ["none", "circuit-breaker", "static-buckets"] {
+            assert_eq!(
+                cli(&["--mitigation", mitigation]).max_runtime(&params),
+                Some(params.reputation_params.revenue_window)
+            );
+            assert_eq!(
+                cli(&["--mitigation", mitigation, "--max-runtime", "1d"]).max_runtime(&params),
+                Some(Duration::from_secs(60 * 60 * 24))
+            );
+        }
+
+        assert!(cli(&["--max-runtime", "0s"]).validate().is_err());
+    }
+
+    /// Tests parsing of single and multiple target aliases, rejecting empty or duplicated lists.
+    #[test]
+    fn test_parse_target_aliases() {
+        assert_eq!(parse_target_aliases("0\n").unwrap(), vec!["0"]);
+        assert_eq!(
+            parse_target_aliases("0, 1\n2\n\n").unwrap(),
+            vec!["0", "1", "2"]
+        );
+        assert!(parse_target_aliases(" \n").is_err());
+        assert!(parse_target_aliases("0,1,0").is_err());
+    }
+
+    /// Tests parsing of per-target bootstrap revenue, including the single value format that only has one target.
+    #[test]
+    fn test_parse_bootstrap_revenue() {
+        let target_1 = ("0".to_string(), get_random_keypair().1);
+        let target_2 = ("1".to_string(), get_random_keypair().1);
+
+        let revenue = parse_bootstrap_revenue("100", &[target_1.clone()]).unwrap();
+        assert_eq!(revenue.get(&target_1.1), Some(&100));
+        assert!(parse_bootstrap_revenue("100", &[target_1.clone(), target_2.clone()]).is_err());
+
+        let contents = format!("{},100\n{},200\n", target_1.1, target_2.1);
+        let revenue =
+            parse_bootstrap_revenue(&contents, &[target_1.clone(), target_2.clone()]).unwrap();
+        assert_eq!(revenue.get(&target_1.1), Some(&100));
+        assert_eq!(revenue.get(&target_2.1), Some(&200));
+
+        // Every target must have bootstrap revenue.
+        let contents = format!("{},100\n", target_1.1);
+        assert!(parse_bootstrap_revenue(&contents, &[target_1, target_2]).is_err());
+    }
 }
@@ -0,0 +1,617 @@
+use crate::clock::InstantClock;
+use crate::reputation_interceptor::FailureReporter;
+use crate::BoxError;
+use async_trait::async_trait;
+use bitcoin::secp256k1::PublicKey;
+use csv::WriterBuilder;
+use lightning::ln::PaymentHash;
+use ln_resource_mgr::{FailureReason, HtlcRef};
+use serde::{Deserialize, Serialize};
+use sim_cli::parsing::NetworkParser;
+use simln_lib::clock::SimulationClock;
+use simln_lib::sim_node::{
+    CriticalError, CustomRecords, ForwardingError, InterceptRequest, InterceptResolution,
+    Interceptor,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// The name of the file that honest payments are written to in the results directory.
+pub const HONEST_PAYMENTS_FILE: &str = "honest_payments.csv";
+
+/// The failure reason recorded for payments that were not failed by a node's resource manager, for example because
+/// of insufficient liquidity or an attacker failing the htlc.
+pub const OTHER_FAILURE: &str = "Other";
+
+/// The failure reason recorded for payments that were failed because they were routed over a channel that is closed
+/// (or not yet opened). Sim-ln's pathfinding graph can't be changed once a simulation has started, so senders may
+/// still route over these channels.
+pub const CLOSED_CHANNEL_FAILURE: &str = "ChannelClosed";
+
+/// The outcome of a single honest payment.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PaymentRecord {
+    pub payment_hash: String,
+    pub sender: PublicKey,
+    /// The first node to forward the payment, or the recipient if the sender paid a peer directly.
+    pub first_hop: PublicKey,
+    pub amount_msat: u64,
+    /// The time that the payment was first sent, relative to the start of the simulation.
+    pub sent_ns: u64,
+    /// The time between the payment first reaching its first hop and its final attempt resolving at its first hop.
+    pub latency_ns: u64,
+    pub attempts: u32,
+    pub success: bool,
+    /// The reason that the payment's final attempt failed, empty if the payment succeeded.
+    pub failure_reason: Option<String>,
+}
+
+/// Summary statistics for a set of honest payments. Latency percentiles only include successful payments. Payments
+/// that failed over closed channels are only counted in `closed_channel_failures`, as they're an artifact of sim-ln
+/// pathfinding over channels that are no longer in the network.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PaymentStats {
+    pub attempted: u64,
+    pub succeeded: u64,
+    /// The number of failed payments for each failure reason.
+    pub failure_reasons: BTreeMap<String, u64>,
+    /// The number of payments that took more than one attempt.
+    pub retried: u64,
+    pub latency_p50_ms: Option<u64>,
+    pub latency_p90_ms: Option<u64>,
+    pub latency_p99_ms: Option<u64>,
+    /// The number of payments that failed because they were routed over a closed channel, which are excluded from
+    /// all other statistics.
+    #[serde(default)]
+    pub closed_channel_failures: u64,
+}
+
+impl PaymentStats {
+    pub fn new(records: &[PaymentRecord]) -> Self {
+        let mut stats = PaymentStats::default();
+
+        let mut latencies = vec![];
+        for record in records {
+            if !record.success && record.failure_reason.as_deref() == Some(CLOSED_CHANNEL_FAILURE) {
+                stats.closed_channel_failures += 1;
+                continue;
+            }
+
+            stats.attempted += 1;
+            if record.attempts > 1 {
+                stats.retried += 1;
+            }
+
+            if record.success {
+                stats.succeeded += 1;
+                latencies.push(record.latency_ns / 1_000_000);
+            } else {
+                *stats
+                    .failure_reasons
+                    .entry(
+                        record
+                            .failure_reason
+                            .clone()
+                            .unwrap_or(OTHER_FAILURE.to_string()),
+                    )
+                    .or_default() += 1;
+            }
+        }
+
+        latencies.sort();
+        stats.latency_p50_ms = percentile(&latencies, 50);
+        stats.latency_p90_ms = percentile(&latencies, 90);
+        stats.latency_p99_ms = percentile(&latencies, 99);
+        stats
+    }
+
+    pub fn success_rate(&self) -> Option<f64> {
+        if self.attempted == 0 {
+            return None;
+        }
+
+        Some(self.succeeded as f64 / self.attempted as f64)
+    }
+}
+
+/// Returns the nearest-rank percentile of a sorted set of values.
+fn percentile(sorted: &[u64], pct: usize) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+/// Returns the path that the honest payments made when generating a traffic file are written to.
+pub fn payments_file(traffic_file: &Path) -> PathBuf {
+    traffic_file.with_file_name(format!("{}_payments.csv", file_stem(traffic_file)))
+}
+
+/// Returns the path that summary statistics for the honest payments made when generating a traffic file are written
+/// to.
+pub fn payment_stats_file(traffic_file: &Path) -> PathBuf {
+    traffic_file.with_file_name(format!("{}_payments.json", file_stem(traffic_file)))
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+pub fn write_payments(path: &Path, records: &[PaymentRecord]) -> Result<(), BoxError> {
+    let mut writer = WriterBuilder::new().from_writer(File::create(path)?);
+    for record in records {
+        writer.serialize(record)?;
+    }
+
+    writer.flush().map_err(|e| e.into())
+}
+
+pub fn write_payment_stats(path: &Path, stats: &PaymentStats) -> Result<(), BoxError> {
+    fs::write(path, serde_json::to_string_pretty(stats)?).map_err(|e| e.into())
+}
+
+/// Reads the payment statistics recorded for a traffic file, returning None if they weren't recorded.
+pub fn read_payment_stats(traffic_file: &Path) -> Result<Option<PaymentStats>, BoxError> {
+    let path = payment_stats_file(traffic_file);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+}
+
+/// A payment that has been seen by its first hop.
+struct TrackedPayment {
+    sender: PublicKey,
+    honest: bool,
+    first_hop: PublicKey,
+    amount_msat: u64,
+    sent_ins: Instant,
+    attempts: u32,
+    /// The first hop of the payment's current attempt, or None if no attempt is in flight.
+    in_flight: Option<(PublicKey, HtlcRef)>,
+    /// The time and outcome of the payment's most recent attempt, if one has resolved.
+    resolved: Option<(Instant, bool)>,
+    failure_reason: Option<FailureReason>,
+    /// Whether the payment's current attempt was failed because it was routed over a closed channel.
+    channel_closed: bool,
+}
+
+#[derive(Default)]
+struct PaymentState {
+    payments: HashMap<PaymentHash, TrackedPayment>,
+    /// The payment that each in-flight htlc belongs to, keyed by forwarding node and incoming htlc.
+    htlcs: HashMap<(PublicKey, HtlcRef), PaymentHash>,
+    /// Failures that were reported for htlcs before this tracker intercepted them.
+    failures: HashMap<(PublicKey, HtlcRef), FailureReason>,
+    /// Htlcs that were reported as failed over a closed channel before this tracker intercepted them.
+    closed_failures: HashSet<(PublicKey, HtlcRef)>,
+}
+
+/// Tracks the outcome of honest payments by watching every htlc that is forwarded in the network. The first htlc seen
+/// for a payment hash without an attempt in flight is treated as the first hop of a new attempt, and the payment's
+/// outcome is set when that htlc resolves. Failure reasons are reported by the reputation interceptor, and htlcs failed
+/// over closed channels by the attack interceptor. Payments that fail before they reach their first hop are not
+/// counted.
+pub struct PaymentTracker {
+    clock: Arc<SimulationClock>,
+    start_ins: Instant,
+    attackers: HashSet<PublicKey>,
+    /// The nodes on each side of every channel in the network, used to identify the sender of a payment.
+    channel_nodes: HashMap<u64, [PublicKey; 2]>,
+    state: Mutex<PaymentState>,
+}
+
+impl PaymentTracker {
+    pub fn new(
+        clock: Arc<SimulationClock>,
+        sim_network: &[NetworkParser],
+        attackers: &[PublicKey],
+    ) -> Self {
+        PaymentTracker {
+            start_ins: InstantClock::now(&*clock),
+            clock,
+            attackers: attackers.iter().copied().collect(),
+            channel_nodes: sim_network
+                .iter()
+                .map(|c| (c.scid.into(), [c.node_1.pubkey, c.node_2.pubkey]))
+                .collect(),
+            state: Mutex::new(PaymentState::default()),
+        }
+    }
+
+    /// Returns every honest payment that has resolved, ordered by the time it was sent. Payments that are still in
+    /// flight are not included.
+    pub fn payments(&self) -> Result<Vec<PaymentRecord>, BoxError> {
+        let state = self
+            .state
+            .lock()
+            .map_err(|e| format!("payment tracker lock poisoned: {e}"))?;
+        let mut records: Vec<PaymentRecord> = state
+            .payments
+            .iter()
+            .filter(|(_, p)| p.honest && p.in_flight.is_none())
+            .filter_map(|(hash, p)| {
+                let (resolved_ins, success) = p.resolved?;
+                Some(PaymentRecord {
+                    payment_hash: hex::encode(hash.0),
+                    sender: p.sender,
+                    first_hop: p.first_hop,
+                    amount_msat: p.amount_msat,
+                    sent_ns: p.sent_ins.duration_since(self.start_ins).as_nanos() as u64,
+                    latency_ns: resolved_ins.duration_since(p.sent_ins).as_nanos() as u64,
+                    attempts: p.attempts,
+                    success,
+                    failure_reason: match (success, p.channel_closed, &p.failure_reason) {
+                        (true, _, _) => None,
+                        (false, true, _) => Some(CLOSED_CHANNEL_FAILURE.to_string()),
+                        (false, false, Some(reason)) => Some(format!("{reason:?}")),
+                        (false, false, None) => Some(OTHER_FAILURE.to_string()),
+                    },
+                })
+            })
+            .collect();
+
+        records.sort_by(|a, b| (a.sent_ns, &a.payment_hash).cmp(&(b.sent_ns, &b.payment_hash)));
+        Ok(records)
+    }
+
+    fn lock_state(&self) -> Result<MutexGuard<'_, PaymentState>, CriticalError> {
+        self.state.lock().map_err(|e| {
+            CriticalError::InterceptorError(format!("payment tracker lock poisoned: {e}"))
+        })
+    }
+
+    /// Returns the sender of a payment, which is the peer that its first hop received it from.
+    fn sender(&self, first_hop: &PublicKey, incoming_channel: u64) -> Option<PublicKey> {
+        self.channel_nodes
+            .get(&incoming_channel)?
+            .iter()
+            .find(|n| *n != first_hop)
+            .copied()
+    }
+}
+
+impl FailureReporter for PaymentTracker {
+    fn report_failure(
+        &self,
+        forwarding_node: PublicKey,
+        incoming_ref: HtlcRef,
+        reason: &FailureReason,
+    ) -> Result<(), CriticalError> {
+        let mut state = self.lock_state()?;
+        let key = (forwarding_node, incoming_ref);
+
+        match state.htlcs.get(&key).copied() {
+            Some(hash) => {
+                if let Some(payment) = state.payments.get_mut(&hash) {
+                    payment.failure_reason = Some(reason.clone());
+                }
+            }
+            None => {
+                state.failures.insert(key, reason.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn report_closed_channel(
+        &self,
+        forwarding_node: PublicKey,
+        incoming_ref: HtlcRef,
+    ) -> Result<(), CriticalError> {
+        let mut state = self.lock_state()?;
+        let key = (forwarding_node, incoming_ref);
+
+        match state.htlcs.get(&key).copied() {
+            Some(hash) => {
+                if let Some(payment) = state.payments.get_mut(&hash) {
+                    payment.channel_closed = true;
+                }
+            }
+            None => {
+                state.closed_failures.insert(key);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Interceptor for PaymentTracker {
+    /// Records the htlcs that each payment uses, does not take any action on htlcs.
+    async fn intercept_htlc(
+        &self,
+        req: InterceptRequest,
+    ) -> Result<Result<CustomRecords, ForwardingError>, CriticalError> {
+        let now = InstantClock::now(&*self.clock);
+        let key = (
+            req.forwarding_node,
+            HtlcRef {
+                channel_id: req.incoming_htlc.channel_id.into(),
+                htlc_index: req.incoming_htlc.index,
+            },
+        );
+
+        let mut state = self.lock_state()?;
+        let reported_failure = state.failures.remove(&key);
+        let reported_closed = state.closed_failures.remove(&key);
+
+        let payment = state.payments.entry(req.payment_hash).or_insert_with(|| {
+            let sender = self
+                .sender(&req.forwarding_node, key.1.channel_id)
+                .unwrap_or(req.forwarding_node);
+
+            TrackedPayment {
+                sender,
+                honest: !self.attackers.contains(&sender),
+                first_hop: req.forwarding_node,
+                amount_msat: req.incoming_amount_msat,
+                sent_ins: now,
+                attempts: 0,
+                in_flight: None,
+                resolved: None,
+                failure_reason: None,
+                channel_closed: false,
+            }
+        });
+
+        if payment.in_flight.is_none() {
+            payment.attempts += 1;
+            payment.in_flight = Some(key);
+            payment.failure_reason = None;
+            payment.channel_closed = false;
+        }
+
+        if reported_failure.is_some() {
+            payment.failure_reason = reported_failure;
+        }
+
+        if reported_closed {
+            payment.channel_closed = true;
+        }
+
+        state.htlcs.insert(key, req.payment_hash);
+
+        Ok(Ok(CustomRecords::new()))
+    }
+
+    /// Records the outcome of a payment's attempt when it resolves at its first hop.
+    async fn notify_resolution(&self, res: InterceptResolution) -> Result<(), CriticalError> {
+        let now = InstantClock::now(&*self.clock);
+        let key = (
+            res.forwarding_node,
+            HtlcRef {
+                channel_id: res.incoming_htlc.channel_id.into(),
+                htlc_index: res.incoming_htlc.index,
+            },
+        );
+
+        let mut state = self.lock_state()?;
+        state.failures.remove(&key);
+        state.closed_failures.remove(&key);
+
+        let hash = match state.htlcs.remove(&key) {
+            Some(hash) => hash,
+            None => return Ok(()),
+        };
+
+        let attacker_payment = match state.payments.get_mut(&hash) {
+            Some(payment) if payment.in_flight == Some(key) => {
+                payment.in_flight = None;
+                payment.resolved = Some((now, res.success));
+                !payment.honest
+            }
+            _ => false,
+        };
+
+        // We don't report on attacker payments, so we can stop tracking them once they resolve.
+        if attacker_payment {
+            state.payments.remove(&hash);
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        "payment tracker".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bitcoin::secp256k1::PublicKey;
+    use lightning::ln::PaymentHash;
+    use ln_resource_mgr::{AccountableSignal, FailureReason, HtlcRef};
+    use simln_lib::clock::SimulationClock;
+    use simln_lib::sim_node::{InterceptResolution, Interceptor};
+    use simln_lib::ShortChannelID;
+
+    use crate::reputation_interceptor::FailureReporter;
+    use crate::test_utils::{get_random_keypair, setup_test_edge, setup_test_request};
+
+    use super::{PaymentStats, PaymentTracker, CLOSED_CHANNEL_FAILURE, OTHER_FAILURE};
+
+    fn resolution(
+        forwarding_node: PublicKey,
+        channel_in: u64,
+        success: bool,
+    ) -> InterceptResolution {
+        InterceptResolution {
+            forwarding_node,
+            incoming_htlc: simln_lib::sim_node::HtlcRef {
+                channel_id: ShortChannelID::from(channel_in),
+                index: 0,
+            },
+            outgoing_channel_id: None,
+            success,
+        }
+    }
+
+    /// Tests tracking of payments on the following network, where failures are reported before the tracker sees the
+    /// failing hop:
+    /// Alice --(0)-- Bob --(1)-- Carol
+    ///                |
+    ///               (2)
+    ///                |
+    ///             Attacker
+    #[tokio::test]
+    async fn test_payment_tracker() {
+        let (alice, bob, carol, attacker) = (
+            get_random_keypair().1,
+            get_random_keypair().1,
+            get_random_keypair().1,
+            get_random_keypair().1,
+        );
+        let edges = vec![
+            setup_test_edge(ShortChannelID::from(0), alice, bob),
+            setup_test_edge(ShortChannelID::from(1), bob, carol),
+            setup_test_edge(ShortChannelID::from(2), attacker, bob),
+        ];
+
+        let tracker = PaymentTracker::new(
+            Arc::new(SimulationClock::new(1).unwrap()),
+            &edges,
+            &[attacker],
+        );
+
+        // Alice pays Carol, succeeding on the second attempt after Carol fails it for lack of reputation.
+        let payment = setup_test_request(bob, 0, 1, AccountableSignal::Unaccountable);
+        let mut final_hop = setup_test_request(carol, 1, 2, AccountableSignal::Unaccountable);
+        final_hop.outgoing_channel_id = None;
+
+        tracker
+            .intercept_htlc(payment.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        tracker
+            .report_failure(
+                carol,
+                HtlcRef {
+                    channel_id: 1,
+                    htlc_index: 0,
+                },
+                &FailureReason::NoReputation,
+            )
+            .unwrap();
+        tracker
+            .intercept_htlc(final_hop.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        tracker
+            .notify_resolution(resolution(carol, 1, false))
+            .await
+            .unwrap();
+        tracker
+            .notify_resolution(resolution(bob, 0, false))
+            .await
+            .unwrap();
+
+        let payments = tracker.payments().unwrap();
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].sender, alice);
+        assert_eq!(payments[0].first_hop, bob);
+        assert!(!payments[0].success);
+        assert_eq!(payments[0].failure_reason, Some("NoReputation".to_string()));
+
+        tracker.intercept_htlc(payment).await.unwrap().unwrap();
+        tracker.intercept_htlc(final_hop).await.unwrap().unwrap();
+        assert!(tracker.payments().unwrap().is_empty());
+
+        tracker
+            .notify_resolution(resolution(carol, 1, true))
+            .await
+            .unwrap();
+        tracker
+            .notify_resolution(resolution(bob, 0, true))
+            .await
+            .unwrap();
+
+        let payments = tracker.payments().unwrap();
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].attempts, 2);
+        assert!(payments[0].success);
+        assert_eq!(payments[0].failure_reason, None);
+
+        // A payment from the attacker is not reported, and an honest payment that fails without a reported reason is
+        // recorded as another failure.
+        let mut attacker_payment = setup_test_request(bob, 2, 1, AccountableSignal::Unaccountable);
+        attacker_payment.payment_hash = PaymentHash([2; 32]);
+        tracker
+            .intercept_htlc(attacker_payment)
+            .await
+            .unwrap()
+            .unwrap();
+        tracker
+            .notify_resolution(resolution(bob, 2, false))
+            .await
+            .unwrap();
+
+        let mut failed_payment = setup_test_request(carol, 1, 0, AccountableSignal::Unaccountable);
+        failed_payment.payment_hash = PaymentHash([3; 32]);
+        tracker
+            .intercept_htlc(failed_payment)
+            .await
+            .unwrap()
+            .unwrap();
+        tracker
+            .notify_resolution(resolution(carol, 1, false))
+            .await
+            .unwrap();
+
+        let payments = tracker.payments().unwrap();
+        assert_eq!(payments.len(), 2);
+        assert_eq!(payments[1].sender, bob);
+        assert_eq!(payments[1].failure_reason, Some(OTHER_FAILURE.to_string()));
+
+        // A payment that fails because it was routed over a closed channel is recorded, but excluded from stats.
+        let mut closed_payment = setup_test_request(bob, 0, 1, AccountableSignal::Unaccountable);
+        closed_payment.payment_hash = PaymentHash([4; 32]);
+        tracker
+            .report_closed_channel(
+                bob,
+                HtlcRef {
+                    channel_id: 0,
+                    htlc_index: 0,
+                },
+            )
+            .unwrap();
+        tracker
+            .intercept_htlc(closed_payment)
+            .await
+            .unwrap()
+            .unwrap();
+        tracker
+            .notify_resolution(resolution(bob, 0, false))
+            .await
+            .unwrap();
+
+        let payments = tracker.payments().unwrap();
+        assert_eq!(payments.len(), 3);
+        assert_eq!(
+            payments[2].failure_reason,
+            Some(CLOSED_CHANNEL_FAILURE.to_string())
+        );
+
+        let stats = PaymentStats::new(&payments);
+        assert_eq!(stats.attempted, 2);
+        assert_eq!(stats.succeeded, 1);
+        assert_eq!(stats.retried, 1);
+        assert_eq!(stats.failure_reasons.get(OTHER_FAILURE), Some(&1));
+        assert_eq!(stats.closed_channel_failures, 1);
+        assert!(stats.latency_p50_ms.is_some());
+    }
+}
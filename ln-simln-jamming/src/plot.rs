@@ -0,0 +1,544 @@
+use crate::BoxError;
+use clap::ValueEnum;
+use csv::ReaderBuilder;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::Path;
+
+const CHART_SIZE: (u32, u32) = (1200, 600);
+
+/// The image format that charts are rendered to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "svg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+/// The subset of a forward written by [`crate::analysis::BatchForwardWriter`] that is required for plotting. Bucket
+/// usage columns were added after the first results were written, so they're optional to allow plotting reputation
+/// for older files.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ForwardRow {
+    pub ts_offset_ns: u64,
+    pub incoming_channel_id: u64,
+    pub outgoing_channel_id: u64,
+    pub revenue_threshold: i64,
+    pub outgoing_reputation: i64,
+    pub htlc_risk: u64,
+    pub in_flight_risk: u64,
+    #[serde(default)]
+    pub general_slots_used: Option<u16>,
+    pub general_slots_available: u16,
+    #[serde(default)]
+    pub general_liquidity_used: Option<u64>,
+    pub general_liquidity_available: u64,
+    #[serde(default)]
+    pub congestion_slots_used: Option<u16>,
+    pub congestion_slots_available: u16,
+    #[serde(default)]
+    pub congestion_liquidity_used: Option<u64>,
+    pub congestion_liquidity_available: u64,
+    #[serde(default)]
+    pub protected_slots_used: Option<u16>,
+    pub protected_slots_available: u16,
+    #[serde(default)]
+    pub protected_liquidity_used: Option<u64>,
+    pub protected_liquidity_available: u64,
+}
+
+impl ForwardRow {
+    fn total_risk(&self) -> i64 {
+        i64::try_from(self.htlc_risk.saturating_add(self.in_flight_risk)).unwrap_or(i64::MAX)
+    }
+
+    /// The amount that a pair's reputation exceeds the risk of the htlc and its revenue threshold by, positive values
+    /// indicate that the htlc would be forwarded with sufficient reputation.
+    fn assessment(&self) -> i64 {
+        self.outgoing_reputation
+            .saturating_sub(self.total_risk())
+            .saturating_sub(self.revenue_threshold)
+    }
+}
+
+/// Reads all the forwards in a node's results file.
+pub fn read_forwards(path: &Path) -> Result<Vec<ForwardRow>, BoxError> {
+    let mut reader = ReaderBuilder::new().from_reader(File::open(path)?);
+
+    let mut rows = vec![];
+    for result in reader.deserialize() {
+        rows.push(result?);
+    }
+
+    Ok(rows)
+}
+
+/// A pair's reputation values at the time of a forward, with timestamps expressed in seconds since the start of the
+/// simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssessmentPoint {
+    pub ts_secs: f64,
+    pub reputation: i64,
+    pub total_risk: i64,
+    pub revenue_threshold: i64,
+    pub assessment: i64,
+}
+
+/// Groups the reputation assessments for forwards over the outgoing channel provided by incoming channel.
+pub fn reputation_series(
+    rows: &[ForwardRow],
+    outgoing_channel: u64,
+) -> BTreeMap<u64, Vec<AssessmentPoint>> {
+    let mut series: BTreeMap<u64, Vec<AssessmentPoint>> = BTreeMap::new();
+    for row in rows
+        .iter()
+        .filter(|r| r.outgoing_channel_id == outgoing_channel)
+    {
+        series
+            .entry(row.incoming_channel_id)
+            .or_default()
+            .push(AssessmentPoint {
+                ts_secs: ns_to_secs(row.ts_offset_ns),
+                reputation: row.outgoing_reputation,
+                total_risk: row.total_risk(),
+                revenue_threshold: row.revenue_threshold,
+                assessment: row.assessment(),
+            });
+    }
+
+    for points in series.values_mut() {
+        points.sort_by(|a, b| a.ts_secs.total_cmp(&b.ts_secs));
+    }
+
+    series
+}
+
+/// Averages the reputation assessment of every pair that had a positive assessment at some point, interpolating each
+/// pair's values over a set of evenly spaced samples that span all forwards. Before a pair's first forward and after
+/// its last, its first and last values are used.
+pub fn average_assessment(
+    series: &BTreeMap<u64, Vec<AssessmentPoint>>,
+    samples: usize,
+) -> Vec<(f64, f64)> {
+    let pairs: Vec<&Vec<AssessmentPoint>> = series
+        .values()
+        .filter(|points| points.iter().any(|p| p.assessment > 0))
+        .collect();
+
+    let (start, end) = match time_range(series.values().flatten().map(|p| p.ts_secs)) {
+        Some(range) => range,
+        None => return vec![],
+    };
+
+    if pairs.is_empty() || samples == 0 {
+        return vec![];
+    }
+
+    let step = if samples > 1 {
+        (end - start) / (samples - 1) as f64
+    } else {
+        0.0
+    };
+
+    (0..samples)
+        .map(|i| {
+            let ts = start + step * i as f64;
+            let total: f64 = pairs.iter().map(|points| interpolate(points, ts)).sum();
+            (ts, total / pairs.len() as f64)
+        })
+        .collect()
+}
+
+/// Linearly interpolates a pair's assessment at the timestamp provided, clamping to the first and last values.
+fn interpolate(points: &[AssessmentPoint], ts: f64) -> f64 {
+    let idx = points.partition_point(|p| p.ts_secs <= ts);
+    if idx == 0 {
+        return points[0].assessment as f64;
+    }
+    if idx == points.len() {
+        return points[idx - 1].assessment as f64;
+    }
+
+    let (before, after) = (&points[idx - 1], &points[idx]);
+    let fraction = (ts - before.ts_secs) / (after.ts_secs - before.ts_secs);
+    before.assessment as f64 + fraction * (after.assessment - before.assessment) as f64
+}
+
+/// The percentage of slots and liquidity used in each bucket of an outgoing channel when a forward was proposed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UtilisationPoint {
+    pub ts_secs: f64,
+    pub general_slots: f64,
+    pub general_liquidity: f64,
+    pub congestion_slots: f64,
+    pub congestion_liquidity: f64,
+    pub protected_slots: f64,
+    pub protected_liquidity: f64,
+}
+
+/// Returns bucket utilisation over time for the outgoing channel provided, or an error if the results file was written
+/// before bucket usage was recorded.
+pub fn bucket_utilisation(
+    rows: &[ForwardRow],
+    outgoing_channel: u64,
+) -> Result<Vec<UtilisationPoint>, BoxError> {
+    let mut points = rows
+        .iter()
+        .filter(|r| r.outgoing_channel_id == outgoing_channel)
+        .map(|r| {
+            Ok(UtilisationPoint {
+                ts_secs: ns_to_secs(r.ts_offset_ns),
+                general_slots: percent(r.general_slots_used, r.general_slots_available)?,
+                general_liquidity: percent(
+                    r.general_liquidity_used,
+                    r.general_liquidity_available,
+                )?,
+                congestion_slots: percent(r.congestion_slots_used, r.congestion_slots_available)?,
+                congestion_liquidity: percent(
+                    r.congestion_liquidity_used,
+                    r.congestion_liquidity_available,
+                )?,
+                protected_slots: percent(r.protected_slots_used, r.protected_slots_available)?,
+                protected_liquidity: percent(
+                    r.protected_liquidity_used,
+                    r.protected_liquidity_available,
+                )?,
+            })
+        })
+        .collect::<Result<Vec<_>, BoxError>>()?;
+
+    points.sort_by(|a, b| a.ts_secs.total_cmp(&b.ts_secs));
+    Ok(points)
+}
+
+fn percent<T: Into<u64>>(used: Option<T>, available: T) -> Result<f64, BoxError> {
+    let used = used.ok_or("results file does not record bucket usage")?;
+    let available = available.into();
+    if available == 0 {
+        return Ok(0.0);
+    }
+
+    Ok(used.into() as f64 / available as f64 * 100.0)
+}
+
+fn ns_to_secs(ns: u64) -> f64 {
+    ns as f64 / 1e9
+}
+
+fn time_range(timestamps: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    timestamps.fold(None, |range, ts| match range {
+        None => Some((ts, ts)),
+        Some((start, end)) => Some((start.min(ts), end.max(ts))),
+    })
+}
+
+/// A single series on a chart.
+pub struct Line {
+    pub label: String,
+    pub points: Vec<(f64, f64)>,
+    pub color: RGBColor,
+    pub width: u32,
+}
+
+/// A line chart with time since the start of the simulation on its x axis.
+pub struct Chart {
+    pub title: String,
+    pub y_label: String,
+    pub lines: Vec<Line>,
+}
+
+/// Charts a pair's reputation, risk, revenue threshold and the resulting reputation assessment.
+pub fn pair_chart(
+    outgoing_channel: u64,
+    incoming_channel: u64,
+    points: &[AssessmentPoint],
+) -> Chart {
+    let line =
+        |label: &str, color: RGBColor, width: u32, value: fn(&AssessmentPoint) -> i64| Line {
+            label: label.to_string(),
+            points: points
+                .iter()
+                .map(|p| (p.ts_secs, value(p) as f64))
+                .collect(),
+            color,
+            width,
+        };
+
+    Chart {
+        title: format!("Outgoing channel {outgoing_channel} - Incoming {incoming_channel}"),
+        y_label: "Value (msat)".to_string(),
+        lines: vec![
+            line("Outgoing Reputation", BLUE, 1, |p| p.reputation),
+            line("Total Risk (HTLC + In-Flight)", RED, 1, |p| p.total_risk),
+            line("Revenue Threshold", GREEN, 1, |p| p.revenue_threshold),
+            line("Reputation Assessment", BLACK, 2, |p| p.assessment),
+        ],
+    }
+}
+
+/// Charts the reputation assessment of each pair that had a positive assessment at some point, along with their
+/// average.
+pub fn average_chart(
+    outgoing_channel: u64,
+    series: &BTreeMap<u64, Vec<AssessmentPoint>>,
+    samples: usize,
+) -> Chart {
+    let mut lines: Vec<Line> = series
+        .iter()
+        .filter(|(_, points)| points.iter().any(|p| p.assessment > 0))
+        .enumerate()
+        .map(|(i, (incoming, points))| Line {
+            label: format!("Incoming {incoming}"),
+            points: points
+                .iter()
+                .map(|p| (p.ts_secs, p.assessment as f64))
+                .collect(),
+            color: {
+                let (r, g, b) = Palette99::pick(i).rgb();
+                RGBColor(r, g, b)
+            },
+            width: 1,
+        })
+        .collect();
+
+    let average = average_assessment(series, samples);
+    if !average.is_empty() {
+        lines.push(Line {
+            label: "Reputation Assessment".to_string(),
+            points: average,
+            color: BLACK,
+            width: 2,
+        });
+    }
+
+    Chart {
+        title: format!("Outgoing channel {outgoing_channel} - Average Reputation Change"),
+        y_label: "Net Value (msat)".to_string(),
+        lines,
+    }
+}
+
+/// Charts the percentage of each bucket's slots and liquidity that were in use over time.
+pub fn utilisation_chart(outgoing_channel: u64, points: &[UtilisationPoint]) -> Chart {
+    let line = |label: &str, color: RGBColor, value: fn(&UtilisationPoint) -> f64| Line {
+        label: label.to_string(),
+        points: points.iter().map(|p| (p.ts_secs, value(p))).collect(),
+        color,
+        width: 1,
+    };
+
+    Chart {
+        title: format!("Outgoing channel {outgoing_channel} - Bucket Utilisation"),
+        y_label: "Used (%)".to_string(),
+        lines: vec![
+            line("General Slots", BLUE, |p| p.general_slots),
+            line("General Liquidity", CYAN, |p| p.general_liquidity),
+            line("Congestion Slots", RED, |p| p.congestion_slots),
+            line("Congestion Liquidity", MAGENTA, |p| p.congestion_liquidity),
+            line("Protected Slots", GREEN, |p| p.protected_slots),
+            line("Protected Liquidity", BLACK, |p| p.protected_liquidity),
+        ],
+    }
+}
+
+/// Registers the TrueType font file provided as the font that charts are rendered with. Plotters is built without
+/// system font discovery, so this must be called before any charts are rendered.
+pub fn register_font(path: &Path) -> Result<(), BoxError> {
+    let bytes = fs::read(path)
+        .map_err(|e| format!("could not read font file {}: {e}", path.to_string_lossy()))?;
+
+    // Plotters holds on to registered fonts for the lifetime of the program.
+    plotters::style::register_font(
+        "sans-serif",
+        FontStyle::Normal,
+        Box::leak(bytes.into_boxed_slice()),
+    )
+    .map_err(|_| format!("invalid font file: {}", path.to_string_lossy()))?;
+
+    Ok(())
+}
+
+/// Renders a chart to the path provided in the format requested.
+pub fn render(chart: &Chart, path: &Path, format: ImageFormat) -> Result<(), BoxError> {
+    match format {
+        ImageFormat::Svg => draw(chart, SVGBackend::new(path, CHART_SIZE).into_drawing_area()),
+        ImageFormat::Png => draw(
+            chart,
+            BitMapBackend::new(path, CHART_SIZE).into_drawing_area(),
+        ),
+    }
+}
+
+fn draw<DB: DrawingBackend>(chart: &Chart, root: DrawingArea<DB, Shift>) -> Result<(), BoxError>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let points = chart.lines.iter().flat_map(|l| l.points.iter());
+    let (x_min, x_max) = padded_range(time_range(points.clone().map(|p| p.0)));
+    // Always include zero so that positive and negative values are easy to distinguish.
+    let (y_min, y_max) = padded_range(time_range(points.map(|p| p.1).chain([0.0])));
+
+    let mut ctx = ChartBuilder::on(&root)
+        .caption(&chart.title, ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(100)
+        .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+    ctx.configure_mesh()
+        .x_desc("Time since start (s)")
+        .y_desc(&chart.y_label)
+        .draw()?;
+
+    for line in chart.lines.iter() {
+        let style = line.color.stroke_width(line.width);
+        ctx.draw_series(LineSeries::new(line.points.iter().copied(), style))?
+            .label(&line.label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style));
+    }
+
+    ctx.configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// Widens a range so that flat series and single points can still be drawn.
+fn padded_range(range: Option<(f64, f64)>) -> (f64, f64) {
+    match range {
+        None => (0.0, 1.0),
+        Some((min, max)) if min == max => (min - 1.0, max + 1.0),
+        Some((min, max)) => {
+            let pad = (max - min) * 0.05;
+            (min - pad, max + pad)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{
+        average_assessment, bucket_utilisation, reputation_series, AssessmentPoint, ForwardRow,
+    };
+
+    fn row(ts_secs: u64, incoming: u64, outgoing: u64, reputation: i64) -> ForwardRow {
+        ForwardRow {
+            ts_offset_ns: ts_secs * 1_000_000_000,
+            incoming_channel_id: incoming,
+            outgoing_channel_id: outgoing,
+            revenue_threshold: 100,
+            outgoing_reputation: reputation,
+            htlc_risk: 10,
+            in_flight_risk: 20,
+            general_slots_used: Some(5),
+            general_slots_available: 20,
+            general_liquidity_used: Some(500),
+            general_liquidity_available: 1_000,
+            congestion_slots_used: Some(0),
+            congestion_slots_available: 10,
+            congestion_liquidity_used: Some(0),
+            congestion_liquidity_available: 0,
+            protected_slots_used: Some(10),
+            protected_slots_available: 10,
+            protected_liquidity_used: Some(250),
+            protected_liquidity_available: 1_000,
+        }
+    }
+
+    fn point(ts_secs: f64, assessment: i64) -> AssessmentPoint {
+        AssessmentPoint {
+            ts_secs,
+            reputation: 0,
+            total_risk: 0,
+            revenue_threshold: 0,
+            assessment,
+        }
+    }
+
+    /// Tests grouping of forwards by incoming channel, and calculation of their reputation assessment.
+    #[test]
+    fn test_reputation_series() {
+        let rows = vec![
+            row(2, 1, 5, 500),
+            row(1, 1, 5, 200),
+            row(1, 2, 5, 50),
+            row(1, 1, 6, 1_000),
+        ];
+
+        let series = reputation_series(&rows, 5);
+        assert_eq!(series.keys().copied().collect::<Vec<_>>(), vec![1, 2]);
+
+        let pair_1 = &series[&1];
+        assert_eq!(
+            pair_1.iter().map(|p| p.ts_secs).collect::<Vec<_>>(),
+            vec![1.0, 2.0]
+        );
+        assert_eq!(
+            pair_1.iter().map(|p| p.assessment).collect::<Vec<_>>(),
+            vec![70, 370]
+        );
+        assert_eq!(pair_1[0].total_risk, 30);
+
+        assert_eq!(series[&2][0].assessment, -80);
+        assert!(reputation_series(&rows, 7).is_empty());
+    }
+
+    /// Tests that the average assessment interpolates each pair with a positive assessment over evenly spaced
+    /// samples, clamping to their first and last values.
+    #[test]
+    fn test_average_assessment() {
+        let mut series = BTreeMap::new();
+        series.insert(1, vec![point(0.0, 0), point(10.0, 100)]);
+        series.insert(2, vec![point(5.0, 50), point(10.0, 50)]);
+        // Never has a positive assessment, so it's excluded from the average.
+        series.insert(3, vec![point(0.0, -1_000)]);
+
+        let average = average_assessment(&series, 3);
+        assert_eq!(average, vec![(0.0, 25.0), (5.0, 50.0), (10.0, 75.0)]);
+
+        assert!(average_assessment(&series, 0).is_empty());
+        assert!(average_assessment(&BTreeMap::new(), 10).is_empty());
+
+        let mut negative = BTreeMap::new();
+        negative.insert(1, vec![point(0.0, -1)]);
+        assert!(average_assessment(&negative, 10).is_empty());
+    }
+
+    /// Tests calculation of bucket utilisation, and failure when bucket usage wasn't recorded.
+    #[test]
+    fn test_bucket_utilisation() {
+        let rows = vec![row(2, 1, 5, 0), row(1, 2, 5, 0), row(1, 1, 6, 0)];
+
+        let utilisation = bucket_utilisation(&rows, 5).unwrap();
+        assert_eq!(utilisation.len(), 2);
+        assert_eq!(utilisation[0].ts_secs, 1.0);
+        assert_eq!(utilisation[0].general_slots, 25.0);
+        assert_eq!(utilisation[0].general_liquidity, 50.0);
+        assert_eq!(utilisation[0].congestion_liquidity, 0.0);
+        assert_eq!(utilisation[0].protected_slots, 100.0);
+        assert_eq!(utilisation[0].protected_liquidity, 25.0);
+
+        let mut old_row = row(1, 1, 5, 0);
+        old_row.general_slots_used = None;
+        assert!(bucket_utilisation(&[old_row], 5).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+use crate::analysis::{write_outcome_breakdowns, OutcomeBreakdown};
+use crate::parsing::find_alias_by_pubkey;
+use crate::reputation_interceptor::BootstrapForward;
+use crate::BoxError;
+use bitcoin::secp256k1::PublicKey;
+use csv::{ReaderBuilder, WriterBuilder};
+use ln_resource_mgr::forward_manager::{ForwardManager, ForwardManagerParams};
+use ln_resource_mgr::{
+    AccountableSignal, ForwardResolution, ForwardingOutcome, HtlcRef, ProposedForward,
+    ReputationManager,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use sim_cli::parsing::NetworkParser;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A htlc that is replayed through a set of forward managers, with timestamps expressed in nanoseconds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReplayHtlc {
+    pub forwarding_node: PublicKey,
+    pub incoming_channel_id: u64,
+    pub outgoing_channel_id: u64,
+    pub amount_in_msat: u64,
+    pub amount_out_msat: u64,
+    pub expiry_in_height: u32,
+    pub expiry_out_height: u32,
+    pub added_height: u32,
+    pub incoming_accountable: AccountableSignal,
+    pub added_ns: u64,
+    pub resolved_ns: u64,
+    pub resolution: ForwardResolution,
+}
+
+impl From<&BootstrapForward> for ReplayHtlc {
+    fn from(forward: &BootstrapForward) -> Self {
+        ReplayHtlc {
+            forwarding_node: forward.forwarding_node,
+            incoming_channel_id: forward.channel_in_id,
+            outgoing_channel_id: forward.channel_out_id,
+            amount_in_msat: forward.incoming_amt,
+            amount_out_msat: forward.outgoing_amt,
+            expiry_in_height: forward.incoming_expiry,
+            expiry_out_height: forward.outgoing_expiry,
+            // Traffic files record expiry heights relative to the height that the htlc was added at.
+            added_height: 0,
+            incoming_accountable: AccountableSignal::Unaccountable,
+            added_ns: forward.added_ns,
+            resolved_ns: forward.settled_ns,
+            resolution: ForwardResolution::Settled,
+        }
+    }
+}
+
+/// A forward recorded in a node's results file by [`crate::analysis::BatchForwardWriter`]. Only the columns that are
+/// needed to replay the forward are read.
+#[derive(Debug, Deserialize)]
+struct RecordedForward {
+    ts_offset_ns: u64,
+    incoming_channel_id: u64,
+    outgoing_channel_id: u64,
+    amount_in_msat: u64,
+    amount_out_msat: u64,
+    expiry_in_height: u32,
+    expiry_out_height: u32,
+    added_height: u32,
+    incoming_accountable: String,
+}
+
+/// Reads the forwards recorded in a node's results file. Results files don't record when or how htlcs were resolved,
+/// so every htlc is assumed to settle once the hold time provided has passed.
+pub fn replay_htlcs_from_results(
+    path: &Path,
+    forwarding_node: PublicKey,
+    hold_time: Duration,
+) -> Result<Vec<ReplayHtlc>, BoxError> {
+    let mut reader = ReaderBuilder::new().from_reader(File::open(path)?);
+
+    let mut htlcs = vec![];
+    for result in reader.deserialize() {
+        let record: RecordedForward = result?;
+        let incoming_accountable = match record.incoming_accountable.as_str() {
+            "Accountable" => AccountableSignal::Accountable,
+            "Unaccountable" => AccountableSignal::Unaccountable,
+            s => return Err(format!("unknown accountable signal: {s}").into()),
+        };
+
+        htlcs.push(ReplayHtlc {
+            forwarding_node,
+            incoming_channel_id: record.incoming_channel_id,
+            outgoing_channel_id: record.outgoing_channel_id,
+            amount_in_msat: record.amount_in_msat,
+            amount_out_msat: record.amount_out_msat,
+            expiry_in_height: record.expiry_in_height,
+            expiry_out_height: record.expiry_out_height,
+            added_height: record.added_height,
+            incoming_accountable,
+            added_ns: record.ts_offset_ns,
+            resolved_ns: record.ts_offset_ns + hold_time.as_nanos() as u64,
+            resolution: ForwardResolution::Settled,
+        });
+    }
+
+    Ok(htlcs)
+}
+
+/// The outcome that each parameter set gave a replayed htlc, in the order that parameter sets were provided.
+#[derive(Clone, Debug)]
+pub struct ReplayDecision {
+    pub htlc: ReplayHtlc,
+    pub outcomes: Vec<ForwardingOutcome>,
+}
+
+/// Replays htlcs through fresh forward managers for each set of parameters provided, returning the decision that each
+/// parameter set made for every htlc in the order that they were provided.
+///
+/// Htlcs are added and resolved in order of their timestamps, in the same way as
+/// [`crate::reputation_interceptor::ReputationInterceptor::bootstrap_network_history`]. Each forwarding node's
+/// managers are seeded identically for every parameter set so that differences in decisions are only due to the
+/// parameters. Htlcs that a parameter set fails are not resolved for that set.
+pub fn replay(
+    htlcs: &[ReplayHtlc],
+    edges: &[NetworkParser],
+    configs: &[(String, ForwardManagerParams)],
+    seed: u64,
+) -> Result<Vec<ReplayDecision>, BoxError> {
+    let start_ins = Instant::now();
+    let first_ns = match htlcs.iter().map(|h| h.added_ns).min() {
+        Some(ns) => ns,
+        None => return Ok(vec![]),
+    };
+
+    // Sort forwarding nodes so that managers are seeded deterministically.
+    let nodes: BTreeSet<PublicKey> = htlcs.iter().map(|h| h.forwarding_node).collect();
+    let mut managers: Vec<HashMap<PublicKey, ForwardManager>> = Vec::with_capacity(configs.len());
+    for (name, params) in configs {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut config_managers = HashMap::with_capacity(nodes.len());
+
+        for node in nodes.iter() {
+            let manager = ForwardManager::new(*params, rng.random());
+            let mut channel_count = 0;
+            for edge in edges
+                .iter()
+                .filter(|e| e.node_1.pubkey == *node || e.node_2.pubkey == *node)
+            {
+                manager.add_channel(edge.scid.into(), edge.capacity_msat, start_ins, None)?;
+                channel_count += 1;
+            }
+
+            if channel_count == 0 {
+                return Err(
+                    format!("forwarding node: {node} not found in graph for {name}").into(),
+                );
+            }
+            config_managers.insert(*node, manager);
+        }
+
+        managers.push(config_managers);
+    }
+
+    // Push each htlc's add before its resolve so that a stable sort keeps them in order if they share a timestamp.
+    let mut events = Vec::with_capacity(htlcs.len() * 2);
+    for (i, htlc) in htlcs.iter().enumerate() {
+        if htlc.resolved_ns < htlc.added_ns {
+            return Err(format!(
+                "htlc: {i} resolved at {} before it was added at {}",
+                htlc.resolved_ns, htlc.added_ns
+            )
+            .into());
+        }
+
+        events.push((htlc.added_ns, i, true));
+        events.push((htlc.resolved_ns, i, false));
+    }
+    events.sort_by_key(|(ts, _, _)| *ts);
+
+    let mut outcomes: Vec<Vec<ForwardingOutcome>> = vec![vec![]; htlcs.len()];
+    let mut failed: Vec<HashSet<usize>> = vec![HashSet::new(); configs.len()];
+    for (ts, i, is_add) in events {
+        let htlc = &htlcs[i];
+        let event_ins = start_ins + Duration::from_nanos(ts - first_ns);
+        let incoming_ref = HtlcRef {
+            channel_id: htlc.incoming_channel_id,
+            htlc_index: i as u64,
+        };
+
+        for (config, config_managers) in managers.iter().enumerate() {
+            let manager = config_managers
+                .get(&htlc.forwarding_node)
+                .ok_or(format!("manager for {} not found", htlc.forwarding_node))?;
+
+            if is_add {
+                let outcome = manager.add_htlc(&ProposedForward {
+                    incoming_ref,
+                    outgoing_channel_id: htlc.outgoing_channel_id,
+                    amount_in_msat: htlc.amount_in_msat,
+                    amount_out_msat: htlc.amount_out_msat,
+                    expiry_in_height: htlc.expiry_in_height,
+                    expiry_out_height: htlc.expiry_out_height,
+                    added_height: htlc.added_height,
+                    added_at: event_ins,
+                    incoming_accountable: htlc.incoming_accountable,
+                    upgradable_accountability: true,
+                })?;
+
+                if let ForwardingOutcome::Fail(_) = outcome {
+                    failed[config].insert(i);
+                }
+                outcomes[i].push(outcome);
+            } else if !failed[config].remove(&i) {
+                manager.resolve_htlc(
+                    htlc.outgoing_channel_id,
+                    incoming_ref,
+                    htlc.resolution,
+                    event_ins,
+                )?;
+            }
+        }
+    }
+
+    Ok(htlcs
+        .iter()
+        .cloned()
+        .zip(outcomes)
+        .map(|(htlc, outcomes)| ReplayDecision { htlc, outcomes })
+        .collect())
+}
+
+/// Writes the decisions made for each replayed htlc to `replay_decisions.csv`, and a breakdown of each forwarding
+/// node's outcomes per parameter set to `replay_outcomes.csv` in the directory provided. Disagreements are counted
+/// against the first parameter set.
+pub fn write_replay_results(
+    dir: &Path,
+    decisions: &[ReplayDecision],
+    configs: &[(String, ForwardManagerParams)],
+    edges: &[NetworkParser],
+) -> Result<(), BoxError> {
+    let mut writer =
+        WriterBuilder::new().from_writer(File::create(dir.join("replay_decisions.csv"))?);
+
+    let mut header = vec![
+        "added_ns".to_string(),
+        "forwarding_node".to_string(),
+        "incoming_channel_id".to_string(),
+        "outgoing_channel_id".to_string(),
+        "amount_in_msat".to_string(),
+        "incoming_accountable".to_string(),
+    ];
+    header.extend(configs.iter().map(|(name, _)| name.clone()));
+    writer.write_record(&header)?;
+
+    let mut aliases = HashMap::new();
+    let mut breakdowns: BTreeMap<(String, String), OutcomeBreakdown> = BTreeMap::new();
+    for decision in decisions {
+        let htlc = &decision.htlc;
+        if let Entry::Vacant(e) = aliases.entry(htlc.forwarding_node) {
+            e.insert(find_alias_by_pubkey(&htlc.forwarding_node, edges)?);
+        }
+        let alias = &aliases[&htlc.forwarding_node];
+
+        let mut record = vec![
+            htlc.added_ns.to_string(),
+            alias.clone(),
+            htlc.incoming_channel_id.to_string(),
+            htlc.outgoing_channel_id.to_string(),
+            htlc.amount_in_msat.to_string(),
+            htlc.incoming_accountable.to_string(),
+        ];
+        record.extend(decision.outcomes.iter().map(|o| o.to_string()));
+        writer.write_record(&record)?;
+
+        for ((name, _), outcome) in configs.iter().zip(decision.outcomes.iter()) {
+            breakdowns
+                .entry((alias.clone(), name.clone()))
+                .or_default()
+                .record(outcome, &decision.outcomes[0]);
+        }
+    }
+    writer.flush()?;
+
+    write_outcome_breakdowns(&dir.join("replay_outcomes.csv"), &breakdowns)
+}
+
+#[cfg(test)]
+mod tests {
+    use ln_resource_mgr::forward_manager::ForwardManagerParams;
+    use ln_resource_mgr::{AccountableSignal, FailureReason, ForwardResolution, ForwardingOutcome};
+    use simln_lib::ShortChannelID;
+
+    use crate::test_utils::{get_random_keypair, setup_test_edge};
+
+    use super::{replay, ReplayHtlc};
+
+    /// Tests that htlcs are added and resolved in order of their timestamps, so that htlcs which are in flight at the
+    /// same time compete for resources, and that each parameter set makes its own decisions.
+    #[test]
+    fn test_replay() {
+        let alice = get_random_keypair().1;
+        let bob = get_random_keypair().1;
+        let carol = get_random_keypair().1;
+        let edges = vec![
+            setup_test_edge(ShortChannelID::from(1), alice, bob),
+            setup_test_edge(ShortChannelID::from(2), bob, carol),
+        ];
+
+        let htlc = |added_ns: u64, resolved_ns: u64| ReplayHtlc {
+            forwarding_node: bob,
+            incoming_channel_id: 1,
+            outgoing_channel_id: 2,
+            amount_in_msat: 2_000,
+            amount_out_msat: 1_000,
+            expiry_in_height: 80,
+            expiry_out_height: 40,
+            added_height: 0,
+            incoming_accountable: AccountableSignal::Unaccountable,
+            added_ns,
+            resolved_ns,
+            resolution: ForwardResolution::Settled,
+        };
+
+        let params = ForwardManagerParams::default();
+        let configs = vec![
+            ("primary".to_string(), params),
+            ("same".to_string(), params),
+        ];
+
+        // Htlcs that resolve before the next is added all use general resources.
+        let sequential: Vec<ReplayHtlc> = (0..30).map(|i| htlc(i * 10, i * 10 + 5)).collect();
+        let decisions = replay(&sequential, &edges, &configs, 0).unwrap();
+        assert_eq!(decisions.len(), sequential.len());
+        for decision in decisions {
+            assert_eq!(
+                decision.outcomes,
+                vec![ForwardingOutcome::Forward(AccountableSignal::Unaccountable); 2]
+            );
+        }
+
+        // Htlcs that are all in flight at the same time fill the general slots that the incoming channel has assigned
+        // to the outgoing channel, and are failed once they're all used. The congestion bucket isn't used because the
+        // incoming channel still has general resources available for other outgoing channels.
+        let concurrent: Vec<ReplayHtlc> = (0..30).map(|i| htlc(i, 1_000)).collect();
+        let decisions = replay(&concurrent, &edges, &configs, 0).unwrap();
+        for decision in decisions.iter() {
+            assert_eq!(decision.outcomes[0], decision.outcomes[1]);
+        }
+        let forwarded = decisions
+            .iter()
+            .take_while(|d| {
+                d.outcomes[0] == ForwardingOutcome::Forward(AccountableSignal::Unaccountable)
+            })
+            .count();
+        assert!(forwarded > 0 && forwarded < decisions.len());
+        for decision in decisions[forwarded..].iter() {
+            assert_eq!(
+                decision.outcomes[0],
+                ForwardingOutcome::Fail(FailureReason::NoGeneralResources)
+            );
+        }
+
+        // Htlcs from nodes that aren't in the graph can't be replayed.
+        let mut unknown = htlc(0, 10);
+        unknown.forwarding_node = get_random_keypair().1;
+        assert!(replay(&[unknown], &edges, &configs, 0).is_err());
+    }
+}
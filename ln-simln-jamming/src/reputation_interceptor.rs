@@ -1,15 +1,19 @@
-use crate::analysis::ForwardReporter;
-use crate::clock::InstantClock;
+use crate::analysis::{ForwardReporter, ShadowOutcome};
+use crate::clock::{BlockClock, InstantClock};
+use crate::deployment::NetworkDeployment;
+use crate::mitigation::Mitigation;
 use crate::{accountable_from_records, records_from_signal, upgradable_from_records, BoxError};
 use async_trait::async_trait;
 use bitcoin::secp256k1::PublicKey;
 use ln_resource_mgr::forward_manager::{
-    ForwardManager, ForwardManagerParams, SimulationDebugManager,
+    ForwardManager, ForwardManagerParams, ObservedOutcome, SimulationDebugManager,
 };
 use ln_resource_mgr::{
-    AccountableSignal, ChannelSnapshot, ForwardResolution, ForwardingOutcome, HtlcRef,
-    ProposedForward, ReputationError, ReputationManager,
+    AccountableSignal, ChannelSnapshot, FailureReason, ForwardResolution, ForwardingOutcome,
+    HtlcRef, ProposedForward, ReputationError, ReputationManager,
 };
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use sim_cli::parsing::NetworkParser;
 use simln_lib::clock::SimulationClock;
@@ -33,6 +37,8 @@ pub struct HtlcAdd {
     pub amount_out_msat: u64,
     pub expiry_in_height: u32,
     pub expiry_out_height: u32,
+    /// The block height at which the htlc was added, relative to which the expiry heights are set.
+    pub added_height: u32,
     pub incoming_accountable: AccountableSignal,
     pub upgradable_accountability: bool,
     /// Optional timestamp for the case where htlcs with existing timestamps are being replayed.
@@ -55,6 +61,15 @@ enum BootstrapEvent {
     BootstrapResolve(HtlcResolve),
 }
 
+/// The reputation state that a network is started from.
+pub struct NetworkSnapshot {
+    /// The reputation of each node's channels, keyed by the node's public key.
+    pub reputation: HashMap<PublicKey, HashMap<u64, ChannelSnapshot>>,
+    /// Nodes that are not expected to be in the snapshot. Their channels, and any channels that other nodes have with
+    /// them, start with no reputation.
+    pub no_reputation: HashSet<PublicKey>,
+}
+
 pub struct BootstrapRecords {
     pub forwards: Vec<BootstrapForward>,
     pub last_timestamp_nanos: u64,
@@ -88,26 +103,178 @@ pub trait ReputationMonitor {
     ) -> Result<HashMap<u64, ChannelSnapshot>, BoxError>;
 }
 
+/// Receives the reason that a node's resource manager failed a htlc, which is otherwise lost when the failure is
+/// returned to the simulator.
+pub trait FailureReporter: Send + Sync {
+    fn report_failure(
+        &self,
+        forwarding_node: PublicKey,
+        incoming_ref: HtlcRef,
+        reason: &FailureReason,
+    ) -> Result<(), CriticalError>;
+
+    /// Reports a htlc that was failed because it was forwarded over a channel that is closed (or not yet opened).
+    fn report_closed_channel(
+        &self,
+        forwarding_node: PublicKey,
+        incoming_ref: HtlcRef,
+    ) -> Result<(), CriticalError>;
+}
+
+/// Implemented by resource managers that report the outcome that they would have given a htlc alongside the outcome
+/// that is applied to it. Managers that always enforce their decisions can rely on the default implementation.
+pub trait ObservingManager: ReputationManager {
+    fn add_observed_htlc(
+        &self,
+        forward: &ProposedForward,
+    ) -> Result<ObservedOutcome, ReputationError> {
+        let outcome = self.add_htlc(forward)?;
+        Ok(ObservedOutcome {
+            enforced: outcome.clone(),
+            would_be: outcome,
+        })
+    }
+}
+
+impl ObservingManager for ForwardManager {
+    fn add_observed_htlc(
+        &self,
+        forward: &ProposedForward,
+    ) -> Result<ObservedOutcome, ReputationError> {
+        ForwardManager::add_observed_htlc(self, forward)
+    }
+}
+
+/// Implemented by resource managers that can be created for each node in a simulated network.
+pub trait NodeManager:
+    ReputationManager + ObservingManager + SimulationDebugManager + Sized
+{
+    /// Creates a manager for a node that uses the mitigation, parameters and seed provided. Observe-only managers
+    /// forward every htlc, tracking the decisions that they would have made.
+    fn new_node_manager(
+        mitigation: Mitigation,
+        params: ForwardManagerParams,
+        seed: u64,
+        observe_only: bool,
+    ) -> Result<Self, BoxError>;
+}
+
+impl NodeManager for ForwardManager {
+    fn new_node_manager(
+        mitigation: Mitigation,
+        params: ForwardManagerParams,
+        seed: u64,
+        observe_only: bool,
+    ) -> Result<Self, BoxError> {
+        if !mitigation.tracks_reputation() {
+            return Err(
+                format!("forward manager can't be used for mitigation: {mitigation:?}").into(),
+            );
+        }
+
+        if observe_only {
+            Ok(ForwardManager::new_observe_only(params, seed))
+        } else {
+            Ok(ForwardManager::new(params, seed))
+        }
+    }
+}
+
+/// Counts the htlcs that an observe-only node forwarded, and the number that it would have failed if it was enforcing
+/// its mitigation.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ObservedFailures {
+    pub forwards: u64,
+    pub would_fail: u64,
+}
+
 struct Node<M>
 where
     M: ReputationManager + SimulationDebugManager,
 {
     forward_manager: M,
     alias: String,
+    /// Nodes that have not deployed the mitigation forward all htlcs without bucketing, relaying the accountable
+    /// signal that they receive.
+    deployed: bool,
+    /// Forward managers that see every htlc that the node forwards, but don't decide forwarding outcomes.
+    shadows: Vec<ShadowManager>,
+    /// Tracks the htlcs that observe-only nodes would have failed, keyed by (incoming, outgoing) channel. None if the
+    /// node enforces its mitigation.
+    observed: Option<HashMap<(u64, u64), ObservedFailures>>,
 }
 
 impl<M> Node<M>
 where
     M: ReputationManager + SimulationDebugManager,
 {
-    fn new(forward_manager: M, alias: String) -> Self {
+    fn new(forward_manager: M, alias: String, deployed: bool, observe_only: bool) -> Self {
         Node {
             forward_manager,
             alias,
+            deployed,
+            shadows: vec![],
+            observed: observe_only.then(HashMap::new),
         }
     }
 }
 
+/// A forward manager that runs alongside a node's primary manager with different parameters, recording the outcome
+/// that it would have given each htlc without affecting the htlc's forwarding.
+struct ShadowManager {
+    name: String,
+    forward_manager: ForwardManager,
+    /// Htlcs that the shadow would have failed, which are not tracked when the primary manager resolves them.
+    failed_htlcs: HashSet<HtlcRef>,
+}
+
+impl ShadowManager {
+    /// Adds a htlc to the shadow manager, returning the outcome that it would have given the htlc. If the primary
+    /// manager failed a htlc that the shadow would have forwarded, it is immediately failed back in the shadow.
+    fn add_htlc(
+        &mut self,
+        htlc: &ProposedForward,
+        enforced: &ForwardingOutcome,
+    ) -> Result<ShadowOutcome, ReputationError> {
+        let outcome = self.forward_manager.add_htlc(htlc)?;
+        match (&outcome, enforced) {
+            (ForwardingOutcome::Fail(_), _) => {
+                self.failed_htlcs.insert(htlc.incoming_ref);
+            }
+            (ForwardingOutcome::Forward(_), ForwardingOutcome::Fail(_)) => {
+                self.forward_manager.resolve_htlc(
+                    htlc.outgoing_channel_id,
+                    htlc.incoming_ref,
+                    ForwardResolution::Failed,
+                    htlc.added_at,
+                )?;
+            }
+            (ForwardingOutcome::Forward(_), ForwardingOutcome::Forward(_)) => {}
+        }
+
+        Ok(ShadowOutcome {
+            name: self.name.clone(),
+            outcome,
+        })
+    }
+
+    /// Resolves a htlc in the shadow manager, skipping htlcs that it failed on add.
+    fn resolve_htlc(
+        &mut self,
+        outgoing_channel: u64,
+        incoming_ref: HtlcRef,
+        resolution: ForwardResolution,
+        resolved_ins: Instant,
+    ) -> Result<(), ReputationError> {
+        if self.failed_htlcs.remove(&incoming_ref) {
+            return Ok(());
+        }
+
+        self.forward_manager
+            .resolve_htlc(outgoing_channel, incoming_ref, resolution, resolved_ins)
+    }
+}
+
 /// Implements a network-wide interceptor that implements resource management for every forwarding node in the
 /// network.
 #[derive(Clone)]
@@ -118,26 +285,40 @@ where
 {
     network_nodes: Arc<Mutex<HashMap<PublicKey, Node<M>>>>,
     clock: Arc<SimulationClock>,
+    block_clock: BlockClock,
     results: Option<Arc<Mutex<R>>>,
+    failure_reporter: Option<Arc<dyn FailureReporter>>,
 }
 
-impl<R> ReputationInterceptor<R, ForwardManager>
+impl<R, M> ReputationInterceptor<R, M>
 where
     R: ForwardReporter,
+    M: NodeManager,
 {
+    /// Creates an interceptor with a forward manager for every node in the network. Each node's forward manager is
+    /// seeded from the seed provided, in the order that nodes appear in the set of edges, and uses the mitigation and
+    /// parameters set for it in the deployment provided (or the default params if none are set).
     pub fn new_for_network(
         params: ForwardManagerParams,
+        deployment: &NetworkDeployment,
+        seed: u64,
         edges: &[NetworkParser],
         clock: Arc<SimulationClock>,
         results: Option<Arc<Mutex<R>>>,
     ) -> Result<Self, BoxError> {
-        let mut network_nodes: HashMap<PublicKey, Node<ForwardManager>> = HashMap::new();
+        let mut network_nodes: HashMap<PublicKey, Node<M>> = HashMap::new();
+        let mut rng = StdRng::seed_from_u64(seed);
 
         macro_rules! add_node_to_network {
             ($network_nodes:expr, $node_pubkey:expr, $node_alias:expr, $channel:expr) => {
                 match $network_nodes.entry($node_pubkey) {
                     Entry::Vacant(e) => {
-                        let forward_manager = ForwardManager::new(params);
+                        let forward_manager = M::new_node_manager(
+                            deployment.mitigation(),
+                            deployment.params(&$node_pubkey, params),
+                            rng.random(),
+                            deployment.is_observe_only(&$node_pubkey),
+                        )?;
 
                         let _ = forward_manager.add_channel(
                             $channel.scid.into(),
@@ -146,7 +327,14 @@ where
                             None,
                         )?;
 
-                        e.insert(Node::new(forward_manager, $node_alias));
+                        let deployed = deployment.is_deployed(&$node_pubkey);
+                        let observe_only = deployment.is_observe_only(&$node_pubkey);
+                        e.insert(Node::new(
+                            forward_manager,
+                            $node_alias,
+                            deployed,
+                            observe_only,
+                        ));
                     }
                     Entry::Occupied(mut e) => {
                         let _ = e.get_mut().forward_manager.add_channel(
@@ -177,20 +365,33 @@ where
 
         Ok(Self {
             network_nodes: Arc::new(Mutex::new(network_nodes)),
+            block_clock: BlockClock::new(
+                clock.clone(),
+                params.reputation_params.expected_block_speed,
+            ),
             clock,
             results,
+            failure_reporter: None,
         })
     }
 
+    /// Creates an interceptor with reputation for each node in the network set from the snapshot provided. Forward
+    /// managers are seeded and configured in the same way as [`Self::new_for_network`].
     pub async fn new_from_snapshot(
         params: ForwardManagerParams,
+        deployment: &NetworkDeployment,
+        seed: u64,
         edges: &[NetworkParser],
-        reputation_snapshot: HashMap<PublicKey, HashMap<u64, ChannelSnapshot>>,
-        no_reputation: HashSet<PublicKey>,
+        snapshot: NetworkSnapshot,
         clock: Arc<SimulationClock>,
         results: Option<Arc<Mutex<R>>>,
     ) -> Result<Self, BoxError> {
+        let NetworkSnapshot {
+            reputation: reputation_snapshot,
+            no_reputation,
+        } = snapshot;
         let mut network_nodes = HashMap::with_capacity(reputation_snapshot.len());
+        let mut rng = StdRng::seed_from_u64(seed);
 
         let add_ins = clock.now();
         macro_rules! add_node_to_network {
@@ -230,14 +431,24 @@ where
 
                 match network_nodes.entry(pubkey) {
                     Entry::Vacant(e) => {
-                        let forward_manager = ForwardManager::new(params);
+                        let forward_manager = M::new_node_manager(
+                            deployment.mitigation(),
+                            deployment.params(&pubkey, params),
+                            rng.random(),
+                            deployment.is_observe_only(&pubkey),
+                        )?;
                         forward_manager.add_channel(
                             scid,
                             $channel.capacity_msat,
                             add_ins,
                             snapshot,
                         )?;
-                        e.insert(Node::new(forward_manager, alias));
+                        e.insert(Node::new(
+                            forward_manager,
+                            alias,
+                            deployment.is_deployed(&pubkey),
+                            deployment.is_observe_only(&pubkey),
+                        ));
                     }
                     Entry::Occupied(mut e) => {
                         e.get_mut().forward_manager.add_channel(
@@ -258,8 +469,13 @@ where
 
         Ok(Self {
             network_nodes: Arc::new(Mutex::new(network_nodes)),
+            block_clock: BlockClock::new(
+                clock.clone(),
+                params.reputation_params.expected_block_speed,
+            ),
             clock,
             results,
+            failure_reporter: None,
         })
     }
 
@@ -289,6 +505,8 @@ where
                 amount_out_msat: h.outgoing_amt,
                 expiry_in_height: h.incoming_expiry,
                 expiry_out_height: h.outgoing_expiry,
+                // Historical forwards are recorded with expiry heights relative to the height they were added at.
+                added_height: 0,
                 added_at: Some(
                     start_ins.sub(Duration::from_nanos(
                         bootstrap
@@ -357,6 +575,88 @@ where
 
         Ok(())
     }
+
+    /// Adds a shadow forward manager for each of the parameter sets provided to every node that has deployed the
+    /// mitigation. Shadows start with a copy of the node's current channel state, and record the outcome that they
+    /// would have given each htlc without affecting forwarding. Shadows should be added before any htlcs are
+    /// forwarded, so that their view of in-flight htlcs matches the primary manager's.
+    pub async fn add_shadow_managers(
+        &self,
+        shadows: &[(String, ForwardManagerParams)],
+        seed: u64,
+    ) -> Result<(), BoxError> {
+        let mut network_nodes = self.network_nodes.lock().await;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let add_ins = self.clock.now();
+
+        // Sort nodes and channels so that shadows are seeded deterministically.
+        let mut pubkeys: Vec<PublicKey> = network_nodes.keys().copied().collect();
+        pubkeys.sort();
+
+        for pubkey in pubkeys {
+            let node = network_nodes
+                .get_mut(&pubkey)
+                .ok_or(format!("node: {pubkey} not found"))?;
+            if !node.deployed {
+                continue;
+            }
+
+            let mut channels: Vec<(u64, ChannelSnapshot)> = node
+                .forward_manager
+                .list_channels(add_ins)?
+                .into_iter()
+                .collect();
+            channels.sort_by_key(|(scid, _)| *scid);
+
+            for (name, params) in shadows {
+                let forward_manager = ForwardManager::new(*params, rng.random());
+                for (scid, snapshot) in channels.iter() {
+                    forward_manager.add_channel(
+                        *scid,
+                        snapshot.capacity_msat,
+                        add_ins,
+                        Some(snapshot.clone()),
+                    )?;
+                }
+
+                node.shadows.push(ShadowManager {
+                    name: name.clone(),
+                    forward_manager,
+                    failed_htlcs: HashSet::new(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of htlcs that an observe-only node forwarded and would have failed, excluding htlcs that
+    /// were received on or forwarded to any of the channels provided.
+    pub async fn observed_failures(
+        &self,
+        node: &PublicKey,
+        exclude_channels: &HashSet<u64>,
+    ) -> Result<ObservedFailures, BoxError> {
+        let network_nodes = self.network_nodes.lock().await;
+        let observed = network_nodes
+            .get(node)
+            .ok_or(format!("node: {node} not found"))?
+            .observed
+            .as_ref()
+            .ok_or(format!("node: {node} is not observe-only"))?;
+
+        Ok(observed
+            .iter()
+            .filter(|((incoming, outgoing), _)| {
+                !exclude_channels.contains(incoming) && !exclude_channels.contains(outgoing)
+            })
+            .fold(ObservedFailures::default(), |acc, (_, counts)| {
+                ObservedFailures {
+                    forwards: acc.forwards + counts.forwards,
+                    would_fail: acc.would_fail + counts.would_fail,
+                }
+            }))
+    }
 }
 
 /// Simulation-specific helper trait for jamming resources on a channel in a specific direction.
@@ -390,14 +690,17 @@ where
         pubkey: &PublicKey,
         channel: u64,
     ) -> Result<(), BoxError> {
-        self.network_nodes
-            .lock()
-            .await
+        let mut network_nodes = self.network_nodes.lock().await;
+        let node = network_nodes
             .get_mut(pubkey)
-            .ok_or(format!("jammed node: {} not found", pubkey))?
-            .forward_manager
-            .general_jam_channel(channel)
-            .map_err(|e| e.into())
+            .ok_or(format!("jammed node: {} not found", pubkey))?;
+
+        node.forward_manager.general_jam_channel(channel)?;
+        for shadow in node.shadows.iter() {
+            shadow.forward_manager.general_jam_channel(channel)?;
+        }
+
+        Ok(())
     }
 
     async fn jam_congestion_resources(
@@ -405,22 +708,123 @@ where
         pubkey: &PublicKey,
         channel: u64,
     ) -> Result<(), BoxError> {
-        self.network_nodes
-            .lock()
-            .await
+        let mut network_nodes = self.network_nodes.lock().await;
+        let node = network_nodes
             .get_mut(pubkey)
-            .ok_or(format!("jammed node: {} not found", pubkey))?
-            .forward_manager
-            .congestion_jam_channel(channel)
-            .map_err(|e| e.into())
+            .ok_or(format!("jammed node: {} not found", pubkey))?;
+
+        node.forward_manager.congestion_jam_channel(channel)?;
+        for shadow in node.shadows.iter() {
+            shadow.forward_manager.congestion_jam_channel(channel)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Simulation-specific helper trait for closing channels in the network.
+#[async_trait]
+pub trait ChannelCloser {
+    /// Closes the channel provided, removing it from the forward managers of the nodes on both sides of the channel.
+    /// Any htlcs in flight on the channel are no longer tracked, as they're assumed to be resolved on-chain.
+    async fn close_channel(&self, channel: u64) -> Result<(), BoxError>;
+}
+
+#[async_trait]
+impl<R, M> ChannelCloser for ReputationInterceptor<R, M>
+where
+    R: ForwardReporter,
+    M: ReputationManager + SimulationDebugManager + Send,
+{
+    async fn close_channel(&self, channel: u64) -> Result<(), BoxError> {
+        let mut removed = 0;
+        for node in self.network_nodes.lock().await.values_mut() {
+            match node.forward_manager.remove_channel(channel) {
+                Ok(()) => removed += 1,
+                Err(ReputationError::ErrChannelNotFound(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            for shadow in node.shadows.iter_mut() {
+                match shadow.forward_manager.remove_channel(channel) {
+                    Ok(()) | Err(ReputationError::ErrChannelNotFound(_)) => {}
+                    Err(e) => return Err(e.into()),
+                }
+                shadow.failed_htlcs.retain(|h| h.channel_id != channel);
+            }
+        }
+
+        if removed == 0 {
+            return Err(format!("closed channel: {channel} not found").into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Simulation-specific helper trait for opening channels in the network.
+#[async_trait]
+pub trait ChannelOpener {
+    /// Opens a channel between the two nodes provided, adding it to the forward managers of both nodes with no prior
+    /// reputation or revenue.
+    async fn open_channel(
+        &self,
+        channel: u64,
+        capacity_msat: u64,
+        nodes: [PublicKey; 2],
+    ) -> Result<(), BoxError>;
+}
+
+#[async_trait]
+impl<R, M> ChannelOpener for ReputationInterceptor<R, M>
+where
+    R: ForwardReporter,
+    M: ReputationManager + SimulationDebugManager + Send,
+{
+    async fn open_channel(
+        &self,
+        channel: u64,
+        capacity_msat: u64,
+        nodes: [PublicKey; 2],
+    ) -> Result<(), BoxError> {
+        let mut network_nodes = self.network_nodes.lock().await;
+
+        // Check that both nodes are known before adding the channel so that we don't end up with a channel on one side.
+        if let Some(pubkey) = nodes.iter().find(|n| !network_nodes.contains_key(n)) {
+            return Err(format!("node: {pubkey} not found opening channel: {channel}").into());
+        }
+
+        let add_ins = self.clock.now();
+        for pubkey in nodes {
+            let node = network_nodes
+                .get_mut(&pubkey)
+                .ok_or(format!("node: {pubkey} not found"))?;
+
+            node.forward_manager
+                .add_channel(channel, capacity_msat, add_ins, None)?;
+            for shadow in node.shadows.iter() {
+                shadow
+                    .forward_manager
+                    .add_channel(channel, capacity_msat, add_ins, None)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
 impl<R, M> ReputationInterceptor<R, M>
 where
     R: ForwardReporter,
-    M: ReputationManager + SimulationDebugManager,
+    M: ReputationManager + ObservingManager + SimulationDebugManager,
 {
+    /// Sets a reporter that is notified of every htlc that a node's resource manager fails, excluding htlcs that are
+    /// replayed for bootstrapping.
+    pub fn with_failure_reporter(mut self, reporter: Arc<dyn FailureReporter>) -> Self {
+        self.failure_reporter = Some(reporter);
+        self
+    }
+
     /// Adds a htlc forward to the jamming interceptor, performing forwarding checks and returning the decided
     /// forwarding outcome for the htlc. Callers should fail if the outer result is an error, because an unexpected
     /// error has occurred.
@@ -438,19 +842,55 @@ where
             amount_out_msat: htlc_add.amount_out_msat,
             expiry_in_height: htlc_add.expiry_in_height,
             expiry_out_height: htlc_add.expiry_out_height,
+            added_height: htlc_add.added_height,
             added_at: htlc_add.added_at.unwrap_or(self.clock.now()),
             incoming_accountable: htlc_add.incoming_accountable,
             upgradable_accountability: htlc_add.upgradable_accountability,
         };
 
         // If the forwarding node can't be found, we've hit a critical error and can't proceed.
-        let (allocation_check, fwd_outcome, alias) =
+        let (allocation_check, fwd_outcome, shadow_outcomes, alias) =
             match network_lock.entry(htlc_add.forwarding_node) {
                 Entry::Occupied(mut e) => {
                     let node = e.get_mut();
+
+                    // Nodes that haven't deployed the mitigation don't apply any resource bucketing or track
+                    // reputation, and just relay the accountable signal that they received.
+                    if !node.deployed {
+                        log::info!(
+                            "Node {} (mitigation not deployed) forwarding: {}",
+                            node.alias,
+                            htlc,
+                        );
+                        return Ok(Ok(records_from_signal(htlc.incoming_accountable)));
+                    }
+
+                    let allocation_check = node.forward_manager.get_allocation_snapshot(&htlc)?;
+                    let observed_outcome = node.forward_manager.add_observed_htlc(&htlc)?;
+
+                    // Observe-only nodes always forward, so we track the outcome they would have given the htlc.
+                    // Htlcs that are replayed for bootstrapping are not counted.
+                    if let Some(observed) = node.observed.as_mut().filter(|_| report) {
+                        let counts = observed
+                            .entry((htlc.incoming_ref.channel_id, htlc.outgoing_channel_id))
+                            .or_default();
+                        counts.forwards += 1;
+                        if let ForwardingOutcome::Fail(_) = observed_outcome.would_be {
+                            counts.would_fail += 1;
+                        }
+                    }
+
+                    let fwd_outcome = observed_outcome.enforced;
+                    let shadow_outcomes = node
+                        .shadows
+                        .iter_mut()
+                        .map(|shadow| shadow.add_htlc(&htlc, &fwd_outcome))
+                        .collect::<Result<Vec<_>, _>>()?;
+
                     (
-                        node.forward_manager.get_allocation_snapshot(&htlc)?,
-                        node.forward_manager.add_htlc(&htlc)?,
+                        allocation_check,
+                        fwd_outcome,
+                        shadow_outcomes,
                         node.alias.to_string(),
                     )
                 }
@@ -465,11 +905,22 @@ where
 
         if let Some(r) = &self.results {
             if report {
-                r.lock()
-                    .await
+                let mut reporter = r.lock().await;
+                reporter
                     .report_forward(htlc_add.forwarding_node, allocation_check, htlc.clone())
                     .await
                     .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
+
+                if !shadow_outcomes.is_empty() {
+                    reporter
+                        .report_shadow_outcomes(
+                            htlc_add.forwarding_node,
+                            &fwd_outcome,
+                            &shadow_outcomes,
+                        )
+                        .await
+                        .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
+                }
             }
         }
 
@@ -485,6 +936,12 @@ where
                 Ok(Ok(records_from_signal(accountable_signal)))
             }
             ForwardingOutcome::Fail(reason) => {
+                if let Some(reporter) = self.failure_reporter.as_ref().filter(|_| report) {
+                    reporter
+                        .report_failure(htlc_add.forwarding_node, htlc.incoming_ref, &reason)
+                        .map_err(|e| ReputationError::ErrUnrecoverable(e.to_string()))?;
+                }
+
                 Ok(Err(ForwardingError::InterceptorError(reason.to_string())))
             }
         }
@@ -506,12 +963,30 @@ where
             .await
             .entry(resolved_htlc.forwarding_node)
         {
-            Entry::Occupied(mut e) => Ok(e.get_mut().forward_manager.resolve_htlc(
-                resolved_htlc.outgoing_channel_id,
-                resolved_htlc.incoming_htlc,
-                resolved_htlc.forward_resolution,
-                resolved_htlc.resolved_ins.unwrap_or(self.clock.now()),
-            )?),
+            // Htlcs forwarded by nodes that haven't deployed the mitigation were never added to their forward manager.
+            Entry::Occupied(e) if !e.get().deployed => Ok(()),
+            Entry::Occupied(mut e) => {
+                let node = e.get_mut();
+                let resolved_ins = resolved_htlc.resolved_ins.unwrap_or(self.clock.now());
+
+                node.forward_manager.resolve_htlc(
+                    resolved_htlc.outgoing_channel_id,
+                    resolved_htlc.incoming_htlc,
+                    resolved_htlc.forward_resolution,
+                    resolved_ins,
+                )?;
+
+                for shadow in node.shadows.iter_mut() {
+                    shadow.resolve_htlc(
+                        resolved_htlc.outgoing_channel_id,
+                        resolved_htlc.incoming_htlc,
+                        resolved_htlc.forward_resolution,
+                        resolved_ins,
+                    )?;
+                }
+
+                Ok(())
+            }
             Entry::Vacant(_) => Err(ReputationError::ErrUnrecoverable(format!(
                 "Node: {} not found",
                 resolved_htlc.forwarding_node
@@ -546,7 +1021,7 @@ where
 impl<R, M> Interceptor for ReputationInterceptor<R, M>
 where
     R: ForwardReporter,
-    M: ReputationManager + Send + Sync + SimulationDebugManager,
+    M: ReputationManager + ObservingManager + Send + Sync + SimulationDebugManager,
 {
     /// Implemented by HTLC interceptors that provide input on the resolution of HTLCs forwarded in the simulation.
     async fn intercept_htlc(
@@ -561,6 +1036,10 @@ where
             }
         };
 
+        // Htlc expiry heights are set relative to a zero block height by sim-ln, so we interpret them as relative to
+        // the current height.
+        let added_height = self.block_clock.current_height();
+
         self.inner_add_htlc(
             HtlcAdd {
                 forwarding_node: req.forwarding_node,
@@ -571,8 +1050,9 @@ where
                 outgoing_channel_id,
                 amount_in_msat: req.incoming_amount_msat,
                 amount_out_msat: req.outgoing_amount_msat,
-                expiry_in_height: req.incoming_expiry_height,
-                expiry_out_height: req.outgoing_expiry_height,
+                expiry_in_height: added_height.saturating_add(req.incoming_expiry_height),
+                expiry_out_height: added_height.saturating_add(req.outgoing_expiry_height),
+                added_height,
                 // We want to use our live clock to set the timestamp on this resolution.
                 added_at: None,
                 incoming_accountable: accountable_from_records(&req.incoming_custom_records),
@@ -620,8 +1100,9 @@ mod tests {
         ForwardManager, ForwardManagerParams, SimulationDebugManager,
     };
     use ln_resource_mgr::{
-        AccountableSignal, AllocationCheck, ChannelSnapshot, ForwardResolution, ForwardingOutcome,
-        HtlcRef, ProposedForward, ReputationError, ReputationManager, ReputationParams,
+        AccountableSignal, AllocationCheck, ChannelSnapshot, FailureReason, ForwardResolution,
+        ForwardingOutcome, HtlcRef, ProposedForward, ReputationError, ReputationManager,
+        ReputationParams,
     };
     use mockall::mock;
     use sim_cli::parsing::NetworkParser;
@@ -634,14 +1115,21 @@ mod tests {
     use tokio::sync::Mutex;
 
     use crate::analysis::BatchForwardWriter;
-    use crate::clock::InstantClock;
-    use crate::reputation_interceptor::{BootstrapForward, BootstrapRecords, ChannelJammer};
+    use crate::clock::{BlockClock, InstantClock};
+    use crate::deployment::NetworkDeployment;
+    use crate::mitigation::Mitigation;
+    use crate::reputation_interceptor::{
+        BootstrapForward, BootstrapRecords, ChannelCloser, ChannelJammer, ChannelOpener,
+    };
     use crate::test_utils::{
         get_random_keypair, setup_test_edge, setup_test_request, test_allocation_check,
     };
     use crate::{accountable_from_records, BoxError};
 
-    use super::{Node, ReputationInterceptor, ReputationMonitor};
+    use super::{
+        FailureReporter, NetworkSnapshot, Node, ObservedFailures, ObservingManager,
+        ReputationInterceptor, ReputationMonitor,
+    };
 
     mock! {
         ForwardManager{}
@@ -687,11 +1175,14 @@ mod tests {
         }
     }
 
+    impl ObservingManager for MockForwardManager {}
+
     /// Creates a test interceptor with three nodes in the network and a vector of their public keys.
     fn setup_test_interceptor() -> (
         ReputationInterceptor<BatchForwardWriter, MockForwardManager>,
         Vec<PublicKey>,
     ) {
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
         let pubkeys = vec![
             get_random_keypair().1,
             get_random_keypair().1,
@@ -704,6 +1195,9 @@ mod tests {
                 Node {
                     forward_manager: MockForwardManager::new(),
                     alias: "0".to_string(),
+                    deployed: true,
+                    shadows: vec![],
+                    observed: None,
                 },
             ),
             (
@@ -711,6 +1205,9 @@ mod tests {
                 Node {
                     forward_manager: MockForwardManager::new(),
                     alias: "1".to_string(),
+                    deployed: true,
+                    shadows: vec![],
+                    observed: None,
                 },
             ),
             (
@@ -718,6 +1215,9 @@ mod tests {
                 Node {
                     forward_manager: MockForwardManager::new(),
                     alias: "2".to_string(),
+                    deployed: true,
+                    shadows: vec![],
+                    observed: None,
                 },
             ),
         ]);
@@ -725,8 +1225,10 @@ mod tests {
         (
             ReputationInterceptor {
                 network_nodes: Arc::new(Mutex::new(nodes)),
-                clock: Arc::new(SimulationClock::new(1).unwrap()),
+                clock: clock.clone(),
+                block_clock: BlockClock::new(clock, None),
                 results: None,
+                failure_reporter: None,
             },
             pubkeys,
         )
@@ -821,6 +1323,44 @@ mod tests {
         assert!(accountable_from_records(&res) == AccountableSignal::Accountable);
     }
 
+    /// Tests that nodes that have not deployed the mitigation forward htlcs without consulting their forward manager,
+    /// relaying the accountable signal that they received.
+    #[tokio::test]
+    async fn test_forward_not_deployed() {
+        let (interceptor, pubkeys) = setup_test_interceptor();
+        interceptor
+            .network_nodes
+            .lock()
+            .await
+            .get_mut(&pubkeys[0])
+            .unwrap()
+            .deployed = false;
+
+        // The mock forward manager has no expectations set, so will panic if it's called.
+        for signal in [
+            AccountableSignal::Accountable,
+            AccountableSignal::Unaccountable,
+        ] {
+            let request = setup_test_request(pubkeys[0], 0, 1, signal);
+            let res = interceptor
+                .intercept_htlc(request.clone())
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(accountable_from_records(&res) == signal);
+
+            interceptor
+                .notify_resolution(InterceptResolution {
+                    forwarding_node: pubkeys[0],
+                    incoming_htlc: request.incoming_htlc,
+                    outgoing_channel_id: request.outgoing_channel_id,
+                    success: true,
+                })
+                .await
+                .unwrap();
+        }
+    }
+
     /// Tests interception of a htlc that should be forwarded as unaccountable.
     #[tokio::test]
     async fn test_forward_unaccountable_htlc() {
@@ -853,6 +1393,76 @@ mod tests {
         assert!(accountable_from_records(&res) == AccountableSignal::Unaccountable);
     }
 
+    #[derive(Default)]
+    struct TestFailureReporter {
+        failures: std::sync::Mutex<Vec<(PublicKey, HtlcRef, FailureReason)>>,
+    }
+
+    impl FailureReporter for TestFailureReporter {
+        fn report_failure(
+            &self,
+            forwarding_node: PublicKey,
+            incoming_ref: HtlcRef,
+            reason: &FailureReason,
+        ) -> Result<(), CriticalError> {
+            self.failures
+                .lock()
+                .unwrap()
+                .push((forwarding_node, incoming_ref, reason.clone()));
+            Ok(())
+        }
+
+        fn report_closed_channel(
+            &self,
+            _forwarding_node: PublicKey,
+            _incoming_ref: HtlcRef,
+        ) -> Result<(), CriticalError> {
+            Ok(())
+        }
+    }
+
+    /// Tests that htlcs failed by a node's forward manager are reported with their failure reason.
+    #[tokio::test]
+    async fn test_failure_reporter() {
+        let (interceptor, pubkeys) = setup_test_interceptor();
+        let reporter = Arc::new(TestFailureReporter::default());
+        let interceptor = interceptor.with_failure_reporter(reporter.clone());
+        let request = setup_test_request(pubkeys[0], 0, 1, AccountableSignal::Unaccountable);
+
+        interceptor
+            .network_nodes
+            .lock()
+            .await
+            .get_mut(&pubkeys[0])
+            .unwrap()
+            .forward_manager
+            .expect_get_allocation_snapshot()
+            .return_once(|_| Ok(test_allocation_check(false)));
+
+        interceptor
+            .network_nodes
+            .lock()
+            .await
+            .get_mut(&pubkeys[0])
+            .unwrap()
+            .forward_manager
+            .expect_add_htlc()
+            .return_once(|_| Ok(ForwardingOutcome::Fail(FailureReason::NoReputation)));
+
+        assert!(interceptor.intercept_htlc(request).await.unwrap().is_err());
+        assert_eq!(
+            *reporter.failures.lock().unwrap(),
+            vec![(
+                pubkeys[0],
+                HtlcRef {
+                    channel_id: 0,
+                    htlc_index: 0,
+                },
+                FailureReason::NoReputation,
+            )]
+        );
+    }
+
     /// Tests that we do not notify resolution of last hop htlcs.
     #[tokio::test]
     async fn test_final_hop_notify() {
@@ -974,6 +1584,8 @@ mod tests {
         let interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
             ReputationInterceptor::new_for_network(
                 params,
+                &NetworkDeployment::default(),
+                0,
                 &edges,
                 Arc::new(SimulationClock::new(1).unwrap()),
                 None,
@@ -1008,6 +1620,47 @@ mod tests {
         assert!(carol_channels.contains_key(&edges[1].scid.into()));
     }
 
+    /// Tests that interceptors created with the same seed make the same forwarding decisions when general bucket slots
+    /// are contested by multiple outgoing channels, which depends on the salt used to assign slots.
+    #[tokio::test]
+    async fn test_seeded_forwarding_decisions() {
+        let bob = get_random_keypair().1;
+        let peers: Vec<PublicKey> = (0..4).map(|_| get_random_keypair().1).collect();
+        let edges: Vec<NetworkParser> = peers
+            .iter()
+            .enumerate()
+            .map(|(i, peer)| setup_test_edge(ShortChannelID::from(i as u64), *peer, bob))
+            .collect();
+
+        let mut decisions = vec![];
+        for _ in 0..2 {
+            let interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
+                ReputationInterceptor::new_for_network(
+                    ForwardManagerParams::default(),
+                    &NetworkDeployment::default(),
+                    42,
+                    &edges,
+                    Arc::new(SimulationClock::new(1).unwrap()),
+                    None,
+                )
+                .unwrap();
+
+            // Send unaccountable htlcs from the first peer to each of the others in turn, so that each outgoing
+            // channel competes for general bucket slots on the incoming channel.
+            let mut outcomes = vec![];
+            for i in 0..90 {
+                let mut request =
+                    setup_test_request(bob, 0, 1 + i % 3, AccountableSignal::Unaccountable);
+                request.incoming_htlc.index = i;
+                outcomes.push(interceptor.intercept_htlc(request).await.unwrap().is_ok());
+            }
+            decisions.push(outcomes);
+        }
+
+        assert!(decisions[0].iter().any(|ok| !ok));
+        assert_eq!(decisions[0], decisions[1]);
+    }
+
     /// Tests that nodes marked to be general jammed appropriately have their general resources slashed, but are still
     /// able to bootstrap reputation.
     #[tokio::test]
@@ -1036,6 +1689,8 @@ mod tests {
         let mut interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
             ReputationInterceptor::new_for_network(
                 params,
+                &NetworkDeployment::default(),
+                0,
                 &edges,
                 Arc::new(SimulationClock::new(1).unwrap()),
                 None,
@@ -1128,6 +1783,293 @@ mod tests {
         assert!(res.is_err());
     }
 
+    /// Tests that shadow managers see every htlc that a node forwards without affecting its forwarding outcome, and
+    /// that htlcs that a shadow would have failed are not resolved in the shadow.
+    #[tokio::test]
+    async fn test_shadow_managers() {
+        let (params, edges, _) = setup_three_hop_network_edges();
+        let bob = edges[0].node_2.pubkey;
+        let alice_to_bob: u64 = edges[0].scid.into();
+        let bob_to_carol: u64 = edges[1].scid.into();
+
+        let interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
+            ReputationInterceptor::new_for_network(
+                params,
+                &NetworkDeployment::default(),
+                0,
+                &edges,
+                Arc::new(SimulationClock::new(1).unwrap()),
+                None,
+            )
+            .unwrap();
+
+        let smaller_general = ForwardManagerParams {
+            general_slot_portion: 20,
+            general_liquidity_portion: 20,
+            ..params
+        };
+        interceptor
+            .add_shadow_managers(
+                &[
+                    ("jammed".to_string(), params),
+                    ("smaller_general".to_string(), smaller_general),
+                ],
+                0,
+            )
+            .await
+            .unwrap();
+
+        // Jam the shared resources of Bob's channel with Alice in the first shadow only, so that it will fail
+        // unaccountable htlcs from Alice while the primary manager forwards them.
+        {
+            let network_nodes = interceptor.network_nodes.lock().await;
+            let shadow = &network_nodes.get(&bob).unwrap().shadows[0];
+            shadow
+                .forward_manager
+                .general_jam_channel(alice_to_bob)
+                .unwrap();
+            shadow
+                .forward_manager
+                .congestion_jam_channel(alice_to_bob)
+                .unwrap();
+        }
+
+        for node in interceptor.network_nodes.lock().await.values() {
+            assert_eq!(node.shadows.len(), 2);
+            assert_eq!(
+                node.shadows[0]
+                    .forward_manager
+                    .list_channels(Instant::now())
+                    .unwrap()
+                    .len(),
+                node.forward_manager
+                    .list_channels(Instant::now())
+                    .unwrap()
+                    .len(),
+            );
+        }
+
+        let request = setup_test_request(
+            bob,
+            alice_to_bob,
+            bob_to_carol,
+            AccountableSignal::Unaccountable,
+        );
+        let res = interceptor
+            .intercept_htlc(request.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(accountable_from_records(&res) == AccountableSignal::Unaccountable);
+
+        {
+            let network_nodes = interceptor.network_nodes.lock().await;
+            let shadows = &network_nodes.get(&bob).unwrap().shadows;
+            assert_eq!(shadows[0].failed_htlcs.len(), 1);
+            assert!(shadows[1].failed_htlcs.is_empty());
+        }
+
+        // Resolving the htlc should succeed in the primary and the shadow that forwarded it, and clear the htlc from
+        // the shadow that failed it.
+        interceptor
+            .notify_resolution(InterceptResolution {
+                forwarding_node: request.forwarding_node,
+                incoming_htlc: request.incoming_htlc,
+                outgoing_channel_id: request.outgoing_channel_id,
+                success: true,
+            })
+            .await
+            .unwrap();
+
+        let network_nodes = interceptor.network_nodes.lock().await;
+        assert!(network_nodes.get(&bob).unwrap().shadows[0]
+            .failed_htlcs
+            .is_empty());
+    }
+
+    /// Tests that observe-only nodes forward htlcs that they would have failed, counting the failures for channels
+    /// that aren't excluded.
+    #[tokio::test]
+    async fn test_observe_only() {
+        let (params, edges, _) = setup_three_hop_network_edges();
+        let alice = edges[0].node_1.pubkey;
+        let bob = edges[0].node_2.pubkey;
+        let alice_to_bob: u64 = edges[0].scid.into();
+        let bob_to_carol: u64 = edges[1].scid.into();
+
+        let mut deployment = NetworkDeployment::new(
+            HashMap::new(),
+            &edges,
+            params,
+            Mitigation::Reputation,
+            None,
+            0,
+            &[],
+        )
+        .unwrap();
+        deployment.set_observe_only(bob).unwrap();
+
+        let interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
+            ReputationInterceptor::new_for_network(
+                params,
+                &deployment,
+                0,
+                &edges,
+                Arc::new(SimulationClock::new(1).unwrap()),
+                None,
+            )
+            .unwrap();
+
+        // With all shared resources jammed, an unaccountable htlc would fail but is forwarded with its signal.
+        interceptor
+            .jam_general_resources(&bob, alice_to_bob)
+            .await
+            .unwrap();
+        interceptor
+            .jam_congestion_resources(&bob, alice_to_bob)
+            .await
+            .unwrap();
+
+        let request = setup_test_request(
+            bob,
+            alice_to_bob,
+            bob_to_carol,
+            AccountableSignal::Unaccountable,
+        );
+        let res = interceptor
+            .intercept_htlc(request.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(accountable_from_records(&res) == AccountableSignal::Unaccountable);
+
+        // The htlc is tracked, so it can be resolved.
+        interceptor
+            .notify_resolution(InterceptResolution {
+                forwarding_node: request.forwarding_node,
+                incoming_htlc: request.incoming_htlc,
+                outgoing_channel_id: request.outgoing_channel_id,
+                success: true,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            interceptor
+                .observed_failures(&bob, &HashSet::new())
+                .await
+                .unwrap(),
+            ObservedFailures {
+                forwards: 1,
+                would_fail: 1,
+            }
+        );
+        assert_eq!(
+            interceptor
+                .observed_failures(&bob, &HashSet::from([alice_to_bob]))
+                .await
+                .unwrap(),
+            ObservedFailures::default(),
+        );
+
+        // Nodes that enforce their mitigation don't track observed failures.
+        assert!(interceptor
+            .observed_failures(&alice, &HashSet::new())
+            .await
+            .is_err());
+    }
+
+    /// Tests that closing a channel removes it from the nodes on both sides of the channel.
+    #[tokio::test]
+    async fn test_close_channel() {
+        let (params, edges, _) = setup_three_hop_network_edges();
+        let bob_to_carol: u64 = edges[1].scid.into();
+
+        let interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
+            ReputationInterceptor::new_for_network(
+                params,
+                &NetworkDeployment::default(),
+                0,
+                &edges,
+                Arc::new(SimulationClock::new(1).unwrap()),
+                None,
+            )
+            .unwrap();
+
+        interceptor.close_channel(bob_to_carol).await.unwrap();
+
+        for pubkey in [edges[1].node_1.pubkey, edges[1].node_2.pubkey] {
+            let channels = interceptor
+                .list_channels(pubkey, Instant::now())
+                .await
+                .unwrap();
+            assert!(!channels.contains_key(&bob_to_carol));
+        }
+
+        // Alice's channel with Bob is unaffected.
+        let alice_channels = interceptor
+            .list_channels(edges[0].node_1.pubkey, Instant::now())
+            .await
+            .unwrap();
+        assert!(alice_channels.contains_key(&edges[0].scid.into()));
+
+        assert!(interceptor.close_channel(bob_to_carol).await.is_err());
+    }
+
+    /// Tests that opening a channel adds it to the nodes on both sides of the channel, and that channels can't be
+    /// opened with unknown nodes.
+    #[tokio::test]
+    async fn test_open_channel() {
+        let (params, edges, _) = setup_three_hop_network_edges();
+        let alice = edges[0].node_1.pubkey;
+        let carol = edges[1].node_2.pubkey;
+        let alice_to_carol = 3;
+
+        let interceptor: ReputationInterceptor<BatchForwardWriter, ForwardManager> =
+            ReputationInterceptor::new_for_network(
+                params,
+                &NetworkDeployment::default(),
+                0,
+                &edges,
+                Arc::new(SimulationClock::new(1).unwrap()),
+                None,
+            )
+            .unwrap();
+
+        interceptor
+            .open_channel(alice_to_carol, 500_000, [alice, carol])
+            .await
+            .unwrap();
+
+        for pubkey in [alice, carol] {
+            let channels = interceptor
+                .list_channels(pubkey, Instant::now())
+                .await
+                .unwrap();
+            assert_eq!(
+                channels.get(&alice_to_carol).unwrap().capacity_msat,
+                500_000
+            );
+        }
+
+        // A channel that is already open can't be opened again.
+        assert!(interceptor
+            .open_channel(alice_to_carol, 500_000, [alice, carol])
+            .await
+            .is_err());
+
+        // Opening a channel with an unknown node fails without adding the channel to the known node.
+        assert!(interceptor
+            .open_channel(4, 500_000, [alice, get_random_keypair().1])
+            .await
+            .is_err());
+        let alice_channels = interceptor
+            .list_channels(alice, Instant::now())
+            .await
+            .unwrap();
+        assert!(!alice_channels.contains_key(&4));
+    }
+
     /// Tests starting interceptor from valid snapshot.
     #[tokio::test]
     async fn test_new_from_snapshot() {
@@ -1139,9 +2081,13 @@ mod tests {
             BoxError,
         > = ReputationInterceptor::new_from_snapshot(
             params,
+            &NetworkDeployment::default(),
+            0,
             &edges,
-            reputation_snapshot.clone(),
-            HashSet::new(),
+            NetworkSnapshot {
+                reputation: reputation_snapshot.clone(),
+                no_reputation: HashSet::new(),
+            },
             clock.clone(),
             None,
         )
@@ -1203,9 +2149,13 @@ mod tests {
             BoxError,
         > = ReputationInterceptor::new_from_snapshot(
             params,
+            &NetworkDeployment::default(),
+            0,
             &edges,
-            reputation_snapshot,
-            HashSet::new(),
+            NetworkSnapshot {
+                reputation: reputation_snapshot,
+                no_reputation: HashSet::new(),
+            },
             Arc::new(SimulationClock::new(1).unwrap()),
             None,
         )
@@ -1231,9 +2181,13 @@ mod tests {
             BoxError,
         > = ReputationInterceptor::new_from_snapshot(
             params,
+            &NetworkDeployment::default(),
+            0,
             &edges,
-            reputation_snapshot,
-            HashSet::new(),
+            NetworkSnapshot {
+                reputation: reputation_snapshot,
+                no_reputation: HashSet::new(),
+            },
             Arc::new(SimulationClock::new(1).unwrap()),
             None,
         )
@@ -1260,9 +2214,13 @@ mod tests {
             BoxError,
         > = ReputationInterceptor::new_from_snapshot(
             params,
+            &NetworkDeployment::default(),
+            0,
             &edges,
-            reputation_snapshot,
-            HashSet::new(),
+            NetworkSnapshot {
+                reputation: reputation_snapshot,
+                no_reputation: HashSet::new(),
+            },
             Arc::new(SimulationClock::new(1).unwrap()),
             None,
         )
@@ -1287,9 +2245,13 @@ mod tests {
             BoxError,
         > = ReputationInterceptor::new_from_snapshot(
             params,
+            &NetworkDeployment::default(),
+            0,
             &edges,
-            reputation_snapshot,
-            HashSet::new(),
+            NetworkSnapshot {
+                reputation: reputation_snapshot,
+                no_reputation: HashSet::new(),
+            },
             Arc::new(SimulationClock::new(1).unwrap()),
             None,
         )
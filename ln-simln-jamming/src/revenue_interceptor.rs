@@ -1,14 +1,17 @@
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::fs::File;
 use std::ops::{Add, Sub};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use bitcoin::secp256k1::PublicKey;
+use csv::WriterBuilder;
 use ln_resource_mgr::HtlcRef;
+use serde::{Deserialize, Serialize};
 use simln_lib::clock::{Clock, SimulationClock};
 use simln_lib::sim_node::{
     CriticalError, CustomRecords, ForwardingError, InterceptRequest, InterceptResolution,
@@ -19,32 +22,52 @@ use tokio::sync::Mutex;
 use triggered::Listener;
 
 use crate::clock::InstantClock;
+use crate::collateral::NodeRevenueChange;
 use crate::parsing::peacetime_from_file;
 use crate::BoxError;
 
-/// Tracks revenue for a target node under attack and in peacetime, shutting down the simulation if the target node
-/// loses revenue under attack compared to peacetime.
+/// Tracks revenue for the target nodes, and any other nodes that are monitored for collateral damage, under attack and
+/// in peacetime.
 pub struct RevenueInterceptor {
     clock: Arc<SimulationClock>,
-    target_node: PublicKey,
-    target_revenue: Mutex<NodeRevenue>,
+    /// Revenue earned in the simulation by each monitored node, always including the targets.
+    node_revenue: Mutex<HashMap<PublicKey, NodeRevenue>>,
     peacetime_revenue: Mutex<PeacetimeRevenue>,
     start_ins: Instant,
     listener: Listener,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// A pair of a node's channels that a htlc was forwarded over, expressed as (incoming, outgoing) channel ids.
+pub type ChannelPair = (u64, u64);
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 struct NodeRevenue {
     revenue_total: u64,
-    /// Tracks pending htlcs on the target node so that we can report fees once we know how they have resolved.
-    pending_htlcs: HashMap<HtlcRef, u64>,
+    /// On-chain fees that the node paid to force close channels, which are deducted from its revenue.
+    force_close_cost_msat: u64,
+    /// Revenue earned by each pair of channels since the simulation started, excluding any bootstrapped revenue.
+    pair_revenue: HashMap<ChannelPair, u64>,
+    /// Tracks pending htlcs on the node so that we can report fees once we know how they have resolved.
+    pending_htlcs: HashMap<HtlcRef, (u64, ChannelPair)>,
+}
+
+impl NodeRevenue {
+    /// The revenue that the node has earned in the simulation, less the on-chain fees that it has paid to force close
+    /// channels. Floored at zero, as revenue is tracked in unsigned amounts.
+    fn simulation_revenue_msat(&self) -> u64 {
+        self.revenue_total
+            .saturating_sub(self.force_close_cost_msat)
+    }
 }
 
 /// Minimally represents a forwarding event for a node.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct RevenueEvent {
+    pub forwarding_node: PublicKey,
     pub timestamp_ns: u64,
     pub fee_msat: u64,
+    pub incoming_channel: u64,
+    pub outgoing_channel: u64,
 }
 
 // Reverse the ordering to create a min-heap based on timestamp_ns.
@@ -60,96 +83,147 @@ impl PartialOrd for RevenueEvent {
     }
 }
 
-/// Polls current revenue against the projected revenue for the target node in times of peace.
+/// Polls current revenue against the projected revenue for a target node in times of peace.
 #[async_trait]
 pub trait PeacetimeRevenueMonitor {
-    async fn get_revenue_difference(&self) -> RevenueSnapshot;
+    async fn get_revenue_difference(&self, target: &PublicKey) -> RevenueSnapshot;
 }
 
-/// Responsible for tracking what the target node's revenue would be in a peacetime network (without the attacker).
+/// Charges the on-chain fees paid to force close a channel to the node that closed it.
+#[async_trait]
+pub trait ForceCloseRecorder {
+    async fn record_force_close(&self, closing_node: &PublicKey, cost_msat: u64);
+}
+
+/// Responsible for tracking what monitored nodes' revenue would be in a peacetime network (without the attacker).
 #[derive(Clone, Debug)]
 struct PeacetimeRevenue {
-    /// Represents the revenue of the target node in peacetime (a network without the attacker).
-    peacetime_revenue: u64,
+    targets: HashSet<PublicKey>,
+
+    /// Represents the revenue of each monitored node in peacetime (a network without the attacker). Only the targets'
+    /// revenue includes revenue accumulated for the bootstrap period.
+    node_revenue: HashMap<PublicKey, u64>,
+
+    /// Peacetime revenue for each of the targets' pairs of channels that has been replayed alongside the simulation,
+    /// excluding revenue accumulated for the bootstrap period.
+    pair_revenue: HashMap<PublicKey, HashMap<ChannelPair, u64>>,
 
     /// A queue of peacetime revenue events that need to be replayed with the simulation to compare peace and attack
     /// time revenue.
     revenue_events: BinaryHeap<RevenueEvent>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct RevenueSnapshot {
     pub peacetime_revenue_msat: u64,
+    /// Revenue earned in the simulation, less the on-chain fees paid to force close channels.
     pub simulation_revenue_msat: u64,
+    /// On-chain fees paid to force close channels, which are deducted from simulation revenue.
+    pub force_close_cost_msat: u64,
     pub runtime: Duration,
 }
 
 impl PeacetimeRevenue {
     async fn new_with_bootstrap(
-        target_pubkey: PublicKey,
+        targets: HashSet<PublicKey>,
+        monitored_nodes: &HashSet<PublicKey>,
         revenue_file: PathBuf,
         bootstrap_duration: Option<Duration>,
     ) -> Result<Self, BoxError> {
-        let mut peacetime_activity = peacetime_from_file(&revenue_file, target_pubkey)
+        let mut peacetime_activity = peacetime_from_file(&revenue_file, monitored_nodes)
             .await
             .map_err(|e| format!("could not read peacetime projections: {}", e))?;
 
-        // Grab the first event to get our starting timestamp, push the event back on so that we can process it.
-        let first_event = peacetime_activity
-            .peek()
-            .ok_or("should have at least one forward for target node".to_string())?;
-
-        let bootstrap = match bootstrap_duration {
-            Some(bootstrap) => bootstrap,
-            None => {
-                // If the attacker did not bootstrap any reputation, we don't need to "catch up"
-                // our peacetime projections with any period of time - we can just start fresh.
-                return Ok(PeacetimeRevenue {
-                    peacetime_revenue: 0,
-                    revenue_events: peacetime_activity,
-                });
-            }
-        };
-        let cutoff_ns = first_event.timestamp_ns.add(bootstrap.as_nanos() as u64);
-
-        // Accumulate the starting peacetime revenue, defined by the period of time that we bootstrapped the simulation
-        // from, so that we're on the same starting point.
-        let mut peacetime_revenue: u64 = 0;
-        while let Some(event) = peacetime_activity.peek() {
-            if event.timestamp_ns >= cutoff_ns {
-                break;
+        // Grab the targets' first event to get our starting timestamp.
+        let first_target_ns = first_event_ns(&peacetime_activity, &targets)
+            .ok_or("should have at least one forward for target nodes".to_string())?;
+
+        let mut node_revenue: HashMap<PublicKey, u64> =
+            monitored_nodes.iter().map(|n| (*n, 0)).collect();
+
+        // If the attacker did not bootstrap any reputation, we don't need to "catch up" our peacetime projections with
+        // any period of time - we can just start fresh. Otherwise, accumulate the targets' starting peacetime revenue,
+        // defined by the period of time that we bootstrapped the simulation from, so that we're on the same starting
+        // point.
+        if let Some(bootstrap) = bootstrap_duration {
+            let cutoff_ns = first_target_ns.add(bootstrap.as_nanos() as u64);
+
+            while let Some(event) = peacetime_activity.peek() {
+                if event.timestamp_ns >= cutoff_ns {
+                    break;
+                }
+
+                if targets.contains(&event.forwarding_node) {
+                    *node_revenue.entry(event.forwarding_node).or_default() += event.fee_msat;
+                }
+                peacetime_activity.pop();
             }
+        }
 
-            peacetime_revenue += event.fee_msat;
-            peacetime_activity.pop();
+        // Peacetime is replayed from the targets' first remaining event, so other nodes' events before that point are
+        // dropped to compare all nodes over the same period.
+        if let Some(start_ns) = first_event_ns(&peacetime_activity, &targets) {
+            while peacetime_activity
+                .peek()
+                .is_some_and(|e| e.timestamp_ns < start_ns)
+            {
+                peacetime_activity.pop();
+            }
         }
 
         Ok(PeacetimeRevenue {
-            peacetime_revenue,
+            targets,
+            node_revenue,
+            pair_revenue: HashMap::new(),
             revenue_events: peacetime_activity,
         })
     }
 }
 
+/// Returns the timestamp of the earliest event for any of the nodes provided.
+fn first_event_ns(events: &BinaryHeap<RevenueEvent>, nodes: &HashSet<PublicKey>) -> Option<u64> {
+    events
+        .iter()
+        .filter(|e| nodes.contains(&e.forwarding_node))
+        .map(|e| e.timestamp_ns)
+        .min()
+}
+
 impl RevenueInterceptor {
+    /// Creates an interceptor that tracks revenue for the targets and the other nodes provided. Bootstrapped revenue
+    /// is only tracked for the targets, so other nodes start with zero revenue in the simulation and peacetime. Any
+    /// target without bootstrapped revenue starts with zero revenue.
+    #[allow(clippy::too_many_arguments)]
     pub async fn new_with_bootstrap(
         clock: Arc<SimulationClock>,
-        target_pubkey: PublicKey,
-        bootstrap_revenue: u64,
+        targets: &[PublicKey],
+        monitored_nodes: &[PublicKey],
+        bootstrap_revenue: &HashMap<PublicKey, u64>,
         bootstrap_duration: Option<Duration>,
         revenue_file: PathBuf,
         listener: Listener,
     ) -> Result<Self, BoxError> {
+        let targets: HashSet<PublicKey> = targets.iter().copied().collect();
+        let nodes: HashSet<PublicKey> = monitored_nodes
+            .iter()
+            .chain(targets.iter())
+            .copied()
+            .collect();
+
+        let mut node_revenue: HashMap<PublicKey, NodeRevenue> =
+            nodes.iter().map(|n| (*n, NodeRevenue::default())).collect();
+        for target in targets.iter() {
+            node_revenue.entry(*target).or_default().revenue_total =
+                bootstrap_revenue.get(target).copied().unwrap_or_default();
+        }
+
         Ok(Self {
             clock: clock.clone(),
-            target_node: target_pubkey,
-            target_revenue: Mutex::new(NodeRevenue {
-                revenue_total: bootstrap_revenue,
-                pending_htlcs: HashMap::new(),
-            }),
+            node_revenue: Mutex::new(node_revenue),
             peacetime_revenue: Mutex::new(
                 PeacetimeRevenue::new_with_bootstrap(
-                    target_pubkey,
+                    targets,
+                    &nodes,
                     revenue_file,
                     bootstrap_duration,
                 )
@@ -189,22 +263,265 @@ impl RevenueInterceptor {
                 _ = self.clock.sleep(Duration::from_nanos(wait)) => {},
             }
 
-            self.peacetime_revenue.lock().await.peacetime_revenue += next_event.fee_msat;
+            let mut peacetime_lock = self.peacetime_revenue.lock().await;
+            *peacetime_lock
+                .node_revenue
+                .entry(next_event.forwarding_node)
+                .or_default() += next_event.fee_msat;
+
+            if peacetime_lock.targets.contains(&next_event.forwarding_node) {
+                *peacetime_lock
+                    .pair_revenue
+                    .entry(next_event.forwarding_node)
+                    .or_default()
+                    .entry((next_event.incoming_channel, next_event.outgoing_channel))
+                    .or_default() += next_event.fee_msat;
+            }
+        }
+    }
+
+    /// Returns the revenue that each monitored node has earned in the simulation and in peacetime.
+    pub async fn get_node_revenue(&self) -> HashMap<PublicKey, NodeRevenueChange> {
+        let peacetime = self.peacetime_revenue.lock().await;
+
+        self.node_revenue
+            .lock()
+            .await
+            .iter()
+            .map(|(node, revenue)| {
+                (
+                    *node,
+                    NodeRevenueChange {
+                        peacetime_revenue_msat: peacetime
+                            .node_revenue
+                            .get(node)
+                            .copied()
+                            .unwrap_or_default(),
+                        simulation_revenue_msat: revenue.simulation_revenue_msat(),
+                        force_close_cost_msat: revenue.force_close_cost_msat,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the revenue that each pair of a target's channels has earned since the simulation started, alongside
+    /// the revenue that it earned in the same period of peacetime. Bootstrapped revenue is not included, because it is
+    /// not tracked per pair.
+    pub async fn get_pair_revenue(&self, target: &PublicKey) -> Vec<PairRevenue> {
+        let mut pairs: BTreeMap<ChannelPair, PairRevenue> = BTreeMap::new();
+
+        let peacetime = self.peacetime_revenue.lock().await;
+        for (pair, fee) in peacetime.pair_revenue.get(target).into_iter().flatten() {
+            pairs
+                .entry(*pair)
+                .or_insert_with(|| PairRevenue::new(*pair))
+                .peacetime_revenue_msat += fee;
         }
+
+        let node_revenue = self.node_revenue.lock().await;
+        let target_pairs = node_revenue
+            .get(target)
+            .map(|r| &r.pair_revenue)
+            .into_iter()
+            .flatten();
+
+        for (pair, fee) in target_pairs {
+            pairs
+                .entry(*pair)
+                .or_insert_with(|| PairRevenue::new(*pair))
+                .simulation_revenue_msat += fee;
+        }
+
+        pairs.into_values().collect()
     }
 }
 
+/// The revenue that a pair of a target's channels earned in the simulation, and in the same period of peacetime.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct PairRevenue {
+    pub incoming_channel: u64,
+    pub outgoing_channel: u64,
+    pub peacetime_revenue_msat: u64,
+    pub simulation_revenue_msat: u64,
+}
+
+impl PairRevenue {
+    fn new(pair: ChannelPair) -> Self {
+        PairRevenue {
+            incoming_channel: pair.0,
+            outgoing_channel: pair.1,
+            ..Default::default()
+        }
+    }
+}
+
+/// The revenue that one of a target's channels earned as the incoming and outgoing channel of its forwards. Each
+/// forward's fee is attributed to both of the channels that it used.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChannelRevenue {
+    pub incoming_peacetime_msat: u64,
+    pub incoming_simulation_msat: u64,
+    pub outgoing_peacetime_msat: u64,
+    pub outgoing_simulation_msat: u64,
+}
+
+impl ChannelRevenue {
+    /// The difference between the channel's simulation and peacetime revenue, negative if the channel lost revenue.
+    pub fn change_msat(&self) -> i64 {
+        (self.incoming_simulation_msat + self.outgoing_simulation_msat) as i64
+            - (self.incoming_peacetime_msat + self.outgoing_peacetime_msat) as i64
+    }
+}
+
+/// Aggregates pair revenue by channel, including any of the channels provided that did not earn any revenue.
+pub fn channel_revenue(
+    pairs: &[PairRevenue],
+    channels: impl Iterator<Item = u64>,
+) -> BTreeMap<u64, ChannelRevenue> {
+    let mut revenue: BTreeMap<u64, ChannelRevenue> =
+        channels.map(|c| (c, ChannelRevenue::default())).collect();
+
+    for pair in pairs {
+        let incoming = revenue.entry(pair.incoming_channel).or_default();
+        incoming.incoming_peacetime_msat += pair.peacetime_revenue_msat;
+        incoming.incoming_simulation_msat += pair.simulation_revenue_msat;
+
+        let outgoing = revenue.entry(pair.outgoing_channel).or_default();
+        outgoing.outgoing_peacetime_msat += pair.peacetime_revenue_msat;
+        outgoing.outgoing_simulation_msat += pair.simulation_revenue_msat;
+    }
+
+    revenue
+}
+
+/// The name of the file that revenue for each of the targets' channel pairs is written to in the results directory.
+pub const PAIR_REVENUE_FILE: &str = "pair_revenue.csv";
+
+/// The name of the file that revenue for each of the targets' channels is written to in the results directory.
+pub const CHANNEL_REVENUE_FILE: &str = "channel_revenue.csv";
+
+/// The revenue earned by a target's channel pairs, along with the target's channels so that channels that did not
+/// earn any revenue are included in the breakdown.
+pub struct TargetRevenue {
+    pub alias: String,
+    pub pairs: Vec<PairRevenue>,
+    pub channels: HashMap<u64, (PublicKey, String)>,
+}
+
+#[derive(Serialize)]
+struct PairRevenueRow<'a> {
+    target: &'a str,
+    incoming_channel: u64,
+    outgoing_channel: u64,
+    peacetime_revenue_msat: u64,
+    simulation_revenue_msat: u64,
+}
+
+#[derive(Serialize)]
+struct ChannelRevenueRow<'a> {
+    target: &'a str,
+    channel_id: u64,
+    peer: &'a str,
+    attacker_peer: bool,
+    incoming_peacetime_msat: u64,
+    incoming_simulation_msat: u64,
+    outgoing_peacetime_msat: u64,
+    outgoing_simulation_msat: u64,
+    change_msat: i64,
+}
+
+/// Writes the revenue earned by each of the targets' channel pairs, and a comparison of each of their channel's
+/// revenue in the simulation and peacetime. Each target's channels are ordered from the largest loss to the largest
+/// gain.
+pub fn write_revenue_breakdown(
+    dir: &Path,
+    targets: &[TargetRevenue],
+    attackers: &[PublicKey],
+) -> Result<(), BoxError> {
+    let mut writer = WriterBuilder::new().from_writer(File::create(dir.join(PAIR_REVENUE_FILE))?);
+    for target in targets {
+        for pair in target.pairs.iter() {
+            writer.serialize(PairRevenueRow {
+                target: &target.alias,
+                incoming_channel: pair.incoming_channel,
+                outgoing_channel: pair.outgoing_channel,
+                peacetime_revenue_msat: pair.peacetime_revenue_msat,
+                simulation_revenue_msat: pair.simulation_revenue_msat,
+            })?;
+        }
+    }
+    writer.flush()?;
+
+    let mut writer =
+        WriterBuilder::new().from_writer(File::create(dir.join(CHANNEL_REVENUE_FILE))?);
+    for target in targets {
+        let mut channels: Vec<(u64, ChannelRevenue)> =
+            channel_revenue(&target.pairs, target.channels.keys().copied())
+                .into_iter()
+                .collect();
+        channels.sort_by_key(|(channel_id, revenue)| (revenue.change_msat(), *channel_id));
+
+        for (channel_id, revenue) in channels {
+            let peer = target.channels.get(&channel_id);
+            writer.serialize(ChannelRevenueRow {
+                target: &target.alias,
+                channel_id,
+                peer: peer.map(|(_, alias)| alias.as_str()).unwrap_or_default(),
+                attacker_peer: peer.is_some_and(|(pubkey, _)| attackers.contains(pubkey)),
+                incoming_peacetime_msat: revenue.incoming_peacetime_msat,
+                incoming_simulation_msat: revenue.incoming_simulation_msat,
+                outgoing_peacetime_msat: revenue.outgoing_peacetime_msat,
+                outgoing_simulation_msat: revenue.outgoing_simulation_msat,
+                change_msat: revenue.change_msat(),
+            })?;
+        }
+    }
+
+    writer.flush().map_err(|e| e.into())
+}
+
 #[async_trait]
 impl PeacetimeRevenueMonitor for RevenueInterceptor {
-    async fn get_revenue_difference(&self) -> RevenueSnapshot {
+    async fn get_revenue_difference(&self, target: &PublicKey) -> RevenueSnapshot {
+        let (simulation_revenue_msat, force_close_cost_msat) = self
+            .node_revenue
+            .lock()
+            .await
+            .get(target)
+            .map(|r| (r.simulation_revenue_msat(), r.force_close_cost_msat))
+            .unwrap_or_default();
+
         RevenueSnapshot {
-            simulation_revenue_msat: self.target_revenue.lock().await.revenue_total,
-            peacetime_revenue_msat: self.peacetime_revenue.lock().await.peacetime_revenue,
+            simulation_revenue_msat,
+            force_close_cost_msat,
+            peacetime_revenue_msat: self
+                .peacetime_revenue
+                .lock()
+                .await
+                .node_revenue
+                .get(target)
+                .copied()
+                .unwrap_or_default(),
             runtime: InstantClock::now(&*self.clock).duration_since(self.start_ins),
         }
     }
 }
 
+#[async_trait]
+impl ForceCloseRecorder for RevenueInterceptor {
+    /// Deducts the cost of a force close from the closing node's revenue. Revenue is only tracked for monitored nodes,
+    /// so the cost of closes by other nodes is only reported in the costs of the attacker that caused them.
+    async fn record_force_close(&self, closing_node: &PublicKey, cost_msat: u64) {
+        match self.node_revenue.lock().await.get_mut(closing_node) {
+            Some(revenue) => revenue.force_close_cost_msat += cost_msat,
+            None => log::warn!(
+                "Force closing node {closing_node} is not monitored, close cost not deducted from revenue"
+            ),
+        }
+    }
+}
+
 #[async_trait]
 impl Interceptor for RevenueInterceptor {
     /// RevenueInterceptor does not need to take any active action on incoming htlcs.
@@ -212,22 +529,24 @@ impl Interceptor for RevenueInterceptor {
         &self,
         req: InterceptRequest,
     ) -> Result<Result<CustomRecords, ForwardingError>, CriticalError> {
-        if req.forwarding_node == self.target_node {
-            match self
-                .target_revenue
-                .lock()
-                .await
-                .pending_htlcs
-                .entry(HtlcRef {
-                    channel_id: req.incoming_htlc.channel_id.into(),
-                    htlc_index: req.incoming_htlc.index,
-                }) {
+        let mut node_revenue = self.node_revenue.lock().await;
+        if let Some(revenue) = node_revenue.get_mut(&req.forwarding_node) {
+            match revenue.pending_htlcs.entry(HtlcRef {
+                channel_id: req.incoming_htlc.channel_id.into(),
+                htlc_index: req.incoming_htlc.index,
+            }) {
                 Entry::Occupied(_) => Err(CriticalError::InterceptorError(format!(
                     "duplicate incoming htlc index: {:?}",
                     req.incoming_htlc
                 ))),
                 Entry::Vacant(e) => {
-                    e.insert(req.incoming_amount_msat - req.outgoing_amount_msat);
+                    e.insert((
+                        req.incoming_amount_msat - req.outgoing_amount_msat,
+                        (
+                            req.incoming_htlc.channel_id.into(),
+                            req.outgoing_channel_id.map(|c| c.into()).unwrap_or(0),
+                        ),
+                    ));
                     Ok(Ok(CustomRecords::new()))
                 }
             }
@@ -239,16 +558,16 @@ impl Interceptor for RevenueInterceptor {
     /// Notifies the underlying jamming interceptor of htlc resolution, as our attacking interceptor doesn't need
     /// to handle notifications.
     async fn notify_resolution(&self, res: InterceptResolution) -> Result<(), CriticalError> {
-        if res.forwarding_node == self.target_node {
-            let mut target_revenue = self.target_revenue.lock().await;
-
-            match target_revenue.pending_htlcs.remove_entry(&HtlcRef {
+        let mut node_revenue = self.node_revenue.lock().await;
+        if let Some(revenue) = node_revenue.get_mut(&res.forwarding_node) {
+            match revenue.pending_htlcs.remove_entry(&HtlcRef {
                 channel_id: res.incoming_htlc.channel_id.into(),
                 htlc_index: res.incoming_htlc.index,
             }) {
-                Some((_, fee)) => {
+                Some((_, (fee, pair))) => {
                     if res.success {
-                        target_revenue.revenue_total += fee;
+                        revenue.revenue_total += fee;
+                        *revenue.pair_revenue.entry(pair).or_default() += fee;
                     }
 
                     Ok(())
@@ -267,3 +586,55 @@ impl Interceptor for RevenueInterceptor {
         "revenue interceptor".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{channel_revenue, ChannelRevenue, PairRevenue};
+
+    /// Tests that each pair's revenue is attributed to both its incoming and outgoing channel, and that channels
+    /// without any revenue are included.
+    #[test]
+    fn test_channel_revenue() {
+        let pairs = vec![
+            PairRevenue {
+                incoming_channel: 1,
+                outgoing_channel: 2,
+                peacetime_revenue_msat: 100,
+                simulation_revenue_msat: 40,
+            },
+            PairRevenue {
+                incoming_channel: 2,
+                outgoing_channel: 3,
+                peacetime_revenue_msat: 10,
+                simulation_revenue_msat: 50,
+            },
+        ];
+
+        let revenue = channel_revenue(&pairs, [1, 2, 3, 4].into_iter());
+        assert_eq!(revenue.len(), 4);
+
+        assert_eq!(
+            revenue[&1],
+            ChannelRevenue {
+                incoming_peacetime_msat: 100,
+                incoming_simulation_msat: 40,
+                outgoing_peacetime_msat: 0,
+                outgoing_simulation_msat: 0,
+            }
+        );
+        assert_eq!(revenue[&1].change_msat(), -60);
+
+        assert_eq!(
+            revenue[&2],
+            ChannelRevenue {
+                incoming_peacetime_msat: 10,
+                incoming_simulation_msat: 50,
+                outgoing_peacetime_msat: 100,
+                outgoing_simulation_msat: 40,
+            }
+        );
+        assert_eq!(revenue[&2].change_msat(), -20);
+        assert_eq!(revenue[&3].change_msat(), 40);
+        assert_eq!(revenue[&4], ChannelRevenue::default());
+    }
+}
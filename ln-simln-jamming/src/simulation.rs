@@ -0,0 +1,988 @@
+use bitcoin::secp256k1::PublicKey;
+use sim_cli::parsing::{create_simulation_with_network, SimParams};
+use simln_lib::clock::Clock;
+use simln_lib::clock::SimulationClock;
+use simln_lib::sim_node::{CustomRecords, Interceptor, SimGraph, SimNode};
+use simln_lib::SimulationCfg;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::select;
+use tokio::sync::Mutex;
+use tokio_util::task::TaskTracker;
+use triggered::Listener;
+
+use crate::analysis::{BatchForwardWriter, ReputationSeriesWriter, REPUTATION_SERIES_FILE};
+use crate::attack_interceptor::AttackInterceptor;
+use crate::attacks::costs::CostTracker;
+use crate::attacks::AttackStatisitcs;
+use crate::clock::{BlockClock, InstantClock};
+use crate::collateral::{monitored_nodes, write_node_revenue, CollateralDamage};
+use crate::deployment::NetworkDeployment;
+use crate::latency_interceptor::LatencyInterceptor;
+use crate::mitigation::{Mitigation, MitigationManager};
+use crate::parsing::{
+    bootstrap_revenue_from_file, deployment_config_from_file, find_pubkey_by_alias,
+    reputation_snapshot_from_file, setup_attack, shadow_params_from_file,
+    topology_events_from_file, AttackResources, AttackType, Cli, NetworkType,
+};
+use crate::payments::{
+    read_payment_stats, write_payments, PaymentStats, PaymentTracker, HONEST_PAYMENTS_FILE,
+};
+use crate::reputation_interceptor::{NetworkSnapshot, ReputationInterceptor, ReputationMonitor};
+use crate::revenue_interceptor::{
+    write_revenue_breakdown, PeacetimeRevenueMonitor, RevenueInterceptor, TargetRevenue,
+};
+use crate::summary::{
+    RunParams, SimulationSummary, TargetDeployment, TargetSummary, TerminationReason,
+    TerminationTracker, SUMMARY_JSON_FILE, SUMMARY_SCHEMA_VERSION,
+};
+use crate::topology::ChannelTopology;
+use crate::{
+    get_network_reputation, node_channels, BoxError, NetworkReputation, SeedComponent,
+    ACCOUNTABLE_TYPE, UPGRADABLE_TYPE,
+};
+
+/// Runs a simulation with the parameters provided, writing its results to the results directory. The simulation is
+/// shut down early if the interrupt provided is triggered.
+///
+/// Timing is driven by the runtime's tokio clock, so when run on a runtime with a paused clock (as in tests) the
+/// simulation skips straight to the next scheduled event whenever every task is idle.
+pub async fn run_simulation(cli: Cli, interrupt: Listener) -> Result<(), BoxError> {
+    let forward_params = cli.validate()?;
+
+    let network = NetworkType::new(
+        &cli.network,
+        Some(cli.attack_type.clone()),
+        cli.attacker_bootstrap,
+    )?;
+    let targets = network.targets();
+    let target_pubkeys: Vec<PublicKey> = targets.iter().map(|t| t.1).collect();
+    let attackers = network.attackers();
+    let attacker_pubkeys: Vec<PublicKey> = attackers.iter().map(|a| a.1).collect();
+    let sim_network = network.active_network();
+
+    if matches!(network, NetworkType::Peacetime(_)) {
+        return Err("must run simulation with attack set".into());
+    }
+
+    let tasks = TaskTracker::new();
+    let (shutdown, listener) = triggered::trigger();
+    let termination = TerminationTracker::new();
+
+    // The channels of each target, in the same order as targets.
+    let target_channels: Vec<HashMap<u64, (PublicKey, String)>> = target_pubkeys
+        .iter()
+        .map(|target| node_channels(sim_network, *target))
+        .collect();
+
+    let clock = Arc::new(SimulationClock::new(cli.clock_speedup)?);
+
+    // Use the channel jamming interceptor and latency for simulated payments.
+    let latency_interceptor: Arc<dyn Interceptor> = Arc::new(LatencyInterceptor::new_poisson(
+        150.0,
+        SeedComponent::Latency.seed(cli.seed),
+    )?);
+
+    let now = InstantClock::now(&*clock);
+
+    // Create a writer to store results for nodes that we care about.
+    let results_dir = match &cli.results_dir {
+        Some(dir) => dir.clone(),
+        None => network
+            .results_dir(Clock::now(&*clock))
+            .ok_or("results dir none for attack")?,
+    };
+    if !results_dir.exists() {
+        fs::create_dir_all(&results_dir)?;
+    }
+
+    // Targets may share peers or have channels with each other, so nodes are deduplicated before results are written.
+    let monitor_channels: Vec<(PublicKey, String)> = target_channels
+        .iter()
+        .flat_map(|channels| channels.values().cloned())
+        .chain(
+            targets
+                .iter()
+                .map(|(alias, pubkey)| (*pubkey, alias.clone())),
+        )
+        .collect::<HashMap<PublicKey, String>>()
+        .into_iter()
+        .collect();
+    let results_writer = Arc::new(Mutex::new(BatchForwardWriter::new(
+        results_dir.clone(),
+        &monitor_channels,
+        cli.result_batch_size,
+        now,
+    )));
+
+    let results_writer_1 = results_writer.clone();
+    let results_listener = listener.clone();
+    let results_shutdown = shutdown.clone();
+    let results_clock = clock.clone();
+    let results_termination = termination.clone();
+    tasks.spawn(async move {
+        let interval = Duration::from_secs(60);
+        loop {
+            select! {
+                _ = results_listener.clone() => {
+                    if let Err(e) = results_writer_1.lock().await.write(true) {
+                        log::error!("Error writing results on shutdown: {e}");
+                    }
+                    return
+                },
+                _ = results_clock.sleep(interval) => {
+                      if let Err(e) = results_writer_1.lock().await.write(false) {
+                        log::error!("Error writing results: {e}");
+                        results_termination.set(TerminationReason::ResultsWriterFailed(e.to_string()));
+                        results_shutdown.trigger();
+                        return
+                    }
+                }
+            }
+        }
+    });
+
+    let reputation_file = network.reputation_file();
+    let reputation_snapshot = reputation_snapshot_from_file(&reputation_file).map_err(|e| {
+        format!(
+            "could not find reputation snapshot {:?}, try generating one with reputation-builder: {:?}",
+            reputation_file.to_string_lossy(), e
+        )
+    })?;
+    let bootstrap_revenue: HashMap<PublicKey, u64> = match network.revenue_file() {
+        Some(revenue_file) => bootstrap_revenue_from_file(&revenue_file, targets)?,
+        None => HashMap::new(),
+    };
+
+    // Pick the nodes that have deployed the mitigation. When picking a fraction of nodes at random, the targets and
+    // attackers always deploy it because we're interested in the protection that targets get as their peers upgrade.
+    let deployment_config = match network.deployment_file() {
+        Some(file) => deployment_config_from_file(&file)?,
+        None => HashMap::new(),
+    };
+    let mut always_deployed = attacker_pubkeys.clone();
+    always_deployed.extend(&target_pubkeys);
+    let mitigation = Mitigation::from(&cli.mitigation_params);
+    log::info!("Running simulation with mitigation: {:?}", mitigation);
+    let mut deployment = NetworkDeployment::new(
+        deployment_config,
+        sim_network,
+        forward_params,
+        mitigation,
+        cli.deployment_fraction,
+        SeedComponent::Deployment.seed(cli.seed),
+        &always_deployed,
+    )?;
+
+    if cli.observe_only_target {
+        for target in target_pubkeys.iter() {
+            deployment.set_observe_only(*target)?;
+        }
+    }
+
+    let mut target_deployment = Vec::with_capacity(targets.len());
+    for ((alias, _), channels) in targets.iter().zip(target_channels.iter()) {
+        let target_peers: HashSet<PublicKey> = channels.values().map(|(pk, _)| *pk).collect();
+        let peer_deployment = TargetDeployment {
+            deployed_peers: target_peers
+                .iter()
+                .filter(|p| deployment.is_deployed(p))
+                .count(),
+            peer_count: target_peers.len(),
+        };
+        log::info!(
+            "Target {alias} has {} of {} peers with the mitigation deployed",
+            peer_deployment.deployed_peers,
+            peer_deployment.peer_count,
+        );
+        target_deployment.push(peer_deployment);
+    }
+
+    // Honest payments are tracked throughout the simulation, with the reputation interceptor reporting the reason that
+    // any htlcs are failed.
+    let payment_tracker = Arc::new(PaymentTracker::new(
+        clock.clone(),
+        sim_network,
+        &attacker_pubkeys,
+    ));
+
+    let reputation_interceptor = Arc::new(
+        ReputationInterceptor::<_, MitigationManager>::new_from_snapshot(
+            forward_params,
+            &deployment,
+            SeedComponent::ForwardManagers.seed(cli.seed),
+            sim_network,
+            NetworkSnapshot {
+                reputation: reputation_snapshot,
+                // If bootstrapping the attacker's reputation, we expect them to be in our snapshot
+                // of starting reputation values. Otherwise, they can be omitted.
+                no_reputation: if cli.attacker_bootstrap.is_some() {
+                    HashSet::new()
+                } else {
+                    HashSet::from_iter(attacker_pubkeys.clone())
+                },
+            },
+            clock.clone(),
+            Some(results_writer),
+        )
+        .await?
+        .with_failure_reporter(payment_tracker.clone()),
+    );
+
+    // Shadow parameter sets start from the same reputation snapshot as the primary forward managers, so they need to
+    // be added before any htlcs are forwarded.
+    if let Some(path) = &cli.shadow_params_file {
+        let shadows = shadow_params_from_file(path, forward_params)?;
+        log::info!(
+            "Running {} shadow parameter sets: {:?}",
+            shadows.len(),
+            shadows.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        );
+        reputation_interceptor
+            .add_shadow_managers(&shadows, SeedComponent::ShadowManagers.seed(cli.seed))
+            .await?;
+    }
+
+    // Apply any scheduled channel opens and closes to the simulated network. Channels that are opened during the
+    // simulation are closed until their open event is reached.
+    let topology_events = match &cli.topology_file {
+        Some(path) => topology_events_from_file(path)?,
+        None => vec![],
+    };
+    if !topology_events.is_empty() {
+        log::warn!(
+            "Sim-ln's pathfinding graph can't be updated during a simulation, so payments may be routed over \
+            channels that are closed or not yet opened. These payments are failed and excluded from honest payment \
+            statistics."
+        );
+    }
+    let topology = Arc::new(
+        ChannelTopology::new(
+            reputation_interceptor.clone(),
+            sim_network,
+            &topology_events,
+        )
+        .await?,
+    );
+
+    let topology_1 = topology.clone();
+    let topology_clock = clock.clone();
+    let topology_listener = listener.clone();
+    let topology_shutdown = shutdown.clone();
+    let topology_termination = termination.clone();
+    tasks.spawn(async move {
+        if let Err(e) = topology_1
+            .run_schedule(topology_events, topology_clock, topology_listener)
+            .await
+        {
+            log::error!("Error applying topology events: {e}");
+            topology_termination.set(TerminationReason::TopologyFailed(e.to_string()));
+            topology_shutdown.trigger();
+        }
+    });
+
+    // While we run the simulation, replay projected peacetime revenue to serve as a comparison. Revenue is tracked
+    // for every node in the monitor scope so that we can measure collateral damage to nodes around the targets.
+    let monitored = monitored_nodes(
+        cli.revenue_monitor_scope,
+        sim_network,
+        &target_pubkeys,
+        &attacker_pubkeys,
+    );
+    let revenue_interceptor = Arc::new(
+        RevenueInterceptor::new_with_bootstrap(
+            clock.clone(),
+            &target_pubkeys,
+            &monitored.iter().map(|n| n.pubkey).collect::<Vec<_>>(),
+            &bootstrap_revenue,
+            cli.attacker_bootstrap,
+            network.peacetime_projections(),
+            listener.clone(),
+        )
+        .await?,
+    );
+
+    let revenue_interceptor_1 = revenue_interceptor.clone();
+    let revenue_shutdown = shutdown.clone();
+    let revenue_termination = termination.clone();
+    tasks.spawn(async move {
+        if let Err(e) = revenue_interceptor_1.process_peacetime_fwds().await {
+            log::error!("Error processing peacetime forwards: {e}");
+            revenue_termination.set(TerminationReason::PeacetimeRevenueFailed(e.to_string()));
+            revenue_shutdown.trigger();
+        }
+    });
+
+    // Reputation is assessed for a channel pair and a specific HTLC that's being proposed. To assess whether pairs
+    // have reputation, we'll use LND's default fee policy to get the HTLC risk for our configured htlc size and hold
+    // time.
+    let risk_margin = forward_params.htlc_opportunity_cost(
+        1000 + (0.0001 * cli.reputation_margin_msat as f64) as u64,
+        cli.reputation_margin_expiry_blocks,
+    );
+
+    // Costs are shared between the attack and the attack interceptor, which records force closes that the attacker
+    // causes.
+    let cost_tracker = Arc::new(CostTracker::new(
+        cli.cost_params.clone().into(),
+        network.active_network(),
+        network.attackers(),
+    ));
+
+    // Next, setup the attack interceptor to use our custom attack.
+    let attack = setup_attack(
+        &cli,
+        &network,
+        Arc::clone(&clock),
+        AttackResources {
+            reputation_monitor: Arc::clone(&reputation_interceptor),
+            revenue_monitor: Arc::clone(&revenue_interceptor),
+            channel_jammer: Arc::clone(&reputation_interceptor),
+            cost_tracker: Arc::clone(&cost_tracker),
+            topology: Arc::clone(&topology),
+        },
+    )?;
+
+    attack.validate()?;
+
+    // Do some preliminary checks on our reputation state - there isn't much point in running if we haven't built up
+    // some reputation with every target.
+    let start_reputation = get_target_reputation(
+        reputation_interceptor.clone(),
+        &target_pubkeys,
+        &target_channels,
+        &attacker_pubkeys,
+        risk_margin,
+        // The reputation_interceptor clock has been set on decaying averages so we use the clock
+        // to provide a new instant rather than the previous fixed point.
+        InstantClock::now(&*clock),
+    )
+    .await?;
+
+    // Baseline mitigations don't track reputation, so there's no reputation state to check.
+    if mitigation.tracks_reputation() {
+        for ((alias, _), reputation) in targets.iter().zip(start_reputation.iter()) {
+            check_reputation_status(&cli, alias, reputation)?;
+        }
+    }
+
+    // Periodically sample the reputation of every channel for the nodes requested, so that we can see how reputation
+    // changes over the course of the attack, including on channels that aren't forwarding any traffic.
+    if let Some(interval) = cli.reputation_interval {
+        let series_nodes: Vec<(PublicKey, String)> = if cli.reputation_nodes.is_empty() {
+            sim_network
+                .iter()
+                .flat_map(|channel| [&channel.node_1, &channel.node_2])
+                .map(|policy| (policy.alias.clone(), policy.pubkey))
+                .collect::<BTreeMap<String, PublicKey>>()
+                .into_iter()
+                .map(|(alias, pubkey)| (pubkey, alias))
+                .collect()
+        } else {
+            cli.reputation_nodes
+                .iter()
+                .map(|alias| Ok((find_pubkey_by_alias(alias, sim_network)?, alias.clone())))
+                .collect::<Result<_, BoxError>>()?
+        };
+
+        let series_writer = ReputationSeriesWriter::new(
+            reputation_interceptor.clone(),
+            series_nodes,
+            risk_margin,
+            results_dir.join(REPUTATION_SERIES_FILE),
+            now,
+        );
+        let series_clock = clock.clone();
+        let series_listener = listener.clone();
+        let series_shutdown = shutdown.clone();
+        let series_termination = termination.clone();
+        tasks.spawn(async move {
+            loop {
+                if let Err(e) = series_writer
+                    .sample(InstantClock::now(&*series_clock))
+                    .await
+                {
+                    log::error!("Error writing reputation samples: {e}");
+                    series_termination
+                        .set(TerminationReason::ReputationSeriesFailed(e.to_string()));
+                    series_shutdown.trigger();
+                    return;
+                }
+
+                select! {
+                    _ = series_listener.clone() => return,
+                    _ = series_clock.sleep(interval) => {},
+                }
+            }
+        });
+    }
+
+    let attack_interceptor = AttackInterceptor::new(
+        attacker_pubkeys.clone(),
+        reputation_interceptor.clone(),
+        attack.clone(),
+        BlockClock::new(
+            clock.clone(),
+            forward_params.reputation_params.expected_block_speed,
+        ),
+        cli.force_close_margin_blocks,
+        cost_tracker,
+        revenue_interceptor.clone(),
+        topology,
+    )
+    .with_failure_reporter(payment_tracker.clone());
+    let attack_interceptor = Arc::new(attack_interceptor);
+
+    let interceptors = vec![
+        latency_interceptor,
+        attack_interceptor.clone(),
+        revenue_interceptor.clone(),
+        payment_tracker.clone(),
+    ];
+
+    let custom_records =
+        CustomRecords::from([(UPGRADABLE_TYPE, vec![1]), (ACCOUNTABLE_TYPE, vec![0])]);
+
+    let mut exclude = attacker_pubkeys.clone();
+    exclude.extend(&target_pubkeys);
+
+    // Setup the simulated network with our fake graph.
+    let sim_params = SimParams {
+        nodes: vec![],
+
+        sim_network: sim_network.to_vec(),
+        activity: vec![],
+        exclude,
+    };
+
+    let sim_cfg = SimulationCfg::new(
+        None,
+        3_800_000,
+        2.0,
+        None,
+        Some(SeedComponent::Activity.seed(cli.seed)),
+    );
+    let (simulation, validated_activities, sim_nodes) = create_simulation_with_network(
+        sim_cfg,
+        &sim_params,
+        clock.clone(),
+        tasks.clone(),
+        interceptors,
+        custom_records,
+    )
+    .await?;
+    let simulation = Arc::new(simulation);
+
+    // Collect all attacker nodes from the network
+    let attacker_pubkeys_map: HashMap<PublicKey, String> = network
+        .attackers()
+        .iter()
+        .map(|(alias, pk)| (*pk, alias.clone()))
+        .collect();
+
+    // Ugly hack specific to SlowJam attack to include this node in the list of nodes passed to run_attack.
+    // This node is used as an "honest" node to send a test payment through our target channel to
+    // check that it is actually jammed.
+    let honest_sender_pubkey = if cli.attack_type == AttackType::SlowJam {
+        Some(find_pubkey_by_alias("69", sim_network)?)
+    } else {
+        None
+    };
+
+    let attacker_nodes: HashMap<String, Arc<Mutex<SimNode<SimGraph, SimulationClock>>>> = sim_nodes
+        .into_iter()
+        .filter_map(|(pk, node)| {
+            if let Some(honest_pk) = honest_sender_pubkey {
+                if honest_pk == pk {
+                    return Some(("69".to_string(), node));
+                }
+            }
+
+            attacker_pubkeys_map
+                .get(&pk)
+                .map(|alias| (alias.clone(), node))
+        })
+        .collect();
+
+    let attack_shutdown_listener = listener.clone();
+    let attack_shutdown_trigger = shutdown.clone();
+    let attack_start_reputation: HashMap<PublicKey, NetworkReputation> = target_pubkeys
+        .iter()
+        .copied()
+        .zip(start_reputation.iter().cloned())
+        .collect();
+    let attack_simulation_shutdown = Arc::clone(&simulation);
+    let attack_clone = Arc::clone(&attack);
+    let attack_termination = termination.clone();
+    tokio::spawn(async move {
+        // run_attack will block until the attack is done so trigger a simulation shutdown after
+        // it returns and log any errors.
+        match attack_clone
+            .run_attack(
+                attack_start_reputation,
+                attacker_nodes,
+                attack_shutdown_listener,
+            )
+            .await
+        {
+            Ok(()) => attack_termination.set(TerminationReason::AttackCompleted),
+            Err(e) => {
+                log::error!("Error running custom attacker actions: {e}");
+                attack_termination.set(TerminationReason::AttackFailed(e.to_string()));
+            }
+        }
+        attack_shutdown_trigger.trigger();
+        attack_simulation_shutdown.shutdown();
+    });
+
+    let interrupt_listener = listener.clone();
+    let interrupt_shutdown = shutdown.clone();
+    let simulation_shutdown = Arc::clone(&simulation);
+    let interrupt_termination = termination.clone();
+    tokio::spawn(async move {
+        select! {
+            _ = interrupt_listener => {},
+            _ = interrupt => {
+                interrupt_termination.set(TerminationReason::Interrupted);
+                interrupt_shutdown.trigger();
+                simulation_shutdown.shutdown();
+            }
+        }
+    });
+
+    // Attacks can't rely on reputation to decide when they've completed if the mitigation doesn't track it, so the
+    // simulation is also shut down once it has run for its maximum runtime.
+    if let Some(max_runtime) = cli.max_runtime(&forward_params) {
+        let runtime_clock = clock.clone();
+        let runtime_listener = listener.clone();
+        let runtime_shutdown = shutdown.clone();
+        let runtime_simulation = Arc::clone(&simulation);
+        let runtime_termination = termination.clone();
+        tokio::spawn(async move {
+            select! {
+                _ = runtime_listener => {},
+                _ = runtime_clock.sleep(max_runtime) => {
+                    log::info!("Simulation reached maximum runtime: {:?}", max_runtime);
+                    runtime_termination.set(TerminationReason::MaxRuntimeReached);
+                    runtime_shutdown.trigger();
+                    runtime_simulation.shutdown();
+                }
+            }
+        });
+    }
+
+    // Run simulation until it shuts down, then wait for the graph to exit.
+    simulation.run(&validated_activities).await?;
+
+    // Write start and end state to a summary file.
+    let end_reputation = get_target_reputation(
+        reputation_interceptor.clone(),
+        &target_pubkeys,
+        &target_channels,
+        &attacker_pubkeys,
+        risk_margin,
+        InstantClock::now(&*clock),
+    )
+    .await?;
+
+    let mut target_summaries = Vec::with_capacity(targets.len());
+    let mut target_revenue = Vec::with_capacity(targets.len());
+    for (i, (alias, pubkey)) in targets.iter().enumerate() {
+        // Htlcs that the target forwarded to or from attackers are excluded, so that we only report on honest traffic.
+        let observed_failures = if cli.observe_only_target {
+            let attacker_channels: HashSet<u64> = target_channels[i]
+                .iter()
+                .filter(|(_, (peer, _))| attacker_pubkeys.contains(peer))
+                .map(|(scid, _)| *scid)
+                .collect();
+
+            Some(
+                reputation_interceptor
+                    .observed_failures(pubkey, &attacker_channels)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        target_summaries.push(TargetSummary {
+            alias: alias.clone(),
+            pubkey: *pubkey,
+            start_reputation: start_reputation[i].clone(),
+            end_reputation: end_reputation[i].clone(),
+            deployment: target_deployment[i],
+            revenue: revenue_interceptor.get_revenue_difference(pubkey).await,
+            observed_failures,
+        });
+
+        target_revenue.push(TargetRevenue {
+            alias: alias.clone(),
+            pairs: revenue_interceptor.get_pair_revenue(pubkey).await,
+            channels: target_channels[i].clone(),
+        });
+    }
+
+    let attack_stats = attack.attack_statistics()?;
+    log::info!("Writing results to directory {:?}", results_dir);
+
+    write_revenue_breakdown(&results_dir, &target_revenue, &attacker_pubkeys)?;
+
+    let node_revenue = revenue_interceptor.get_node_revenue().await;
+    write_node_revenue(&results_dir, &monitored, &node_revenue)?;
+
+    let collateral_damage =
+        CollateralDamage::new(cli.revenue_monitor_scope, &target_pubkeys, &node_revenue);
+
+    let honest_payments = payment_tracker.payments()?;
+    write_payments(&results_dir.join(HONEST_PAYMENTS_FILE), &honest_payments)?;
+    let honest_payments = PaymentStats::new(&honest_payments);
+
+    let peacetime_payments = read_payment_stats(&network.peacetime_projections())?;
+    if peacetime_payments.is_none() {
+        log::warn!("Peacetime payment stats not found, re-run forward-builder to record them for comparison");
+    }
+
+    SimulationSummary {
+        schema_version: SUMMARY_SCHEMA_VERSION,
+        network_dir: cli.network.network_dir.clone(),
+        seed: Some(cli.seed),
+        run_params: RunParams::from(&cli),
+        forward_params,
+        targets: target_summaries.clone(),
+        attack_statistics: attack_stats.clone(),
+        termination_reason: termination.reason(),
+        collateral_damage: collateral_damage.clone(),
+        honest_payments: honest_payments.clone(),
+        peacetime_payments: peacetime_payments.clone(),
+    }
+    .write(&results_dir.join(SUMMARY_JSON_FILE))?;
+
+    write_simulation_summary(
+        &cli,
+        results_dir,
+        &target_summaries,
+        attack_stats,
+        &collateral_damage,
+        &honest_payments,
+        peacetime_payments.as_ref(),
+    )?;
+
+    Ok(())
+}
+
+/// Gets the reputation of each target with its peers, and the attacker's reputation with each target, returned in the
+/// same order as the targets provided.
+async fn get_target_reputation<R: ReputationMonitor>(
+    reputation_monitor: Arc<R>,
+    targets: &[PublicKey],
+    target_channels: &[HashMap<u64, (PublicKey, String)>],
+    attacker_pubkeys: &[PublicKey],
+    risk_margin: u64,
+    access_ins: Instant,
+) -> Result<Vec<NetworkReputation>, BoxError> {
+    let mut reputation = Vec::with_capacity(targets.len());
+    for (target, channels) in targets.iter().zip(target_channels.iter()) {
+        reputation.push(
+            get_network_reputation(
+                reputation_monitor.clone(),
+                *target,
+                attacker_pubkeys,
+                &channels.iter().map(|(k, v)| (*k, v.0)).collect(),
+                risk_margin,
+                access_ins,
+            )
+            .await?,
+        );
+    }
+
+    Ok(reputation)
+}
+
+/// Checks whether the attacker and target meet the required portion of high reputation pairs to required.
+fn check_reputation_status(
+    cli: &Cli,
+    target_alias: &str,
+    status: &NetworkReputation,
+) -> Result<(), BoxError> {
+    log::info!(
+        "Attacker has {} out of {} pairs with reputation with target {target_alias}",
+        status.attacker_reputation,
+        status.attacker_pair_count,
+    );
+
+    log::info!(
+        "Target {target_alias} has {}/{} pairs with reputation with its peers",
+        status.target_reputation,
+        status.target_pair_count,
+    );
+
+    if let Some(attacker_percentage) = cli.attacker_reputation_percent {
+        let attacker_threshold = status.attacker_pair_count * attacker_percentage as usize / 100;
+        if status.attacker_reputation < attacker_threshold {
+            return Err(format!(
+                "attacker has {}/{} good reputation pairs with target {target_alias} which does not meet threshold {}",
+                status.attacker_reputation, status.attacker_pair_count, attacker_threshold,
+            )
+            .into());
+        }
+    }
+
+    let target_threshold = status.target_pair_count * cli.target_reputation_percent as usize / 100;
+    if status.target_reputation < target_threshold {
+        return Err(format!(
+            "target {target_alias} has {}/{} good reputation pairs which does not meet threshold {}",
+            status.target_reputation, status.target_pair_count, target_threshold,
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+fn write_simulation_summary(
+    cli: &Cli,
+    data_dir: PathBuf,
+    targets: &[TargetSummary],
+    attack_stats: AttackStatisitcs,
+    collateral_damage: &CollateralDamage,
+    honest_payments: &PaymentStats,
+    peacetime_payments: Option<&PaymentStats>,
+) -> Result<(), BoxError> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(data_dir.join("summary.txt"))?;
+
+    let mut writer = BufWriter::new(file);
+
+    writeln!(
+        writer,
+        "{:?} ran for (seconds): {:?}",
+        cli.attack_type,
+        targets
+            .iter()
+            .map(|t| t.revenue.runtime.as_secs())
+            .max()
+            .unwrap_or_default()
+    )?;
+    writeln!(
+        writer,
+        "Attacker bootstrapped reputation for: {} seconds",
+        cli.attacker_bootstrap.unwrap_or(Duration::ZERO).as_secs(),
+    )?;
+
+    for target in targets {
+        let revenue = &target.revenue;
+        writeln!(writer, "Target {}:", target.alias)?;
+        writeln!(
+            writer,
+            "Peacetime revenue (msat): {}",
+            revenue.peacetime_revenue_msat
+        )?;
+        writeln!(
+            writer,
+            "Simulation revenue (msat): {}",
+            revenue.simulation_revenue_msat,
+        )?;
+        writeln!(
+            writer,
+            "Force close costs deducted from simulation revenue (msat): {}",
+            revenue.force_close_cost_msat,
+        )?;
+
+        if revenue.simulation_revenue_msat > revenue.peacetime_revenue_msat {
+            writeln!(
+                writer,
+                "Revenue gain in simulation: {}",
+                revenue.simulation_revenue_msat - revenue.peacetime_revenue_msat,
+            )?;
+        } else {
+            writeln!(
+                writer,
+                "Revenue loss in simulation: {}",
+                revenue.peacetime_revenue_msat - revenue.simulation_revenue_msat,
+            )?;
+        }
+        writeln!(
+            writer,
+            "Attacker start reputation (pairs): {}/{}",
+            target.start_reputation.attacker_reputation,
+            target.start_reputation.attacker_pair_count,
+        )?;
+        writeln!(
+            writer,
+            "Attacker end reputation (pairs): {}/{}",
+            target.end_reputation.attacker_reputation, target.end_reputation.attacker_pair_count,
+        )?;
+        writeln!(
+            writer,
+            "Target start reputation (pairs): {}/{}",
+            target.start_reputation.target_reputation, target.start_reputation.target_pair_count,
+        )?;
+        writeln!(
+            writer,
+            "Target end reputation (pairs): {}/{}",
+            target.end_reputation.target_reputation, target.end_reputation.target_pair_count,
+        )?;
+        writeln!(
+            writer,
+            "Target has {} of {} peers with the mitigation deployed",
+            target.deployment.deployed_peers, target.deployment.peer_count,
+        )?;
+        if let Some(observed) = &target.observed_failures {
+            writeln!(
+                writer,
+                "Honest htlcs target would have failed (observe-only): {}/{}",
+                observed.would_fail, observed.forwards,
+            )?;
+        }
+    }
+
+    writeln!(
+        writer,
+        "Attacker general jammed {} edges (directional)",
+        attack_stats.general_jammed_channels,
+    )?;
+    writeln!(
+        writer,
+        "Attacker congestion jammed {} edges (directional)",
+        attack_stats.congestion_jammed_channels,
+    )?;
+    for cost in attack_stats.attacker_costs.iter() {
+        writeln!(
+            writer,
+            "Attacker {} opened {} channels with {} msat capacity (on-chain cost {} msat)",
+            cost.alias, cost.channel_count, cost.channel_capacity_msat, cost.onchain_open_cost_msat,
+        )?;
+        writeln!(
+            writer,
+            "Attacker {} paid {} msat routing fees, {} msat unconditional fees and lost {} msat forwarding revenue",
+            cost.alias, cost.fees_paid_msat, cost.unconditional_fees_msat, cost.lost_revenue_msat,
+        )?;
+        writeln!(
+            writer,
+            "Attacker {} held {} htlcs locking {} msat*s liquidity",
+            cost.alias, cost.htlcs_held, cost.liquidity_locked_msat_secs,
+        )?;
+        writeln!(
+            writer,
+            "Attacker {} caused {} force closes (on-chain cost to closing party {} msat)",
+            cost.alias, cost.force_closes_caused, cost.force_close_cost_msat,
+        )?;
+    }
+
+    let total_cost: u64 = attack_stats
+        .attacker_costs
+        .iter()
+        .map(|c| c.total_msat())
+        .sum();
+    let total_liquidity: u128 = attack_stats
+        .attacker_costs
+        .iter()
+        .map(|c| c.liquidity_locked_msat_secs)
+        .sum();
+    writeln!(writer, "Total attacker cost (msat): {}", total_cost)?;
+    writeln!(
+        writer,
+        "Total attacker liquidity locked (msat*s): {}",
+        total_liquidity
+    )?;
+    writeln!(
+        writer,
+        "Target revenue loss against attacker cost (msat): {} / {}",
+        targets
+            .iter()
+            .map(|t| t
+                .revenue
+                .peacetime_revenue_msat
+                .saturating_sub(t.revenue.simulation_revenue_msat))
+            .sum::<u64>(),
+        total_cost,
+    )?;
+    if let Some(honest_reputation) = attack_stats.honest_peer_reputation {
+        writeln!(
+            writer,
+            "Honest peers start reputation with target (pairs): {}/{}",
+            honest_reputation.start.good_pairs, honest_reputation.start.pair_count,
+        )?;
+        writeln!(
+            writer,
+            "Honest peers end reputation with target (pairs): {}/{}",
+            honest_reputation.end.good_pairs, honest_reputation.end.pair_count,
+        )?;
+    }
+    if collateral_damage.monitored_nodes > 0 {
+        writeln!(
+            writer,
+            "Collateral revenue peacetime / simulation (msat, {} nodes): {} / {}",
+            collateral_damage.monitored_nodes,
+            collateral_damage.peacetime_revenue_msat,
+            collateral_damage.simulation_revenue_msat,
+        )?;
+        writeln!(
+            writer,
+            "Collateral force close costs deducted from simulation revenue (msat): {}",
+            collateral_damage.force_close_cost_msat,
+        )?;
+        writeln!(
+            writer,
+            "Monitored nodes with revenue loss: {}/{}",
+            collateral_damage.nodes_with_loss, collateral_damage.monitored_nodes,
+        )?;
+    }
+    write_payment_stats(&mut writer, "Honest", honest_payments)?;
+    if let Some(peacetime) = peacetime_payments {
+        write_payment_stats(&mut writer, "Peacetime", peacetime)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_payment_stats<W: Write>(
+    writer: &mut W,
+    label: &str,
+    stats: &PaymentStats,
+) -> Result<(), BoxError> {
+    writeln!(
+        writer,
+        "{label} payments succeeded: {}/{}",
+        stats.succeeded, stats.attempted,
+    )?;
+    if !stats.failure_reasons.is_empty() {
+        writeln!(
+            writer,
+            "{label} payment failures: {}",
+            stats
+                .failure_reasons
+                .iter()
+                .map(|(reason, count)| format!("{reason}={count}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        )?;
+    }
+    if stats.closed_channel_failures > 0 {
+        writeln!(
+            writer,
+            "{label} payments failed over closed channels (excluded above): {}",
+            stats.closed_channel_failures,
+        )?;
+    }
+    if let (Some(p50), Some(p90), Some(p99)) = (
+        stats.latency_p50_ms,
+        stats.latency_p90_ms,
+        stats.latency_p99_ms,
+    ) {
+        writeln!(
+            writer,
+            "{label} payment latency p50 / p90 / p99 (ms): {p50} / {p90} / {p99}",
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,397 @@
+use bitcoin::secp256k1::PublicKey;
+use ln_resource_mgr::forward_manager::ForwardManagerParams;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::attacks::AttackStatisitcs;
+use crate::collateral::{CollateralDamage, MonitorScope};
+use crate::mitigation::Mitigation;
+use crate::parsing::{AttackType, Cli};
+use crate::payments::PaymentStats;
+use crate::reputation_interceptor::ObservedFailures;
+use crate::revenue_interceptor::RevenueSnapshot;
+use crate::{BoxError, NetworkReputation};
+
+/// The version of the json summary schema. This must be incremented whenever a field is added, removed or changes
+/// meaning so that results from different versions are not mistakenly aggregated together.
+pub const SUMMARY_SCHEMA_VERSION: u32 = 18;
+
+/// The name of the machine-readable summary file written to the results directory.
+pub const SUMMARY_JSON_FILE: &str = "summary.json";
+
+/// A machine-readable summary of a single simulation run, including all parameters required to reproduce it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SimulationSummary {
+    pub schema_version: u32,
+    pub network_dir: PathBuf,
+    pub seed: Option<u64>,
+    pub run_params: RunParams,
+    pub forward_params: ForwardManagerParams,
+    /// The outcome of the attack for each target, in the order that they're listed in the network's target file.
+    pub targets: Vec<TargetSummary>,
+    pub attack_statistics: AttackStatisitcs,
+    pub termination_reason: TerminationReason,
+    pub collateral_damage: CollateralDamage,
+    /// Statistics for payments sent by honest nodes in the simulation.
+    pub honest_payments: PaymentStats,
+    /// Statistics for payments sent when the peacetime projections were generated, if they were recorded.
+    pub peacetime_payments: Option<PaymentStats>,
+}
+
+impl SimulationSummary {
+    /// Writes the summary as pretty-printed json to the path provided, overwriting any existing file.
+    pub fn write(&self, path: &Path) -> Result<(), BoxError> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Summarizes the reputation, deployment and revenue of a single target over the course of the attack.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TargetSummary {
+    pub alias: String,
+    pub pubkey: PublicKey,
+    pub start_reputation: NetworkReputation,
+    pub end_reputation: NetworkReputation,
+    pub deployment: TargetDeployment,
+    pub revenue: RevenueSnapshot,
+    /// Htlcs from honest peers that the target would have failed, set when the target is observe-only.
+    pub observed_failures: Option<ObservedFailures>,
+}
+
+/// The number of the target's peers that have deployed the mitigation.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TargetDeployment {
+    pub deployed_peers: usize,
+    pub peer_count: usize,
+}
+
+/// The command line parameters that a simulation was run with. Kept separate from [`Cli`] so that the summary schema
+/// does not change when flags are reorganized. The network directory, seed and reputation parameters are recorded at
+/// the top level of [`SimulationSummary`]. The results directory is not recorded, as the summary is written to it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RunParams {
+    pub attack_type: AttackType,
+    pub inflation_channels: Vec<u64>,
+    pub inflation_loss_percent: u8,
+    pub mitigation: Mitigation,
+    pub target_reputation_percent: u8,
+    pub attacker_reputation_percent: Option<u8>,
+    pub clock_speedup: u16,
+    pub fast_forward: bool,
+    pub observe_only_target: bool,
+    pub reputation_margin_msat: u64,
+    pub reputation_margin_expiry_blocks: u32,
+    pub force_close_margin_blocks: u32,
+    pub hold_to_expiry: bool,
+    pub topology_file: Option<PathBuf>,
+    pub deployment_fraction: Option<f64>,
+    pub shadow_params_file: Option<PathBuf>,
+    pub attacker_bootstrap_secs: Option<u64>,
+    pub result_batch_size: u16,
+    pub max_runtime_secs: Option<u64>,
+    pub reputation_dir: Option<PathBuf>,
+    pub reputation_interval_secs: Option<u64>,
+    pub reputation_nodes: Vec<String>,
+    pub revenue_monitor_scope: MonitorScope,
+    pub revenue_window_seconds: Option<u64>,
+    pub reputation_multiplier: Option<u8>,
+    pub unconditional_fee_percent: u64,
+    pub onchain_fee_rate: u64,
+    pub force_close_vbytes: u64,
+    pub log_level: String,
+}
+
+impl From<&Cli> for RunParams {
+    fn from(cli: &Cli) -> Self {
+        RunParams {
+            attack_type: cli.attack_type.clone(),
+            inflation_channels: cli.inflation_channels.clone(),
+            inflation_loss_percent: cli.inflation_loss_percent,
+            mitigation: Mitigation::from(&cli.mitigation_params),
+            target_reputation_percent: cli.target_reputation_percent,
+            attacker_reputation_percent: cli.attacker_reputation_percent,
+            clock_speedup: cli.clock_speedup,
+            fast_forward: cli.fast_forward(),
+            observe_only_target: cli.observe_only_target,
+            reputation_margin_msat: cli.reputation_margin_msat,
+            reputation_margin_expiry_blocks: cli.reputation_margin_expiry_blocks,
+            force_close_margin_blocks: cli.force_close_margin_blocks,
+            hold_to_expiry: cli.hold_to_expiry,
+            topology_file: cli.topology_file.clone(),
+            deployment_fraction: cli.deployment_fraction,
+            shadow_params_file: cli.shadow_params_file.clone(),
+            attacker_bootstrap_secs: cli.attacker_bootstrap.map(|d| d.as_secs()),
+            result_batch_size: cli.result_batch_size,
+            max_runtime_secs: cli
+                .max_runtime(&cli.reputation_params.clone().into())
+                .map(|d| d.as_secs()),
+            reputation_dir: cli.network.reputation_dir.clone(),
+            reputation_interval_secs: cli.reputation_interval.map(|d| d.as_secs()),
+            reputation_nodes: cli.reputation_nodes.clone(),
+            revenue_monitor_scope: cli.revenue_monitor_scope,
+            revenue_window_seconds: cli.reputation_params.revenue_window_seconds,
+            reputation_multiplier: cli.reputation_params.reputation_multiplier,
+            unconditional_fee_percent: cli.cost_params.unconditional_fee_percent,
+            onchain_fee_rate: cli.cost_params.onchain_fee_rate,
+            force_close_vbytes: cli.cost_params.force_close_vbytes,
+            log_level: cli.log_level.to_string(),
+        }
+    }
+}
+
+/// Describes why a simulation run ended.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "reason", content = "details", rename_all = "snake_case")]
+pub enum TerminationReason {
+    /// The attack reached its own completion condition.
+    AttackCompleted,
+    /// The attack exited with an error.
+    AttackFailed(String),
+    /// The simulation was interrupted by the user.
+    Interrupted,
+    /// The simulation ran for its maximum runtime.
+    MaxRuntimeReached,
+    /// Replaying peacetime revenue for the target failed.
+    PeacetimeRevenueFailed(String),
+    /// Writing forwarding results to disk failed.
+    ResultsWriterFailed(String),
+    /// Applying a scheduled channel open or close failed.
+    TopologyFailed(String),
+    /// Writing periodic reputation samples to disk failed.
+    ReputationSeriesFailed(String),
+    /// The simulation ended without any of the above reasons being recorded.
+    SimulationEnded,
+}
+
+/// Tracks the reason that a simulation terminated, shared between the tasks that are able to shut it down. Only the
+/// first reason recorded is kept, as subsequent shutdowns are a consequence of the first.
+#[derive(Clone, Debug, Default)]
+pub struct TerminationTracker(Arc<Mutex<Option<TerminationReason>>>);
+
+impl TerminationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the reason for termination if no reason has been set yet.
+    pub fn set(&self, reason: TerminationReason) {
+        match self.0.lock() {
+            Ok(mut current) => {
+                if current.is_none() {
+                    *current = Some(reason);
+                }
+            }
+            Err(e) => log::error!("Could not record termination reason {:?}: {e}", reason),
+        }
+    }
+
+    /// Returns the recorded termination reason, or [`TerminationReason::SimulationEnded`] if none was set.
+    pub fn reason(&self) -> TerminationReason {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|r| r.clone())
+            .unwrap_or(TerminationReason::SimulationEnded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use clap::{CommandFactory, Parser};
+    use ln_resource_mgr::forward_manager::ForwardManagerParams;
+
+    use crate::attacks::costs::AttackerCost;
+    use crate::attacks::AttackStatisitcs;
+    use crate::collateral::{CollateralDamage, MonitorScope};
+    use crate::mitigation::Mitigation;
+    use crate::parsing::{
+        AttackType, Cli, DEFAULT_CIRCUIT_BREAKER_LIQUIDITY_PORTION,
+        DEFAULT_CIRCUIT_BREAKER_MAX_PENDING_HTLCS,
+    };
+    use crate::payments::PaymentStats;
+    use crate::revenue_interceptor::RevenueSnapshot;
+    use crate::test_utils::get_random_keypair;
+    use crate::NetworkReputation;
+
+    use super::{
+        RunParams, SimulationSummary, TargetDeployment, TargetSummary, TerminationReason,
+        TerminationTracker, SUMMARY_SCHEMA_VERSION,
+    };
+
+    #[test]
+    fn test_termination_tracker() {
+        let tracker = TerminationTracker::new();
+        assert_eq!(tracker.reason(), TerminationReason::SimulationEnded);
+
+        // Only the first reason is recorded.
+        tracker.clone().set(TerminationReason::Interrupted);
+        tracker.set(TerminationReason::AttackCompleted);
+        assert_eq!(tracker.reason(), TerminationReason::Interrupted);
+    }
+
+    /// Tests that a summary survives a round trip through json, and that fields that pipelines rely on are present
+    /// at the top level.
+    #[test]
+    fn test_summary_round_trip() {
+        let cli = Cli::try_parse_from([
+            "sim-ln-jam",
+            "--network-dir",
+            "/tmp/network",
+            "--attack-type",
+            "sink",
+            "--attacker-bootstrap",
+            "1d",
+            "--mitigation",
+            "circuit-breaker",
+        ])
+        .unwrap();
+
+        let run_params = RunParams::from(&cli);
+        assert_eq!(run_params.attack_type, AttackType::Sink);
+        assert_eq!(run_params.attacker_bootstrap_secs, Some(60 * 60 * 24));
+        assert_eq!(
+            run_params.mitigation,
+            Mitigation::CircuitBreaker {
+                max_pending_htlcs: DEFAULT_CIRCUIT_BREAKER_MAX_PENDING_HTLCS,
+                max_pending_liquidity_portion: DEFAULT_CIRCUIT_BREAKER_LIQUIDITY_PORTION,
+            }
+        );
+
+        let reputation = NetworkReputation {
+            target_reputation: 1,
+            target_pair_count: 2,
+            attacker_reputation: 3,
+            attacker_pair_count: 4,
+        };
+
+        let summary = SimulationSummary {
+            schema_version: SUMMARY_SCHEMA_VERSION,
+            network_dir: cli.network.network_dir.clone(),
+            seed: Some(42),
+            run_params,
+            forward_params: ForwardManagerParams::default(),
+            targets: vec![TargetSummary {
+                alias: "target".to_string(),
+                pubkey: get_random_keypair().1,
+                start_reputation: reputation.clone(),
+                end_reputation: reputation,
+                deployment: TargetDeployment {
+                    deployed_peers: 3,
+                    peer_count: 4,
+                },
+                revenue: RevenueSnapshot {
+                    peacetime_revenue_msat: 100,
+                    simulation_revenue_msat: 50,
+                    force_close_cost_msat: 0,
+                    runtime: Duration::from_secs(10),
+                },
+                observed_failures: None,
+            }],
+            attack_statistics: AttackStatisitcs {
+                general_jammed_channels: 1,
+                congestion_jammed_channels: 0,
+                attacker_costs: vec![AttackerCost {
+                    alias: "attacker".to_string(),
+                    liquidity_locked_msat_secs: 1_000_000,
+                    ..Default::default()
+                }],
+                honest_peer_reputation: None,
+            },
+            termination_reason: TerminationReason::AttackFailed("error".to_string()),
+            collateral_damage: CollateralDamage {
+                scope: MonitorScope::Peers,
+                monitored_nodes: 4,
+                nodes_with_loss: 1,
+                peacetime_revenue_msat: 500,
+                simulation_revenue_msat: 400,
+                force_close_cost_msat: 0,
+            },
+            honest_payments: PaymentStats {
+                attempted: 10,
+                succeeded: 8,
+                failure_reasons: BTreeMap::from([("NoReputation".to_string(), 2)]),
+                retried: 0,
+                latency_p50_ms: Some(1_500),
+                latency_p90_ms: Some(3_000),
+                latency_p99_ms: Some(4_000),
+                closed_channel_failures: 1,
+            },
+            peacetime_payments: None,
+        };
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["schema_version"], SUMMARY_SCHEMA_VERSION);
+        assert_eq!(json["run_params"]["attack_type"], "Sink");
+        assert_eq!(json["run_params"]["mitigation"]["type"], "circuit_breaker");
+        assert_eq!(json["termination_reason"]["reason"], "attack_failed");
+        assert_eq!(json["targets"][0]["alias"], "target");
+
+        let decoded: SimulationSummary =
+            serde_json::from_str(&serde_json::to_string(&summary).unwrap()).unwrap();
+        assert_eq!(decoded, summary);
+    }
+
+    /// Tests that every command line argument is recorded in the summary, either in [`RunParams`] or at the top level
+    /// of [`SimulationSummary`], so that runs can always be reproduced from their summary. Fails when a new argument
+    /// is added to [`Cli`] without being recorded.
+    #[test]
+    fn test_run_params_cover_cli() {
+        let cli = Cli::try_parse_from([
+            "sim-ln-jam",
+            "--network-dir",
+            "/tmp/network",
+            "--attack-type",
+            "sink",
+        ])
+        .unwrap();
+
+        let run_params = serde_json::to_value(RunParams::from(&cli)).unwrap();
+        let recorded = run_params.as_object().unwrap();
+
+        for arg in Cli::command().get_arguments() {
+            let id = arg.get_id().as_str();
+            let field = match id {
+                "help" | "version" => continue,
+                // Recorded at the top level of the summary.
+                "network_dir" | "seed" => continue,
+                // Where the summary itself is written, which isn't recorded so that runs with the same inputs
+                // produce identical summaries.
+                "results_dir" => continue,
+                // Resolved into the summary's forward params.
+                "resolution_period_seconds"
+                | "general_slot_portion"
+                | "general_liquidity_portion"
+                | "congestion_slot_portion"
+                | "congestion_liquidity_portion" => continue,
+                "circuit_breaker_max_pending_htlcs" | "circuit_breaker_liquidity_portion" => {
+                    "mitigation"
+                }
+                "attacker_bootstrap" => "attacker_bootstrap_secs",
+                "reputation_interval" => "reputation_interval_secs",
+                "max_runtime" => "max_runtime_secs",
+                _ => id,
+            };
+
+            assert!(
+                recorded.contains_key(field),
+                "cli argument {id} is not recorded in run params"
+            );
+        }
+    }
+}
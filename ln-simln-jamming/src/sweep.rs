@@ -0,0 +1,673 @@
+use clap::ValueEnum;
+use ln_resource_mgr::forward_manager::ForwardManagerParams;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::parsing::{parse_duration, AttackType, MitigationType};
+use crate::summary::SimulationSummary;
+use crate::BoxError;
+
+/// Describes a set of simulations to run. Every parameter set is run for each attack type and attacker bootstrap
+/// duration provided.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SweepConfig {
+    pub attack_types: Vec<AttackType>,
+
+    /// Durations that attacker reputation is bootstrapped for, expressed as human readable values (eg: 1w, 3d). A
+    /// null entry runs without bootstrapping, and an empty list is equivalent to a single null entry.
+    #[serde(default)]
+    pub attacker_bootstrap: Vec<Option<String>>,
+
+    /// A grid of parameters, expanded to every combination of its values.
+    #[serde(default)]
+    pub grid: SweepGrid,
+
+    /// An explicit list of parameter sets to run, in addition to the grid.
+    #[serde(default)]
+    pub runs: Vec<SweepParams>,
+
+    /// The seed that every simulation and reputation build is run with, using the simulator's default if unset.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// Lists of values for each parameter in a sweep. Parameters with no values use the simulator's default.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct SweepGrid {
+    #[serde(default)]
+    pub revenue_window_seconds: Vec<u64>,
+    #[serde(default)]
+    pub reputation_multiplier: Vec<u8>,
+    #[serde(default)]
+    pub resolution_period_seconds: Vec<u64>,
+    #[serde(default)]
+    pub general_slot_portion: Vec<u8>,
+    #[serde(default)]
+    pub general_liquidity_portion: Vec<u8>,
+    #[serde(default)]
+    pub congestion_slot_portion: Vec<u8>,
+    #[serde(default)]
+    pub congestion_liquidity_portion: Vec<u8>,
+    #[serde(default)]
+    pub mitigation: Vec<MitigationType>,
+}
+
+impl SweepGrid {
+    fn is_empty(&self) -> bool {
+        self == &SweepGrid::default()
+    }
+
+    /// Returns every combination of the values in the grid.
+    fn expand(&self) -> Vec<SweepParams> {
+        fn values<T: Copy>(v: &[T]) -> Vec<Option<T>> {
+            if v.is_empty() {
+                vec![None]
+            } else {
+                v.iter().map(|x| Some(*x)).collect()
+            }
+        }
+
+        let mut params = vec![SweepParams::default()];
+        macro_rules! expand_field {
+            ($field:ident) => {
+                params = params
+                    .into_iter()
+                    .flat_map(|p| {
+                        values(&self.$field).into_iter().map(move |v| SweepParams {
+                            $field: v,
+                            ..p.clone()
+                        })
+                    })
+                    .collect();
+            };
+        }
+
+        expand_field!(revenue_window_seconds);
+        expand_field!(reputation_multiplier);
+        expand_field!(resolution_period_seconds);
+        expand_field!(general_slot_portion);
+        expand_field!(general_liquidity_portion);
+        expand_field!(congestion_slot_portion);
+        expand_field!(congestion_liquidity_portion);
+        expand_field!(mitigation);
+
+        params
+    }
+}
+
+/// A single set of [`ForwardManagerParams`] overrides and the mitigation to run with, unset values use the simulator's
+/// defaults.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct SweepParams {
+    pub revenue_window_seconds: Option<u64>,
+    pub reputation_multiplier: Option<u8>,
+    pub resolution_period_seconds: Option<u64>,
+    pub general_slot_portion: Option<u8>,
+    pub general_liquidity_portion: Option<u8>,
+    pub congestion_slot_portion: Option<u8>,
+    pub congestion_liquidity_portion: Option<u8>,
+    /// The mitigation that the simulation is run with, which does not affect reputation snapshots.
+    #[serde(default)]
+    pub mitigation: Option<MitigationType>,
+}
+
+impl SweepParams {
+    /// Returns the command line arguments that set forward manager parameters for the simulator and reputation
+    /// builder.
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        let mut push = |flag: &str, value: Option<String>| {
+            if let Some(v) = value {
+                args.push(format!("--{flag}"));
+                args.push(v);
+            }
+        };
+
+        push(
+            "revenue-window-seconds",
+            self.revenue_window_seconds.map(|v| v.to_string()),
+        );
+        push(
+            "reputation-multiplier",
+            self.reputation_multiplier.map(|v| v.to_string()),
+        );
+        push(
+            "resolution-period-seconds",
+            self.resolution_period_seconds.map(|v| v.to_string()),
+        );
+        push(
+            "general-slot-portion",
+            self.general_slot_portion.map(|v| v.to_string()),
+        );
+        push(
+            "general-liquidity-portion",
+            self.general_liquidity_portion.map(|v| v.to_string()),
+        );
+        push(
+            "congestion-slot-portion",
+            self.congestion_slot_portion.map(|v| v.to_string()),
+        );
+        push(
+            "congestion-liquidity-portion",
+            self.congestion_liquidity_portion.map(|v| v.to_string()),
+        );
+
+        args
+    }
+
+    /// Returns a key identifying the reputation snapshot required for these parameters, or None if the default
+    /// snapshot for the network can be used. Reputation is built over the revenue window and multiplier and is
+    /// affected by the resolution period, so snapshots must be regenerated when any of these change. Bucket portions
+    /// don't affect reputation, so they share snapshots.
+    pub fn reputation_key(&self) -> Option<String> {
+        if self.revenue_window_seconds.is_none()
+            && self.reputation_multiplier.is_none()
+            && self.resolution_period_seconds.is_none()
+        {
+            return None;
+        }
+
+        let defaults = ForwardManagerParams::default().reputation_params;
+        Some(format!(
+            "window_{}_multiplier_{}_resolution_{}",
+            self.revenue_window_seconds
+                .unwrap_or(defaults.revenue_window.as_secs()),
+            self.reputation_multiplier
+                .unwrap_or(defaults.reputation_multiplier),
+            self.resolution_period_seconds
+                .unwrap_or(defaults.resolution_period.as_secs()),
+        ))
+    }
+}
+
+/// A single simulation to be run as part of a sweep.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepJob {
+    pub name: String,
+    pub attack_type: AttackType,
+    pub attacker_bootstrap: Option<String>,
+    pub params: SweepParams,
+    pub seed: Option<u64>,
+}
+
+impl SweepJob {
+    /// Returns the value used to select this job's attack type on the command line.
+    pub fn attack_arg(&self) -> String {
+        self.attack_type
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_else(|| format!("{:?}", self.attack_type))
+    }
+
+    /// Returns the directory, relative to the sweep's reputation directory, that this job's reputation snapshot is
+    /// built in. Returns None if the network's default snapshot should be used. Bootstrapped attacks include the
+    /// attacker's history in their snapshot, so they each require their own directory. Snapshots built with a seed
+    /// depend on it, so jobs with a seed never use the network's default snapshot.
+    pub fn reputation_subdir(&self) -> Option<PathBuf> {
+        let key = match (self.params.reputation_key(), self.seed) {
+            (None, None) => return None,
+            (Some(key), None) => key,
+            (key, Some(seed)) => format!("{}_seed_{seed}", key.unwrap_or("default".to_string())),
+        };
+        let mut dir = PathBuf::from(key);
+        if let Some(bootstrap) = &self.attacker_bootstrap {
+            dir.push(format!("{}_{bootstrap}", self.attack_arg()));
+        }
+
+        Some(dir)
+    }
+
+    /// Returns the arguments to run the reputation builder with to create this job's snapshot.
+    pub fn reputation_builder_args(&self) -> Vec<String> {
+        let mut args = self.params.cli_args();
+        if let Some(seed) = self.seed {
+            args.extend(["--seed".to_string(), seed.to_string()]);
+        }
+        if let Some(bootstrap) = &self.attacker_bootstrap {
+            args.extend([
+                "--attack-type".to_string(),
+                self.attack_arg(),
+                "--attacker-bootstrap".to_string(),
+                bootstrap.clone(),
+            ]);
+        }
+
+        args
+    }
+
+    /// Returns the arguments to run the simulator with for this job, excluding directories.
+    pub fn simulation_args(&self) -> Vec<String> {
+        let mut args = vec!["--attack-type".to_string(), self.attack_arg()];
+        if let Some(bootstrap) = &self.attacker_bootstrap {
+            args.extend(["--attacker-bootstrap".to_string(), bootstrap.clone()]);
+        }
+        args.extend(self.params.cli_args());
+        if let Some(seed) = self.seed {
+            args.extend(["--seed".to_string(), seed.to_string()]);
+        }
+        if let Some(mitigation) = self.params.mitigation.and_then(|m| m.to_possible_value()) {
+            args.extend([
+                "--mitigation".to_string(),
+                mitigation.get_name().to_string(),
+            ]);
+        }
+
+        args
+    }
+}
+
+impl SweepConfig {
+    /// Expands the sweep into the individual simulations that need to be run, validating bootstrap durations.
+    pub fn jobs(&self) -> Result<Vec<SweepJob>, BoxError> {
+        if self.attack_types.is_empty() {
+            return Err("sweep requires at least one attack type".into());
+        }
+
+        for bootstrap in self.attacker_bootstrap.iter().flatten() {
+            parse_duration(bootstrap)?;
+        }
+
+        let mut params = self.runs.clone();
+        if !self.grid.is_empty() || params.is_empty() {
+            params.extend(self.grid.expand());
+        }
+
+        let bootstraps = if self.attacker_bootstrap.is_empty() {
+            vec![None]
+        } else {
+            self.attacker_bootstrap.clone()
+        };
+
+        let mut jobs = vec![];
+        for attack_type in self.attack_types.iter() {
+            for bootstrap in bootstraps.iter() {
+                for p in params.iter() {
+                    jobs.push(SweepJob {
+                        name: format!("run_{:04}", jobs.len()),
+                        attack_type: attack_type.clone(),
+                        attacker_bootstrap: bootstrap.clone(),
+                        params: p.clone(),
+                        seed: self.seed,
+                    });
+                }
+            }
+        }
+
+        Ok(jobs)
+    }
+}
+
+/// A single row in the comparison table produced by a sweep. Revenue, reputation and deployment are summed across all
+/// of a run's targets.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ComparisonRow {
+    pub run: String,
+    pub attack_type: String,
+    pub mitigation: String,
+    pub attacker_bootstrap_secs: Option<u64>,
+    pub revenue_window_secs: Option<u64>,
+    pub reputation_multiplier: Option<u8>,
+    pub resolution_period_secs: Option<u64>,
+    pub general_slot_portion: Option<u8>,
+    pub general_liquidity_portion: Option<u8>,
+    pub congestion_slot_portion: Option<u8>,
+    pub congestion_liquidity_portion: Option<u8>,
+    pub runtime_secs: Option<u64>,
+    pub peacetime_revenue_msat: Option<u64>,
+    pub simulation_revenue_msat: Option<u64>,
+    pub target_start_reputation: Option<usize>,
+    pub target_end_reputation: Option<usize>,
+    pub target_pair_count: Option<usize>,
+    pub attacker_start_reputation: Option<usize>,
+    pub attacker_end_reputation: Option<usize>,
+    pub attacker_pair_count: Option<usize>,
+    pub target_peers_deployed: Option<usize>,
+    pub target_peer_count: Option<usize>,
+    pub attacker_cost_msat: Option<u64>,
+    pub force_closes: Option<u64>,
+    pub outcome: String,
+}
+
+impl ComparisonRow {
+    /// Creates a row for a job, using the simulation's summary if it completed or the error it failed with. Parameters
+    /// are reported from the summary when available so that defaults are filled in.
+    pub fn new(job: &SweepJob, result: &Result<SimulationSummary, String>) -> Self {
+        let bootstrap_secs = job
+            .attacker_bootstrap
+            .as_ref()
+            .and_then(|b| parse_duration(b).ok())
+            .map(|d: Duration| d.as_secs());
+
+        let mut row = ComparisonRow {
+            run: job.name.clone(),
+            attack_type: format!("{:?}", job.attack_type),
+            mitigation: format!("{:?}", job.params.mitigation.unwrap_or_default()),
+            attacker_bootstrap_secs: bootstrap_secs,
+            revenue_window_secs: job.params.revenue_window_seconds,
+            reputation_multiplier: job.params.reputation_multiplier,
+            resolution_period_secs: job.params.resolution_period_seconds,
+            general_slot_portion: job.params.general_slot_portion,
+            general_liquidity_portion: job.params.general_liquidity_portion,
+            congestion_slot_portion: job.params.congestion_slot_portion,
+            congestion_liquidity_portion: job.params.congestion_liquidity_portion,
+            runtime_secs: None,
+            peacetime_revenue_msat: None,
+            simulation_revenue_msat: None,
+            target_start_reputation: None,
+            target_end_reputation: None,
+            target_pair_count: None,
+            attacker_start_reputation: None,
+            attacker_end_reputation: None,
+            attacker_pair_count: None,
+            target_peers_deployed: None,
+            target_peer_count: None,
+            attacker_cost_msat: None,
+            force_closes: None,
+            outcome: String::new(),
+        };
+
+        let summary = match result {
+            Ok(s) => s,
+            Err(e) => {
+                row.outcome = format!("run_failed: {e}");
+                return row;
+            }
+        };
+
+        let forward_params = summary.forward_params;
+        row.revenue_window_secs = Some(forward_params.reputation_params.revenue_window.as_secs());
+        row.reputation_multiplier = Some(forward_params.reputation_params.reputation_multiplier);
+        row.resolution_period_secs =
+            Some(forward_params.reputation_params.resolution_period.as_secs());
+        row.general_slot_portion = Some(forward_params.general_slot_portion);
+        row.general_liquidity_portion = Some(forward_params.general_liquidity_portion);
+        row.congestion_slot_portion = Some(forward_params.congestion_slot_portion);
+        row.congestion_liquidity_portion = Some(forward_params.congestion_liquidity_portion);
+        let targets = &summary.targets;
+        row.runtime_secs = targets.iter().map(|t| t.revenue.runtime.as_secs()).max();
+        row.peacetime_revenue_msat = Some(
+            targets
+                .iter()
+                .map(|t| t.revenue.peacetime_revenue_msat)
+                .sum(),
+        );
+        row.simulation_revenue_msat = Some(
+            targets
+                .iter()
+                .map(|t| t.revenue.simulation_revenue_msat)
+                .sum(),
+        );
+        row.target_start_reputation = Some(
+            targets
+                .iter()
+                .map(|t| t.start_reputation.target_reputation)
+                .sum(),
+        );
+        row.target_end_reputation = Some(
+            targets
+                .iter()
+                .map(|t| t.end_reputation.target_reputation)
+                .sum(),
+        );
+        row.target_pair_count = Some(
+            targets
+                .iter()
+                .map(|t| t.end_reputation.target_pair_count)
+                .sum(),
+        );
+        row.attacker_start_reputation = Some(
+            targets
+                .iter()
+                .map(|t| t.start_reputation.attacker_reputation)
+                .sum(),
+        );
+        row.attacker_end_reputation = Some(
+            targets
+                .iter()
+                .map(|t| t.end_reputation.attacker_reputation)
+                .sum(),
+        );
+        row.attacker_pair_count = Some(
+            targets
+                .iter()
+                .map(|t| t.end_reputation.attacker_pair_count)
+                .sum(),
+        );
+        row.target_peers_deployed = Some(targets.iter().map(|t| t.deployment.deployed_peers).sum());
+        row.target_peer_count = Some(targets.iter().map(|t| t.deployment.peer_count).sum());
+        row.attacker_cost_msat = Some(
+            summary
+                .attack_statistics
+                .attacker_costs
+                .iter()
+                .map(|c| c.total_msat())
+                .sum(),
+        );
+        row.force_closes = Some(
+            summary
+                .attack_statistics
+                .attacker_costs
+                .iter()
+                .map(|c| c.force_closes_caused)
+                .sum(),
+        );
+        row.outcome = serde_json::to_value(&summary.termination_reason)
+            .ok()
+            .and_then(|v| v["reason"].as_str().map(|s| s.to_string()))
+            .unwrap_or_default();
+
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::parsing::AttackType;
+
+    use super::{ComparisonRow, SweepConfig, SweepGrid, SweepJob, SweepParams};
+
+    /// Tests expansion of grids and explicit runs into jobs.
+    #[test]
+    fn test_sweep_jobs() {
+        let config: SweepConfig = serde_json::from_str(
+            r#"{
+                "attack_types": ["Sink", "SlowJam"],
+                "attacker_bootstrap": [null, "1w"],
+                "grid": {
+                    "revenue_window_seconds": [100, 200],
+                    "general_slot_portion": [10, 20, 30]
+                },
+                "runs": [{"reputation_multiplier": 5, "mitigation": "CircuitBreaker"}]
+            }"#,
+        )
+        .unwrap();
+
+        let jobs = config.jobs().unwrap();
+        assert_eq!(jobs.len(), 2 * 2 * (2 * 3 + 1));
+        assert_eq!(jobs[0].name, "run_0000");
+        assert_eq!(jobs[0].attack_type, AttackType::Sink);
+        assert_eq!(jobs[0].attack_arg(), "sink");
+        assert_eq!(jobs[0].attacker_bootstrap, None);
+        assert_eq!(jobs[0].params.reputation_multiplier, Some(5));
+        assert_eq!(
+            jobs[0].simulation_args(),
+            vec![
+                "--attack-type",
+                "sink",
+                "--reputation-multiplier",
+                "5",
+                "--mitigation",
+                "circuit-breaker"
+            ]
+        );
+        assert_eq!(
+            jobs[0].reputation_builder_args(),
+            vec!["--reputation-multiplier", "5"]
+        );
+        assert_eq!(
+            jobs[1].params,
+            SweepParams {
+                revenue_window_seconds: Some(100),
+                general_slot_portion: Some(10),
+                ..Default::default()
+            }
+        );
+
+        let last = jobs.last().unwrap();
+        assert_eq!(last.attack_type, AttackType::SlowJam);
+        assert_eq!(last.attack_arg(), "slow-jam");
+        assert_eq!(last.attacker_bootstrap, Some("1w".to_string()));
+        assert_eq!(last.params.revenue_window_seconds, Some(200));
+        assert_eq!(last.params.general_slot_portion, Some(30));
+        assert_eq!(
+            last.reputation_subdir().unwrap(),
+            PathBuf::from("window_200_multiplier_12_resolution_90").join("slow-jam_1w")
+        );
+        assert_eq!(
+            last.reputation_builder_args(),
+            vec![
+                "--revenue-window-seconds",
+                "200",
+                "--general-slot-portion",
+                "30",
+                "--attack-type",
+                "slow-jam",
+                "--attacker-bootstrap",
+                "1w"
+            ]
+        );
+        assert_eq!(
+            last.simulation_args()[..4],
+            ["--attack-type", "slow-jam", "--attacker-bootstrap", "1w"]
+        );
+
+        // An empty sweep runs with defaults once, explicit runs without a grid are run alone.
+        let config = SweepConfig {
+            attack_types: vec![AttackType::Sink],
+            attacker_bootstrap: vec![],
+            grid: SweepGrid::default(),
+            runs: vec![],
+            seed: None,
+        };
+        assert_eq!(config.jobs().unwrap()[0].params, SweepParams::default());
+
+        let config = SweepConfig {
+            runs: vec![SweepParams::default(); 3],
+            ..config
+        };
+        assert_eq!(config.jobs().unwrap().len(), 3);
+
+        // Invalid bootstrap durations and missing attacks are rejected.
+        let config = SweepConfig {
+            attacker_bootstrap: vec![Some("invalid".to_string())],
+            ..config
+        };
+        assert!(config.jobs().is_err());
+
+        let config = SweepConfig {
+            attack_types: vec![],
+            attacker_bootstrap: vec![],
+            ..config
+        };
+        assert!(config.jobs().is_err());
+    }
+
+    #[test]
+    fn test_sweep_params() {
+        let params = SweepParams::default();
+        assert!(params.cli_args().is_empty());
+        assert!(params.reputation_key().is_none());
+
+        // Bucket portions don't require a new reputation snapshot.
+        let params = SweepParams {
+            general_slot_portion: Some(10),
+            congestion_liquidity_portion: Some(15),
+            ..Default::default()
+        };
+        assert_eq!(
+            params.cli_args(),
+            vec![
+                "--general-slot-portion",
+                "10",
+                "--congestion-liquidity-portion",
+                "15"
+            ]
+        );
+        assert!(params.reputation_key().is_none());
+
+        // Reputation parameters require a snapshot, with defaults filled in for unset values.
+        let params = SweepParams {
+            revenue_window_seconds: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(
+            params.reputation_key().unwrap(),
+            "window_100_multiplier_12_resolution_90"
+        );
+    }
+
+    /// Tests that a sweep's seed is passed to both the reputation builder and simulator, and that snapshots built
+    /// with a seed are kept apart from the network's default snapshot.
+    #[test]
+    fn test_sweep_seed() {
+        let config: SweepConfig = serde_json::from_str(
+            r#"{
+                "attack_types": ["Sink"],
+                "runs": [{}, {"reputation_multiplier": 5}],
+                "seed": 42
+            }"#,
+        )
+        .unwrap();
+
+        let jobs = config.jobs().unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert!(jobs.iter().all(|j| j.seed == Some(42)));
+
+        assert_eq!(
+            jobs[0].reputation_subdir().unwrap(),
+            PathBuf::from("default_seed_42")
+        );
+        assert_eq!(jobs[0].reputation_builder_args(), vec!["--seed", "42"]);
+        assert_eq!(
+            jobs[0].simulation_args(),
+            vec!["--attack-type", "sink", "--seed", "42"]
+        );
+
+        assert_eq!(
+            jobs[1].reputation_subdir().unwrap(),
+            PathBuf::from("window_1209600_multiplier_5_resolution_90_seed_42")
+        );
+        assert_eq!(
+            jobs[1].reputation_builder_args(),
+            vec!["--reputation-multiplier", "5", "--seed", "42"]
+        );
+    }
+
+    /// Tests that failed runs still produce a row with the parameters that were requested.
+    #[test]
+    fn test_comparison_row_failed() {
+        let job = SweepJob {
+            name: "run_0001".to_string(),
+            attack_type: AttackType::Sybil,
+            attacker_bootstrap: Some("1d".to_string()),
+            params: SweepParams {
+                reputation_multiplier: Some(5),
+                ..Default::default()
+            },
+            seed: None,
+        };
+
+        let row = ComparisonRow::new(&job, &Err("exited".to_string()));
+        assert_eq!(row.run, "run_0001");
+        assert_eq!(row.attack_type, "Sybil");
+        assert_eq!(row.mitigation, "Reputation");
+        assert_eq!(row.attacker_bootstrap_secs, Some(60 * 60 * 24));
+        assert_eq!(row.reputation_multiplier, Some(5));
+        assert_eq!(row.revenue_window_secs, None);
+        assert_eq!(row.simulation_revenue_msat, None);
+        assert_eq!(row.outcome, "run_failed: exited");
+    }
+}
@@ -1,8 +1,11 @@
 #![cfg(test)]
 use std::time::Instant;
 
-use crate::reputation_interceptor::{BootstrapForward, ChannelJammer, ReputationMonitor};
-use crate::revenue_interceptor::PeacetimeRevenueMonitor;
+use crate::reputation_interceptor::{
+    BootstrapForward, ChannelCloser, ChannelJammer, ChannelOpener, ReputationMonitor,
+};
+use crate::revenue_interceptor::{ForceCloseRecorder, PeacetimeRevenueMonitor};
+use crate::topology::TopologyController;
 use crate::{records_from_signal, BoxError};
 use async_trait::async_trait;
 use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
@@ -35,6 +38,16 @@ mock! {
     impl ReputationMonitor for ReputationInterceptor{
         async fn list_channels(&self, node: PublicKey, access_ins: Instant) -> Result<HashMap<u64, ChannelSnapshot>, BoxError>;
     }
+
+    #[async_trait]
+    impl ChannelCloser for ReputationInterceptor{
+        async fn close_channel(&self, channel: u64) -> Result<(), BoxError>;
+    }
+
+    #[async_trait]
+    impl ChannelOpener for ReputationInterceptor{
+        async fn open_channel(&self, channel: u64, capacity_msat: u64, nodes: [PublicKey; 2]) -> Result<(), BoxError>;
+    }
 }
 
 mock! {
@@ -42,7 +55,12 @@ mock! {
 
     #[async_trait]
     impl PeacetimeRevenueMonitor for PeacetimeMonitor {
-        async fn get_revenue_difference(&self) -> crate::revenue_interceptor::RevenueSnapshot;
+        async fn get_revenue_difference(&self, target: &PublicKey) -> crate::revenue_interceptor::RevenueSnapshot;
+    }
+
+    #[async_trait]
+    impl ForceCloseRecorder for PeacetimeMonitor {
+        async fn record_force_close(&self, closing_node: &PublicKey, cost_msat: u64);
     }
 }
 
@@ -57,6 +75,19 @@ mock! {
     }
 }
 
+mock! {
+    pub Topology{}
+
+    #[async_trait]
+    impl TopologyController for Topology {
+        fn is_closed(&self, channel: u64) -> Result<bool, BoxError>;
+
+        async fn open_channel(&self, channel: u64) -> Result<(), BoxError>;
+
+        async fn close_channel(&self, channel: u64) -> Result<bool, BoxError>;
+    }
+}
+
 pub fn get_random_bytes(size: usize) -> Vec<u8> {
     let mut rng = rand::rng();
     let mut bytes = vec![0u8; size];
@@ -149,6 +180,7 @@ pub fn test_proposed_forward(id: u64) -> ProposedForward {
         amount_out_msat: 1000,
         expiry_in_height: 80,
         expiry_out_height: 40,
+        added_height: 0,
         added_at: Instant::now(),
         incoming_accountable: AccountableSignal::Accountable,
         upgradable_accountability: true,
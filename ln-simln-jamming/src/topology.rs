@@ -0,0 +1,457 @@
+use crate::clock::InstantClock;
+use crate::reputation_interceptor::{ChannelCloser, ChannelOpener};
+use crate::BoxError;
+use async_trait::async_trait;
+use bitcoin::secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+use sim_cli::parsing::NetworkParser;
+use simln_lib::clock::{Clock, SimulationClock};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::select;
+use triggered::Listener;
+
+/// The change made to a channel by a topology event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TopologyAction {
+    Open,
+    Close,
+}
+
+/// A change to the network's topology, scheduled relative to the start of the simulation.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TopologyEvent {
+    /// The number of seconds after the simulation starts that the event happens.
+    pub offset_secs: u64,
+    pub scid: u64,
+    pub action: TopologyAction,
+}
+
+/// Allows attacks to check, open and close channels in the simulated network while a simulation is running.
+#[async_trait]
+pub trait TopologyController {
+    /// Returns a boolean indicating whether the channel is closed.
+    fn is_closed(&self, channel: u64) -> Result<bool, BoxError>;
+
+    /// Opens a channel from the graph that is currently closed.
+    async fn open_channel(&self, channel: u64) -> Result<(), BoxError>;
+
+    /// Closes a channel from the graph, returning a boolean indicating whether the channel was open before it was
+    /// closed.
+    async fn close_channel(&self, channel: u64) -> Result<bool, BoxError>;
+}
+
+struct TopologyChannel {
+    capacity_msat: u64,
+    nodes: [PublicKey; 2],
+}
+
+/// Tracks which channels in the simulated network are open, opening and closing channels in the forward managers of
+/// the nodes on each side of the channel.
+///
+/// Sim-ln does not support changing its graph once a simulation has been created, so every channel that is opened
+/// during a simulation must be included in the graph that the simulation is created with. Channels that are closed,
+/// or not yet opened, remain in sim-ln's graph and honest senders may still route over them, so htlcs over them must
+/// be failed by checking [`Self::any_closed`]. These failures are reported separately from the mitigation's failures
+/// (see [`crate::reputation_interceptor::FailureReporter::report_closed_channel`]) so that they don't count against
+/// honest payment statistics.
+pub struct ChannelTopology<C>
+where
+    C: ChannelOpener + ChannelCloser,
+{
+    channel_manager: Arc<C>,
+    channels: HashMap<u64, TopologyChannel>,
+    closed_channels: Mutex<HashSet<u64>>,
+    /// Held while a channel is opened or closed, so that the channel's state can't be changed by a concurrent open or
+    /// close between checking it and updating forward managers.
+    update_lock: tokio::sync::Mutex<()>,
+}
+
+impl<C> ChannelTopology<C>
+where
+    C: ChannelOpener + ChannelCloser,
+{
+    /// Creates a topology for the graph provided, checking that the events provided can be applied to it. Channels
+    /// that are opened by the events are closed until their open event is reached.
+    pub async fn new(
+        channel_manager: Arc<C>,
+        edges: &[NetworkParser],
+        events: &[TopologyEvent],
+    ) -> Result<Self, BoxError> {
+        let channels: HashMap<u64, TopologyChannel> = edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.scid.into(),
+                    TopologyChannel {
+                        capacity_msat: edge.capacity_msat,
+                        nodes: [edge.node_1.pubkey, edge.node_2.pubkey],
+                    },
+                )
+            })
+            .collect();
+
+        let mut sorted_events = events.to_vec();
+        sorted_events.sort_by_key(|e| e.offset_secs);
+
+        // A channel starts closed if the first event for it opens it, otherwise it's open at the start of the
+        // simulation. Track the state of each channel as we run through events so that we can catch invalid
+        // sequences before the simulation starts.
+        let mut initially_closed = HashSet::new();
+        let mut open_state: HashMap<u64, bool> = HashMap::new();
+        for event in sorted_events {
+            if !channels.contains_key(&event.scid) {
+                return Err(format!("topology event for unknown channel: {}", event.scid).into());
+            }
+
+            let is_open = open_state.entry(event.scid).or_insert_with(|| {
+                if event.action == TopologyAction::Open {
+                    initially_closed.insert(event.scid);
+                    false
+                } else {
+                    true
+                }
+            });
+
+            match (event.action, *is_open) {
+                (TopologyAction::Open, false) => *is_open = true,
+                (TopologyAction::Close, true) => *is_open = false,
+                (action, _) => {
+                    return Err(format!(
+                        "invalid topology event {:?} for channel {} at offset {}s, open: {is_open}",
+                        action, event.scid, event.offset_secs,
+                    )
+                    .into())
+                }
+            }
+        }
+
+        for channel in initially_closed.iter() {
+            channel_manager.close_channel(*channel).await?;
+        }
+
+        Ok(Self {
+            channel_manager,
+            channels,
+            closed_channels: Mutex::new(initially_closed),
+            update_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Returns a boolean indicating whether any of the channels provided are closed.
+    pub fn any_closed(&self, channels: &[Option<u64>]) -> Result<bool, BoxError> {
+        let closed_channels = self
+            .closed_channels
+            .lock()
+            .map_err(|e| format!("closed channels lock poisoned: {e}"))?;
+
+        Ok(channels
+            .iter()
+            .flatten()
+            .any(|c| closed_channels.contains(c)))
+    }
+
+    /// Returns the node on the other side of the channel provided.
+    pub fn channel_peer(&self, channel: u64, node: &PublicKey) -> Result<PublicKey, BoxError> {
+        let [node_1, node_2] = self
+            .channels
+            .get(&channel)
+            .ok_or(format!("channel: {channel} not found in graph"))?
+            .nodes;
+
+        if node_1 == *node {
+            Ok(node_2)
+        } else if node_2 == *node {
+            Ok(node_1)
+        } else {
+            Err(format!("node: {node} is not a party to channel: {channel}").into())
+        }
+    }
+
+    /// Opens a channel from the graph that is currently closed. Only channels in the graph that the simulation was
+    /// created with can be opened, as sim-ln can't add channels to its graph once a simulation has been created.
+    pub async fn open_channel(&self, channel: u64) -> Result<(), BoxError> {
+        let topology_channel = self
+            .channels
+            .get(&channel)
+            .ok_or(format!("open channel: {channel} not found in graph"))?;
+
+        let _update_guard = self.update_lock.lock().await;
+        if !self.any_closed(&[Some(channel)])? {
+            return Err(format!("open channel: {channel} already open").into());
+        }
+
+        // Add the channel to forward managers before we stop failing htlcs over it, so that forwards don't reach the
+        // reputation interceptor for a channel that it doesn't know about.
+        self.channel_manager
+            .open_channel(
+                channel,
+                topology_channel.capacity_msat,
+                topology_channel.nodes,
+            )
+            .await?;
+
+        self.closed_channels
+            .lock()
+            .map_err(|e| format!("closed channels lock poisoned: {e}"))?
+            .remove(&channel);
+
+        Ok(())
+    }
+
+    /// Closes a channel from the graph, returning a boolean indicating whether the channel was open before it was
+    /// closed.
+    ///
+    /// The channel is removed from the forward managers of its nodes, but not from sim-ln's graph: sim-ln builds its
+    /// pathfinding graph when the simulation is created and doesn't expose it, so the edge can't be removed without
+    /// changes to sim-ln. Htlcs over the channel are instead failed by [`Self::any_closed`] checks.
+    pub async fn close_channel(&self, channel: u64) -> Result<bool, BoxError> {
+        if !self.channels.contains_key(&channel) {
+            return Err(format!("close channel: {channel} not found in graph").into());
+        }
+
+        let _update_guard = self.update_lock.lock().await;
+
+        // Start failing htlcs over the channel before we remove it from forward managers.
+        if !self
+            .closed_channels
+            .lock()
+            .map_err(|e| format!("closed channels lock poisoned: {e}"))?
+            .insert(channel)
+        {
+            return Ok(false);
+        }
+
+        self.channel_manager.close_channel(channel).await?;
+        Ok(true)
+    }
+
+    /// Applies the events provided at their scheduled offset from the start of the simulation, returning once all
+    /// events have been applied or shutdown is signaled. Events should first be validated by [`Self::new`].
+    pub async fn run_schedule(
+        &self,
+        mut events: Vec<TopologyEvent>,
+        clock: Arc<SimulationClock>,
+        shutdown_listener: Listener,
+    ) -> Result<(), BoxError> {
+        events.sort_by_key(|e| e.offset_secs);
+        let start_ins: Instant = clock.get_start_instant().into();
+
+        for event in events {
+            let event_ins = start_ins + Duration::from_secs(event.offset_secs);
+            let wait = event_ins.saturating_duration_since(InstantClock::now(&*clock));
+
+            select! {
+                _ = shutdown_listener.clone() => return Ok(()),
+                _ = clock.sleep(wait) => {},
+            }
+
+            log::info!(
+                "Applying topology event {:?} to channel {} at offset {}s",
+                event.action,
+                event.scid,
+                event.offset_secs,
+            );
+
+            match event.action {
+                TopologyAction::Open => self.open_channel(event.scid).await?,
+                TopologyAction::Close => {
+                    // The channel may already have been force closed by the simulation.
+                    if !self.close_channel(event.scid).await? {
+                        log::info!("Channel {} already closed", event.scid);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C> TopologyController for ChannelTopology<C>
+where
+    C: ChannelOpener + ChannelCloser + Send + Sync,
+{
+    fn is_closed(&self, channel: u64) -> Result<bool, BoxError> {
+        self.any_closed(&[Some(channel)])
+    }
+
+    async fn open_channel(&self, channel: u64) -> Result<(), BoxError> {
+        ChannelTopology::open_channel(self, channel).await
+    }
+
+    async fn close_channel(&self, channel: u64) -> Result<bool, BoxError> {
+        ChannelTopology::close_channel(self, channel).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use bitcoin::secp256k1::PublicKey;
+    use mockall::predicate::eq;
+    use simln_lib::clock::SimulationClock;
+    use simln_lib::ShortChannelID;
+
+    use crate::clock::InstantClock;
+    use crate::reputation_interceptor::{ChannelCloser, ChannelOpener};
+    use crate::test_utils::{get_random_keypair, setup_test_edge, MockReputationInterceptor};
+    use crate::BoxError;
+
+    use super::{ChannelTopology, TopologyAction, TopologyEvent};
+
+    fn event(offset_secs: u64, scid: u64, action: TopologyAction) -> TopologyEvent {
+        TopologyEvent {
+            offset_secs,
+            scid,
+            action,
+        }
+    }
+
+    fn setup_test_edges() -> Vec<sim_cli::parsing::NetworkParser> {
+        (1..=2)
+            .map(|scid| {
+                setup_test_edge(
+                    ShortChannelID::from(scid),
+                    get_random_keypair().1,
+                    get_random_keypair().1,
+                )
+            })
+            .collect()
+    }
+
+    /// Tests validation of topology events against the graph.
+    #[tokio::test]
+    async fn test_invalid_events() {
+        let edges = setup_test_edges();
+
+        let invalid_events = vec![
+            // Unknown channel.
+            vec![event(10, 5, TopologyAction::Close)],
+            // Closed twice.
+            vec![
+                event(10, 1, TopologyAction::Close),
+                event(20, 1, TopologyAction::Close),
+            ],
+            // Opened twice (events are sorted by offset).
+            vec![
+                event(20, 1, TopologyAction::Open),
+                event(10, 1, TopologyAction::Open),
+            ],
+        ];
+
+        for events in invalid_events {
+            assert!(ChannelTopology::new(
+                Arc::new(MockReputationInterceptor::new()),
+                &edges,
+                &events,
+            )
+            .await
+            .is_err());
+        }
+    }
+
+    /// Tests that channels that are opened by a topology event start closed, and that events are applied at their
+    /// scheduled time.
+    #[tokio::test(start_paused = true)]
+    async fn test_run_schedule() {
+        let edges = setup_test_edges();
+        let events = vec![
+            event(60, 1, TopologyAction::Close),
+            event(30, 2, TopologyAction::Open),
+            event(90, 2, TopologyAction::Close),
+        ];
+
+        let clock = Arc::new(SimulationClock::new(1).unwrap());
+        let start = InstantClock::now(&*clock);
+
+        let mut mock = MockReputationInterceptor::new();
+        mock.expect_close_channel()
+            .with(eq(2))
+            .times(2)
+            .returning(|_| Ok(()));
+        mock.expect_close_channel()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(()));
+        let nodes = [edges[1].node_1.pubkey, edges[1].node_2.pubkey];
+        mock.expect_open_channel()
+            .with(eq(2), eq(edges[1].capacity_msat), eq(nodes))
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let topology = ChannelTopology::new(Arc::new(mock), &edges, &events)
+            .await
+            .unwrap();
+        assert!(topology.any_closed(&[Some(2)]).unwrap());
+        assert!(!topology.any_closed(&[Some(1), None]).unwrap());
+
+        let (_shutdown, listener) = triggered::trigger();
+        topology
+            .run_schedule(events, clock.clone(), listener)
+            .await
+            .unwrap();
+
+        assert!(topology.any_closed(&[Some(1)]).unwrap());
+        assert!(topology.any_closed(&[Some(2)]).unwrap());
+        assert!(InstantClock::now(&*clock).duration_since(start) >= Duration::from_secs(90));
+
+        // Closing a channel that's already closed is a no-op, and opening one that's unknown fails.
+        assert!(!topology.close_channel(1).await.unwrap());
+        assert!(topology.open_channel(5).await.is_err());
+    }
+
+    /// Channel manager that takes time to open channels, so that concurrent opens overlap.
+    #[derive(Default)]
+    struct SlowChannelManager {
+        opens: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ChannelOpener for SlowChannelManager {
+        async fn open_channel(
+            &self,
+            _channel: u64,
+            _capacity_msat: u64,
+            _nodes: [PublicKey; 2],
+        ) -> Result<(), BoxError> {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            self.opens.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl ChannelCloser for SlowChannelManager {
+        async fn close_channel(&self, _channel: u64) -> Result<(), BoxError> {
+            Ok(())
+        }
+    }
+
+    /// Tests that a channel is only opened once when concurrent opens race, with the second open failing because the
+    /// channel is already open.
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrent_open() {
+        let edges = setup_test_edges();
+        let channel_manager = Arc::new(SlowChannelManager::default());
+        let topology = ChannelTopology::new(
+            channel_manager.clone(),
+            &edges,
+            &[event(10, 1, TopologyAction::Open)],
+        )
+        .await
+        .unwrap();
+
+        let (first, second) = tokio::join!(topology.open_channel(1), topology.open_channel(1));
+        assert!(first.is_ok() != second.is_ok());
+        assert_eq!(channel_manager.opens.load(Ordering::SeqCst), 1);
+        assert!(!topology.any_closed(&[Some(1)]).unwrap());
+    }
+}
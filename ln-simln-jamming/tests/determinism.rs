@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Parser;
+use ln_simln_jamming::parsing::Cli;
+use ln_simln_jamming::simulation::run_simulation;
+
+/// Runs a sink attack on the example network with the seed provided, writing results to the directory provided.
+async fn run_seeded_simulation(results_dir: &Path, seed: u64) {
+    let network_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../networks/ln_50");
+    let seed = seed.to_string();
+    let cli = Cli::try_parse_from([
+        "ln-simln-jamming",
+        "--network-dir",
+        network_dir.to_str().unwrap(),
+        "--results-dir",
+        results_dir.to_str().unwrap(),
+        "--attack-type",
+        "sink",
+        "--max-runtime",
+        "1d",
+        "--target-reputation-percent",
+        "1",
+        "--seed",
+        &seed,
+    ])
+    .unwrap();
+
+    let (_interrupt, interrupt_listener) = triggered::trigger();
+    run_simulation(cli, interrupt_listener).await.unwrap();
+}
+
+/// Reads every file in the directory provided, keyed by its path relative to the directory.
+fn read_results(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.insert(
+                    path.strip_prefix(dir).unwrap().to_path_buf(),
+                    fs::read(&path).unwrap(),
+                );
+            }
+        }
+    }
+
+    files
+}
+
+/// Tests that two runs with the same seed produce byte for byte identical results. The simulation is run in-process
+/// on a single threaded runtime with tokio's clock paused, as is used by `--fast-forward`, so that timing depends only
+/// on the events scheduled by the simulation rather than on the wall clock or load on the machine. Runs against the
+/// wall clock are not expected to be identical.
+#[tokio::test(start_paused = true)]
+async fn test_same_seed_identical_results() {
+    let base_dir = std::env::temp_dir().join(format!(
+        "determinism_test_{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos(),
+    ));
+    let first_dir = base_dir.join("first");
+    let second_dir = base_dir.join("second");
+
+    run_seeded_simulation(&first_dir, 42).await;
+    run_seeded_simulation(&second_dir, 42).await;
+
+    let first = read_results(&first_dir);
+    let second = read_results(&second_dir);
+    assert!(!first.is_empty());
+    assert_eq!(
+        first.keys().collect::<Vec<_>>(),
+        second.keys().collect::<Vec<_>>()
+    );
+    for (path, contents) in first.iter() {
+        assert!(
+            *contents == second[path],
+            "{} differs between runs with the same seed",
+            path.display()
+        );
+    }
+
+    fs::remove_dir_all(base_dir).unwrap();
+}